@@ -0,0 +1,3 @@
+UPDATE asset_tickers SET ticker = '' WHERE ticker IS NULL;
+
+ALTER TABLE asset_tickers ALTER COLUMN ticker SET NOT NULL;
@@ -0,0 +1,3 @@
+ALTER TABLE asset_tickers ALTER COLUMN ticker DROP NOT NULL;
+
+UPDATE asset_tickers SET ticker = NULL WHERE ticker = '';
@@ -0,0 +1 @@
+DROP INDEX IF EXISTS asset_tickers_ticker_idx;
@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS asset_tickers_ticker_idx ON asset_tickers (ticker);
@@ -0,0 +1,37 @@
+-- Projection of the current assets used by the search when `SEARCH_MATERIALIZED_VIEW` is enabled.
+-- It is refreshed by the consumer, so the search lags behind the live tables until the next refresh.
+CREATE MATERIALIZED VIEW IF NOT EXISTS searchable_assets AS
+SELECT
+    a.id,
+    a.name,
+    ast.ticker,
+    awl.labels,
+    a.smart,
+    a.issuer,
+    a.precision,
+    a.quantity,
+    a.min_sponsored_fee,
+    a.nft,
+    (SELECT min(a1.block_uid) FROM assets AS a1 WHERE a1.id = a.id) AS block_uid
+FROM assets AS a
+LEFT JOIN asset_tickers AS ast ON ast.asset_id = a.id AND ast.superseded_by = 9223372036854775806
+LEFT JOIN (
+    SELECT asset_id, ARRAY_AGG(DISTINCT labels_list) AS labels
+    FROM (
+        SELECT al.asset_id AS asset_id, al.labels
+        FROM asset_labels AS al
+        WHERE al.superseded_by = 9223372036854775806
+        UNION
+        SELECT awl.asset_id AS asset_id, ARRAY_AGG(awl.label) AS labels
+        FROM asset_wx_labels AS awl
+        GROUP BY awl.asset_id
+    ) AS data, UNNEST(labels) AS labels_list
+    GROUP BY asset_id
+) AS awl ON awl.asset_id = a.id
+WHERE a.superseded_by = 9223372036854775806;
+
+-- unique index is required by REFRESH MATERIALIZED VIEW CONCURRENTLY
+CREATE UNIQUE INDEX IF NOT EXISTS searchable_assets_id_idx ON searchable_assets (id);
+CREATE INDEX IF NOT EXISTS searchable_assets_block_uid_idx ON searchable_assets (block_uid);
+CREATE INDEX IF NOT EXISTS searchable_assets_ticker_idx ON searchable_assets (ticker);
+CREATE INDEX IF NOT EXISTS searchable_assets_issuer_idx ON searchable_assets (issuer);
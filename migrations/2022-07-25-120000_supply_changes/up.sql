@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS supply_changes (
+    uid BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    block_uid BIGINT NOT NULL CONSTRAINT supply_changes_block_uid_fkey REFERENCES blocks_microblocks (uid) ON DELETE CASCADE,
+    asset_id TEXT NOT NULL,
+    height INTEGER NOT NULL,
+    prev_quantity BIGINT NOT NULL,
+    new_quantity BIGINT NOT NULL,
+    change BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS supply_changes_asset_id_height_idx ON supply_changes (asset_id, height);
+CREATE INDEX IF NOT EXISTS supply_changes_block_uid_idx ON supply_changes (block_uid);
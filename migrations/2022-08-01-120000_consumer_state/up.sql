@@ -0,0 +1,7 @@
+-- the last block (or microblock) handled by the consumer, updated in the batch transaction
+CREATE TABLE IF NOT EXISTS consumer_state (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CONSTRAINT consumer_state_single_row CHECK (id),
+    block_uid BIGINT NOT NULL,
+    block_id TEXT NOT NULL,
+    height INTEGER NOT NULL
+);
@@ -0,0 +1,92 @@
+CREATE TABLE IF NOT EXISTS asset_names (
+    uid BIGINT GENERATED BY DEFAULT AS IDENTITY,
+    superseded_by BIGINT DEFAULT 9223372036854775806 NOT NULL,
+    block_uid BIGINT NOT NULL CONSTRAINT asset_names_block_uid_fkey REFERENCES blocks_microblocks (uid) ON DELETE CASCADE,
+    asset_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    PRIMARY KEY (superseded_by, asset_id)
+);
+
+CREATE TABLE IF NOT EXISTS asset_descriptions (
+    uid BIGINT GENERATED BY DEFAULT AS IDENTITY,
+    superseded_by BIGINT DEFAULT 9223372036854775806 NOT NULL,
+    block_uid BIGINT NOT NULL CONSTRAINT asset_descriptions_block_uid_fkey REFERENCES blocks_microblocks (uid) ON DELETE CASCADE,
+    asset_id TEXT NOT NULL,
+    description TEXT NOT NULL,
+    PRIMARY KEY (superseded_by, asset_id)
+);
+
+-- the current values are the base of the history, so that only the following changes are stored
+INSERT INTO asset_names (block_uid, asset_id, name)
+SELECT block_uid, id, name FROM assets WHERE superseded_by = 9223372036854775806;
+
+INSERT INTO asset_descriptions (block_uid, asset_id, description)
+SELECT block_uid, id, description FROM assets WHERE superseded_by = 9223372036854775806;
+
+-- the consumer takes the uids of the new rows from the sequences
+SELECT setval('asset_names_uid_seq', (SELECT COALESCE(max(uid), 0) + 1 FROM asset_names), false);
+SELECT setval('asset_descriptions_uid_seq', (SELECT COALESCE(max(uid), 0) + 1 FROM asset_descriptions), false);
+
+
+CREATE OR REPLACE FUNCTION reopen_asset_names() RETURNS VOID 
+    language plpgsql 
+AS $$
+BEGIN
+    UPDATE
+        asset_names
+    SET
+        superseded_by = 9223372036854775806
+    WHERE
+    uid IN (
+        SELECT
+            an1.uid
+        FROM
+            asset_names an1
+            LEFT JOIN asset_names an2 ON an1.superseded_by = an2.uid
+        WHERE
+            an1.superseded_by != 9223372036854775806
+            AND an2.uid IS NULL
+    );
+END;
+$$;
+
+
+CREATE OR REPLACE FUNCTION reopen_asset_descriptions() RETURNS VOID 
+    language plpgsql 
+AS $$
+BEGIN
+    UPDATE
+        asset_descriptions
+    SET
+        superseded_by = 9223372036854775806
+    WHERE
+    uid IN (
+        SELECT
+            ad1.uid
+        FROM
+            asset_descriptions ad1
+            LEFT JOIN asset_descriptions ad2 ON ad1.superseded_by = ad2.uid
+        WHERE
+            ad1.superseded_by != 9223372036854775806
+            AND ad2.uid IS NULL
+    );
+END;
+$$;
+
+
+CREATE OR REPLACE FUNCTION rollback_to(target_height INTEGER) RETURNS VOID 
+    language plpgsql 
+AS $$ 
+BEGIN
+    DELETE FROM blocks_microblocks WHERE height >= target_height;
+
+    EXECUTE reopen_assets();
+    EXECUTE reopen_asset_labels();
+    EXECUTE reopen_asset_tickers();
+    EXECUTE reopen_asset_names();
+    EXECUTE reopen_asset_descriptions();
+    EXECUTE reopen_data_entries();
+    EXECUTE reopen_issuer_balances();
+    EXECUTE reopen_out_leasings();
+END;
+$$;
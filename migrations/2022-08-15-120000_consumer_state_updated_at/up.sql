@@ -0,0 +1,2 @@
+-- heartbeat of the consumer, updated with the state after every handled batch
+ALTER TABLE consumer_state ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
@@ -0,0 +1,2 @@
+-- the original case of the labels is not kept
+SELECT 1;
@@ -0,0 +1,19 @@
+-- the labels of the data entries are trimmed, uppercased and unique since the labels are typed,
+-- the older rows are normalized the same way keeping the order of the first occurrence
+UPDATE asset_labels
+SET labels = ARRAY(
+    SELECT l.label
+    FROM (
+        SELECT UPPER(TRIM(label)) AS label, MIN(n) AS n
+        FROM UNNEST(asset_labels.labels) WITH ORDINALITY AS t (label, n)
+        WHERE TRIM(label) <> ''
+        GROUP BY UPPER(TRIM(label))
+    ) AS l
+    ORDER BY l.n
+)
+WHERE EXISTS (
+    SELECT 1
+    FROM UNNEST(asset_labels.labels) AS label
+    GROUP BY UPPER(TRIM(label))
+    HAVING COUNT(*) > 1 OR bool_or(label <> UPPER(TRIM(label))) OR UPPER(TRIM(label)) = ''
+);
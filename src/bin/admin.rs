@@ -4,7 +4,8 @@ use anyhow::Result;
 use app_lib::{
     admin, api_clients, async_redis,
     cache::{
-        self, ASSET_BLOCKCHAIN_DATA_KEY_PREFIX, ASSET_USER_DEFINED_DATA_KEY_PREFIX, KEY_SEPARATOR,
+        self, ASSET_BLOCKCHAIN_DATA_KEY_PREFIX, ASSET_BLOCKCHAIN_DATA_SCHEMA_VERSION,
+        ASSET_USER_DEFINED_DATA_KEY_PREFIX, ASSET_USER_DEFINED_DATA_SCHEMA_VERSION, KEY_SEPARATOR,
     },
     config, db,
 };
@@ -21,22 +22,37 @@ async fn main() -> Result<()> {
         redis_pool.clone(),
         ASSET_BLOCKCHAIN_DATA_KEY_PREFIX,
         KEY_SEPARATOR,
-    );
+        admin_config.redis.ttl,
+    )
+    .with_schema_version(ASSET_BLOCKCHAIN_DATA_SCHEMA_VERSION)
+    .with_mget_chunk_size(admin_config.redis.mget_chunk_size)
+    .with_codec(admin_config.redis.codec);
+
+    let invalidation_publisher = admin_config
+        .redis
+        .invalidation_channel
+        .as_ref()
+        .map(|channel| cache::invalidation_publisher::new_async(redis_pool.clone(), channel));
 
     let assets_user_defined_data_redis_cache = cache::async_redis_cache::new(
         redis_pool.clone(),
         ASSET_USER_DEFINED_DATA_KEY_PREFIX,
         KEY_SEPARATOR,
-    );
+        admin_config.redis.ttl,
+    )
+    .with_schema_version(ASSET_USER_DEFINED_DATA_SCHEMA_VERSION)
+    .with_mget_chunk_size(admin_config.redis.mget_chunk_size)
+    .with_codec(admin_config.redis.codec);
 
     let assets_service = {
-        let pg_repo = app_lib::services::assets::repo::pg::PgRepo::new(pg_pool.clone());
+        let pg_repo = app_lib::services::assets::repo::pg::PgRepo::new(pg_pool.clone())
+            .with_search_weights(admin_config.app.search_weights.clone());
 
         app_lib::services::assets::AssetsService::new(
             Arc::new(pg_repo),
             Box::new(assets_blockchain_data_cache.clone()),
             Box::new(assets_user_defined_data_redis_cache.clone()),
-            &admin_config.app.waves_association_address,
+            &admin_config.app.waves_association_addresses,
         )
     };
 
@@ -46,7 +62,11 @@ async fn main() -> Result<()> {
             redis_pool,
             ASSET_USER_DEFINED_DATA_KEY_PREFIX,
             KEY_SEPARATOR,
-        );
+            admin_config.redis.ttl,
+        )
+        .with_schema_version(ASSET_USER_DEFINED_DATA_SCHEMA_VERSION)
+        .with_mget_chunk_size(admin_config.redis.mget_chunk_size)
+        .with_codec(admin_config.redis.codec);
         app_lib::services::admin_assets::AdminAssetsService::new(
             Arc::new(pg_repo),
             Box::new(redis_cache),
@@ -68,6 +88,8 @@ async fn main() -> Result<()> {
             assets_blockchain_data_cache,
             assets_user_defined_data_redis_cache,
             api_key.clone(),
+            admin_config.admin.allowed_labels.clone(),
+            invalidation_publisher,
         )
         .await;
     } else {
@@ -87,6 +109,8 @@ async fn main() -> Result<()> {
             assets_blockchain_data_cache,
             assets_user_defined_data_redis_cache,
             api_key.clone(),
+            admin_config.admin.allowed_labels.clone(),
+            invalidation_publisher,
         )
         .await;
     }
@@ -1,43 +1,100 @@
 use anyhow::Result;
 use std::sync::Arc;
+use std::time::Duration;
 use wavesexchange_log::info;
 
 use app_lib::{
-    api::{self},
+    api::{self, rate_limit::RateLimiter},
     api_clients, async_redis,
     cache::{
-        self, ASSET_BLOCKCHAIN_DATA_KEY_PREFIX, ASSET_USER_DEFINED_DATA_KEY_PREFIX, KEY_SEPARATOR,
+        self, AssetBlockchainData, AssetUserDefinedData, ASSET_BLOCKCHAIN_DATA_KEY_PREFIX,
+        ASSET_BLOCKCHAIN_DATA_SCHEMA_VERSION, ASSET_USER_DEFINED_DATA_KEY_PREFIX,
+        ASSET_USER_DEFINED_DATA_SCHEMA_VERSION, KEY_SEPARATOR,
     },
-    config, db,
+    config, db, metrics,
 };
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let config = config::load_api_config().await?;
 
-    let pg_pool = db::pool(&config.postgres)?;
+    let pg_pool = db::read_pool(&config.postgres)?;
     let redis_pool = async_redis::pool(&config.redis).await?;
 
+    {
+        let pg_pool = pg_pool.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(5));
+            loop {
+                interval.tick().await;
+                metrics::observe_db_pool(&pg_pool);
+            }
+        });
+    }
+
     let assets_service = {
-        let pg_repo = app_lib::services::assets::repo::pg::PgRepo::new(pg_pool);
+        let pg_repo = app_lib::services::assets::repo::pg::PgRepo::new(pg_pool)
+            .with_search_weights(config.app.search_weights.clone())
+            .with_materialized_view(config.app.search_materialized_view);
         let assets_blockchain_data_redis_cache = cache::async_redis_cache::new(
             redis_pool.clone(),
             ASSET_BLOCKCHAIN_DATA_KEY_PREFIX,
             KEY_SEPARATOR,
-        );
+            config.redis.ttl,
+        )
+        .with_schema_version(ASSET_BLOCKCHAIN_DATA_SCHEMA_VERSION)
+        .with_mget_chunk_size(config.redis.mget_chunk_size)
+        .with_codec(config.redis.codec);
         let assets_user_defined_data_redis_cache = cache::async_redis_cache::new(
             redis_pool,
             ASSET_USER_DEFINED_DATA_KEY_PREFIX,
             KEY_SEPARATOR,
-        );
+            config.redis.ttl,
+        )
+        .with_schema_version(ASSET_USER_DEFINED_DATA_SCHEMA_VERSION)
+        .with_mget_chunk_size(config.redis.mget_chunk_size)
+        .with_codec(config.redis.codec);
+        let assets_blockchain_data_cache = cache::lru_mem::new::<AssetBlockchainData, _>(
+            assets_blockchain_data_redis_cache,
+            config.app.mem_cache_capacity,
+            config.app.mem_cache_ttl,
+        )
+        .with_max_idle(config.app.mem_cache_max_idle);
+        let assets_user_defined_data_cache = cache::lru_mem::new::<AssetUserDefinedData, _>(
+            assets_user_defined_data_redis_cache,
+            config.app.mem_cache_capacity,
+            config.app.mem_cache_ttl,
+        )
+        .with_max_idle(config.app.mem_cache_max_idle);
+
+        if let Some(channel) = config.redis.invalidation_channel.clone() {
+            let evictors: Vec<Arc<dyn cache::lru_mem::Evict>> = vec![
+                Arc::new(assets_blockchain_data_cache.evictor()),
+                Arc::new(assets_user_defined_data_cache.evictor()),
+            ];
+            let redis_client = async_redis::client(&config.redis)?;
+            tokio::spawn(cache::invalidation_listener::subscribe(
+                redis_client,
+                channel,
+                evictors,
+            ));
+        }
         app_lib::services::assets::AssetsService::new(
             Arc::new(pg_repo),
-            Box::new(assets_blockchain_data_redis_cache),
-            Box::new(assets_user_defined_data_redis_cache),
-            &config.app.waves_association_address,
+            Box::new(assets_blockchain_data_cache),
+            Box::new(assets_user_defined_data_cache),
+            &config.app.waves_association_addresses,
         )
+        .with_cache_fallback_to_db(config.api.cache_fallback_to_db)
     };
 
+    let rate_limiter = config.api.rate_limit_per_second.map(|per_second| {
+        RateLimiter::new(
+            per_second,
+            config.api.rate_limit_burst.unwrap_or(per_second),
+        )
+    });
+
     let port = config.api.port;
     let metrics_port = config.api.metrics_port;
 
@@ -48,15 +105,40 @@ async fn main() -> Result<()> {
             metrics_port,
             assets_service,
             app_lib::services::images::dummy::DummyService::new(),
+            config.api.debug_absence_reasons,
+            config.api.max_limit,
+            config.api.consumer_liveness_window,
+            config.api.chain_id,
+            rate_limiter,
+            config.api.trust_forwarded_for,
+            config.app.label_display_names.clone(),
         )
         .await;
     } else {
         let images_service = {
             let images_api_client = api_clients::HttpClient::new(&config.api.image_service_url)?
                 .with_user_agent("Asset search Service");
-            app_lib::services::images::http::HttpService::new(images_api_client)
+            let images_service =
+                app_lib::services::images::http::HttpService::new(images_api_client);
+            match config.api.image_cache_ttl {
+                Some(ttl) => images_service.with_cache(cache::lru_mem::DEFAULT_CAPACITY, ttl),
+                None => images_service,
+            }
         };
-        api::server::start(port, metrics_port, assets_service, images_service).await;
+        api::server::start(
+            port,
+            metrics_port,
+            assets_service,
+            images_service,
+            config.api.debug_absence_reasons,
+            config.api.max_limit,
+            config.api.consumer_liveness_window,
+            config.api.chain_id,
+            rate_limiter,
+            config.api.trust_forwarded_for,
+            config.app.label_display_names.clone(),
+        )
+        .await;
     }
 
     Ok(())
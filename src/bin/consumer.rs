@@ -1,12 +1,16 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use app_lib::{
+    api_clients,
     cache::{
-        self, ASSET_BLOCKCHAIN_DATA_KEY_PREFIX, ASSET_USER_DEFINED_DATA_KEY_PREFIX, KEY_SEPARATOR,
+        self, invalidation_publisher::InvalidationPublisher, ASSET_BLOCKCHAIN_DATA_KEY_PREFIX,
+        ASSET_BLOCKCHAIN_DATA_SCHEMA_VERSION, ASSET_USER_DEFINED_DATA_KEY_PREFIX,
+        ASSET_USER_DEFINED_DATA_SCHEMA_VERSION, KEY_SEPARATOR,
     },
-    config, consumer, db, sync_redis,
+    config, consumer, db, metrics, sync_redis,
 };
 use std::sync::Arc;
-use tokio::select;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::{select, sync::watch};
 use wavesexchange_log::{error, info};
 use wavesexchange_warp::MetricsWarpBuilder;
 
@@ -19,9 +23,9 @@ async fn main() -> Result<()> {
         config.consumer
     );
 
-    let conn = db::unpooled(&config.postgres)?;
+    let rollback_height = parse_rollback_height(std::env::args().skip(1))?;
 
-    let updates_src = consumer::updates::new(&config.consumer.blockchain_updates_url).await?;
+    let conn = db::unpooled(&config.postgres)?;
 
     let pg_repo = Arc::new(consumer::repo::pg::new(conn));
 
@@ -31,13 +35,101 @@ async fn main() -> Result<()> {
         redis_pool.clone(),
         ASSET_BLOCKCHAIN_DATA_KEY_PREFIX,
         KEY_SEPARATOR,
-    );
+        config.redis.ttl,
+    )
+    .with_schema_version(ASSET_BLOCKCHAIN_DATA_SCHEMA_VERSION)
+    .with_codec(config.redis.codec);
     let user_defined_data_cache = cache::sync_redis_cache::new(
-        redis_pool,
+        redis_pool.clone(),
         ASSET_USER_DEFINED_DATA_KEY_PREFIX,
         KEY_SEPARATOR,
+        config.redis.ttl,
+    )
+    .with_schema_version(ASSET_USER_DEFINED_DATA_SCHEMA_VERSION)
+    .with_codec(config.redis.codec);
+
+    if let Some(height) = rollback_height {
+        let summary = consumer::rollback_to_height(
+            pg_repo,
+            blockchain_data_cache,
+            user_defined_data_cache,
+            &config.consumer.waves_association_addresses,
+            height,
+        )?;
+
+        println!("Rolled back the blocks from height {}", height);
+        println!("affected_assets: {}", summary.affected_assets);
+        for (table, deleted) in summary.deleted_rows {
+            println!("{}: {} rows deleted", table, deleted);
+        }
+
+        if let Some(channel) = config.redis.invalidation_channel.as_ref() {
+            cache::invalidation_publisher::new(redis_pool, channel)
+                .publish(&summary.invalidated_asset_ids)?;
+        }
+        return Ok(());
+    }
+
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("shutdown signal received");
+        let _ = shutdown_tx.send(true);
+    });
+
+    let updates_src = consumer::updates::AdaptiveBatchesSource::new(
+        consumer::updates::new(&config.consumer.blockchain_updates_url).await?,
+        config.consumer.adaptive_batch_size,
     );
 
+    let checkpoint = if config.consumer.redis_checkpoint {
+        Some(consumer::checkpoint::new(
+            redis_pool.clone(),
+            consumer::checkpoint::key(config.consumer.chain_id),
+        ))
+    } else {
+        None
+    };
+
+    let invalidation_publisher = config
+        .redis
+        .invalidation_channel
+        .as_ref()
+        .map(|channel| cache::invalidation_publisher::new(redis_pool, channel));
+
+    let progress = Arc::new(consumer::health::Progress::default());
+
+    let health = {
+        let progress = progress.clone();
+        let node_client = config
+            .consumer
+            .node_url
+            .as_ref()
+            .map(|node_url| {
+                api_clients::HttpClient::new(node_url)
+                    .map(|client| client.with_user_agent("Asset search Service"))
+            })
+            .transpose()?;
+        let health_port = config.consumer.health_port;
+        let max_handled_lag = config.consumer.max_handled_lag;
+        let liveness_max_idle = config.consumer.liveness_max_idle;
+        async move {
+            match node_client {
+                Some(node_client) => {
+                    consumer::health::start(
+                        health_port,
+                        progress,
+                        node_client,
+                        max_handled_lag,
+                        liveness_max_idle,
+                    )
+                    .await
+                }
+                None => futures::future::pending::<()>().await,
+            }
+        }
+    };
+
     let consumer = consumer::start(
         config.consumer.starting_height,
         updates_src,
@@ -47,22 +139,73 @@ async fn main() -> Result<()> {
         config.consumer.updates_per_request,
         config.consumer.max_wait_time_in_secs,
         config.consumer.chain_id,
-        &config.consumer.waves_association_address,
+        &config.consumer.waves_association_addresses,
+        config.consumer.empty_ticker_as_deleted,
+        &config.consumer.debug_asset_ids,
+        config.consumer.searchable_assets_refresh_interval,
+        &config.consumer.stream_reconnect,
+        invalidation_publisher,
+        checkpoint,
+        progress,
+        shutdown_rx,
     );
 
-    let metrics = MetricsWarpBuilder::new()
-        .with_metrics_port(config.consumer.metrics_port)
-        .run_async();
+    let metrics = async {
+        if config.consumer.metrics_enabled {
+            MetricsWarpBuilder::new()
+                .with_metrics_port(config.consumer.metrics_port)
+                .with_metric(&*metrics::LAST_HANDLED_HEIGHT)
+                .with_metric(&*metrics::UPDATES_PER_BATCH)
+                .with_metric(&*metrics::UPDATES_PROCESSED)
+                .with_metric(&*metrics::BATCH_HANDLE_DURATION)
+                .with_metric(&*metrics::LAST_HANDLED_BLOCK_TIMESTAMP)
+                .with_metric(&*metrics::ROLLBACKS)
+                .with_metric(&*metrics::CACHE_SET_FAILURES)
+                .run_async()
+                .await;
+        } else {
+            futures::future::pending::<()>().await;
+        }
+    };
 
     select! {
-        Err(err) = consumer =>
-        {
-            error!("{}", err);
-            panic!("asset-search consumer panic: {}", err);
+        result = consumer => match result {
+            Ok(()) => info!("asset-search consumer stopped"),
+            Err(err) => {
+                error!("{}", err);
+                panic!("asset-search consumer panic: {}", err);
+            }
         },
         _ = metrics => {
             error!("metrics server stopped")
         }
+        _ = health => {
+            error!("health server stopped")
+        }
     }
     Ok(())
 }
+
+/// Height of `rollback --height N`, none without arguments
+fn parse_rollback_height(mut args: impl Iterator<Item = String>) -> Result<Option<i32>> {
+    match args.next().as_deref() {
+        None => Ok(None),
+        Some("rollback") => match (args.next().as_deref(), args.next()) {
+            (Some("--height"), Some(height)) => match height.parse() {
+                Ok(height) => Ok(Some(height)),
+                Err(_) => bail!("invalid height: {}", height),
+            },
+            _ => bail!("usage: consumer rollback --height N"),
+        },
+        Some(command) => bail!("unknown command: {}", command),
+    }
+}
+
+/// SIGTERM sent by the orchestrator or Ctrl+C
+async fn shutdown_signal() {
+    let mut sigterm = signal(SignalKind::terminate()).expect("cannot listen to SIGTERM");
+    select! {
+        _ = sigterm.recv() => {},
+        _ = tokio::signal::ctrl_c() => {},
+    }
+}
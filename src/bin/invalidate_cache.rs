@@ -5,7 +5,8 @@ use anyhow::Result;
 use app_lib::{
     async_redis,
     cache::{
-        self, ASSET_BLOCKCHAIN_DATA_KEY_PREFIX, ASSET_USER_DEFINED_DATA_KEY_PREFIX, KEY_SEPARATOR,
+        self, ASSET_BLOCKCHAIN_DATA_KEY_PREFIX, ASSET_BLOCKCHAIN_DATA_SCHEMA_VERSION,
+        ASSET_USER_DEFINED_DATA_KEY_PREFIX, ASSET_USER_DEFINED_DATA_SCHEMA_VERSION, KEY_SEPARATOR,
     },
     config, db,
 };
@@ -19,7 +20,8 @@ async fn main() -> Result<()> {
     let redis_pool = async_redis::pool(&config.redis).await?;
 
     let pg_repo = {
-        let r = app_lib::services::assets::repo::pg::PgRepo::new(pg_pool.clone());
+        let r = app_lib::services::assets::repo::pg::PgRepo::new(pg_pool.clone())
+            .with_search_weights(config.app.search_weights.clone());
         Arc::new(r)
     };
 
@@ -27,13 +29,21 @@ async fn main() -> Result<()> {
         redis_pool.clone(),
         ASSET_BLOCKCHAIN_DATA_KEY_PREFIX,
         KEY_SEPARATOR,
-    );
+        config.redis.ttl,
+    )
+    .with_schema_version(ASSET_BLOCKCHAIN_DATA_SCHEMA_VERSION)
+    .with_mget_chunk_size(config.redis.mget_chunk_size)
+    .with_codec(config.redis.codec);
 
     let assets_user_defined_data_redis_cache = cache::async_redis_cache::new(
         redis_pool.clone(),
         ASSET_USER_DEFINED_DATA_KEY_PREFIX,
         KEY_SEPARATOR,
-    );
+        config.redis.ttl,
+    )
+    .with_schema_version(ASSET_USER_DEFINED_DATA_SCHEMA_VERSION)
+    .with_mget_chunk_size(config.redis.mget_chunk_size)
+    .with_codec(config.redis.codec);
 
     info!(
         "starting cache invalidating, mode={:?}",
@@ -44,7 +54,7 @@ async fn main() -> Result<()> {
         pg_repo.clone(),
         Box::new(assets_blockchain_data_redis_cache.clone()),
         Box::new(assets_user_defined_data_redis_cache.clone()),
-        &config.app.waves_association_address,
+        &config.app.waves_association_addresses,
     );
 
     cache::invalidator::run(
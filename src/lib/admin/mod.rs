@@ -1,10 +1,95 @@
+pub mod openapi;
 pub mod server;
 
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 
+use self::openapi::{LabelCountSchema, LabelUpdateResultSchema};
 use crate::cache::InvalidateCacheMode;
+use crate::models::AssetLabel;
+use crate::services::admin_assets::{LabelCount, LabelUpdateResult};
 
 #[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "RawInvalidateCacheQueryParams")]
 pub struct InvalidateCacheQueryParams {
     pub mode: InvalidateCacheMode,
 }
+
+/// Flat form of the query since `ByIssuer` and `ByIds` can't be expressed as a single urlencoded value:
+/// `?mode=by_issuer&issuer=<address>`, `?mode=by_ids&ids=<id>&ids=<id>`
+#[derive(Deserialize, JsonSchema)]
+struct RawInvalidateCacheQueryParams {
+    /// `all_data`, `user_defined_data`, `by_issuer` or `by_ids`
+    mode: String,
+    /// Issuer address of the assets, with `mode=by_issuer`
+    issuer: Option<String>,
+    /// Asset ids, with `mode=by_ids`
+    #[serde(default)]
+    ids: Vec<String>,
+}
+
+impl TryFrom<RawInvalidateCacheQueryParams> for InvalidateCacheQueryParams {
+    type Error = String;
+
+    fn try_from(raw: RawInvalidateCacheQueryParams) -> Result<Self, Self::Error> {
+        let mode = InvalidateCacheMode::from_flat(&raw.mode, raw.issuer, raw.ids)?;
+
+        Ok(Self { mode })
+    }
+}
+
+/// `?ids=<id>&ids=<id>`, all assets are rebuilt without ids
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema)]
+pub struct RebuildUserDefinedDataQueryParams {
+    /// Asset ids, all assets are rebuilt without ids
+    #[serde(default)]
+    pub ids: Vec<String>,
+}
+
+#[derive(Clone, Debug, JsonSchema, Serialize)]
+pub struct RebuildUserDefinedDataResponse {
+    pub assets_count: usize,
+}
+
+#[derive(Clone, Debug, JsonSchema, Serialize)]
+pub struct LabelsResponse {
+    /// Allow-list, empty if any label is allowed
+    pub labels: Vec<String>,
+    /// Labels the assets carry
+    #[schemars(with = "Vec<LabelCountSchema>")]
+    pub counts: Vec<LabelCount>,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+pub struct LabelsBulkRequest {
+    #[schemars(with = "String")]
+    pub label: AssetLabel,
+    #[serde(default)]
+    pub add: Vec<String>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+/// Body of `POST /admin/labels/{label}`, the label being taken from the path
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+pub struct LabelAssetsRequest {
+    #[serde(default)]
+    pub add: Vec<String>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema)]
+pub struct LabelsBulkQueryParams {
+    /// Fail the whole request if any of the assets is missing
+    pub strict: Option<bool>,
+}
+
+#[derive(Clone, Debug, JsonSchema, Serialize)]
+pub struct LabelsBulkResponse {
+    #[schemars(with = "String")]
+    pub label: AssetLabel,
+    #[schemars(with = "Vec<LabelUpdateResultSchema>")]
+    pub results: Vec<LabelUpdateResult>,
+}
@@ -0,0 +1,224 @@
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use serde_json::{json, Value};
+
+use super::server::API_KEY_HEADER_NAME;
+use super::{
+    LabelAssetsRequest, LabelsBulkQueryParams, LabelsBulkRequest, LabelsBulkResponse,
+    LabelsResponse, RawInvalidateCacheQueryParams, RebuildUserDefinedDataQueryParams,
+    RebuildUserDefinedDataResponse,
+};
+use crate::api::models::Asset;
+use crate::api::openapi::{
+    error_response, error_schema, json_request, json_response, path_parameter, paths_object,
+    query_parameters, with_errors,
+};
+
+/// Schema of `crate::services::admin_assets::LabelUpdateStatus`
+#[derive(JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[schemars(rename = "LabelUpdateStatus")]
+pub enum LabelUpdateStatusSchema {
+    Added,
+    Removed,
+    NotFound,
+}
+
+/// Schema of `crate::services::admin_assets::LabelUpdateResult`
+#[derive(JsonSchema)]
+#[schemars(rename = "LabelUpdateResult")]
+pub struct LabelUpdateResultSchema {
+    pub id: String,
+    pub status: LabelUpdateStatusSchema,
+}
+
+/// Schema of `crate::services::admin_assets::LabelCount`
+#[derive(JsonSchema)]
+#[schemars(rename = "LabelCount")]
+pub struct LabelCountSchema {
+    pub label: String,
+    pub count: i64,
+}
+
+/// Builds the OpenAPI 3.0 document of the admin API from the request and response types
+pub fn document() -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+
+    let strict_parameters = query_parameters::<LabelsBulkQueryParams>(&mut gen);
+    let invalidate_parameters = query_parameters::<RawInvalidateCacheQueryParams>(&mut gen);
+    let rebuild_parameters = query_parameters::<RebuildUserDefinedDataQueryParams>(&mut gen);
+    let labels_bulk_request = gen.subschema_for::<LabelsBulkRequest>();
+    let label_assets_request = gen.subschema_for::<LabelAssetsRequest>();
+    let labels_bulk_response = gen.subschema_for::<LabelsBulkResponse>();
+    let labels_response = gen.subschema_for::<LabelsResponse>();
+    let rebuild_response = gen.subschema_for::<RebuildUserDefinedDataResponse>();
+    let asset = gen.subschema_for::<Asset>();
+
+    let api_key_parameter = json!({
+        "name": API_KEY_HEADER_NAME,
+        "in": "header",
+        "required": true,
+        "schema": { "type": "string" },
+    });
+    let with_api_key =
+        |parameters: Vec<Value>| [vec![api_key_parameter.clone()], parameters].concat();
+    let asset_label_parameters = with_api_key(vec![path_parameter("id"), path_parameter("label")]);
+    let label_assets_parameters =
+        with_api_key([vec![path_parameter("label")], strict_parameters.clone()].concat());
+
+    let mut schemas = serde_json::to_value(gen.take_definitions()).unwrap();
+    schemas["Error"] = error_schema();
+
+    let authorized = |responses: Value| {
+        let mut responses = with_errors(responses);
+        responses["401"] = error_response("Invalid API key");
+        responses
+    };
+
+    let paths = vec![
+        (
+            "/admin/asset/{id}/labels/{label}",
+            json!({
+                "post": {
+                    "summary": "Add the label to the asset",
+                    "parameters": asset_label_parameters.clone(),
+                    "responses": authorized(json!({
+                        "200": json_response("Labeled asset", &asset),
+                    })),
+                },
+                "delete": {
+                    "summary": "Remove the label from the asset",
+                    "parameters": asset_label_parameters,
+                    "responses": authorized(json!({
+                        "200": json_response("Asset without the label", &asset),
+                    })),
+                },
+            }),
+        ),
+        (
+            "/admin/assets/labels",
+            json!({
+                "post": {
+                    "summary": "Add the label to and remove it from the assets",
+                    "parameters": with_api_key(strict_parameters),
+                    "requestBody": json_request(&labels_bulk_request),
+                    "responses": authorized(json!({
+                        "200": json_response("Result of every asset", &labels_bulk_response),
+                    })),
+                },
+            }),
+        ),
+        (
+            "/admin/labels/{label}",
+            json!({
+                "post": {
+                    "summary": "Add the label of the path to and remove it from the assets",
+                    "parameters": label_assets_parameters,
+                    "requestBody": json_request(&label_assets_request),
+                    "responses": authorized(json!({
+                        "200": json_response("Result of every asset", &labels_bulk_response),
+                    })),
+                },
+            }),
+        ),
+        (
+            "/admin/labels",
+            json!({
+                "get": {
+                    "summary": "Get the allowed labels and the counts of the labeled assets",
+                    "parameters": with_api_key(vec![]),
+                    "responses": authorized(json!({
+                        "200": json_response("Labels", &labels_response),
+                    })),
+                },
+            }),
+        ),
+        (
+            "/admin/cache/invalidate",
+            json!({
+                "post": {
+                    "summary": "Recompute the cache of the assets",
+                    "parameters": with_api_key(invalidate_parameters),
+                    "responses": authorized(json!({
+                        "200": { "description": "Cache invalidated" },
+                    })),
+                },
+            }),
+        ),
+        (
+            "/admin/asset/{id}/cache/invalidate",
+            json!({
+                "post": {
+                    "summary": "Recompute the cache of the asset",
+                    "parameters": with_api_key(vec![path_parameter("id")]),
+                    "responses": authorized(json!({
+                        "200": { "description": "Cache invalidated" },
+                    })),
+                },
+            }),
+        ),
+        (
+            "/admin/cache/user-defined-data/rebuild",
+            json!({
+                "post": {
+                    "summary": "Rewrite the cached labels of the assets",
+                    "parameters": with_api_key(rebuild_parameters),
+                    "responses": authorized(json!({
+                        "200": json_response("Count of rewritten assets", &rebuild_response),
+                    })),
+                },
+            }),
+        ),
+    ];
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Asset search admin API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths_object(paths),
+        "components": { "schemas": schemas },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::document;
+    use crate::services::admin_assets::LabelUpdateStatus;
+
+    #[test]
+    fn should_describe_every_route() {
+        let doc = document();
+
+        for (path, method) in [
+            ("/admin/asset/{id}/labels/{label}", "post"),
+            ("/admin/asset/{id}/labels/{label}", "delete"),
+            ("/admin/assets/labels", "post"),
+            ("/admin/labels/{label}", "post"),
+            ("/admin/labels", "get"),
+            ("/admin/cache/invalidate", "post"),
+            ("/admin/asset/{id}/cache/invalidate", "post"),
+            ("/admin/cache/user-defined-data/rebuild", "post"),
+        ] {
+            let operation = &doc["paths"][path][method];
+            assert!(operation.is_object(), "missing {} {}", method, path);
+            assert_eq!(operation["parameters"][0]["name"], "X-Api-Key");
+            assert!(operation["responses"]["401"].is_object());
+        }
+
+        let schemas = &doc["components"]["schemas"];
+        assert!(schemas["LabelsBulkResponse"].is_object());
+        assert!(schemas["LabelUpdateResult"].is_object());
+        assert!(schemas["LabelCount"].is_object());
+
+        let statuses = schemas["LabelUpdateStatus"]["enum"].as_array().unwrap();
+        for status in [
+            LabelUpdateStatus::Added,
+            LabelUpdateStatus::Removed,
+            LabelUpdateStatus::NotFound,
+        ] {
+            assert!(statuses.contains(&serde_json::to_value(status).unwrap()));
+        }
+    }
+}
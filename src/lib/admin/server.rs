@@ -1,7 +1,8 @@
 use futures::TryFutureExt;
 use std::collections::HashMap;
+use std::convert::Infallible;
 use std::sync::Arc;
-use warp::{reject, Filter, Rejection};
+use warp::{reject, Filter, Rejection, Reply};
 use wavesexchange_log::{debug, error, info};
 use wavesexchange_warp::error::{
     authorization, error_handler_with_serde_qs, handler, internal, timeout, validation,
@@ -9,15 +10,24 @@ use wavesexchange_warp::error::{
 use wavesexchange_warp::log::access;
 use wavesexchange_warp::MetricsWarpBuilder;
 
-use super::InvalidateCacheQueryParams;
-use crate::api::{dtos::ResponseFormat, models::Asset};
+use super::openapi;
+use super::{
+    InvalidateCacheQueryParams, LabelAssetsRequest, LabelsBulkQueryParams, LabelsBulkRequest,
+    LabelsBulkResponse, LabelsResponse, RebuildUserDefinedDataQueryParams,
+    RebuildUserDefinedDataResponse,
+};
+use crate::api::dtos::{escape_querystring_field, ResponseFormat};
+use crate::api::json_charset;
+use crate::api::models::Asset;
+use crate::cache::invalidation_publisher::{AsyncRedisPublisher, EVICT_ALL};
 use crate::cache::{self, AssetBlockchainData, AssetUserDefinedData, InvalidateCacheMode};
 use crate::error;
+use crate::models::AssetLabel;
 use crate::services;
 use crate::services::assets::GetOptions;
 
 const ERROR_CODES_PREFIX: u16 = 95;
-const API_KEY_HEADER_NAME: &str = "X-Api-Key";
+pub(super) const API_KEY_HEADER_NAME: &str = "X-Api-Key";
 const DEFAULT_INCLUDE_METADATA: bool = true;
 const DEFAULT_FORMAT: ResponseFormat = ResponseFormat::Full;
 
@@ -36,7 +46,50 @@ pub async fn start(
         + Sync
         + 'static,
     api_key: String,
+    allowed_labels: Vec<String>,
+    invalidation_publisher: Option<AsyncRedisPublisher>,
 ) {
+    let log = warp::log::custom(access);
+
+    info!("Starting API server at 0.0.0.0:{}", port);
+
+    let routes = routes(
+        assets_service,
+        images_service,
+        admin_assets_service,
+        assets_blockchain_data_redis_cache,
+        assets_user_defined_data_redis_cache,
+        api_key,
+        allowed_labels,
+        invalidation_publisher,
+    )
+    .with(log);
+
+    MetricsWarpBuilder::new()
+        .with_main_routes(routes)
+        .with_main_routes_port(port)
+        .with_metrics_port(metrics_port)
+        .run_async()
+        .await;
+}
+
+/// Admin routes along with the handling of their errors
+fn routes(
+    assets_service: impl services::assets::Service + Send + Sync + 'static,
+    images_service: impl services::images::Service + Send + Sync + 'static,
+    admin_assets_service: impl services::admin_assets::Service + Send + Sync + 'static,
+    assets_blockchain_data_redis_cache: impl cache::AsyncWriteCache<AssetBlockchainData>
+        + Send
+        + Sync
+        + 'static,
+    assets_user_defined_data_redis_cache: impl cache::AsyncWriteCache<AssetUserDefinedData>
+        + Send
+        + Sync
+        + 'static,
+    api_key: String,
+    allowed_labels: Vec<String>,
+    invalidation_publisher: Option<AsyncRedisPublisher>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     let with_assets_service = {
         let assets_service = Arc::new(assets_service);
         warp::any().map(move || assets_service.clone())
@@ -64,6 +117,13 @@ pub async fn start(
 
     let with_api_key = warp::any().map(move || api_key.to_owned());
 
+    let with_invalidation_publisher = warp::any().map(move || invalidation_publisher.clone());
+
+    let with_allowed_labels = {
+        let allowed_labels = Arc::new(allowed_labels);
+        warp::any().map(move || allowed_labels.clone())
+    };
+
     let error_handler = handler(ERROR_CODES_PREFIX, |err| match err {
         error::Error::ValidationError(_error_message, error_details) => {
             validation::invalid_parameter(
@@ -94,6 +154,7 @@ pub async fn start(
         .and(with_assets_service.clone())
         .and(with_images_service.clone())
         .and(with_admin_assets_service.clone())
+        .and(with_allowed_labels.clone())
         .and_then(
             |asset_id: String,
              label: String,
@@ -101,7 +162,8 @@ pub async fn start(
              provided_api_key: String,
              assets_service,
              images_service,
-             admin_assets_service| async move {
+             admin_assets_service,
+             allowed_labels| async move {
                 api_key_validation(&expected_api_key, &provided_api_key)
                     .and_then(|_| {
                         asset_add_label_controller(
@@ -110,6 +172,7 @@ pub async fn start(
                             assets_service,
                             images_service,
                             admin_assets_service,
+                            allowed_labels,
                         )
                     })
                     .await
@@ -147,21 +210,114 @@ pub async fn start(
         )
         .map(|res| warp::reply::json(&res));
 
+    let assets_labels_bulk_handler = warp::post()
+        .and(warp::path!("admin" / "assets" / "labels"))
+        .and(warp::query::<LabelsBulkQueryParams>())
+        .and(warp::body::json::<LabelsBulkRequest>())
+        .and(with_api_key.clone())
+        .and(warp::header::<String>(API_KEY_HEADER_NAME))
+        .and(with_admin_assets_service.clone())
+        .and(with_allowed_labels.clone())
+        .and_then(
+            |query: LabelsBulkQueryParams,
+             req: LabelsBulkRequest,
+             expected_api_key: String,
+             provided_api_key: String,
+             admin_assets_service,
+             allowed_labels| async move {
+                api_key_validation(&expected_api_key, &provided_api_key)
+                    .and_then(|_| {
+                        assets_labels_bulk_controller(
+                            req,
+                            query.strict.unwrap_or(false),
+                            admin_assets_service,
+                            allowed_labels,
+                        )
+                    })
+                    .await
+            },
+        )
+        .map(|res| warp::reply::json(&res));
+
+    let label_assets_handler = warp::post()
+        .and(warp::path!("admin" / "labels" / String))
+        .and(warp::query::<LabelsBulkQueryParams>())
+        .and(warp::body::json::<LabelAssetsRequest>())
+        .and(with_api_key.clone())
+        .and(warp::header::<String>(API_KEY_HEADER_NAME))
+        .and(with_admin_assets_service.clone())
+        .and(with_allowed_labels.clone())
+        .and_then(
+            |label: String,
+             query: LabelsBulkQueryParams,
+             req: LabelAssetsRequest,
+             expected_api_key: String,
+             provided_api_key: String,
+             admin_assets_service,
+             allowed_labels| async move {
+                let req = LabelsBulkRequest {
+                    label: AssetLabel::from(label),
+                    add: req.add,
+                    remove: req.remove,
+                };
+                api_key_validation(&expected_api_key, &provided_api_key)
+                    .and_then(|_| {
+                        assets_labels_bulk_controller(
+                            req,
+                            query.strict.unwrap_or(false),
+                            admin_assets_service,
+                            allowed_labels,
+                        )
+                    })
+                    .await
+            },
+        )
+        .map(|res| warp::reply::json(&res));
+
+    let labels_handler = warp::get()
+        .and(warp::path!("admin" / "labels"))
+        .and(with_api_key.clone())
+        .and(warp::header::<String>(API_KEY_HEADER_NAME))
+        .and(with_admin_assets_service.clone())
+        .and(with_allowed_labels.clone())
+        .and_then(
+            |expected_api_key: String,
+             provided_api_key: String,
+             admin_assets_service,
+             allowed_labels| async move {
+                api_key_validation(&expected_api_key, &provided_api_key)
+                    .and_then(|_| labels_controller(admin_assets_service, allowed_labels))
+                    .await
+            },
+        )
+        .map(|res| warp::reply::json(&res));
+
     let cache_invalidate_handler = warp::post()
         .and(warp::path!("admin" / "cache" / "invalidate"))
-        .and(warp::query::<InvalidateCacheQueryParams>())
+        .and(
+            warp::query::raw()
+                .or_else(|_rej| futures::future::ok::<(String,), Infallible>(("".to_owned(),)))
+                .and_then(|qs: String| async move {
+                    let qs = escape_querystring_field(&qs, "ids");
+                    serde_qs::Config::new(5, false)
+                        .deserialize_str::<InvalidateCacheQueryParams>(&qs)
+                        .map_err(warp::reject::custom)
+                }),
+        )
         .and(with_api_key.clone())
         .and(warp::header::<String>(API_KEY_HEADER_NAME))
         .and(with_assets_service.clone())
         .and(with_assets_blockchain_data_redis_cache.clone())
         .and(with_assets_user_defined_data_redis_cache.clone())
+        .and(with_invalidation_publisher.clone())
         .and_then(
             |query: InvalidateCacheQueryParams,
              expected_api_key: String,
              provided_api_key: String,
              assets_service,
              assets_blockchain_data_redis_cache,
-             assets_user_defined_data_redis_cache| async move {
+             assets_user_defined_data_redis_cache,
+             invalidation_publisher| async move {
                 api_key_validation(&expected_api_key, &provided_api_key)
                     .and_then(|_| {
                         cache_invalidate_controller(
@@ -171,30 +327,126 @@ pub async fn start(
                             assets_user_defined_data_redis_cache,
                         )
                     })
-                    .await
+                    .await?;
+                let evicted = match query.mode {
+                    InvalidateCacheMode::ByIds(asset_ids) => asset_ids,
+                    _ => vec![EVICT_ALL.to_owned()],
+                };
+                publish_invalidation(invalidation_publisher, &evicted).await;
+                Ok::<_, Rejection>(())
             },
         )
         .map(|res| warp::reply::json(&res));
 
-    let log = warp::log::custom(access);
+    let asset_cache_invalidate_handler = warp::post()
+        .and(warp::path!(
+            "admin" / "asset" / String / "cache" / "invalidate"
+        ))
+        .and(with_api_key.clone())
+        .and(warp::header::<String>(API_KEY_HEADER_NAME))
+        .and(with_assets_service.clone())
+        .and(with_assets_blockchain_data_redis_cache.clone())
+        .and(with_assets_user_defined_data_redis_cache.clone())
+        .and(with_invalidation_publisher.clone())
+        .and_then(
+            |asset_id: String,
+             expected_api_key: String,
+             provided_api_key: String,
+             assets_service,
+             assets_blockchain_data_redis_cache,
+             assets_user_defined_data_redis_cache,
+             invalidation_publisher| async move {
+                api_key_validation(&expected_api_key, &provided_api_key)
+                    .and_then(|_| {
+                        asset_cache_invalidate_controller(
+                            asset_id.clone(),
+                            assets_service,
+                            assets_blockchain_data_redis_cache,
+                            assets_user_defined_data_redis_cache,
+                        )
+                    })
+                    .await?;
+                publish_invalidation(invalidation_publisher, &[asset_id]).await;
+                Ok::<_, Rejection>(())
+            },
+        )
+        .map(|res| warp::reply::json(&res));
 
-    info!("Starting API server at 0.0.0.0:{}", port);
+    let user_defined_data_rebuild_handler = warp::post()
+        .and(warp::path!(
+            "admin" / "cache" / "user-defined-data" / "rebuild"
+        ))
+        .and(
+            warp::query::raw()
+                .or_else(|_rej| futures::future::ok::<(String,), Infallible>(("".to_owned(),)))
+                .and_then(|qs: String| async move {
+                    let qs = escape_querystring_field(&qs, "ids");
+                    serde_qs::Config::new(5, false)
+                        .deserialize_str::<RebuildUserDefinedDataQueryParams>(&qs)
+                        .map_err(warp::reject::custom)
+                }),
+        )
+        .and(with_api_key.clone())
+        .and(warp::header::<String>(API_KEY_HEADER_NAME))
+        .and(with_assets_service.clone())
+        .and(with_assets_user_defined_data_redis_cache.clone())
+        .and(with_invalidation_publisher.clone())
+        .and_then(
+            |query: RebuildUserDefinedDataQueryParams,
+             expected_api_key: String,
+             provided_api_key: String,
+             assets_service,
+             assets_user_defined_data_redis_cache,
+             invalidation_publisher| async move {
+                let res = api_key_validation(&expected_api_key, &provided_api_key)
+                    .and_then(|_| {
+                        user_defined_data_rebuild_controller(
+                            &query.ids,
+                            assets_service,
+                            assets_user_defined_data_redis_cache,
+                        )
+                    })
+                    .await?;
+                let evicted = if query.ids.is_empty() {
+                    vec![EVICT_ALL.to_owned()]
+                } else {
+                    query.ids
+                };
+                publish_invalidation(invalidation_publisher, &evicted).await;
+                Ok::<_, Rejection>(res)
+            },
+        )
+        .map(|res| warp::reply::json(&res));
+
+    let openapi_handler = {
+        let document = openapi::document();
+        warp::path!("openapi.json")
+            .and(warp::get())
+            .map(move || warp::reply::json(&document))
+    };
 
-    let routes = asset_add_label_handler
+    let docs_handler = {
+        let html = crate::api::openapi::docs_html("Asset search admin API");
+        warp::path!("docs")
+            .and(warp::get())
+            .map(move || warp::reply::html(html.clone()))
+    };
+
+    asset_add_label_handler
         .or(asset_delete_label_handler)
+        .or(assets_labels_bulk_handler)
+        .or(label_assets_handler)
+        .or(labels_handler)
         .or(cache_invalidate_handler)
+        .or(asset_cache_invalidate_handler)
+        .or(user_defined_data_rebuild_handler)
+        .or(openapi_handler)
+        .or(docs_handler)
         .recover(move |rej| {
             error!("rej: {:?}", rej);
             error_handler_with_serde_qs(ERROR_CODES_PREFIX, error_handler.clone())(rej)
         })
-        .with(log);
-
-    MetricsWarpBuilder::new()
-        .with_main_routes(routes)
-        .with_main_routes_port(port)
-        .with_metrics_port(metrics_port)
-        .run_async()
-        .await;
+        .map(json_charset)
 }
 
 async fn asset_add_label_controller(
@@ -203,19 +455,23 @@ async fn asset_add_label_controller(
     assets_service: Arc<impl services::assets::Service>,
     images_service: Arc<impl services::images::Service>,
     admin_assets_service: Arc<impl services::admin_assets::Service>,
+    allowed_labels: Arc<Vec<String>>,
 ) -> Result<Asset, Rejection> {
     debug!("asset_add_label_controller"; "asset_id" => &asset_id, "label" => &label);
 
+    let label = AssetLabel::from(label);
+    label_validation(&allowed_labels, &label)?;
+
     admin_assets_service.add_label(&asset_id, &label).await?;
 
     let maybe_asset_info = assets_service
         .get(&asset_id, &GetOptions::default())
         .await?;
-    let has_image = images_service.has_image(&asset_id).await?;
+    let image_url = images_service.get_image_url(&asset_id).await?;
 
     Ok(Asset::new(
         maybe_asset_info,
-        has_image,
+        image_url,
         DEFAULT_INCLUDE_METADATA,
         &DEFAULT_FORMAT,
     ))
@@ -230,21 +486,58 @@ async fn asset_delete_label_controller(
 ) -> Result<Asset, Rejection> {
     debug!("asset_delete_label_controller"; "asset_id" => &asset_id, "label" => &label);
 
+    let label = AssetLabel::from(label);
     admin_assets_service.delete_label(&asset_id, &label).await?;
 
     let maybe_asset_info = assets_service
         .get(&asset_id, &GetOptions::default())
         .await?;
-    let has_image = images_service.has_image(&asset_id).await?;
+    let image_url = images_service.get_image_url(&asset_id).await?;
 
     Ok(Asset::new(
         maybe_asset_info,
-        has_image,
+        image_url,
         DEFAULT_INCLUDE_METADATA,
         &DEFAULT_FORMAT,
     ))
 }
 
+async fn assets_labels_bulk_controller(
+    req: LabelsBulkRequest,
+    strict: bool,
+    admin_assets_service: Arc<impl services::admin_assets::Service>,
+    allowed_labels: Arc<Vec<String>>,
+) -> Result<LabelsBulkResponse, Rejection> {
+    debug!("assets_labels_bulk_controller"; "label" => req.label.as_str(), "strict" => strict);
+
+    if !req.add.is_empty() {
+        label_validation(&allowed_labels, &req.label)?;
+    }
+
+    let results = admin_assets_service
+        .update_label_bulk(&req.label, &req.add, &req.remove, strict)
+        .await?;
+
+    Ok(LabelsBulkResponse {
+        label: req.label,
+        results,
+    })
+}
+
+async fn labels_controller(
+    admin_assets_service: Arc<impl services::admin_assets::Service>,
+    allowed_labels: Arc<Vec<String>>,
+) -> Result<LabelsResponse, Rejection> {
+    debug!("labels_controller");
+
+    let counts = admin_assets_service.label_counts().await?;
+
+    Ok(LabelsResponse {
+        labels: allowed_labels.as_ref().clone(),
+        counts,
+    })
+}
+
 async fn cache_invalidate_controller<S, BDC, UDDC>(
     invalidate_cache_mode: &InvalidateCacheMode,
     assets_service: Arc<S>,
@@ -270,6 +563,96 @@ where
     Ok(())
 }
 
+async fn asset_cache_invalidate_controller<S, BDC, UDDC>(
+    asset_id: String,
+    assets_service: Arc<S>,
+    assets_blockchain_data_redis_cache: Arc<BDC>,
+    assets_user_defined_data_redis_cache: Arc<UDDC>,
+) -> Result<(), Rejection>
+where
+    S: services::assets::Service,
+    BDC: cache::AsyncWriteCache<AssetBlockchainData>,
+    UDDC: cache::AsyncWriteCache<AssetUserDefinedData>,
+{
+    debug!("asset_cache_invalidate_controller"; "asset_id" => &asset_id);
+
+    let found = crate::cache::invalidator::invalidate_asset(
+        assets_service,
+        assets_blockchain_data_redis_cache,
+        assets_user_defined_data_redis_cache,
+        &asset_id,
+    )
+    .await
+    .map_err(|e| error::Error::InvalidateCacheError(e.to_string()))?;
+
+    if !found {
+        info!("cache of absent asset {} was deleted", asset_id);
+    }
+
+    Ok(())
+}
+
+/// Rebuilds the user defined data cache of the given assets, of all assets if none are given
+async fn user_defined_data_rebuild_controller<S, UDDC>(
+    asset_ids: &[String],
+    assets_service: Arc<S>,
+    assets_user_defined_data_redis_cache: Arc<UDDC>,
+) -> Result<RebuildUserDefinedDataResponse, Rejection>
+where
+    S: services::assets::Service,
+    UDDC: cache::AsyncWriteCache<AssetUserDefinedData>,
+{
+    debug!("user_defined_data_rebuild_controller"; "assets count" => asset_ids.len());
+
+    let asset_ids = if asset_ids.is_empty() {
+        None
+    } else {
+        Some(asset_ids)
+    };
+
+    let assets_count = crate::cache::invalidator::rebuild_user_defined_data(
+        assets_service,
+        assets_user_defined_data_redis_cache,
+        asset_ids,
+    )
+    .await
+    .map_err(|e| error::Error::InvalidateCacheError(e.to_string()))?;
+
+    Ok(RebuildUserDefinedDataResponse { assets_count })
+}
+
+/// Evicts the assets from the in-memory caches of the api instances,
+/// failures are logged only since the entries expire anyway
+async fn publish_invalidation(publisher: Option<AsyncRedisPublisher>, asset_ids: &[String]) {
+    if let Some(publisher) = publisher {
+        if let Err(e) = publisher.publish(asset_ids).await {
+            error!("failed to publish cache invalidation: {}", e);
+        }
+    }
+}
+
+/// Rejects labels missing in the allow-list unless it is empty
+fn label_validation(allowed_labels: &[String], label: &AssetLabel) -> Result<(), error::Error> {
+    if allowed_labels.is_empty()
+        || allowed_labels
+            .iter()
+            .any(|l| AssetLabel::from(l.as_str()) == *label)
+    {
+        return Ok(());
+    }
+
+    let details = vec![
+        ("reason".to_owned(), format!("unknown label {}", label)),
+        ("allowed_values".to_owned(), allowed_labels.join(",")),
+    ]
+    .into_iter()
+    .collect::<HashMap<_, _>>();
+    Err(error::Error::ValidationError(
+        "label".to_owned(),
+        Some(details),
+    ))
+}
+
 async fn api_key_validation(expected: &str, provided: &str) -> Result<(), Rejection> {
     if expected == provided {
         Ok(())
@@ -279,3 +662,393 @@ async fn api_key_validation(expected: &str, provided: &str) -> Result<(), Reject
         )))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use warp::http::StatusCode;
+    use warp::test::RequestBuilder;
+    use warp::{Filter, Rejection, Reply};
+
+    use super::{
+        api_key_validation, asset_cache_invalidate_controller, cache_invalidate_controller,
+        label_validation, routes, user_defined_data_rebuild_controller, API_KEY_HEADER_NAME,
+    };
+    use crate::admin::InvalidateCacheQueryParams;
+    use crate::cache::{
+        memory_cache::MemoryCache, AssetBlockchainData, AssetUserDefinedData, InvalidateCacheMode,
+    };
+    use crate::error::Error as AppError;
+    use crate::models::{Asset, AssetInfo, AssetLabel, AssetMetadata};
+    use crate::services::admin_assets::{
+        LabelCount, LabelUpdateResult, LabelUpdateStatus, Service as AdminAssetsService,
+    };
+    use crate::services::assets::mock::MockService;
+    use crate::services::images::dummy::DummyService;
+
+    fn asset_info(id: &str, name: &str, labels: &[&str]) -> AssetInfo {
+        AssetInfo {
+            asset: Asset {
+                id: id.to_owned(),
+                name: name.to_owned(),
+                precision: 8,
+                description: "".to_owned(),
+                height: 1,
+                timestamp: Utc::now(),
+                issuer: "3PC4roN512iugc6xGVTTM2XkoWKEdSiiscd".to_owned(),
+                quantity: 100,
+                reissuable: false,
+                min_sponsored_fee: None,
+                smart: false,
+                nft: false,
+                ticker: None,
+            },
+            metadata: AssetMetadata {
+                labels: labels.iter().map(|l| AssetLabel::from(*l)).collect(),
+                sponsor_balance: None,
+                oracles_data: HashMap::new(),
+            },
+        }
+    }
+
+    fn user_defined_data(id: &str, labels: &[&str]) -> AssetUserDefinedData {
+        AssetUserDefinedData {
+            asset_id: id.to_owned(),
+            labels: labels.iter().map(|l| AssetLabel::from(*l)).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_invalidate_single_asset_cache() {
+        let service = Arc::new(MockService::new(vec![asset_info("A", "fixed", &["DEFI"])]));
+        let stale_a = AssetBlockchainData::from(&asset_info("A", "stale", &[]));
+        let stale_b = AssetBlockchainData::from(&asset_info("B", "vanished", &[]));
+        let blockchain_data_cache = Arc::new(MemoryCache::new(vec![
+            ("A", stale_a),
+            ("B", stale_b.clone()),
+            ("C", stale_b),
+        ]));
+        let user_defined_data_cache = Arc::new(MemoryCache::new(vec![
+            ("A", user_defined_data("A", &[])),
+            ("B", user_defined_data("B", &["GATEWAY"])),
+        ]));
+
+        for id in ["A", "B"] {
+            asset_cache_invalidate_controller(
+                id.to_owned(),
+                service.clone(),
+                blockchain_data_cache.clone(),
+                user_defined_data_cache.clone(),
+            )
+            .await
+            .unwrap();
+        }
+
+        let blockchain_data = blockchain_data_cache.values();
+        assert_eq!(blockchain_data["A"].name, "fixed");
+        assert!(!blockchain_data.contains_key("B"));
+        // other assets are kept as is
+        assert_eq!(blockchain_data["C"].name, "vanished");
+
+        let user_defined_data = user_defined_data_cache.values();
+        assert_eq!(user_defined_data["A"].labels, vec![AssetLabel::DeFi]);
+        assert!(!user_defined_data.contains_key("B"));
+    }
+
+    #[tokio::test]
+    async fn should_invalidate_issuer_assets_cache_only() {
+        let issuer_a = "3PC4roN512iugc6xGVTTM2XkoWKEdSiiscd";
+        let issuer_b = "3PEgG7eZHLFhcfsTSaYxgRhZsh4AxMvA4Ms";
+        let mut b = asset_info("B", "fixed", &["DEFI"]);
+        b.asset.issuer = issuer_b.to_owned();
+        let service = Arc::new(MockService::new(vec![
+            asset_info("A", "fixed", &["DEFI"]),
+            b,
+        ]));
+        let stale = AssetBlockchainData::from(&asset_info("A", "stale", &[]));
+        let blockchain_data_cache =
+            Arc::new(MemoryCache::new(vec![("A", stale.clone()), ("B", stale)]));
+        let user_defined_data_cache = Arc::new(MemoryCache::new(vec![
+            ("A", user_defined_data("A", &[])),
+            ("B", user_defined_data("B", &[])),
+        ]));
+
+        cache_invalidate_controller(
+            &InvalidateCacheMode::ByIssuer(issuer_b.to_owned()),
+            service,
+            blockchain_data_cache.clone(),
+            user_defined_data_cache.clone(),
+        )
+        .await
+        .unwrap();
+
+        let blockchain_data = blockchain_data_cache.values();
+        assert_eq!(blockchain_data["A"].name, "stale");
+        assert_eq!(blockchain_data["B"].name, "fixed");
+        assert_eq!(blockchain_data["B"].issuer, issuer_b);
+
+        let user_defined_data = user_defined_data_cache.values();
+        assert!(user_defined_data["A"].labels.is_empty());
+        assert_eq!(user_defined_data["B"].labels, vec![AssetLabel::DeFi]);
+    }
+
+    #[tokio::test]
+    async fn should_invalidate_given_assets_cache_only() {
+        let service = Arc::new(MockService::new(vec![
+            asset_info("A", "fixed", &["DEFI"]),
+            asset_info("B", "fixed", &["DEFI"]),
+        ]));
+        let stale = AssetBlockchainData::from(&asset_info("A", "stale", &[]));
+        let blockchain_data_cache = Arc::new(MemoryCache::new(vec![
+            ("A", stale.clone()),
+            ("B", stale.clone()),
+            ("C", stale),
+        ]));
+        let user_defined_data_cache = Arc::new(MemoryCache::new(vec![
+            ("A", user_defined_data("A", &[])),
+            ("B", user_defined_data("B", &[])),
+            ("C", user_defined_data("C", &[])),
+        ]));
+
+        cache_invalidate_controller(
+            &InvalidateCacheMode::ByIds(vec!["B".to_owned(), "C".to_owned()]),
+            service,
+            blockchain_data_cache.clone(),
+            user_defined_data_cache.clone(),
+        )
+        .await
+        .unwrap();
+
+        let blockchain_data = blockchain_data_cache.values();
+        assert_eq!(blockchain_data["A"].name, "stale");
+        assert_eq!(blockchain_data["B"].name, "fixed");
+        // absent assets are deleted
+        assert!(!blockchain_data.contains_key("C"));
+
+        let user_defined_data = user_defined_data_cache.values();
+        assert!(user_defined_data["A"].labels.is_empty());
+        assert_eq!(user_defined_data["B"].labels, vec![AssetLabel::DeFi]);
+        assert!(!user_defined_data.contains_key("C"));
+    }
+
+    #[tokio::test]
+    async fn should_rebuild_user_defined_data_cache_from_db() {
+        let service = Arc::new(MockService::new(vec![
+            asset_info("A", "fixed", &["DEFI"]),
+            asset_info("B", "fixed", &["GATEWAY", "DEFI"]),
+        ]));
+        let user_defined_data_cache = Arc::new(MemoryCache::new(vec![
+            ("A", user_defined_data("A", &[])),
+            ("B", user_defined_data("B", &["STALE"])),
+            ("C", user_defined_data("C", &["STALE"])),
+        ]));
+
+        let res = user_defined_data_rebuild_controller(
+            &["B".to_owned(), "C".to_owned()],
+            service.clone(),
+            user_defined_data_cache.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.assets_count, 1);
+        {
+            let cached = user_defined_data_cache.values();
+            // not requested assets are kept as is
+            assert!(cached["A"].labels.is_empty());
+            assert_eq!(
+                cached["B"].labels,
+                vec![AssetLabel::Gateway, AssetLabel::DeFi]
+            );
+            assert!(!cached.contains_key("C"));
+        }
+
+        let res = user_defined_data_rebuild_controller(
+            &[],
+            service.clone(),
+            user_defined_data_cache.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.assets_count, 2);
+        let cached = user_defined_data_cache.values();
+        for (id, asset_info) in service.assets.iter() {
+            assert_eq!(cached[id].labels, asset_info.metadata.labels);
+        }
+    }
+
+    #[test]
+    fn should_parse_invalidate_cache_mode() {
+        let parse = |q: &str| serde_qs::from_str::<InvalidateCacheQueryParams>(q).map(|p| p.mode);
+
+        assert_eq!(
+            parse("mode=all_data").unwrap(),
+            InvalidateCacheMode::AllData
+        );
+        assert_eq!(
+            parse("mode=by_issuer&issuer=3PC4roN512iugc6xGVTTM2XkoWKEdSiiscd").unwrap(),
+            InvalidateCacheMode::ByIssuer("3PC4roN512iugc6xGVTTM2XkoWKEdSiiscd".to_owned())
+        );
+        assert_eq!(
+            parse("mode=by_ids&ids[]=A&ids[]=B").unwrap(),
+            InvalidateCacheMode::ByIds(vec!["A".to_owned(), "B".to_owned()])
+        );
+        assert!(parse("mode=by_ids").is_err());
+        assert!(parse("mode=all_data&ids[]=A").is_err());
+        assert!(parse("mode=by_issuer").is_err());
+        assert!(parse("mode=all_data&issuer=3PC4roN512iugc6xGVTTM2XkoWKEdSiiscd").is_err());
+        assert!(parse("mode=unknown").is_err());
+    }
+
+    #[test]
+    fn should_reject_unknown_label() {
+        let allowed_labels = vec!["GATEWAY".to_owned(), "DEFI".to_owned()];
+
+        assert!(label_validation(&allowed_labels, &AssetLabel::Gateway).is_ok());
+        match label_validation(&allowed_labels, &AssetLabel::from("GATWAY")) {
+            Err(AppError::ValidationError(param, Some(details))) => {
+                assert_eq!(param, "label");
+                assert_eq!(details["allowed_values"], "GATEWAY,DEFI");
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn should_allow_any_label_without_allow_list() {
+        assert!(label_validation(&[], &AssetLabel::from("GATWAY")).is_ok());
+    }
+
+    /// Admin assets service labelling the assets of the "database" only
+    struct MockAdminAssetsService {
+        asset_ids: Vec<String>,
+    }
+
+    #[async_trait::async_trait]
+    impl AdminAssetsService for MockAdminAssetsService {
+        async fn add_label(&self, _id: &str, _label: &AssetLabel) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        async fn delete_label(&self, _id: &str, _label: &AssetLabel) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        async fn label_counts(&self) -> Result<Vec<LabelCount>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn update_label_bulk(
+            &self,
+            _label: &AssetLabel,
+            add: &[String],
+            remove: &[String],
+            strict: bool,
+        ) -> Result<Vec<LabelUpdateResult>, AppError> {
+            let is_missing = |id: &String| !self.asset_ids.contains(id);
+            if strict && (add.iter().any(is_missing) || remove.iter().any(is_missing)) {
+                return Err(AppError::ValidationError("add".to_owned(), None));
+            }
+
+            let result = |id: &String, status: LabelUpdateStatus| LabelUpdateResult {
+                id: id.clone(),
+                status: if self.asset_ids.contains(id) {
+                    status
+                } else {
+                    LabelUpdateStatus::NotFound
+                },
+            };
+
+            Ok(add
+                .iter()
+                .map(|id| result(id, LabelUpdateStatus::Added))
+                .chain(
+                    remove
+                        .iter()
+                        .map(|id| result(id, LabelUpdateStatus::Removed)),
+                )
+                .collect())
+        }
+    }
+
+    fn admin_routes() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        routes(
+            MockService::default(),
+            DummyService::new(),
+            MockAdminAssetsService {
+                asset_ids: vec!["A".to_owned(), "B".to_owned()],
+            },
+            MemoryCache::<AssetBlockchainData>::default(),
+            MemoryCache::<AssetUserDefinedData>::default(),
+            "secret".to_owned(),
+            vec!["DEFI".to_owned()],
+            None,
+        )
+    }
+
+    fn label_assets_request(path: &str) -> RequestBuilder {
+        warp::test::request()
+            .method("POST")
+            .path(path)
+            .json(&json!({
+                "add": ["A", "missing1"],
+                "remove": ["B", "missing2"],
+            }))
+    }
+
+    #[tokio::test]
+    async fn should_label_existing_assets_in_bulk() {
+        let routes = admin_routes();
+
+        let res = label_assets_request("/admin/labels/defi")
+            .header(API_KEY_HEADER_NAME, "secret")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = serde_json::from_slice::<Value>(res.body()).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "label": "DEFI",
+                "results": [
+                    { "id": "A", "status": "added" },
+                    { "id": "missing1", "status": "not_found" },
+                    { "id": "B", "status": "removed" },
+                    { "id": "missing2", "status": "not_found" },
+                ],
+            })
+        );
+
+        // any missing asset fails the strict request
+        let res = label_assets_request("/admin/labels/defi?strict=true")
+            .header(API_KEY_HEADER_NAME, "secret")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn should_require_api_key_to_label_assets() {
+        let routes = admin_routes();
+
+        let res = label_assets_request("/admin/labels/defi")
+            .header(API_KEY_HEADER_NAME, "wrong")
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let res = label_assets_request("/admin/labels/defi")
+            .reply(&routes)
+            .await;
+        assert!(res.status().is_client_error());
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_api_key() {
+        assert!(api_key_validation("secret", "secret").await.is_ok());
+        assert!(api_key_validation("secret", "wrong").await.is_err());
+    }
+}
@@ -1,29 +1,81 @@
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer};
+use std::borrow::Cow;
+use std::convert::TryFrom;
 use validator::{Validate, ValidationError};
 
-use super::DEFAULT_LIMIT;
-use crate::waves::is_valid_base58;
+use super::models::ASSET_FIELDS;
+use super::openapi::{SearchSortSchema, VerificationStatusSchema};
+use super::{DEFAULT_FORMAT, DEFAULT_INCLUDE_NFTS, DEFAULT_LIMIT};
+use crate::models::VerificationStatus;
+use crate::services::assets::SearchSort;
+use crate::waves::{is_valid_base58, Address, PUBLIC_KEY_LENGTH};
 
-#[derive(Clone, Debug, Deserialize, Validate)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Validate)]
+#[validate(schema(function = "validate_search_request", skip_on_field_errors = false))]
 pub struct SearchRequest {
     #[validate(custom = "validate_vec_base58")]
     pub ids: Option<Vec<String>>,
     #[validate(custom = "validate_sql_valid")]
     pub ticker: Option<String>,
+    /// Assets having any of the tickers, exclusive with `ticker`
+    #[serde(rename = "ticker__in")]
+    #[validate(custom = "validate_vec_sql_valid")]
+    pub ticker_in: Option<Vec<String>>,
     #[validate(custom = "validate_sql_valid")]
     pub label: Option<String>,
     pub search: Option<String>,
     #[serde(default, deserialize_with = "deserialize_optional_bool_from_string")]
     pub smart: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_optional_bool_from_string")]
+    pub reissuable: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_optional_bool_from_string")]
+    pub include_nfts: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_optional_bool_from_string")]
+    pub sponsored: Option<bool>,
+    #[serde(rename = "precision__gte")]
+    pub precision_gte: Option<i32>,
+    #[serde(rename = "precision__lte")]
+    pub precision_lte: Option<i32>,
+    #[serde(rename = "created_height__gte")]
+    pub created_height_gte: Option<i32>,
+    #[serde(rename = "created_height__lte")]
+    pub created_height_lte: Option<i32>,
+    /// Assets issued above the height
+    pub created_after_height: Option<i32>,
+    /// Assets issued below the height
+    pub created_before_height: Option<i32>,
+    /// Assets issued after the time
+    pub created_after: Option<DateTime<Utc>>,
+    /// Assets issued before the time
+    pub created_before: Option<DateTime<Utc>>,
     #[serde(rename = "label__in")]
     #[validate(custom = "validate_vec_sql_valid")]
     pub asset_label_in: Option<Vec<String>>,
+    #[serde(rename = "label__not_in")]
+    #[validate(custom = "validate_vec_sql_valid")]
+    pub asset_label_not_in: Option<Vec<String>>,
     #[serde(rename = "issuer__in")]
     #[validate(custom = "validate_vec_base58")]
     pub issuer_in: Option<Vec<String>>,
-    #[validate(range(max = 100))]
+    /// Assets issued by any of the public keys, joined with `issuer__in`
+    #[serde(rename = "issuer_public_key__in")]
+    #[validate(custom = "validate_vec_public_keys")]
+    pub issuer_public_key_in: Option<Vec<String>>,
+    /// Assets having any of the oracle verification statuses, assets without status are unknown
+    #[serde(rename = "verified_status__in")]
+    #[schemars(with = "Option<Vec<VerificationStatusSchema>>")]
+    pub verified_status_in: Option<Vec<VerificationStatus>>,
+    /// Page size, 100 by default and `API__MAX_LIMIT` at most, checked by the controllers
     pub limit: Option<u32>,
+    /// Cursor of the last asset of the previous page, returns the next page
     pub after: Option<String>,
+    /// Cursor of the first asset of the next page, returns the previous page
+    pub before: Option<String>,
+    #[schemars(with = "Option<SearchSortSchema>")]
+    pub sort: Option<SearchSort>,
 }
 
 impl From<SearchRequest> for crate::services::assets::SearchRequest {
@@ -31,16 +83,133 @@ impl From<SearchRequest> for crate::services::assets::SearchRequest {
         Self {
             ids: sr.ids,
             ticker: sr.ticker,
+            ticker_in: sr.ticker_in,
             label: sr.label,
             search: sr.search,
             smart: sr.smart,
+            reissuable: sr.reissuable,
+            include_nfts: sr.include_nfts.unwrap_or(DEFAULT_INCLUDE_NFTS),
+            sponsored: sr.sponsored,
+            precision_gte: sr.precision_gte,
+            precision_lte: sr.precision_lte,
+            created_height_gte: sr.created_height_gte,
+            created_height_lte: sr.created_height_lte,
+            created_after_height: sr.created_after_height,
+            created_before_height: sr.created_before_height,
+            created_after: sr.created_after,
+            created_before: sr.created_before,
             asset_label_in: sr.asset_label_in,
+            asset_label_not_in: sr.asset_label_not_in,
             limit: sr.limit.unwrap_or(DEFAULT_LIMIT),
             issuer_in: sr.issuer_in,
+            verified_status_in: sr.verified_status_in,
             after: sr.after.clone(),
+            before: sr.before.clone(),
+            sort: sr.sort,
+        }
+    }
+}
+
+impl SearchRequest {
+    /// Adds the addresses of the `issuer_public_key__in` keys to `issuer__in`
+    pub fn resolve_issuer_public_keys(mut self, chain_id: u8) -> Self {
+        if let Some(public_keys) = self.issuer_public_key_in.take() {
+            let addresses = public_keys
+                .iter()
+                .filter_map(|public_key| bs58::decode(public_key).into_vec().ok())
+                .filter_map(|public_key| Address::try_from((public_key.as_slice(), chain_id)).ok())
+                .map(String::from);
+
+            self.issuer_in = Some(
+                self.issuer_in
+                    .unwrap_or_default()
+                    .into_iter()
+                    .chain(addresses)
+                    .unique()
+                    .collect(),
+            );
+        }
+        self
+    }
+}
+
+fn validate_search_request(req: &SearchRequest) -> Result<(), ValidationError> {
+    validate_cursors(req)?;
+    validate_ticker_filters(req)?;
+    validate_labels_not_included_and_excluded(req)?;
+    validate_precision_range(req)
+}
+
+fn validate_cursors(req: &SearchRequest) -> Result<(), ValidationError> {
+    if req.after.is_some() && req.before.is_some() {
+        Err(ValidationError::new(
+            "Parameters after and before are mutually exclusive",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn validate_ticker_filters(req: &SearchRequest) -> Result<(), ValidationError> {
+    if req.ticker.is_some() && req.ticker_in.is_some() {
+        Err(ValidationError::new(
+            "Parameters ticker and ticker__in are mutually exclusive",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn validate_labels_not_included_and_excluded(req: &SearchRequest) -> Result<(), ValidationError> {
+    if let (Some(included), Some(excluded)) = (&req.asset_label_in, &req.asset_label_not_in) {
+        let both = included
+            .iter()
+            .filter(|label| excluded.contains(label))
+            .map(|label| label.as_str())
+            .collect::<Vec<_>>();
+
+        if !both.is_empty() {
+            let mut err = ValidationError::new("");
+            err.code = Cow::from(format!(
+                "Labels {} are both in label__in and label__not_in",
+                both.join(", ")
+            ));
+            return Err(err);
         }
     }
+
+    Ok(())
+}
+
+fn validate_precision_range(req: &SearchRequest) -> Result<(), ValidationError> {
+    match (req.precision_gte, req.precision_lte) {
+        (Some(gte), Some(lte)) if gte > lte => Err(ValidationError::new(
+            "Parameter precision__gte has to be less or equal to precision__lte",
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn validate_asset_fields(fields: &Vec<String>) -> Result<(), ValidationError> {
+    if fields.is_empty() {
+        return Err(ValidationError::new(
+            "At least one field has to be requested",
+        ));
+    }
+
+    match fields
+        .iter()
+        .find(|field| !ASSET_FIELDS.contains(&field.as_str()))
+    {
+        Some(field) => {
+            let mut err = ValidationError::new("");
+            err.code = Cow::from(format!("Unknown asset field {}", field));
+            Err(err)
+        }
+        None => Ok(()),
+    }
 }
+
 fn validate_sql_valid(value: &String) -> Result<(), ValidationError> {
     if value
         .chars()
@@ -69,25 +238,112 @@ fn validate_vec_base58(issuers: &Vec<String>) -> Result<(), ValidationError> {
     })
 }
 
-#[derive(Clone, Debug, Deserialize)]
+fn validate_vec_public_keys(public_keys: &Vec<String>) -> Result<(), ValidationError> {
+    let is_public_key = |public_key: &String| {
+        bs58::decode(public_key)
+            .into_vec()
+            .map_or(false, |bytes| bytes.len() == PUBLIC_KEY_LENGTH)
+    };
+
+    if public_keys.iter().all(is_public_key) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("Got invalid public key"))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Validate)]
+pub struct UpdatedAssetsRequest {
+    /// Start of the time range of the last asset updates, in RFC 3339
+    pub from: DateTime<Utc>,
+    /// End of the time range of the last asset updates, in RFC 3339
+    pub to: DateTime<Utc>,
+    pub limit: Option<u32>,
+    /// Cursor of the previous page
+    pub after: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Validate)]
+pub struct IssuerAssetsRequest {
+    pub limit: Option<u32>,
+    /// Cursor of the previous page
+    pub after: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+pub struct SupplyChangesRequest {
+    #[serde(rename = "height__gte")]
+    pub height_gte: Option<i32>,
+    #[serde(rename = "height__lte")]
+    pub height_lte: Option<i32>,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+pub struct AssetChangesRequest {
+    /// Client-known asset version
+    pub version: i64,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
 pub struct MgetRequest {
     pub ids: Vec<String>,
 }
 
-#[derive(Clone, Debug, Deserialize, Validate)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Validate)]
 pub struct RequestOptions {
+    /// Asset fields to respond with, `full` by default
     pub format: Option<ResponseFormat>,
+    /// Whether to respond with asset metadata, `true` by default
     #[serde(default, deserialize_with = "deserialize_optional_bool_from_string")]
     pub include_metadata: Option<bool>,
     #[serde(rename = "height__gte")]
     pub height_gte: Option<i32>,
+    pub empty: Option<EmptyResponseFormat>,
+    #[serde(default, deserialize_with = "deserialize_optional_bool_from_string")]
+    pub with_total: Option<bool>,
+    /// Fail requests by ids if any of the assets is missing
+    #[serde(default, deserialize_with = "deserialize_optional_bool_from_string")]
+    pub strict: Option<bool>,
+    /// Comma-separated asset fields to respond with, takes precedence over `format`
+    #[serde(default, deserialize_with = "deserialize_optional_comma_separated")]
+    #[schemars(with = "Option<String>")]
+    #[validate(custom = "validate_asset_fields")]
+    pub fields: Option<Vec<String>>,
+    /// Annotates the assets with whether they were served from the cache
+    #[serde(default, deserialize_with = "deserialize_optional_bool_from_string")]
+    pub debug_cache: Option<bool>,
+    /// Responds with the labels as `{code, display}` objects instead of the codes
+    #[serde(default, deserialize_with = "deserialize_optional_bool_from_string")]
+    pub label_display: Option<bool>,
+}
+
+impl RequestOptions {
+    /// Requested fields if any, otherwise the requested format
+    pub fn response_format(&self) -> ResponseFormat {
+        match &self.fields {
+            Some(fields) => ResponseFormat::Fields(fields.clone()),
+            None => self.format.clone().unwrap_or(DEFAULT_FORMAT),
+        }
+    }
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ResponseFormat {
     Full,
     Brief,
+    /// Only the given fields of the full format, requested with `fields`
+    #[serde(skip)]
+    Fields(Vec<String>),
+}
+
+/// Response format for searches yielding no assets
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq)]
+pub enum EmptyResponseFormat {
+    #[serde(rename = "list")]
+    List,
+    #[serde(rename = "204")]
+    NoContent,
 }
 
 fn deserialize_optional_bool_from_string<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
@@ -114,6 +370,23 @@ where
     }
 }
 
+/// Splits the comma-separated value like `fields=id,name`, dropping the empty items
+fn deserialize_optional_comma_separated<'de, D>(
+    deserializer: D,
+) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    Ok(Some(
+        s.split(',')
+            .map(|field| field.trim())
+            .filter(|field| !field.is_empty())
+            .map(ToOwned::to_owned)
+            .collect(),
+    ))
+}
+
 /// Escapes querystring field replacing sequence params with `<field>[]`
 ///
 /// Backward compatibility door for clients,
@@ -127,8 +400,10 @@ pub fn escape_querystring_field<'de>(qs: &'de str, field: &str) -> String {
 #[cfg(test)]
 mod tests {
     use serde::Deserialize;
+    use validator::Validate;
 
-    use super::deserialize_optional_bool_from_string;
+    use super::{deserialize_optional_bool_from_string, RequestOptions, SearchRequest};
+    use crate::services::assets::SearchRequest as ServiceSearchRequest;
 
     #[derive(Deserialize, Debug, Clone)]
     pub struct Element {
@@ -153,4 +428,117 @@ mod tests {
         let r: Result<Option<bool>, _> = serde_qs::from_str(r#"value=asd"#);
         assert!(matches!(r, Err(_)));
     }
+
+    #[test]
+    fn should_reject_both_after_and_before() {
+        let req: SearchRequest = serde_qs::from_str("after=asd").unwrap();
+        assert!(req.validate().is_ok());
+
+        let req: SearchRequest = serde_qs::from_str("before=asd").unwrap();
+        assert!(req.validate().is_ok());
+
+        let req: SearchRequest = serde_qs::from_str("after=asd&before=dsa").unwrap();
+        assert!(req.validate().is_err());
+    }
+
+    #[test]
+    fn should_reject_overflowing_limit() {
+        // the maximum is checked against the config by the controllers
+        assert!(serde_qs::from_str::<SearchRequest>("limit=4294967296").is_err());
+    }
+
+    #[test]
+    fn should_reject_both_ticker_and_ticker_in() {
+        let req: SearchRequest = serde_qs::from_str("ticker__in[]=BTC&ticker__in[]=ETH").unwrap();
+        assert_eq!(
+            req.ticker_in,
+            Some(vec!["BTC".to_owned(), "ETH".to_owned()])
+        );
+        assert!(req.validate().is_ok());
+
+        let req: SearchRequest = serde_qs::from_str("ticker=BTC&ticker__in[]=ETH").unwrap();
+        assert!(req.validate().is_err());
+    }
+
+    #[test]
+    fn should_reject_label_both_included_and_excluded() {
+        let req: SearchRequest =
+            serde_qs::from_str("label__in[]=DEFI&label__not_in[]=GATEWAY").unwrap();
+        assert!(req.validate().is_ok());
+
+        let req: SearchRequest =
+            serde_qs::from_str("label__in[]=DEFI&label__not_in[]=GATEWAY&label__not_in[]=DEFI")
+                .unwrap();
+        let errs = req.validate().unwrap_err();
+        let reason = match errs.errors().get("__all__") {
+            Some(validator::ValidationErrorsKind::Field(errs)) => errs[0].code.to_string(),
+            _ => panic!("expected struct level validation error"),
+        };
+        assert_eq!(
+            reason,
+            "Labels DEFI are both in label__in and label__not_in"
+        );
+    }
+
+    #[test]
+    fn should_resolve_issuer_public_key_to_address() {
+        let by_public_key: SearchRequest = serde_qs::from_str(
+            "issuer_public_key__in[]=8cj6YzvQPhSHGvnjupNTW8zrADTT8CMAAd2xTuej84gB",
+        )
+        .unwrap();
+        assert!(by_public_key.validate().is_ok());
+
+        let by_address: SearchRequest =
+            serde_qs::from_str("issuer__in[]=3P58vKb42hvuCNFSJ7iB6cRGtiNqAP6Tsdr").unwrap();
+
+        let by_public_key =
+            ServiceSearchRequest::from(by_public_key.resolve_issuer_public_keys(b'W'));
+        let by_address = ServiceSearchRequest::from(by_address.resolve_issuer_public_keys(b'W'));
+        assert_eq!(by_public_key.issuer_in, by_address.issuer_in);
+        assert_eq!(
+            by_public_key.issuer_in,
+            Some(vec!["3P58vKb42hvuCNFSJ7iB6cRGtiNqAP6Tsdr".to_owned()])
+        );
+
+        let req: SearchRequest =
+            serde_qs::from_str("issuer_public_key__in[]=3P58vKb42hvuCNFSJ7iB6cRGtiNqAP6Tsdr")
+                .unwrap();
+        assert!(req.validate().is_err());
+    }
+
+    #[test]
+    fn should_reject_unknown_asset_fields() {
+        let opts: RequestOptions = serde_qs::from_str("fields=id,name,ticker").unwrap();
+        assert_eq!(
+            opts.fields,
+            Some(vec![
+                "id".to_owned(),
+                "name".to_owned(),
+                "ticker".to_owned()
+            ])
+        );
+        assert!(opts.validate().is_ok());
+
+        let opts: RequestOptions = serde_qs::from_str("fields=id,issuer").unwrap();
+        assert!(opts.validate().is_err());
+
+        let opts: RequestOptions = serde_qs::from_str("fields=").unwrap();
+        assert!(opts.validate().is_err());
+
+        let opts: RequestOptions = serde_qs::from_str("").unwrap();
+        assert_eq!(opts.fields, None);
+        assert!(opts.validate().is_ok());
+    }
+
+    #[test]
+    fn should_validate_precision_range() {
+        let req: SearchRequest = serde_qs::from_str("precision__gte=2&precision__lte=8").unwrap();
+        assert!(req.validate().is_ok());
+
+        let req: SearchRequest = serde_qs::from_str("precision__gte=8&precision__lte=8").unwrap();
+        assert!(req.validate().is_ok());
+
+        let req: SearchRequest = serde_qs::from_str("precision__gte=8&precision__lte=2").unwrap();
+        assert!(req.validate().is_err());
+    }
 }
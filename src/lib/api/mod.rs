@@ -1,8 +1,84 @@
+use warp::http::header::{HeaderValue, CONTENT_TYPE};
+use warp::{reply::Response, Reply};
+
 pub mod dtos;
 pub mod models;
+pub mod openapi;
+pub mod rate_limit;
 pub mod server;
 
+pub use crate::config::api::{DEFAULT_LIMIT, MAX_LIMIT};
+
 const ERROR_CODES_PREFIX: u16 = 95;
-pub const DEFAULT_LIMIT: u32 = 100;
 pub const DEFAULT_INCLUDE_METADATA: bool = true;
+pub const DEFAULT_INCLUDE_NFTS: bool = false;
+pub const DEFAULT_WITH_TOTAL: bool = false;
+pub const DEFAULT_STRICT: bool = false;
+pub const DEFAULT_DEBUG_CACHE: bool = false;
+pub const DEFAULT_LABEL_DISPLAY: bool = false;
 pub const DEFAULT_FORMAT: dtos::ResponseFormat = dtos::ResponseFormat::Full;
+pub const DEFAULT_EMPTY_RESPONSE_FORMAT: dtos::EmptyResponseFormat =
+    dtos::EmptyResponseFormat::List;
+
+/// Content type of the JSON responses, some strict clients require the charset
+pub const JSON_CONTENT_TYPE: &str = "application/json; charset=utf-8";
+
+/// Sets the charset of the JSON responses, both of the controllers and of the error handler
+pub fn json_charset(reply: impl Reply) -> Response {
+    let mut response = reply.into_response();
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        == Some("application/json");
+    if is_json {
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(JSON_CONTENT_TYPE));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use warp::{http::StatusCode, Filter, Rejection};
+    use wavesexchange_warp::error::{error_handler_with_serde_qs, handler, internal, validation};
+
+    use super::{json_charset, ERROR_CODES_PREFIX, JSON_CONTENT_TYPE};
+    use crate::error::Error as AppError;
+
+    #[tokio::test]
+    async fn should_set_charset_of_success_and_error_responses() {
+        let error_handler = handler(ERROR_CODES_PREFIX, |err| match err {
+            AppError::ValidationError(_error_message, error_details) => {
+                validation::invalid_parameter(ERROR_CODES_PREFIX, error_details.to_owned())
+            }
+            _ => internal(ERROR_CODES_PREFIX),
+        });
+        let routes = warp::path!("ok")
+            .map(|| warp::reply::json(&"ok"))
+            .or(warp::path!("fail").and_then(|| async {
+                Err::<String, Rejection>(warp::reject::custom(AppError::ValidationError(
+                    "invalid".to_owned(),
+                    None,
+                )))
+            }))
+            .or(warp::path!("html").map(|| warp::reply::html("<html></html>")))
+            .recover(move |rej| {
+                error_handler_with_serde_qs(ERROR_CODES_PREFIX, error_handler.clone())(rej)
+            })
+            .map(json_charset);
+
+        let ok = warp::test::request().path("/ok").reply(&routes).await;
+        assert_eq!(ok.status(), StatusCode::OK);
+        assert_eq!(ok.headers()["content-type"], JSON_CONTENT_TYPE);
+
+        let fail = warp::test::request().path("/fail").reply(&routes).await;
+        assert_eq!(fail.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(fail.headers()["content-type"], JSON_CONTENT_TYPE);
+
+        // other content types are kept
+        let html = warp::test::request().path("/html").reply(&routes).await;
+        assert_eq!(html.headers()["content-type"], "text/html; charset=utf-8");
+    }
+}
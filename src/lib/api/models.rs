@@ -1,37 +1,69 @@
 use chrono::{DateTime, Utc};
 use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::Serialize;
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 
 use crate::consumer::models::data_entry::DataEntryValue;
-use crate::models::DataEntryType;
+use crate::models::{DataEntryType, VerificationStatus};
 use crate::waves::{parse_waves_association_key, KNOWN_WAVES_ASSOCIATION_ASSET_ATTRIBUTES};
 
 use super::dtos::ResponseFormat;
+use super::openapi::{AbsenceReasonSchema, DataEntryValueSchema, VerificationStatusSchema};
+use crate::services::assets::AbsenceReason;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, JsonSchema, Serialize)]
 #[serde(tag = "type", rename = "list")]
 pub struct List<T> {
     pub data: Vec<T>,
+    /// Cursor of the last item to pass as `after`, `null` on the last page
     pub cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, JsonSchema, Serialize)]
 #[serde(tag = "type", rename = "asset")]
 pub struct Asset {
     pub data: Option<AssetInfo>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<AssetMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<AbsenceReasonSchema>")]
+    pub reason: Option<AbsenceReason>,
+    /// Whether the asset was served from the cache, with `debug_cache=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_cache: Option<bool>,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, JsonSchema, Serialize)]
 #[serde(untagged)]
 pub enum AssetInfo {
     Full(FullAssetInfo),
     Brief(BriefAssetInfo),
+    /// Requested fields of the full asset info
+    Fields(Map<String, Value>),
 }
 
-#[derive(Clone, Debug, Serialize)]
+/// Fields of the full asset info that can be requested with `fields`
+pub const ASSET_FIELDS: &[&str] = &[
+    "ticker",
+    "id",
+    "name",
+    "precision",
+    "description",
+    "height",
+    "timestamp",
+    "sender",
+    "quantity",
+    "reissuable",
+    "has_script",
+    "min_sponsored_fee",
+    "smart",
+];
+
+#[derive(Clone, Debug, JsonSchema, Serialize)]
 pub struct FullAssetInfo {
     pub ticker: Option<String>,
     pub id: String,
@@ -48,7 +80,42 @@ pub struct FullAssetInfo {
     pub smart: bool,
 }
 
-#[derive(Clone, Debug, Serialize)]
+impl FullAssetInfo {
+    /// Keeps only the given fields, unknown ones are skipped
+    fn select(self, fields: &[String]) -> Map<String, Value> {
+        let mut all = match serde_json::to_value(self) {
+            Ok(Value::Object(all)) => all,
+            _ => Map::new(),
+        };
+
+        fields
+            .iter()
+            .filter_map(|field| all.remove(field).map(|value| (field.clone(), value)))
+            .collect()
+    }
+}
+
+impl From<crate::models::Asset> for FullAssetInfo {
+    fn from(asset: crate::models::Asset) -> Self {
+        Self {
+            id: asset.id,
+            name: asset.name,
+            description: asset.description,
+            precision: asset.precision,
+            height: asset.height,
+            timestamp: asset.timestamp,
+            sender: asset.issuer,
+            quantity: asset.quantity,
+            reissuable: asset.reissuable,
+            has_script: asset.smart,
+            smart: asset.smart,
+            min_sponsored_fee: asset.min_sponsored_fee,
+            ticker: asset.ticker,
+        }
+    }
+}
+
+#[derive(Clone, Debug, JsonSchema, Serialize)]
 pub struct BriefAssetInfo {
     pub ticker: Option<String>,
     pub id: String,
@@ -56,12 +123,141 @@ pub struct BriefAssetInfo {
     pub smart: bool,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, JsonSchema, PartialEq, Serialize)]
+#[serde(tag = "type", rename = "sponsorship")]
+pub struct Sponsorship {
+    pub min_sponsored_fee: i64,
+    pub min_sponsored_fee_decimal: String,
+    pub sponsor_regular_balance: Option<i64>,
+    pub sponsor_out_leasing: Option<i64>,
+    pub sponsor_available: Option<i64>,
+}
+
+impl Sponsorship {
+    /// Returns `None` for not sponsored assets
+    pub fn from_asset_info(asset_info: &crate::models::AssetInfo) -> Option<Self> {
+        asset_info.asset.min_sponsored_fee.map(|min_sponsored_fee| {
+            let sponsor_balance = asset_info.metadata.sponsor_balance.as_ref();
+            Self {
+                min_sponsored_fee,
+                min_sponsored_fee_decimal: to_decimal_string(
+                    min_sponsored_fee,
+                    asset_info.asset.precision,
+                ),
+                sponsor_regular_balance: sponsor_balance.map(|sb| sb.regular_balance),
+                sponsor_out_leasing: sponsor_balance.and_then(|sb| sb.out_leasing),
+                sponsor_available: sponsor_balance
+                    .map(|sb| sb.regular_balance - sb.out_leasing.unwrap_or(0)),
+            }
+        })
+    }
+}
+
+#[derive(Clone, Debug, JsonSchema, PartialEq, Serialize)]
+#[serde(tag = "type", rename = "supply_change")]
+pub struct SupplyChange {
+    pub height: i32,
+    pub prev_quantity: i64,
+    pub new_quantity: i64,
+    /// Positive for issues and reissues, negative for burns
+    pub change: i64,
+}
+
+impl From<crate::services::assets::entities::SupplyChange> for SupplyChange {
+    fn from(sc: crate::services::assets::entities::SupplyChange) -> Self {
+        Self {
+            height: sc.height,
+            prev_quantity: sc.prev_quantity,
+            new_quantity: sc.new_quantity,
+            change: sc.change,
+        }
+    }
+}
+
+/// Whether the consumer saved its state recently
+#[derive(Clone, Debug, JsonSchema, PartialEq, Serialize)]
+pub struct ConsumerLiveness {
+    pub alive: bool,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, JsonSchema, PartialEq, Serialize)]
+#[serde(tag = "type", rename = "asset_changes")]
+pub struct AssetChanges {
+    pub id: String,
+    /// Version to pass with the next request
+    pub version: i64,
+    pub changes: ChangedFields,
+}
+
+/// Fields changed since the requested version, unchanged ones are omitted
+#[derive(Clone, Debug, JsonSchema, PartialEq, Serialize)]
+pub struct ChangedFields {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reissuable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_sponsored_fee: Option<Option<i64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smart: Option<bool>,
+}
+
+impl AssetChanges {
+    pub fn new(id: String, delta: crate::services::assets::entities::AssetDelta) -> Self {
+        let changes = delta.changes;
+        Self {
+            id,
+            version: delta.version,
+            changes: ChangedFields {
+                name: changes.name,
+                description: changes.description,
+                quantity: changes.quantity,
+                reissuable: changes.reissuable,
+                min_sponsored_fee: changes.min_sponsored_fee,
+                smart: changes.smart,
+            },
+        }
+    }
+}
+
+/// Formats amount given in the smallest units as decimal with the given precision
+fn to_decimal_string(amount: i64, precision: i32) -> String {
+    let precision = precision.max(0) as usize;
+    let sign = if amount < 0 { "-" } else { "" };
+    let digits = format!("{:0>width$}", (amount as i128).abs(), width = precision + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - precision);
+
+    if fraction.is_empty() {
+        format!("{}{}", sign, integer)
+    } else {
+        format!("{}{}.{}", sign, integer, fraction)
+    }
+}
+
+#[derive(Clone, Debug, JsonSchema, Serialize)]
 pub struct AssetMetadata {
     pub oracle_data: Vec<OracleData>,
-    pub labels: Vec<String>,
+    pub labels: Vec<MetadataLabel>,
     pub sponsor_balance: Option<i64>,
     pub has_image: bool,
+    /// URL of the logo of the asset at the images service
+    pub image_url: Option<String>,
+    /// Status published by the verification oracle
+    #[schemars(with = "VerificationStatusSchema")]
+    pub verification_status: VerificationStatus,
+}
+
+/// Label code, or the code with its display name with `label_display=true`
+#[derive(Clone, Debug, JsonSchema, Serialize)]
+#[serde(untagged)]
+pub enum MetadataLabel {
+    Code(String),
+    WithDisplay { code: String, display: String },
 }
 
 #[derive(Clone, Debug)]
@@ -70,44 +266,43 @@ pub struct AssetLabel {
     pub label: String,
 }
 
-#[derive(Clone, Debug, Serialize)]
-pub struct OracleData(HashMap<String, DataEntryValue>);
+#[derive(Clone, Debug, JsonSchema, Serialize)]
+pub struct OracleData(
+    #[schemars(with = "HashMap<String, DataEntryValueSchema>")] HashMap<String, DataEntryValue>,
+);
 
 impl Asset {
     pub fn new(
         asset_info: Option<crate::models::AssetInfo>,
-        has_image: bool,
+        image_url: Option<String>,
         include_metadata: bool,
         format: &ResponseFormat,
     ) -> Self {
         match asset_info {
             Some(asset_info) => {
                 let ai = match format {
-                    ResponseFormat::Full => AssetInfo::Full(FullAssetInfo {
-                        id: asset_info.asset.id,
-                        name: asset_info.asset.name,
-                        description: asset_info.asset.description,
-                        precision: asset_info.asset.precision,
-                        height: asset_info.asset.height,
-                        timestamp: asset_info.asset.timestamp,
-                        sender: asset_info.asset.issuer,
-                        quantity: asset_info.asset.quantity,
-                        reissuable: asset_info.asset.reissuable,
-                        has_script: asset_info.asset.smart,
-                        smart: asset_info.asset.smart,
-                        min_sponsored_fee: asset_info.asset.min_sponsored_fee,
-                        ticker: asset_info.asset.ticker,
-                    }),
+                    ResponseFormat::Full => AssetInfo::Full(asset_info.asset.into()),
                     ResponseFormat::Brief => AssetInfo::Brief(BriefAssetInfo {
                         id: asset_info.asset.id,
                         name: asset_info.asset.name,
                         smart: asset_info.asset.smart,
                         ticker: asset_info.asset.ticker,
                     }),
+                    ResponseFormat::Fields(fields) => {
+                        AssetInfo::Fields(FullAssetInfo::from(asset_info.asset).select(fields))
+                    }
                 };
+                let verification_status = asset_info.metadata.verification_status();
                 let metadata = AssetMetadata {
-                    has_image: has_image,
-                    labels: asset_info.metadata.labels,
+                    has_image: image_url.is_some(),
+                    image_url,
+                    verification_status,
+                    labels: asset_info
+                        .metadata
+                        .labels
+                        .into_iter()
+                        .map(|label| MetadataLabel::Code(String::from(label)))
+                        .collect(),
                     oracle_data: asset_info
                         .metadata
                         .oracles_data
@@ -172,12 +367,214 @@ impl Asset {
                     } else {
                         None
                     },
+                    reason: None,
+                    from_cache: None,
                 }
             }
             _ => Self {
                 data: None,
                 metadata: None,
+                reason: None,
+                from_cache: None,
+            },
+        }
+    }
+
+    /// Missing asset explaining why it is missing
+    pub fn absent(reason: AbsenceReason) -> Self {
+        Self {
+            data: None,
+            metadata: None,
+            reason: Some(reason),
+            from_cache: None,
+        }
+    }
+
+    pub fn with_from_cache(mut self, from_cache: Option<bool>) -> Self {
+        self.from_cache = from_cache;
+        self
+    }
+
+    /// Resolves the display names of the labels, the labels missing in the map are displayed as is
+    pub fn with_label_display_names(
+        mut self,
+        display_names: Option<&HashMap<String, String>>,
+    ) -> Self {
+        if let (Some(display_names), Some(metadata)) = (display_names, self.metadata.as_mut()) {
+            metadata.labels = std::mem::take(&mut metadata.labels)
+                .into_iter()
+                .map(|label| match label {
+                    MetadataLabel::Code(code) => {
+                        let display = display_names.get(&code).unwrap_or(&code).to_owned();
+                        MetadataLabel::WithDisplay { code, display }
+                    }
+                    label => label,
+                })
+                .collect();
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use std::collections::HashMap;
+
+    use super::{to_decimal_string, Asset as AssetResponse, Sponsorship};
+    use crate::models::{
+        Asset, AssetInfo, AssetLabel, AssetMetadata, AssetOracleDataEntry, AssetSponsorBalance,
+        DataEntryType,
+    };
+    use crate::services::assets::AbsenceReason;
+
+    fn asset_info(min_sponsored_fee: Option<i64>) -> AssetInfo {
+        AssetInfo {
+            asset: Asset {
+                id: "asset".to_owned(),
+                name: "Asset".to_owned(),
+                precision: 6,
+                description: "".to_owned(),
+                height: 1,
+                timestamp: Utc::now(),
+                issuer: "issuer".to_owned(),
+                quantity: 1_000_000_000,
+                reissuable: false,
+                min_sponsored_fee,
+                smart: false,
+                nft: false,
+                ticker: None,
+            },
+            metadata: AssetMetadata {
+                labels: vec![],
+                sponsor_balance: Some(AssetSponsorBalance {
+                    regular_balance: 500,
+                    out_leasing: Some(200),
+                }),
+                oracles_data: HashMap::new(),
             },
         }
     }
+
+    #[test]
+    fn should_build_sponsorship_of_sponsored_asset() {
+        assert_eq!(
+            Sponsorship::from_asset_info(&asset_info(Some(1_500))),
+            Some(Sponsorship {
+                min_sponsored_fee: 1_500,
+                min_sponsored_fee_decimal: "0.001500".to_owned(),
+                sponsor_regular_balance: Some(500),
+                sponsor_out_leasing: Some(200),
+                sponsor_available: Some(300),
+            })
+        );
+    }
+
+    #[test]
+    fn should_not_build_sponsorship_of_not_sponsored_asset() {
+        assert_eq!(Sponsorship::from_asset_info(&asset_info(None)), None);
+    }
+
+    #[test]
+    fn should_format_decimal() {
+        assert_eq!(to_decimal_string(12345, 2), "123.45");
+        assert_eq!(to_decimal_string(5, 3), "0.005");
+        assert_eq!(to_decimal_string(100, 0), "100");
+        assert_eq!(to_decimal_string(-5, 1), "-0.5");
+    }
+
+    #[test]
+    fn should_serialize_requested_fields_only() {
+        let format = super::ResponseFormat::Fields(vec![
+            "id".to_owned(),
+            "ticker".to_owned(),
+            "name".to_owned(),
+        ]);
+        let asset = AssetResponse::new(Some(asset_info(None)), None, false, &format);
+
+        assert_eq!(
+            serde_json::to_value(asset).unwrap(),
+            serde_json::json!({
+                "type": "asset",
+                "data": {"id": "asset", "ticker": null, "name": "Asset"},
+            })
+        );
+    }
+
+    #[test]
+    fn should_resolve_verification_status() {
+        let format = super::ResponseFormat::Brief;
+        let status = |asset_info: AssetInfo| {
+            let asset = AssetResponse::new(Some(asset_info), None, true, &format);
+            serde_json::to_value(asset).unwrap()["metadata"]["verification_status"].clone()
+        };
+
+        assert_eq!(status(asset_info(None)), serde_json::json!("unknown"));
+
+        let status_entry = |oracle: &str, code: i64, uid: i64| AssetOracleDataEntry {
+            asset_id: "asset".to_owned(),
+            oracle_address: oracle.to_owned(),
+            key: "status_<asset>".to_owned(),
+            data_type: DataEntryType::Int,
+            bin_val: None,
+            bool_val: None,
+            int_val: Some(code),
+            str_val: None,
+            uid,
+        };
+
+        let mut verified = asset_info(None);
+        verified
+            .metadata
+            .oracles_data
+            .insert("oracle".to_owned(), vec![status_entry("oracle", 2, 1)]);
+        assert_eq!(status(verified.clone()), serde_json::json!("verified"));
+
+        // the last written status wins whatever the order of the oracles
+        let mut declined = verified;
+        declined
+            .metadata
+            .oracles_data
+            .insert("another".to_owned(), vec![status_entry("another", -2, 2)]);
+        assert_eq!(status(declined), serde_json::json!("declined"));
+    }
+
+    #[test]
+    fn should_resolve_label_display_names() {
+        let format = super::ResponseFormat::Brief;
+        let mut labeled = asset_info(None);
+        labeled.metadata.labels = vec![AssetLabel::WaVerified, AssetLabel::DeFi];
+        let display_names = vec![("WA_VERIFIED".to_owned(), "Verified".to_owned())]
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        let labels = |display_names: Option<&HashMap<String, String>>| {
+            let asset = AssetResponse::new(Some(labeled.clone()), None, true, &format)
+                .with_label_display_names(display_names);
+            serde_json::to_value(asset).unwrap()["metadata"]["labels"].clone()
+        };
+
+        assert_eq!(labels(None), serde_json::json!(["WA_VERIFIED", "DEFI"]));
+        assert_eq!(
+            labels(Some(&display_names)),
+            serde_json::json!([
+                {"code": "WA_VERIFIED", "display": "Verified"},
+                {"code": "DEFI", "display": "DEFI"},
+            ])
+        );
+    }
+
+    #[test]
+    fn should_serialize_absence_reason() {
+        assert_eq!(
+            serde_json::to_value(AssetResponse::absent(AbsenceReason::Hidden)).unwrap(),
+            serde_json::json!({"type": "asset", "data": null, "reason": "hidden"})
+        );
+
+        let format = super::ResponseFormat::Full;
+        assert_eq!(
+            serde_json::to_value(AssetResponse::new(None, None, false, &format)).unwrap(),
+            serde_json::json!({"type": "asset", "data": null})
+        );
+    }
 }
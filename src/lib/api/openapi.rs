@@ -0,0 +1,486 @@
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde_json::{json, Value};
+
+use super::dtos::{
+    AssetChangesRequest, IssuerAssetsRequest, MgetRequest, RequestOptions, SearchRequest,
+    SupplyChangesRequest, UpdatedAssetsRequest,
+};
+use super::models::{Asset, AssetChanges, ConsumerLiveness, List, Sponsorship, SupplyChange};
+
+/// Pinned to not break the docs page by an incompatible release
+const REDOC_VERSION: &str = "2.0.0";
+
+/// Schema of `crate::services::assets::SearchSort`
+#[derive(JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[schemars(rename = "SearchSort")]
+pub enum SearchSortSchema {
+    CreatedAsc,
+    CreatedDesc,
+    NameAsc,
+    NameDesc,
+    QuantityAsc,
+    QuantityDesc,
+    /// Assets having the labels of the highest priority first, then by creation
+    LabelPriority,
+}
+
+/// Schema of `crate::models::VerificationStatus`
+#[derive(JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[schemars(rename = "VerificationStatus")]
+pub enum VerificationStatusSchema {
+    Verified,
+    Declined,
+    Unknown,
+}
+
+/// Schema of `crate::services::assets::AbsenceReason`
+#[derive(JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[schemars(rename = "AbsenceReason")]
+pub enum AbsenceReasonSchema {
+    NotFound,
+    /// The asset exists but is excluded by policy (e.g. NFT)
+    Hidden,
+    /// The asset is an NFT excluded from the `mget` result
+    NftExcluded,
+}
+
+/// Schema of `crate::consumer::models::data_entry::DataEntryValue`
+#[derive(JsonSchema)]
+#[serde(untagged)]
+#[schemars(rename = "DataEntryValue")]
+pub enum DataEntryValueSchema {
+    BinVal(Vec<u8>),
+    BoolVal(bool),
+    IntVal(i64),
+    StrVal(String),
+}
+
+/// Page rendering the OpenAPI document at `/openapi.json` with Redoc
+pub fn docs_html(title: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+  <head>
+    <title>{title}</title>
+    <meta charset="utf-8">
+  </head>
+  <body>
+    <redoc spec-url="/openapi.json"></redoc>
+    <script src="https://cdn.jsdelivr.net/npm/redoc@{version}/bundles/redoc.standalone.js"></script>
+  </body>
+</html>
+"#,
+        title = title,
+        version = REDOC_VERSION,
+    )
+}
+
+/// Builds the OpenAPI 3.0 document of the public API from the request and response types
+pub fn document() -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+
+    let search_parameters = query_parameters::<SearchRequest>(&mut gen);
+    let options_parameters = query_parameters::<RequestOptions>(&mut gen);
+    let updated_parameters = query_parameters::<UpdatedAssetsRequest>(&mut gen);
+    let issuer_assets_parameters = query_parameters::<IssuerAssetsRequest>(&mut gen);
+    let supply_changes_parameters = query_parameters::<SupplyChangesRequest>(&mut gen);
+    let asset_changes_parameters = query_parameters::<AssetChangesRequest>(&mut gen);
+    let mget_request = gen.subschema_for::<MgetRequest>();
+    let assets = gen.subschema_for::<List<Asset>>();
+    let asset = gen.subschema_for::<Asset>();
+    let sponsorship = gen.subschema_for::<Sponsorship>();
+    let supply_changes = gen.subschema_for::<List<SupplyChange>>();
+    let asset_changes = gen.subschema_for::<AssetChanges>();
+    let consumer_liveness = gen.subschema_for::<ConsumerLiveness>();
+
+    let id_parameter = path_parameter("id");
+    let with_options = |parameters: Vec<Value>| [parameters, options_parameters.clone()].concat();
+    let supply_changes_parameters =
+        [vec![id_parameter.clone()], supply_changes_parameters].concat();
+    let asset_changes_parameters = [vec![id_parameter.clone()], asset_changes_parameters].concat();
+    let issuer_assets_parameters =
+        with_options([vec![path_parameter("address")], issuer_assets_parameters].concat());
+
+    let mut schemas = serde_json::to_value(gen.take_definitions()).unwrap();
+    schemas["Error"] = error_schema();
+
+    let rate_limited = |responses: Value| {
+        let mut responses = with_errors(responses);
+        responses["429"] =
+            error_response("Too many requests of the client, see the `Retry-After` header");
+        responses
+    };
+
+    let paths = vec![
+        (
+            "/assets",
+            json!({
+                "get": {
+                    "summary": "Search assets or get assets by ids",
+                    "description": "Assets are searched unless `ids` are given. The result is paginated with cursors: \
+                        pass the `cursor` of the response as `after` to get the next page, or the cursor of the first asset \
+                        of a page as `before` to get the previous one. `after` and `before` are mutually exclusive. \
+                        Assets requested by ids are returned in the order of the ids, missing ones as `data: null`.",
+                    "parameters": with_options(search_parameters),
+                    "responses": rate_limited(json!({
+                        "200": json_response("Assets", &assets),
+                        "204": { "description": "No assets found, with `empty=204`" },
+                        "404": error_response("Missing assets, with `strict=true`"),
+                    })),
+                },
+                "post": {
+                    "summary": "Get assets by ids",
+                    "parameters": options_parameters.clone(),
+                    "requestBody": json_request(&mget_request),
+                    "responses": rate_limited(json!({
+                        "200": json_response("Assets in the order of the ids, missing ones as `data: null`", &assets),
+                        "404": error_response("Missing assets, with `strict=true`"),
+                    })),
+                },
+            }),
+        ),
+        (
+            "/assets/updated",
+            json!({
+                "get": {
+                    "summary": "Get assets updated within the time range",
+                    "parameters": with_options(updated_parameters),
+                    "responses": rate_limited(json!({
+                        "200": json_response("Assets ordered by id", &assets),
+                    })),
+                },
+            }),
+        ),
+        (
+            "/assets/{id}",
+            json!({
+                "get": {
+                    "summary": "Get asset by id",
+                    "parameters": with_options(vec![id_parameter.clone()]),
+                    "responses": rate_limited(json!({
+                        "200": json_response("Asset", &asset),
+                        "404": error_response("Asset not found"),
+                    })),
+                },
+            }),
+        ),
+        (
+            "/assets/by-ticker/{ticker}",
+            json!({
+                "get": {
+                    "summary": "Get asset by ticker",
+                    "parameters": with_options(vec![path_parameter("ticker")]),
+                    "responses": rate_limited(json!({
+                        "200": json_response("Asset", &asset),
+                        "404": error_response("Asset not found"),
+                    })),
+                },
+            }),
+        ),
+        (
+            "/assets/{id}/sponsorship",
+            json!({
+                "get": {
+                    "summary": "Get sponsorship of the asset",
+                    "parameters": [id_parameter],
+                    "responses": rate_limited(json!({
+                        "200": json_response("Sponsorship", &sponsorship),
+                        "404": error_response("Asset not found or not sponsored"),
+                    })),
+                },
+            }),
+        ),
+        (
+            "/assets/{id}/supply-changes",
+            json!({
+                "get": {
+                    "summary": "Get issues, reissues and burns of the asset",
+                    "parameters": supply_changes_parameters,
+                    "responses": rate_limited(json!({
+                        "200": json_response("Supply changes ordered by height", &supply_changes),
+                    })),
+                },
+            }),
+        ),
+        (
+            "/assets/{id}/changes",
+            json!({
+                "get": {
+                    "summary": "Get asset fields changed after the version",
+                    "parameters": asset_changes_parameters,
+                    "responses": rate_limited(json!({
+                        "200": json_response("Changed fields", &asset_changes),
+                        "304": { "description": "No fields changed" },
+                        "404": error_response("Asset not found"),
+                    })),
+                },
+            }),
+        ),
+        (
+            "/issuers/{address}/assets",
+            json!({
+                "get": {
+                    "summary": "Get assets issued by the address",
+                    "parameters": issuer_assets_parameters,
+                    "responses": rate_limited(json!({
+                        "200": json_response("Assets ordered by id", &assets),
+                    })),
+                },
+            }),
+        ),
+        (
+            "/sync/liveness",
+            json!({
+                "get": {
+                    "summary": "Whether the consumer saved its state recently",
+                    "responses": with_errors(json!({
+                        "200": json_response("Consumer liveness", &consumer_liveness),
+                    })),
+                },
+            }),
+        ),
+    ];
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Asset search API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths_object(paths),
+        "components": { "schemas": schemas },
+    })
+}
+
+/// Collects the path items keyed by the paths
+pub fn paths_object(paths: Vec<(&str, Value)>) -> Value {
+    Value::Object(
+        paths
+            .into_iter()
+            .map(|(path, item)| (path.to_owned(), item))
+            .collect(),
+    )
+}
+
+/// Describes the path segment as a required string parameter
+pub fn path_parameter(name: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "path",
+        "required": true,
+        "schema": { "type": "string" },
+    })
+}
+
+/// Adds the responses of the invalid parameters and of the internal errors
+pub fn with_errors(responses: Value) -> Value {
+    let mut responses = responses;
+    responses["400"] = error_response("Invalid parameters");
+    responses["500"] = error_response("Internal error");
+    responses["504"] = error_response("Query timeout");
+    responses
+}
+
+/// Describes every field of the query type as a query parameter
+pub fn query_parameters<T: JsonSchema>(gen: &mut SchemaGenerator) -> Vec<Value> {
+    let schema = T::json_schema(gen).into_object();
+
+    schema
+        .object
+        .map(|object| {
+            let required = object.required;
+            object
+                .properties
+                .into_iter()
+                .map(|(name, schema)| {
+                    let description = match &schema {
+                        Schema::Object(o) => {
+                            o.metadata.as_ref().and_then(|m| m.description.clone())
+                        }
+                        _ => None,
+                    };
+                    let mut parameter = json!({
+                        "name": name,
+                        "in": "query",
+                        "required": required.contains(&name),
+                        "schema": schema,
+                    });
+                    if let Some(description) = description {
+                        parameter["description"] = Value::String(description);
+                    }
+                    parameter
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn json_request(schema: &Schema) -> Value {
+    json!({
+        "required": true,
+        "content": { "application/json": { "schema": schema } },
+    })
+}
+
+pub fn json_response(description: &str, schema: &Schema) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema } },
+    })
+}
+
+pub fn error_response(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Error" } } },
+    })
+}
+
+/// Error envelope of the wavesexchange_warp error handlers
+pub fn error_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["errors"],
+        "properties": {
+            "errors": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["code", "message"],
+                    "properties": {
+                        "code": {
+                            "type": "integer",
+                            "description": "Error code prefixed with the service code 95, e.g. 950200 for invalid parameters",
+                        },
+                        "message": { "type": "string" },
+                        "details": {
+                            "type": "object",
+                            "description": "E.g. the invalid `parameter` and the `reason`",
+                            "additionalProperties": { "type": "string" },
+                        },
+                    },
+                },
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::document;
+    use crate::models::VerificationStatus;
+    use crate::services::assets::{AbsenceReason, SearchSort};
+
+    /// Values of the enum schema, documented variants being described by separate subschemas
+    fn enum_values(doc: &Value, schema: &str) -> Vec<Value> {
+        let schema = &doc["components"]["schemas"][schema];
+        let subschemas = match schema["oneOf"].as_array() {
+            Some(subschemas) => subschemas.iter().collect(),
+            None => vec![schema],
+        };
+        subschemas
+            .into_iter()
+            .flat_map(|s| s["enum"].as_array().cloned().unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn should_describe_assets_path_and_asset_schema() {
+        let json = serde_json::to_string(&document()).unwrap();
+        let doc: Value = serde_json::from_str(&json).unwrap();
+
+        let get_assets = &doc["paths"]["/assets"]["get"];
+        assert!(get_assets.is_object());
+        assert!(doc["paths"]["/assets"]["post"]["requestBody"].is_object());
+
+        let parameters = get_assets["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        for name in [
+            "ids",
+            "search",
+            "limit",
+            "after",
+            "before",
+            "format",
+            "include_metadata",
+        ] {
+            assert!(parameters.contains(&name), "missing parameter {}", name);
+        }
+
+        let schemas = &doc["components"]["schemas"];
+        assert!(schemas["Asset"].is_object());
+        assert!(schemas["FullAssetInfo"].is_object());
+        assert!(schemas["BriefAssetInfo"].is_object());
+        assert!(schemas["Error"].is_object());
+    }
+
+    #[test]
+    fn should_describe_every_route() {
+        let doc = document();
+
+        for (path, method) in [
+            ("/assets", "get"),
+            ("/assets", "post"),
+            ("/assets/updated", "get"),
+            ("/assets/{id}", "get"),
+            ("/assets/by-ticker/{ticker}", "get"),
+            ("/assets/{id}/sponsorship", "get"),
+            ("/assets/{id}/supply-changes", "get"),
+            ("/assets/{id}/changes", "get"),
+            ("/issuers/{address}/assets", "get"),
+            ("/sync/liveness", "get"),
+        ] {
+            let operation = &doc["paths"][path][method];
+            assert!(operation.is_object(), "missing {} {}", method, path);
+            assert!(operation["responses"]["200"].is_object());
+        }
+
+        let version = doc["paths"]["/assets/{id}/changes"]["get"]["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p["name"] == "version")
+            .unwrap();
+        assert_eq!(version["required"], true);
+    }
+
+    #[test]
+    fn should_describe_enums_as_serialized() {
+        let doc = document();
+
+        let sorts = enum_values(&doc, "SearchSort");
+        assert_eq!(sorts.len(), 7);
+        for sort in sorts {
+            serde_json::from_value::<SearchSort>(sort).unwrap();
+        }
+
+        let statuses = enum_values(&doc, "VerificationStatus");
+        for status in [
+            VerificationStatus::Verified,
+            VerificationStatus::Declined,
+            VerificationStatus::Unknown,
+        ] {
+            assert!(statuses.contains(&serde_json::to_value(status).unwrap()));
+        }
+
+        let reasons = enum_values(&doc, "AbsenceReason");
+        for reason in [
+            AbsenceReason::NotFound,
+            AbsenceReason::Hidden,
+            AbsenceReason::NftExcluded,
+        ] {
+            assert!(reasons.contains(&serde_json::to_value(reason).unwrap()));
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use warp::http::{header::RETRY_AFTER, StatusCode};
+use warp::reply::Response;
+use warp::{reject::Reject, Filter, Rejection, Reply};
+
+/// Per client token buckets of the public API
+pub struct RateLimiter {
+    per_second: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+    clock: Box<dyn Fn() -> Instant + Send + Sync>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl RateLimiter {
+    /// Allows `per_second` requests of every client on average, `burst` of them at once
+    pub fn new(per_second: u32, burst: u32) -> Self {
+        Self {
+            per_second: per_second.max(1) as f64,
+            burst: burst.max(1) as f64,
+            buckets: Mutex::new(HashMap::new()),
+            clock: Box::new(Instant::now),
+        }
+    }
+
+    /// Replaces the clock of the filter and of the eviction, e.g. by a controlled one in the tests
+    pub fn with_clock(mut self, clock: impl Fn() -> Instant + Send + Sync + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    fn now(&self) -> Instant {
+        (self.clock)()
+    }
+
+    /// Takes a token of the client, or returns the time until the next one
+    pub fn check(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: self.burst,
+            updated_at: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated_at);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * self.per_second).min(self.burst);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.per_second,
+            ))
+        }
+    }
+
+    /// Drops the buckets refilled entirely, so that they do not differ from the new ones
+    pub fn evict_idle(&self, now: Instant) {
+        let refill = Duration::from_secs_f64(self.burst / self.per_second);
+        self.buckets
+            .lock()
+            .unwrap()
+            .retain(|_, bucket| now.saturating_duration_since(bucket.updated_at) < refill);
+    }
+
+    pub fn spawn_eviction(self: &Arc<Self>, period: Duration) {
+        let limiter = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                limiter.evict_idle(limiter.now());
+            }
+        });
+    }
+}
+
+/// Rejection of the requests over the limit
+#[derive(Debug)]
+pub struct RateLimited {
+    pub retry_after: Duration,
+}
+
+impl Reject for RateLimited {}
+
+impl RateLimited {
+    /// 429 with the error envelope of the other errors and the seconds to wait
+    pub fn response(&self, error_codes_prefix: u16) -> Response {
+        let body = json!({
+            "errors": [{
+                "code": error_codes_prefix as u32 * 10000 + 429,
+                "message": "Too many requests",
+            }]
+        });
+        let retry_after = self.retry_after.as_secs_f64().ceil().max(1.0) as u64;
+
+        let mut response =
+            warp::reply::with_status(warp::reply::json(&body), StatusCode::TOO_MANY_REQUESTS)
+                .into_response();
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, retry_after.into());
+        response
+    }
+}
+
+/// Rejects the requests of the clients over the limit with `RateLimited`, passes all of them without a limiter
+///
+/// Clients are told apart by the last address of `X-Forwarded-For` if `trust_forwarded_for`,
+/// i.e. the one appended by the trusted proxy, by the remote address otherwise
+pub fn rate_limit(
+    limiter: Option<Arc<RateLimiter>>,
+    trust_forwarded_for: bool,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::addr::remote()
+        .and(warp::header::optional::<String>("x-forwarded-for"))
+        .and_then(
+            move |remote: Option<SocketAddr>, forwarded_for: Option<String>| {
+                let limiter = limiter.clone();
+                async move {
+                    let client = client_ip(remote, forwarded_for.as_deref(), trust_forwarded_for);
+                    match (limiter, client) {
+                        (Some(limiter), Some(client)) => {
+                            limiter.check(client, limiter.now()).map_err(|retry_after| {
+                                warp::reject::custom(RateLimited { retry_after })
+                            })
+                        }
+                        _ => Ok(()),
+                    }
+                }
+            },
+        )
+        .untuple_one()
+}
+
+fn client_ip(
+    remote: Option<SocketAddr>,
+    forwarded_for: Option<&str>,
+    trust_forwarded_for: bool,
+) -> Option<IpAddr> {
+    let forwarded = forwarded_for
+        .filter(|_| trust_forwarded_for)
+        // the preceding addresses come from the client, which may forge them
+        .and_then(|addrs| addrs.rsplit(',').next())
+        .and_then(|addr| addr.trim().parse().ok());
+
+    forwarded.or_else(|| remote.map(|addr| addr.ip()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use warp::http::StatusCode;
+    use warp::{Filter, Rejection};
+
+    use super::{rate_limit, RateLimited, RateLimiter};
+
+    #[tokio::test]
+    async fn should_limit_requests_until_bucket_refills() {
+        let now = Arc::new(Mutex::new(Instant::now()));
+        let limiter = {
+            let now = now.clone();
+            Arc::new(RateLimiter::new(20, 2).with_clock(move || *now.lock().unwrap()))
+        };
+        let filter = rate_limit(Some(limiter), true)
+            .map(|| StatusCode::OK)
+            .recover(|rej: Rejection| async move {
+                match rej.find::<RateLimited>().map(|r| r.response(95)) {
+                    Some(response) => Ok(response),
+                    None => Err(rej),
+                }
+            });
+
+        // the client forges the addresses preceding the one appended by the proxy
+        let request = |forged: &str| {
+            warp::test::request()
+                .remote_addr("10.0.0.1:5000".parse().unwrap())
+                .header("x-forwarded-for", format!("{}, 203.0.113.7", forged))
+        };
+
+        for forged in ["198.51.100.1", "198.51.100.2"] {
+            let response = request(forged).reply(&filter).await;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let response = request("198.51.100.3").reply(&filter).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["retry-after"], "1");
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["errors"][0]["code"], 950429);
+
+        // other clients have their own buckets
+        let response = warp::test::request()
+            .remote_addr("10.0.0.1:5000".parse().unwrap())
+            .header("x-forwarded-for", "203.0.113.8")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        *now.lock().unwrap() += Duration::from_millis(100);
+
+        let response = request("198.51.100.4").reply(&filter).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn should_evict_refilled_buckets_only() {
+        let limiter = RateLimiter::new(1, 2);
+        let started_at = Instant::now();
+        let client = "203.0.113.7".parse().unwrap();
+
+        limiter.check(client, started_at).unwrap();
+        limiter.check(client, started_at).unwrap();
+        assert!(limiter.check(client, started_at).is_err());
+
+        limiter.evict_idle(started_at + Duration::from_secs(1));
+        assert_eq!(limiter.buckets.lock().unwrap().len(), 1);
+
+        limiter.evict_idle(started_at + Duration::from_secs(2));
+        assert!(limiter.buckets.lock().unwrap().is_empty());
+    }
+}
@@ -1,28 +1,53 @@
+use chrono::{DateTime, Utc};
 use itertools::Itertools;
+use percent_encoding::percent_decode_str;
 use serde_qs::Config;
 use std::collections::HashMap;
 use std::convert::Infallible;
 use std::sync::Arc;
+use std::time::Duration;
 use validator::Validate;
-use warp::{Filter, Rejection};
+use warp::{http::StatusCode, reply::Response, Filter, Rejection, Reply};
 use wavesexchange_log::{debug, error, info};
 use wavesexchange_warp::error::{
-    error_handler_with_serde_qs, handler, internal, timeout, validation,
+    error_handler_with_serde_qs, handler, internal, not_found, timeout, validation,
 };
 use wavesexchange_warp::{log::access, MetricsWarpBuilder};
 
-use super::dtos::{escape_querystring_field, MgetRequest, RequestOptions, SearchRequest};
-use super::models::{Asset, AssetInfo, List};
-use super::{DEFAULT_FORMAT, DEFAULT_INCLUDE_METADATA, DEFAULT_LIMIT, ERROR_CODES_PREFIX};
+use super::dtos::{
+    escape_querystring_field, AssetChangesRequest, EmptyResponseFormat, IssuerAssetsRequest,
+    MgetRequest, RequestOptions, ResponseFormat, SearchRequest, SupplyChangesRequest,
+    UpdatedAssetsRequest,
+};
+use super::models::{Asset, AssetChanges, ConsumerLiveness, List, Sponsorship, SupplyChange};
+use super::openapi;
+use super::rate_limit::{rate_limit, RateLimited, RateLimiter};
+use super::{
+    json_charset, DEFAULT_DEBUG_CACHE, DEFAULT_EMPTY_RESPONSE_FORMAT, DEFAULT_INCLUDE_METADATA,
+    DEFAULT_INCLUDE_NFTS, DEFAULT_LABEL_DISPLAY, DEFAULT_LIMIT, DEFAULT_STRICT, DEFAULT_WITH_TOTAL,
+    ERROR_CODES_PREFIX,
+};
 use crate::error;
+use crate::metrics;
 use crate::services;
-use crate::services::assets::MgetOptions;
+use crate::services::assets::{AbsenceReason, GetOptions, MgetOptions, MgetResult, SearchItem};
+use crate::waves::is_valid_base58;
+
+/// How often the buckets of the clients gone idle are dropped
+const RATE_LIMIT_EVICTION_PERIOD: Duration = Duration::from_secs(60);
 
 pub async fn start(
     port: u16,
     metrics_port: u16,
     assets_service: impl services::assets::Service + Send + Sync + 'static,
     images_service: impl services::images::Service + Send + Sync + 'static,
+    debug_absence_reasons: bool,
+    max_limit: u32,
+    consumer_liveness_window: Duration,
+    chain_id: u8,
+    rate_limiter: Option<RateLimiter>,
+    trust_forwarded_for: bool,
+    label_display_names: HashMap<String, String>,
 ) {
     let with_assets_service = {
         let assets_service = Arc::new(assets_service);
@@ -34,6 +59,25 @@ pub async fn start(
         warp::any().map(move || images_service.clone())
     };
 
+    let with_debug_absence_reasons = warp::any().map(move || debug_absence_reasons);
+
+    let with_max_limit = warp::any().map(move || max_limit);
+
+    let with_chain_id = warp::any().map(move || chain_id);
+
+    let with_label_display_names = {
+        let label_display_names = Arc::new(label_display_names);
+        warp::any().map(move || label_display_names.clone())
+    };
+
+    let with_rate_limit = {
+        let rate_limiter = rate_limiter.map(Arc::new);
+        if let Some(rate_limiter) = &rate_limiter {
+            rate_limiter.spawn_eviction(RATE_LIMIT_EVICTION_PERIOD);
+        }
+        rate_limit(rate_limiter, trust_forwarded_for)
+    };
+
     let error_handler = handler(ERROR_CODES_PREFIX, |err| match err {
         error::Error::ValidationError(field, error_details) => {
             let mut error_details = error_details.to_owned();
@@ -48,6 +92,14 @@ pub async fn start(
             error!("{:?}", err);
             timeout(ERROR_CODES_PREFIX)
         }
+        error::Error::NotFound(_) => not_found(ERROR_CODES_PREFIX),
+        error::Error::UnresolvedAssets(unresolved) => {
+            let mut details = unresolved.to_owned();
+            details.insert("parameter".to_owned(), "ids".to_owned());
+            let mut response = validation::invalid_parameter(ERROR_CODES_PREFIX, Some(details));
+            response.http_code = StatusCode::NOT_FOUND;
+            response
+        }
         _ => {
             error!("{:?}", err);
             internal(ERROR_CODES_PREFIX)
@@ -56,6 +108,7 @@ pub async fn start(
 
     let assets_get_handler = warp::path!("assets")
         .and(warp::get())
+        .and(with_rate_limit.clone())
         .and(with_assets_service.clone())
         .and(with_images_service.clone())
         // parse SearchRequest
@@ -65,8 +118,10 @@ pub async fn start(
                 .and_then(|qs: String| async move {
                     let cfg = create_serde_qs_config();
                     let qs = escape_querystring_field(&qs, "ids");
+                    let qs = escape_querystring_field(&qs, "ticker__in");
                     let qs = escape_querystring_field(&qs, "label__in");
-                    let qs = escape_querystring_field(&qs, "verified_status");
+                    let qs = escape_querystring_field(&qs, "label__not_in");
+                    let qs = escape_querystring_field(&qs, "verified_status__in");
                     parse_querystring(&cfg, qs.as_str())
                 })
                 .and_then(|value| async move { validate(value).map_err(warp::reject::custom) }),
@@ -78,41 +133,169 @@ pub async fn start(
                 .and_then(|qs: String| async move {
                     let cfg = create_serde_qs_config();
                     let qs = escape_querystring_field(&qs, "ids");
+                    let qs = escape_querystring_field(&qs, "ticker__in");
                     let qs = escape_querystring_field(&qs, "label__in");
-                    let qs = escape_querystring_field(&qs, "verified_status");
+                    let qs = escape_querystring_field(&qs, "label__not_in");
+                    let qs = escape_querystring_field(&qs, "verified_status__in");
                     parse_querystring(&cfg, qs.as_str())
                 })
                 .and_then(|value| async move { validate(value).map_err(warp::reject::custom) }),
         )
-        .and_then(assets_get_controller)
+        .and(with_max_limit)
+        .and(with_chain_id)
+        .and(with_label_display_names.clone())
+        .and_then(assets_get_controller);
+
+    let assets_updated_handler = warp::path!("assets" / "updated")
+        .and(warp::get())
+        .and(with_rate_limit.clone())
+        .and(with_assets_service.clone())
+        .and(with_images_service.clone())
+        .and(
+            serde_qs::warp::query::<UpdatedAssetsRequest>(create_serde_qs_config())
+                .and_then(|value| async move { validate(value).map_err(warp::reject::custom) }),
+        )
+        .and(request_options())
+        .and(with_max_limit)
+        .and(with_label_display_names.clone())
+        .and_then(assets_updated_controller)
+        .map(|res| warp::reply::json(&res));
+
+    let asset_get_handler = warp::path!("assets" / String)
+        .and(warp::get())
+        .and(with_rate_limit.clone())
+        .and(with_assets_service.clone())
+        .and(with_images_service.clone())
+        .and(request_options())
+        .and(with_debug_absence_reasons)
+        .and(with_label_display_names.clone())
+        .and_then(asset_get_controller)
+        .map(|res| warp::reply::json(&res));
+
+    let asset_by_ticker_handler = warp::path!("assets" / "by-ticker" / String)
+        .and(warp::get())
+        .and(with_rate_limit.clone())
+        .and(with_assets_service.clone())
+        .and(with_images_service.clone())
+        .and(request_options())
+        .and(with_label_display_names.clone())
+        .and_then(asset_by_ticker_controller)
+        .map(|res| warp::reply::json(&res));
+
+    let asset_sponsorship_handler = warp::path!("assets" / String / "sponsorship")
+        .and(warp::get())
+        .and(with_rate_limit.clone())
+        .and(with_assets_service.clone())
+        .and_then(asset_sponsorship_controller)
+        .map(|res| warp::reply::json(&res));
+
+    let asset_supply_changes_handler = warp::path!("assets" / String / "supply-changes")
+        .and(warp::get())
+        .and(with_rate_limit.clone())
+        .and(with_assets_service.clone())
+        .and(serde_qs::warp::query::<SupplyChangesRequest>(
+            create_serde_qs_config(),
+        ))
+        .and_then(asset_supply_changes_controller)
+        .map(|res| warp::reply::json(&res));
+
+    let asset_changes_handler = warp::path!("assets" / String / "changes")
+        .and(warp::get())
+        .and(with_rate_limit.clone())
+        .and(with_assets_service.clone())
+        .and(serde_qs::warp::query::<AssetChangesRequest>(
+            create_serde_qs_config(),
+        ))
+        .and_then(asset_changes_controller);
+
+    let issuer_assets_handler = warp::path!("issuers" / String / "assets")
+        .and(warp::get())
+        .and(with_rate_limit.clone())
+        .and(with_assets_service.clone())
+        .and(with_images_service.clone())
+        .and(
+            serde_qs::warp::query::<IssuerAssetsRequest>(create_serde_qs_config())
+                .and_then(|value| async move { validate(value).map_err(warp::reject::custom) }),
+        )
+        .and(request_options())
+        .and(with_max_limit)
+        .and(with_label_display_names.clone())
+        .and_then(issuer_assets_controller)
         .map(|res| warp::reply::json(&res));
 
     let assets_post_handler = warp::path!("assets")
         .and(warp::post())
+        .and(with_rate_limit.clone())
         .and(with_assets_service.clone())
         .and(with_images_service.clone())
         .and(warp::body::json::<MgetRequest>())
-        .and(serde_qs::warp::query::<RequestOptions>(
-            create_serde_qs_config(),
-        ))
+        .and(request_options())
+        .and(with_label_display_names.clone())
         .and_then(assets_post_controller)
         .map(|res| warp::reply::json(&res));
 
+    let consumer_liveness_handler = warp::path!("sync" / "liveness")
+        .and(warp::get())
+        .and(with_assets_service.clone())
+        .and(warp::any().map(move || consumer_liveness_window))
+        .and_then(consumer_liveness_controller);
+
+    let openapi_handler = {
+        let document = openapi::document();
+        warp::path!("openapi.json")
+            .and(warp::get())
+            .map(move || warp::reply::json(&document))
+    };
+
+    let docs_handler = {
+        let html = openapi::docs_html("Asset search API");
+        warp::path!("docs")
+            .and(warp::get())
+            .map(move || warp::reply::html(html.clone()))
+    };
+
     let log = warp::log::custom(access);
 
     info!("Starting API server at 0.0.0.0:{}", port);
 
     let routes = assets_get_handler
+        .or(assets_updated_handler)
+        .or(asset_get_handler)
+        .or(asset_by_ticker_handler)
+        .or(asset_sponsorship_handler)
+        .or(asset_supply_changes_handler)
+        .or(asset_changes_handler)
+        .or(issuer_assets_handler)
         .or(assets_post_handler)
-        .recover(move |rej| {
-            error!("{:?}", rej);
-            error_handler_with_serde_qs(ERROR_CODES_PREFIX, error_handler.clone())(rej)
+        .or(consumer_liveness_handler)
+        .or(openapi_handler)
+        .or(docs_handler)
+        .recover(move |rej: Rejection| {
+            let rate_limited = rej
+                .find::<RateLimited>()
+                .map(|limited| limited.response(ERROR_CODES_PREFIX));
+            if rate_limited.is_none() {
+                error!("{:?}", rej);
+            }
+            let handled =
+                error_handler_with_serde_qs(ERROR_CODES_PREFIX, error_handler.clone())(rej);
+            async move {
+                match rate_limited {
+                    Some(response) => Ok(response),
+                    None => handled.await.map(Reply::into_response),
+                }
+            }
         })
+        .map(json_charset)
         .with(log);
 
     MetricsWarpBuilder::new()
         .with_main_routes(routes)
         .with_metrics_port(metrics_port)
+        .with_metric(&*metrics::API_REQUESTS)
+        .with_metric(&*metrics::DB_POOL_CONNECTIONS)
+        .with_metric(&*metrics::DB_POOL_IDLE_CONNECTIONS)
+        .with_metric(&*metrics::CACHE_READS)
         .with_main_routes_port(port)
         .run_async()
         .await;
@@ -123,58 +306,113 @@ async fn assets_get_controller(
     images_service: Arc<impl services::images::Service>,
     req: SearchRequest,
     opts: RequestOptions,
-) -> Result<List<Asset>, Rejection> {
+    max_limit: u32,
+    chain_id: u8,
+    label_display_names: Arc<HashMap<String, String>>,
+) -> Result<Response, Rejection> {
     debug!("assets_get_controller"; "req" => format!("{:?}", req), "opts" => format!("{:?}", opts));
+    metrics::API_REQUESTS
+        .with_label_values(&["assets_get"])
+        .inc();
 
-    let limit = req.limit.unwrap_or(DEFAULT_LIMIT);
+    let limit = page_size(req.limit, max_limit)?;
     let include_metadata = opts.include_metadata.unwrap_or(DEFAULT_INCLUDE_METADATA);
-    let format = opts.format.unwrap_or(DEFAULT_FORMAT);
+    let format = opts.response_format();
+    let debug_cache = opts.debug_cache.unwrap_or(DEFAULT_DEBUG_CACHE);
+    let label_display_names = requested_label_display_names(&opts, &label_display_names);
+    let empty_response_format = opts.empty.unwrap_or(DEFAULT_EMPTY_RESPONSE_FORMAT);
+    let with_total = opts.with_total.unwrap_or(DEFAULT_WITH_TOTAL);
+    let include_nfts = req.include_nfts.unwrap_or(DEFAULT_INCLUDE_NFTS);
+    let backward = req.before.is_some();
+    let by_ids = req.ids.is_some();
+    let strict = opts.strict.unwrap_or(DEFAULT_STRICT);
 
-    let asset_ids: Vec<String> = if let Some(ids) = req.ids {
-        ids
+    let (found_assets, total): (Vec<SearchItem>, Option<u64>) = if let Some(ids) = req.ids {
+        let total = if with_total {
+            Some(ids.len() as u64)
+        } else {
+            None
+        };
+        let found_assets = ids
+            .into_iter()
+            .map(|id| SearchItem {
+                cursor: id.clone(),
+                id,
+            })
+            .collect();
+        (found_assets, total)
     } else {
-        let req = services::assets::SearchRequest::from(req).with_limit(limit + 1);
-        assets_service.search(&req)?
+        let req = services::assets::SearchRequest::from(req.resolve_issuer_public_keys(chain_id))
+            .with_limit(limit.saturating_add(1));
+        let total = if with_total {
+            Some(assets_service.count(&req)?)
+        } else {
+            None
+        };
+        (assets_service.search(&req)?, total)
     };
 
-    let has_next_page = if asset_ids.len() as u32 > limit {
+    let has_next_page = if found_assets.len() as u32 > limit {
         true
     } else {
         false
     };
 
-    let asset_ids = asset_ids
+    // backward page is ordered ascending too, so the extra asset is the first one
+    let skip = if backward && has_next_page { 1 } else { 0 };
+
+    let page = found_assets
         .iter()
+        .skip(skip)
         .take(limit as usize)
-        .map(AsRef::as_ref)
         .collect_vec();
 
+    let asset_ids = page.iter().map(|a| a.id.as_str()).collect_vec();
+
     let mget_options = match opts.height_gte {
         Some(height) => MgetOptions::with_height(height),
         _ => MgetOptions::default(),
-    };
+    }
+    .set_include_nfts(include_nfts);
 
-    let assets = assets_service.mget(&asset_ids, &mget_options).await?;
+    let assets = mget_assets(
+        assets_service.as_ref(),
+        &asset_ids,
+        &mget_options,
+        &format,
+        include_metadata,
+    )
+    .await?;
 
-    let has_images = if include_metadata {
-        images_service.has_images(&asset_ids).await?
+    if by_ids && strict {
+        check_resolved(&asset_ids, &assets)?;
+    }
+
+    let image_urls = if include_metadata {
+        images_service.get_image_urls(&asset_ids).await?
     } else {
-        vec![false; asset_ids.len()]
+        vec![None; asset_ids.len()]
     };
 
     let assets = assets
         .into_iter()
-        .zip(has_images)
-        .map(|(o, has_image)| Asset::new(o, has_image, include_metadata, &format))
+        .zip(image_urls)
+        .map(|(o, image_url)| {
+            let from_cache = cache_status(&o, debug_cache);
+            let asset = if by_ids {
+                asset_with_reason(o, image_url, include_metadata, &format)
+            } else {
+                Asset::new(o.found(), image_url, include_metadata, &format)
+            };
+            asset
+                .with_from_cache(from_cache)
+                .with_label_display_names(label_display_names)
+        })
         .collect_vec();
 
     let last_cursor = if has_next_page {
-        assets.last().and_then(|a| {
-            a.data.as_ref().map(|ai| match ai {
-                AssetInfo::Full(ai) => ai.id.clone(),
-                AssetInfo::Brief(ai) => ai.id.clone(),
-            })
-        })
+        let cursor_asset = if backward { page.first() } else { page.last() };
+        cursor_asset.map(|a| a.cursor.clone())
     } else {
         None
     };
@@ -182,6 +420,309 @@ async fn assets_get_controller(
     let list = List {
         data: assets,
         cursor: last_cursor,
+        total,
+    };
+
+    Ok(list_reply(list, &empty_response_format))
+}
+
+async fn assets_updated_controller(
+    assets_service: Arc<impl services::assets::Service>,
+    images_service: Arc<impl services::images::Service>,
+    req: UpdatedAssetsRequest,
+    opts: RequestOptions,
+    max_limit: u32,
+    label_display_names: Arc<HashMap<String, String>>,
+) -> Result<List<Asset>, Rejection> {
+    debug!("assets_updated_controller"; "req" => format!("{:?}", req));
+    metrics::API_REQUESTS
+        .with_label_values(&["assets_updated"])
+        .inc();
+
+    let limit = page_size(req.limit, max_limit)?;
+    let include_metadata = opts.include_metadata.unwrap_or(DEFAULT_INCLUDE_METADATA);
+    let format = opts.response_format();
+    let debug_cache = opts.debug_cache.unwrap_or(DEFAULT_DEBUG_CACHE);
+    let label_display_names = requested_label_display_names(&opts, &label_display_names);
+
+    let asset_ids = assets_service.updated_between(
+        req.from,
+        req.to,
+        req.after.as_deref(),
+        limit.saturating_add(1),
+    )?;
+
+    let has_next_page = asset_ids.len() as u32 > limit;
+
+    let asset_ids = asset_ids
+        .iter()
+        .take(limit as usize)
+        .map(AsRef::as_ref)
+        .collect_vec();
+
+    let assets = assets_service
+        .mget(&asset_ids, &MgetOptions::default())
+        .await?;
+
+    let image_urls = if include_metadata {
+        images_service.get_image_urls(&asset_ids).await?
+    } else {
+        vec![None; asset_ids.len()]
+    };
+
+    let list = List {
+        data: assets
+            .into_iter()
+            .zip(image_urls)
+            .map(|(o, image_url)| {
+                let from_cache = cache_status(&o, debug_cache);
+                Asset::new(o.found(), image_url, include_metadata, &format)
+                    .with_from_cache(from_cache)
+                    .with_label_display_names(label_display_names)
+            })
+            .collect_vec(),
+        // keyset cursor is the last asset id regardless of whether the asset is still present
+        cursor: if has_next_page {
+            asset_ids.last().map(|id| id.to_string())
+        } else {
+            None
+        },
+        total: None,
+    };
+
+    Ok(list)
+}
+
+async fn asset_get_controller(
+    id: String,
+    assets_service: Arc<impl services::assets::Service>,
+    images_service: Arc<impl services::images::Service>,
+    opts: RequestOptions,
+    debug_absence_reasons: bool,
+    label_display_names: Arc<HashMap<String, String>>,
+) -> Result<Asset, Rejection> {
+    debug!("asset_get_controller"; "id" => &id);
+    metrics::API_REQUESTS
+        .with_label_values(&["asset_get"])
+        .inc();
+
+    let include_metadata = opts.include_metadata.unwrap_or(DEFAULT_INCLUDE_METADATA);
+    let format = opts.response_format();
+
+    let asset_info = match assets_service.get(&id, &GetOptions::default()).await? {
+        Some(asset_info) => asset_info,
+        None => {
+            if debug_absence_reasons {
+                if let Some(reason) = assets_service.absence_reason(&id)? {
+                    return Ok(Asset::absent(reason));
+                }
+            }
+            return Err(warp::reject::custom(error::Error::NotFound(format!(
+                "Asset {} not found",
+                id
+            ))));
+        }
+    };
+
+    let image_url = if include_metadata {
+        images_service.get_image_url(&id).await?
+    } else {
+        None
+    };
+
+    Ok(
+        Asset::new(Some(asset_info), image_url, include_metadata, &format)
+            .with_label_display_names(requested_label_display_names(&opts, &label_display_names)),
+    )
+}
+
+async fn asset_by_ticker_controller(
+    ticker: String,
+    assets_service: Arc<impl services::assets::Service>,
+    images_service: Arc<impl services::images::Service>,
+    opts: RequestOptions,
+    label_display_names: Arc<HashMap<String, String>>,
+) -> Result<Asset, Rejection> {
+    let ticker = percent_decode_str(&ticker).decode_utf8_lossy();
+    debug!("asset_by_ticker_controller"; "ticker" => ticker.as_ref());
+    metrics::API_REQUESTS
+        .with_label_values(&["asset_by_ticker"])
+        .inc();
+
+    let include_metadata = opts.include_metadata.unwrap_or(DEFAULT_INCLUDE_METADATA);
+    let format = opts.response_format();
+
+    let asset_info = assets_service
+        .get_by_ticker(&ticker, &GetOptions::default())
+        .await?
+        .ok_or_else(|| error::Error::NotFound(format!("Asset with ticker {} not found", ticker)))?;
+
+    let image_url = if include_metadata {
+        images_service.get_image_url(&asset_info.asset.id).await?
+    } else {
+        None
+    };
+
+    Ok(
+        Asset::new(Some(asset_info), image_url, include_metadata, &format)
+            .with_label_display_names(requested_label_display_names(&opts, &label_display_names)),
+    )
+}
+
+async fn asset_sponsorship_controller(
+    id: String,
+    assets_service: Arc<impl services::assets::Service>,
+) -> Result<Sponsorship, Rejection> {
+    debug!("asset_sponsorship_controller"; "id" => &id);
+    metrics::API_REQUESTS
+        .with_label_values(&["asset_sponsorship"])
+        .inc();
+
+    let asset_info = assets_service
+        .get(&id, &GetOptions::default())
+        .await?
+        .ok_or_else(|| error::Error::NotFound(format!("Asset {} not found", id)))?;
+
+    let sponsorship = Sponsorship::from_asset_info(&asset_info)
+        .ok_or_else(|| error::Error::NotFound(format!("Asset {} is not sponsored", id)))?;
+
+    Ok(sponsorship)
+}
+
+async fn asset_supply_changes_controller(
+    id: String,
+    assets_service: Arc<impl services::assets::Service>,
+    req: SupplyChangesRequest,
+) -> Result<List<SupplyChange>, Rejection> {
+    debug!("asset_supply_changes_controller"; "id" => &id, "req" => format!("{:?}", req));
+    metrics::API_REQUESTS
+        .with_label_values(&["asset_supply_changes"])
+        .inc();
+
+    let supply_changes = assets_service.supply_changes(&id, req.height_gte, req.height_lte)?;
+
+    Ok(List {
+        data: supply_changes.into_iter().map(SupplyChange::from).collect(),
+        cursor: None,
+        total: None,
+    })
+}
+
+/// Replies with the fields changed since the requested version or with 304 Not Modified
+async fn asset_changes_controller(
+    id: String,
+    assets_service: Arc<impl services::assets::Service>,
+    req: AssetChangesRequest,
+) -> Result<Response, Rejection> {
+    debug!("asset_changes_controller"; "id" => &id, "version" => req.version);
+    metrics::API_REQUESTS
+        .with_label_values(&["asset_changes"])
+        .inc();
+
+    let delta = assets_service
+        .changes_since(&id, req.version)?
+        .ok_or_else(|| error::Error::NotFound(format!("Asset {} not found", id)))?;
+
+    if delta.changes.is_empty() {
+        return Ok(StatusCode::NOT_MODIFIED.into_response());
+    }
+
+    Ok(warp::reply::json(&AssetChanges::new(id, delta)).into_response())
+}
+
+/// Tells whether the consumer has saved its state within the window
+///
+/// Informational only: it replies with 200 OK either way, as failing it would restart the api
+/// instead of the stalled consumer.
+async fn consumer_liveness_controller(
+    assets_service: Arc<impl services::assets::Service>,
+    window: Duration,
+) -> Result<Response, Rejection> {
+    let updated_at = assets_service.consumer_updated_at()?;
+    let alive = is_consumer_alive(updated_at, Utc::now(), window);
+
+    Ok(warp::reply::json(&ConsumerLiveness { alive, updated_at }).into_response())
+}
+
+/// A heartbeat from the future (e.g. after clock skew) is considered fresh
+fn is_consumer_alive(
+    updated_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    window: Duration,
+) -> bool {
+    updated_at.map_or(false, |updated_at| {
+        now.signed_duration_since(updated_at)
+            .to_std()
+            .map_or(true, |age| age <= window)
+    })
+}
+
+async fn issuer_assets_controller(
+    address: String,
+    assets_service: Arc<impl services::assets::Service>,
+    images_service: Arc<impl services::images::Service>,
+    req: IssuerAssetsRequest,
+    opts: RequestOptions,
+    max_limit: u32,
+    label_display_names: Arc<HashMap<String, String>>,
+) -> Result<List<Asset>, Rejection> {
+    debug!("issuer_assets_controller"; "address" => &address, "req" => format!("{:?}", req));
+    metrics::API_REQUESTS
+        .with_label_values(&["issuer_assets"])
+        .inc();
+
+    if !is_valid_base58(&address) {
+        let details = vec![("reason".to_owned(), "Got invalid base58 string".to_owned())]
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        return Err(warp::reject::custom(error::Error::ValidationError(
+            "address".to_owned(),
+            Some(details),
+        )));
+    }
+
+    let limit = page_size(req.limit, max_limit)?;
+    let include_metadata = opts.include_metadata.unwrap_or(DEFAULT_INCLUDE_METADATA);
+    let format = opts.response_format();
+    let debug_cache = opts.debug_cache.unwrap_or(DEFAULT_DEBUG_CACHE);
+    let label_display_names = requested_label_display_names(&opts, &label_display_names);
+
+    let found_assets =
+        assets_service.by_issuer(&address, req.after.as_deref(), limit.saturating_add(1))?;
+
+    let has_next_page = found_assets.len() as u32 > limit;
+
+    let page = found_assets.iter().take(limit as usize).collect_vec();
+
+    let asset_ids = page.iter().map(|a| a.id.as_str()).collect_vec();
+
+    let assets = assets_service
+        .mget(&asset_ids, &MgetOptions::default())
+        .await?;
+
+    let image_urls = if include_metadata {
+        images_service.get_image_urls(&asset_ids).await?
+    } else {
+        vec![None; asset_ids.len()]
+    };
+
+    let list = List {
+        data: assets
+            .into_iter()
+            .zip(image_urls)
+            .map(|(o, image_url)| {
+                let from_cache = cache_status(&o, debug_cache);
+                Asset::new(o.found(), image_url, include_metadata, &format)
+                    .with_from_cache(from_cache)
+                    .with_label_display_names(label_display_names)
+            })
+            .collect_vec(),
+        cursor: if has_next_page {
+            page.last().map(|a| a.cursor.clone())
+        } else {
+            None
+        },
+        total: None,
     };
 
     Ok(list)
@@ -192,11 +733,18 @@ async fn assets_post_controller(
     images_service: Arc<impl services::images::Service>,
     req: MgetRequest,
     opts: RequestOptions,
+    label_display_names: Arc<HashMap<String, String>>,
 ) -> Result<List<Asset>, Rejection> {
     debug!("assets_post_controller");
+    metrics::API_REQUESTS
+        .with_label_values(&["assets_post"])
+        .inc();
 
     let include_metadata = opts.include_metadata.unwrap_or(DEFAULT_INCLUDE_METADATA);
-    let format = opts.format.unwrap_or(DEFAULT_FORMAT);
+    let format = opts.response_format();
+    let debug_cache = opts.debug_cache.unwrap_or(DEFAULT_DEBUG_CACHE);
+    let label_display_names = requested_label_display_names(&opts, &label_display_names);
+    let strict = opts.strict.unwrap_or(DEFAULT_STRICT);
 
     let asset_ids = req.ids.iter().map(AsRef::as_ref).collect_vec();
 
@@ -205,26 +753,132 @@ async fn assets_post_controller(
         _ => MgetOptions::default(),
     };
 
-    let assets = assets_service.mget(&asset_ids, &mget_options).await?;
+    let assets = mget_assets(
+        assets_service.as_ref(),
+        &asset_ids,
+        &mget_options,
+        &format,
+        include_metadata,
+    )
+    .await?;
 
-    let has_images = if include_metadata {
-        images_service.has_images(&asset_ids).await?
+    if strict {
+        check_resolved(&asset_ids, &assets)?;
+    }
+
+    let image_urls = if include_metadata {
+        images_service.get_image_urls(&asset_ids).await?
     } else {
-        vec![false; asset_ids.len()]
+        vec![None; asset_ids.len()]
     };
 
     let list = List {
         data: assets
             .into_iter()
-            .zip(has_images)
-            .map(|(o, has_image)| Asset::new(o, has_image, include_metadata, &format))
+            .zip(image_urls)
+            .map(|(o, image_url)| {
+                let from_cache = cache_status(&o, debug_cache);
+                asset_with_reason(o, image_url, include_metadata, &format)
+                    .with_from_cache(from_cache)
+                    .with_label_display_names(label_display_names)
+            })
             .collect_vec(),
         cursor: None,
+        total: None,
     };
 
     Ok(list)
 }
 
+/// Fails with the ids of the missing assets grouped by the absence reason
+fn check_resolved(ids: &[&str], assets: &[MgetResult]) -> Result<(), error::Error> {
+    let unresolved = ids
+        .iter()
+        .zip(assets)
+        .filter_map(|(id, o)| o.absence_reason().map(|reason| (reason, *id)))
+        .into_group_map()
+        .into_iter()
+        .map(|(reason, ids)| {
+            let reason = match reason {
+                AbsenceReason::NotFound => "not_found",
+                AbsenceReason::Hidden => "hidden",
+                AbsenceReason::NftExcluded => "nft_excluded",
+            };
+            (reason.to_owned(), ids.join(","))
+        })
+        .collect::<HashMap<_, _>>();
+
+    if unresolved.is_empty() {
+        Ok(())
+    } else {
+        Err(error::Error::UnresolvedAssets(unresolved))
+    }
+}
+
+/// Brief assets without the metadata are served from the blockchain data cache alone
+async fn mget_assets(
+    assets_service: &impl services::assets::Service,
+    asset_ids: &[&str],
+    opts: &MgetOptions,
+    format: &ResponseFormat,
+    include_metadata: bool,
+) -> Result<Vec<MgetResult>, error::Error> {
+    if matches!(format, ResponseFormat::Brief) && !include_metadata {
+        assets_service.mget_brief(asset_ids, opts).await
+    } else {
+        assets_service.mget(asset_ids, opts).await
+    }
+}
+
+/// Whether the asset was served from the cache, if it is requested
+fn cache_status(result: &MgetResult, debug_cache: bool) -> Option<bool> {
+    if debug_cache {
+        Some(result.from_cache())
+    } else {
+        None
+    }
+}
+
+/// Asset requested by id, missing assets explain why they are missing
+fn asset_with_reason(
+    result: MgetResult,
+    image_url: Option<String>,
+    include_metadata: bool,
+    format: &ResponseFormat,
+) -> Asset {
+    match result.absence_reason() {
+        Some(reason) => Asset::absent(reason),
+        None => Asset::new(result.found(), image_url, include_metadata, format),
+    }
+}
+
+/// Display names of the labels if they are requested with `label_display=true`
+fn requested_label_display_names<'a>(
+    opts: &RequestOptions,
+    label_display_names: &'a HashMap<String, String>,
+) -> Option<&'a HashMap<String, String>> {
+    if opts.label_display.unwrap_or(DEFAULT_LABEL_DISPLAY) {
+        Some(label_display_names)
+    } else {
+        None
+    }
+}
+
+/// Replies with the list or with 204 No Content if the list is empty and it was requested
+fn list_reply(list: List<Asset>, empty_response_format: &EmptyResponseFormat) -> Response {
+    if list.data.is_empty() && *empty_response_format == EmptyResponseFormat::NoContent {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        warp::reply::json(&list).into_response()
+    }
+}
+
+/// Parses and validates the request options of the querystring
+fn request_options() -> impl Filter<Extract = (RequestOptions,), Error = Rejection> + Clone {
+    serde_qs::warp::query::<RequestOptions>(create_serde_qs_config())
+        .and_then(|value| async move { validate(value).map_err(warp::reject::custom) })
+}
+
 fn create_serde_qs_config() -> serde_qs::Config {
     serde_qs::Config::new(5, false)
 }
@@ -239,6 +893,26 @@ where
         .map_err(|e| warp::reject::custom(e))
 }
 
+/// Requested page size, the default one is reduced to the configured maximum
+fn page_size(limit: Option<u32>, max_limit: u32) -> Result<u32, error::Error> {
+    match limit {
+        Some(limit) if limit > max_limit => {
+            let details = vec![(
+                "reason".to_owned(),
+                format!("Page size has to be {} at most", max_limit),
+            )]
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+            Err(error::Error::ValidationError(
+                "limit".to_owned(),
+                Some(details),
+            ))
+        }
+        Some(limit) => Ok(limit),
+        None => Ok(DEFAULT_LIMIT.min(max_limit)),
+    }
+}
+
 fn validate<T>(value: T) -> Result<T, error::Error>
 where
     T: Validate,
@@ -273,10 +947,141 @@ where
 
 #[cfg(test)]
 mod tests {
+    use chrono::{TimeZone, Utc};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use warp::http::StatusCode;
+
     use super::super::{
-        dtos::SearchRequest,
-        server::{create_serde_qs_config, parse_querystring},
+        dtos::{EmptyResponseFormat, MgetRequest, RequestOptions, SearchRequest},
+        models::List,
+        server::{
+            assets_post_controller, consumer_liveness_controller, create_serde_qs_config,
+            is_consumer_alive, list_reply, page_size, parse_querystring,
+        },
+        MAX_LIMIT,
     };
+    use crate::error::Error as AppError;
+    use crate::models::{Asset, AssetInfo, AssetMetadata};
+    use crate::services::assets::mock::MockService;
+    use crate::services::images::dummy::DummyService;
+
+    fn asset_info(id: &str, nft: bool) -> AssetInfo {
+        AssetInfo {
+            asset: Asset {
+                id: id.to_owned(),
+                name: id.to_owned(),
+                precision: 0,
+                description: "".to_owned(),
+                height: 1,
+                timestamp: Utc::now(),
+                issuer: "issuer".to_owned(),
+                quantity: 1,
+                reissuable: false,
+                min_sponsored_fee: None,
+                smart: false,
+                nft,
+                ticker: None,
+            },
+            metadata: AssetMetadata {
+                labels: vec![],
+                sponsor_balance: None,
+                oracles_data: HashMap::new(),
+            },
+        }
+    }
+
+    /// Assets service knowing a single asset and a single NFT
+    fn service() -> Arc<MockService> {
+        Arc::new(
+            MockService::new(vec![asset_info("asset", false), asset_info("nft", true)])
+                .with_consumer_updated_at(Utc.timestamp(0, 0)),
+        )
+    }
+
+    fn mget_request() -> MgetRequest {
+        MgetRequest {
+            ids: vec!["asset".to_owned(), "nft".to_owned(), "absent".to_owned()],
+        }
+    }
+
+    fn request_options(strict: Option<bool>) -> RequestOptions {
+        RequestOptions {
+            format: None,
+            include_metadata: Some(false),
+            height_gte: None,
+            empty: None,
+            with_total: None,
+            strict,
+            fields: None,
+            debug_cache: None,
+            label_display: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn should_explain_missing_assets_if_not_strict() {
+        let list = assets_post_controller(
+            service(),
+            Arc::new(DummyService::new()),
+            mget_request(),
+            request_options(None),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+
+        let reasons = list
+            .data
+            .iter()
+            .map(|a| serde_json::to_value(a).unwrap()["reason"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reasons,
+            vec![
+                serde_json::Value::Null,
+                serde_json::json!("nft_excluded"),
+                serde_json::json!("not_found")
+            ]
+        );
+        assert!(list.data[0].data.is_some());
+    }
+
+    #[tokio::test]
+    async fn should_reject_missing_assets_if_strict() {
+        let rejection = assets_post_controller(
+            service(),
+            Arc::new(DummyService::new()),
+            mget_request(),
+            request_options(Some(true)),
+            Default::default(),
+        )
+        .await
+        .unwrap_err();
+
+        match rejection.find::<AppError>() {
+            Some(AppError::UnresolvedAssets(unresolved)) => {
+                assert_eq!(unresolved.len(), 2);
+                assert_eq!(unresolved["not_found"], "absent");
+                assert_eq!(unresolved["nft_excluded"], "nft");
+            }
+            other => panic!("unexpected rejection: {:?}", other),
+        }
+
+        let list = assets_post_controller(
+            service(),
+            Arc::new(DummyService::new()),
+            MgetRequest {
+                ids: vec!["asset".to_owned()],
+            },
+            request_options(Some(true)),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(list.data.len(), 1);
+    }
 
     #[test]
     fn should_parse_querystring() {
@@ -306,4 +1111,78 @@ mod tests {
         assert!(matches!(res, Ok(_)));
         assert!(matches!(res.unwrap().ids, None));
     }
+
+    #[test]
+    fn should_cap_page_size() {
+        assert_eq!(page_size(Some(100), 100).unwrap(), 100);
+        assert_eq!(page_size(None, 100).unwrap(), 100);
+        // the default page size does not exceed the lower maximum
+        assert_eq!(page_size(None, 50).unwrap(), 50);
+
+        match page_size(Some(101), 100) {
+            Err(AppError::ValidationError(field, Some(details))) => {
+                assert_eq!(field, "limit");
+                assert_eq!(details["reason"], "Page size has to be 100 at most");
+            }
+            res => panic!("expected validation error, got {:?}", res),
+        }
+
+        // the hard maximum of the config
+        assert_eq!(page_size(Some(MAX_LIMIT), MAX_LIMIT).unwrap(), MAX_LIMIT);
+        assert!(page_size(Some(MAX_LIMIT + 1), MAX_LIMIT).is_err());
+    }
+
+    #[test]
+    fn should_reply_no_content_for_empty_list_when_requested() {
+        let empty = || List {
+            data: vec![],
+            cursor: None,
+            total: None,
+        };
+
+        let res = list_reply(empty(), &EmptyResponseFormat::List);
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = list_reply(empty(), &EmptyResponseFormat::NoContent);
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[test]
+    fn should_report_stalled_consumer_after_heartbeat_goes_stale() {
+        let updated_at = Utc.timestamp(1660000000, 0);
+        let window = Duration::from_secs(60);
+
+        assert!(is_consumer_alive(
+            Some(updated_at),
+            updated_at + chrono::Duration::seconds(59),
+            window
+        ));
+        assert!(!is_consumer_alive(
+            Some(updated_at),
+            updated_at + chrono::Duration::seconds(61),
+            window
+        ));
+        // the consumer has not handled any block yet
+        assert!(!is_consumer_alive(None, updated_at, window));
+
+        assert!(is_consumer_alive(
+            Some(updated_at),
+            updated_at - chrono::Duration::seconds(1),
+            window
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_report_stalled_consumer_without_failing() {
+        let res = consumer_liveness_controller(service(), Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = warp::hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            serde_json::json!({"alive": false, "updated_at": null})
+        );
+    }
 }
@@ -10,6 +10,7 @@ use super::{ApiBaseUrl, Error, HttpClient};
 pub trait Client: ApiBaseUrl {
     async fn has_svg(&self, asset_id: &str) -> Result<bool, Error>;
 
+    /// Checks all of the images in a single request, in the order of the ids
     async fn has_svgs(&self, asset_ids: &[&str]) -> Result<Vec<bool>, Error>;
 }
 
@@ -48,6 +49,10 @@ impl Client for HttpClient {
     }
 
     async fn has_svgs(&self, asset_ids: &[&str]) -> Result<Vec<bool>, Error> {
+        if asset_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
         let endpoint_url = format!("{}images_existence", &self.root_url);
         let body = json!({ "images": asset_ids.clone().into_iter().map(|asset_id| format!("{}.svg", asset_id)).collect::<Vec<_>>() });
 
@@ -65,12 +70,21 @@ impl Client for HttpClient {
             })?;
 
         if resp.status() == StatusCode::OK {
-            resp.json::<Vec<bool>>().await.map_err(|err| {
+            let has_svgs = resp.json::<Vec<bool>>().await.map_err(|err| {
                 Error::HttpRequestError(
                     Arc::new(err),
                     "Failed to the get result from the images service".to_string(),
                 )
-            })
+            })?;
+            // the results are matched with the ids by position
+            if has_svgs.len() != asset_ids.len() {
+                return Err(Error::ParseResultError(format!(
+                    "Images service returned {} results for {} images",
+                    has_svgs.len(),
+                    asset_ids.len()
+                )));
+            }
+            Ok(has_svgs)
         } else {
             Err(Error::InvalidStatus(
                 resp.status(),
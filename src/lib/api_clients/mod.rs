@@ -1,5 +1,6 @@
 mod error;
 pub mod images;
+pub mod node;
 
 use anyhow::{anyhow, Result};
 use reqwest::Url;
@@ -0,0 +1,46 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::sync::Arc;
+use wavesexchange_log::trace;
+
+use super::{ApiBaseUrl, Error, HttpClient};
+
+#[async_trait]
+pub trait Client: ApiBaseUrl {
+    /// Height of the last block of the node
+    async fn height(&self) -> Result<u32, Error>;
+}
+
+#[derive(Deserialize)]
+struct HeightResponse {
+    height: u32,
+}
+
+#[async_trait]
+impl Client for HttpClient {
+    async fn height(&self) -> Result<u32, Error> {
+        let endpoint_url = format!("{}blocks/height", &self.root_url);
+
+        trace!("Node request: {}", endpoint_url);
+
+        let resp = self.client.get(&endpoint_url).send().await.map_err(|err| {
+            Error::HttpRequestError(
+                Arc::new(err),
+                "Failed to get the height from the node".to_string(),
+            )
+        })?;
+
+        if resp.status() == StatusCode::OK {
+            resp.json::<HeightResponse>()
+                .await
+                .map(|resp| resp.height)
+                .map_err(|err| Error::DecodeResponseBytesError(Arc::new(err)))
+        } else {
+            Err(Error::InvalidStatus(
+                resp.status(),
+                "Failed to get the height from the node".to_string(),
+            ))
+        }
+    }
+}
@@ -7,13 +7,15 @@ use crate::error::Error as AppError;
 
 pub type RedisPool = Pool<RedisConnectionManager>;
 
-pub async fn pool(config: &Config) -> Result<RedisPool, AppError> {
-    let redis_connection_url = format!(
+fn connection_url(config: &Config) -> String {
+    format!(
         "redis://{}:{}@{}:{}",
         config.user, config.password, config.host, config.port
-    );
+    )
+}
 
-    let manager = RedisConnectionManager::new(redis_connection_url)?;
+pub async fn pool(config: &Config) -> Result<RedisPool, AppError> {
+    let manager = RedisConnectionManager::new(connection_url(config))?;
 
     Pool::builder()
         .min_idle(Some(1))
@@ -24,3 +26,8 @@ pub async fn pool(config: &Config) -> Result<RedisPool, AppError> {
         .await
         .map_err(|e| AppError::RedisError(e))
 }
+
+/// Client for the dedicated connections, such as pub/sub ones
+pub fn client(config: &Config) -> Result<redis::Client, AppError> {
+    Ok(redis::Client::open(connection_url(config))?)
+}
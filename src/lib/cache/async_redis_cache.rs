@@ -1,27 +1,108 @@
-use itertools::Itertools;
 use redis::AsyncCommands;
 use serde::{de::DeserializeOwned, Serialize};
 use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
 use wavesexchange_log::trace;
 
-use super::{AsyncReadCache, AsyncWriteCache, CacheKeyFn};
+use super::codec::{CacheCodec, Codec};
+use super::{
+    versioned_key, AsyncNegativeCache, AsyncReadCache, AsyncWriteCache, CacheCounters, CacheKeyFn,
+    Cached, DEFAULT_ABSENT_TTL_SECS,
+};
 use crate::{async_redis::RedisPool, error::Error as AppError};
+
+pub const DEFAULT_MGET_CHUNK_SIZE: usize = 500;
+
 #[derive(Clone)]
 pub struct AsyncRedisCache {
     redis_pool: RedisPool,
     key_prefix: String,
     key_separator: String,
+    schema_version: Option<u32>,
+    ttl: Option<Duration>,
+    counters: Arc<CacheCounters>,
+    absent_ttl_secs: usize,
+    mget_chunk_size: usize,
+    codec: Codec,
 }
 
 pub fn new(
     redis_pool: RedisPool,
     key_prefix: impl AsRef<str>,
     key_separator: impl AsRef<str>,
+    ttl: Option<Duration>,
 ) -> AsyncRedisCache {
     AsyncRedisCache {
         redis_pool,
         key_prefix: key_prefix.as_ref().to_string(),
         key_separator: key_separator.as_ref().to_string(),
+        schema_version: None,
+        ttl,
+        counters: Arc::new(CacheCounters::with_metrics_label(key_prefix.as_ref())),
+        absent_ttl_secs: DEFAULT_ABSENT_TTL_SECS,
+        mget_chunk_size: DEFAULT_MGET_CHUNK_SIZE,
+        codec: Codec::default(),
+    }
+}
+
+impl AsyncRedisCache {
+    pub fn with_absent_ttl_secs(mut self, absent_ttl_secs: usize) -> Self {
+        self.absent_ttl_secs = absent_ttl_secs;
+        self
+    }
+
+    pub fn with_mget_chunk_size(mut self, mget_chunk_size: usize) -> Self {
+        self.mget_chunk_size = mget_chunk_size.max(1);
+        self
+    }
+
+    /// Versions the keys, so the values cached with other versions are not read
+    pub fn with_schema_version(mut self, schema_version: u32) -> Self {
+        self.schema_version = Some(schema_version);
+        self
+    }
+
+    pub fn with_codec(mut self, codec: Codec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Fetches raw values preserving keys order, one MGET per chunk of keys in a single pipeline
+    async fn mget_raw(&self, keys: &[String]) -> Result<Vec<Option<Vec<u8>>>, AppError> {
+        if keys.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut pipe = redis::pipe();
+        for chunk in keys.chunks(self.mget_chunk_size) {
+            // explicit MGET replies with a list even for a single key
+            pipe.cmd("MGET").arg(chunk);
+        }
+
+        let mut con = self
+            .redis_pool
+            .get()
+            .await
+            .map_err(|e| AppError::Bb8RunError(e.to_string()))?;
+
+        let chunks: Vec<Vec<Option<Vec<u8>>>> = pipe.query_async(&mut *con).await?;
+
+        Ok(chunks.into_iter().flatten().collect())
+    }
+
+    /// Tombstones are stored as encoded `None`
+    fn parse_cached<T: DeserializeOwned>(
+        &self,
+        value: Option<Vec<u8>>,
+    ) -> Result<Option<Cached<T>>, AppError> {
+        match value {
+            Some(bytes) => self
+                .counters
+                .record_decode(self.codec.decode::<Option<T>>(&bytes))
+                .map(|o| Some(o.map(Cached::Value).unwrap_or(Cached::Absent))),
+            _ => Ok(None),
+        }
     }
 }
 
@@ -40,14 +121,11 @@ where
             .get()
             .await
             .map_err(|e| AppError::Bb8RunError(e.to_string()))?;
-        let value: Option<String> = con.get(key).await?;
+        let value: Option<Vec<u8>> = con.get(key).await?;
+        let value = self.parse_cached(value)?;
+        self.counters.record_get(&value);
 
-        match value {
-            Some(s) => serde_json::from_str(&s)
-                .map(|v| Some(v))
-                .map_err(|e| AppError::from(e)),
-            _ => Ok(None),
-        }
+        Ok(value.and_then(Cached::into_value))
     }
 
     async fn mget(&self, keys: &[&str]) -> Result<Vec<Option<T>>, AppError> {
@@ -55,38 +133,18 @@ where
 
         trace!("mget values from redis cache for keys {:?}", keys);
 
-        let mut con = self
-            .redis_pool
-            .get()
-            .await
-            .map_err(|e| AppError::Bb8RunError(e.to_string()))?;
-        match keys.len() {
-            0 => Ok(vec![]),
-            1 => {
-                con.get(keys)
-                    .await
-                    .map_err(|e| AppError::from(e))
-                    .and_then(|m: Option<String>| match m {
-                        Some(s) => {
-                            let v = serde_json::from_str(&s)?;
-                            Ok(vec![v])
-                        }
-                        _ => Ok(vec![None]),
-                    })
-            }
-            _ => con.get(keys).await.map_err(|e| AppError::from(e)).and_then(
-                |ms: Vec<Option<String>>| {
-                    ms.into_iter()
-                        .map(|m| match m {
-                            Some(s) => serde_json::from_str(&s)
-                                .map(|v| Some(v))
-                                .map_err(|e| AppError::from(e)),
-                            _ => Ok(None),
-                        })
-                        .try_collect()
-                },
-            ),
-        }
+        let values = self
+            .mget_raw(&keys)
+            .await?
+            .into_iter()
+            .map(|m| self.parse_cached(m))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.counters.record_mget(&values);
+
+        Ok(values
+            .into_iter()
+            .map(|v| v.and_then(Cached::into_value))
+            .collect())
     }
 }
 
@@ -105,9 +163,60 @@ where
             .get()
             .await
             .map_err(|e| AppError::Bb8RunError(e.to_string()))?;
-        let value = serde_json::to_string(&value)?;
+        let value = self.codec.encode(&value)?;
+
+        match self.ttl {
+            Some(ttl) => con.set_ex(key, value, ttl.as_secs() as usize).await,
+            None => con.set(key, value).await,
+        }
+        .map_err(|e| AppError::from(e))?;
+
+        Ok(())
+    }
+
+    async fn mset(&self, entries: Vec<(String, T)>) -> Result<(), AppError> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        trace!("mset {} redis cache values", entries.len());
+
+        // MSET does not support expiration, so SETs are pipelined instead
+        let mut pipe = redis::pipe();
+        for (key, value) in entries {
+            let key = self.key_fn(&key);
+            let value = self.codec.encode(&value)?;
+
+            match self.ttl {
+                Some(ttl) => pipe.set_ex(key, value, ttl.as_secs() as usize),
+                None => pipe.set(key, value),
+            }
+            .ignore();
+        }
+
+        let mut con = self
+            .redis_pool
+            .get()
+            .await
+            .map_err(|e| AppError::Bb8RunError(e.to_string()))?;
+
+        let () = pipe.query_async(&mut *con).await?;
+
+        Ok(())
+    }
+
+    async fn del(&self, key: &str) -> Result<(), AppError> {
+        let key = self.key_fn(key);
+
+        trace!("delete redis cache value for key {}", key);
+
+        let mut con = self
+            .redis_pool
+            .get()
+            .await
+            .map_err(|e| AppError::Bb8RunError(e.to_string()))?;
 
-        con.set(key, value).await.map_err(|e| AppError::from(e))?;
+        con.del(key).await.map_err(|e| AppError::from(e))?;
 
         Ok(())
     }
@@ -140,8 +249,190 @@ where
     }
 }
 
+#[async_trait::async_trait]
+impl<T> AsyncNegativeCache<T> for AsyncRedisCache
+where
+    T: Serialize + DeserializeOwned + Clone + Debug + Send,
+{
+    async fn get_cached(&self, key: &str) -> Result<Option<Cached<T>>, AppError> {
+        let key = self.key_fn(key);
+
+        trace!("get value or tombstone from redis cache for key {}", key);
+
+        let mut con = self
+            .redis_pool
+            .get()
+            .await
+            .map_err(|e| AppError::Bb8RunError(e.to_string()))?;
+        let value: Option<Vec<u8>> = con.get(key).await?;
+        let value = self.parse_cached(value)?;
+        self.counters.record_get(&value);
+
+        Ok(value)
+    }
+
+    async fn mget_cached(&self, keys: &[&str]) -> Result<Vec<Option<Cached<T>>>, AppError> {
+        let keys = keys.into_iter().map(|k| self.key_fn(k)).collect::<Vec<_>>();
+
+        trace!(
+            "mget values or tombstones from redis cache for keys {:?}",
+            keys
+        );
+
+        let values = self
+            .mget_raw(&keys)
+            .await?
+            .into_iter()
+            .map(|m| self.parse_cached(m))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.counters.record_mget(&values);
+
+        Ok(values)
+    }
+
+    async fn set_absent(&self, key: &str) -> Result<(), AppError> {
+        let key = self.key_fn(key);
+
+        trace!("set redis cache tombstone for key {}", key);
+
+        let mut con = self
+            .redis_pool
+            .get()
+            .await
+            .map_err(|e| AppError::Bb8RunError(e.to_string()))?;
+
+        let tombstone = self.codec.encode(&None::<T>)?;
+
+        // the value cached since the miss is kept
+        let _: redis::Value = redis::cmd("SET")
+            .arg(key)
+            .arg(tombstone)
+            .arg("NX")
+            .arg("EX")
+            .arg(self.absent_ttl_secs)
+            .query_async(&mut *con)
+            .await
+            .map_err(|e| AppError::from(e))?;
+
+        Ok(())
+    }
+}
+
 impl CacheKeyFn for AsyncRedisCache {
     fn key_fn(&self, source_key: &str) -> String {
-        format!("{}{}{}", self.key_prefix, self.key_separator, source_key)
+        versioned_key(
+            &self.key_prefix,
+            &self.key_separator,
+            self.schema_version,
+            source_key,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::new;
+    use crate::cache::codec::Codec;
+    use crate::cache::{
+        AssetUserDefinedData, AsyncNegativeCache, AsyncReadCache, AsyncWriteCache, Cached,
+    };
+    use crate::models::AssetLabel;
+    use crate::{async_redis, config};
+
+    #[tokio::test]
+    #[ignore = "requires running Redis configured with REDIS__* env variables"]
+    async fn should_mget_in_chunks_preserving_order() {
+        let config = config::redis::load().unwrap();
+        let pool = async_redis::pool(&config).await.unwrap();
+        let cache = new(pool, "mget_chunks_test", ":", None).with_mget_chunk_size(3);
+
+        let keys = (0..10).map(|i| i.to_string()).collect::<Vec<_>>();
+        for key in keys.iter().filter(|k| *k != "4") {
+            cache
+                .set(key.clone(), format!("value{}", key))
+                .await
+                .unwrap();
+        }
+
+        let keys = keys.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+        let values: Vec<Option<String>> = cache.mget(&keys).await.unwrap();
+
+        assert_eq!(values.len(), 10);
+        values.iter().enumerate().for_each(|(i, value)| {
+            if i == 4 {
+                assert_eq!(value, &None);
+            } else {
+                assert_eq!(value, &Some(format!("value{}", i)));
+            }
+        });
+
+        let single: Vec<Option<String>> = cache.mget(&["1"]).await.unwrap();
+        assert_eq!(single, vec![Some("value1".to_owned())]);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires running Redis configured with REDIS__* env variables"]
+    async fn should_not_replace_value_with_tombstone() {
+        let config = config::redis::load().unwrap();
+        let pool = async_redis::pool(&config).await.unwrap();
+        let cache = new(pool, "tombstone_test", ":", None);
+
+        cache.del("missing").await.unwrap();
+        cache.set_absent("missing").await.unwrap();
+        let cached: Option<Cached<String>> = cache.get_cached("missing").await.unwrap();
+        assert_eq!(cached, Some(Cached::Absent));
+
+        // the value cached by the consumer since the miss
+        cache
+            .set("found".to_owned(), "value".to_owned())
+            .await
+            .unwrap();
+        cache.set_absent("found").await.unwrap();
+        let cached: Option<Cached<String>> = cache.get_cached("found").await.unwrap();
+        assert_eq!(cached, Some(Cached::Value("value".to_owned())));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires running Redis configured with REDIS__* env variables"]
+    async fn should_expire_value_after_ttl() {
+        let config = config::redis::load().unwrap();
+        let pool = async_redis::pool(&config).await.unwrap();
+        let cache = new(pool, "ttl_test", ":", Some(Duration::from_secs(1)));
+
+        cache
+            .set("key".to_owned(), "value".to_owned())
+            .await
+            .unwrap();
+        let value: Option<String> = cache.get("key").await.unwrap();
+        assert_eq!(value, Some("value".to_owned()));
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+
+        let value: Option<String> = cache.get("key").await.unwrap();
+        assert_eq!(value, None);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires running Redis configured with REDIS__* env variables"]
+    async fn should_round_trip_other_labels() {
+        let config = config::redis::load().unwrap();
+        let pool = async_redis::pool(&config).await.unwrap();
+
+        let labels = vec![AssetLabel::Gateway, AssetLabel::Other("CUSTOM".to_owned())];
+        let udd = AssetUserDefinedData {
+            asset_id: "asset_id".to_owned(),
+            labels: labels.clone(),
+        };
+
+        for codec in [Codec::Json, Codec::MessagePack, Codec::Bincode] {
+            let cache = new(pool.clone(), "labels_test", ":", None).with_codec(codec);
+
+            cache.set("asset_id".to_owned(), udd.clone()).await.unwrap();
+            let cached: Option<AssetUserDefinedData> = cache.get("asset_id").await.unwrap();
+
+            assert_eq!(cached.unwrap().labels, labels);
+        }
     }
 }
@@ -0,0 +1,188 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::error::Error as AppError;
+
+/// Serialization of the cached values
+pub trait CacheCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, AppError>;
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, AppError>;
+}
+
+pub struct JsonCodec;
+
+impl CacheCodec for JsonCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, AppError> {
+        serde_json::to_vec(value).map_err(AppError::from)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, AppError> {
+        serde_json::from_slice(bytes).map_err(AppError::from)
+    }
+}
+
+pub struct MessagePackCodec;
+
+impl CacheCodec for MessagePackCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, AppError> {
+        // field names are kept so that values stay readable after adding new fields
+        rmp_serde::to_vec_named(value).map_err(|e| AppError::CacheError(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, AppError> {
+        rmp_serde::from_slice(bytes).map_err(|e| AppError::CacheError(e.to_string()))
+    }
+}
+
+/// Compact positional encoding, values have to be re-cached after changing the cached types
+pub struct BincodeCodec;
+
+impl CacheCodec for BincodeCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, AppError> {
+        bincode::serialize(value).map_err(|e| AppError::CacheError(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, AppError> {
+        bincode::deserialize(bytes).map_err(|e| AppError::CacheError(e.to_string()))
+    }
+}
+
+/// Codec chosen by the config
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Codec {
+    Json,
+    #[serde(rename = "msgpack")]
+    MessagePack,
+    Bincode,
+}
+
+impl Default for Codec {
+    fn default() -> Self {
+        Self::Json
+    }
+}
+
+impl CacheCodec for Codec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, AppError> {
+        match self {
+            Self::Json => JsonCodec.encode(value),
+            Self::MessagePack => MessagePackCodec.encode(value),
+            Self::Bincode => BincodeCodec.encode(value),
+        }
+    }
+
+    /// Binary codecs fall back to JSON, so values written before switching the codec are still readable
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, AppError> {
+        match self {
+            Self::Json => JsonCodec.decode(bytes),
+            Self::MessagePack => MessagePackCodec
+                .decode(bytes)
+                .or_else(|e| JsonCodec.decode(bytes).map_err(|_| e)),
+            Self::Bincode => BincodeCodec
+                .decode(bytes)
+                .or_else(|e| JsonCodec.decode(bytes).map_err(|_| e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::{CacheCodec, Codec};
+    use crate::cache::{AssetBlockchainData, AssetUserDefinedData};
+    use crate::models::{AssetLabel, AssetOracleDataEntry, AssetSponsorBalance, DataEntryType};
+
+    fn asset_blockchain_data() -> AssetBlockchainData {
+        let oracle_address = "3PEgG7eZHLFhcfsTSaYxgRhZsh4AxMvA4Ms".to_owned();
+        let oracle_data = (0..20)
+            .map(|i| AssetOracleDataEntry {
+                asset_id: "8LQW8f7P5d5PZM7GtZEBgaqRPGSzS3DfPuiXrURJ4AJS".to_owned(),
+                oracle_address: oracle_address.clone(),
+                key: format!("description_<{}>", i),
+                data_type: DataEntryType::Str,
+                bin_val: None,
+                bool_val: None,
+                int_val: None,
+                str_val: Some(format!("Localized description {}", i)),
+                uid: i,
+            })
+            .collect();
+
+        AssetBlockchainData {
+            id: "8LQW8f7P5d5PZM7GtZEBgaqRPGSzS3DfPuiXrURJ4AJS".to_owned(),
+            name: "WBTC".to_owned(),
+            ticker: Some("BTC".to_owned()),
+            precision: 8,
+            description: "Bitcoin Token".to_owned(),
+            height: 257457,
+            timestamp: Utc.timestamp(1480690876, 0),
+            issuer: "3PC4roN512iugc6xGVTTM2XkoWKEdSiiscd".to_owned(),
+            quantity: 2100000000000000,
+            reissuable: false,
+            min_sponsored_fee: Some(7),
+            smart: false,
+            nft: false,
+            oracles_data: vec![(oracle_address, oracle_data)].into_iter().collect(),
+            sponsor_balance: Some(AssetSponsorBalance {
+                regular_balance: 100,
+                out_leasing: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn should_round_trip_asset_blockchain_data() {
+        let data = asset_blockchain_data();
+
+        for codec in [Codec::Json, Codec::MessagePack, Codec::Bincode] {
+            let bytes = codec.encode(&Some(data.clone())).unwrap();
+            let decoded: Option<AssetBlockchainData> = codec.decode(&bytes).unwrap();
+
+            // compare serialized forms since the dto is not comparable
+            assert_eq!(
+                serde_json::to_value(decoded.unwrap()).unwrap(),
+                serde_json::to_value(&data).unwrap()
+            );
+
+            let tombstone = codec.encode(&None::<AssetBlockchainData>).unwrap();
+            let decoded: Option<AssetBlockchainData> = codec.decode(&tombstone).unwrap();
+            assert!(decoded.is_none());
+        }
+    }
+
+    #[test]
+    fn should_round_trip_asset_labels() {
+        let udd = AssetUserDefinedData {
+            asset_id: "asset_id".to_owned(),
+            labels: vec![AssetLabel::DeFi, AssetLabel::Other("CUSTOM".to_owned())],
+        };
+
+        for codec in [Codec::Json, Codec::MessagePack, Codec::Bincode] {
+            let bytes = codec.encode(&udd).unwrap();
+            let decoded: AssetUserDefinedData = codec.decode(&bytes).unwrap();
+
+            assert_eq!(decoded.labels, udd.labels);
+        }
+
+        // labels are cached as plain strings
+        assert_eq!(
+            serde_json::to_value(&udd.labels).unwrap(),
+            serde_json::json!(["DEFI", "CUSTOM"])
+        );
+    }
+
+    #[test]
+    fn should_read_json_values_with_binary_codec() {
+        let json = Codec::Json.encode(&Some(asset_blockchain_data())).unwrap();
+
+        for codec in [Codec::MessagePack, Codec::Bincode] {
+            let decoded: Option<AssetBlockchainData> = codec.decode(&json).unwrap();
+            assert!(decoded.is_some());
+
+            let decoded: Option<AssetBlockchainData> = codec.decode(b"null").unwrap();
+            assert!(decoded.is_none());
+        }
+    }
+}
@@ -1,11 +1,13 @@
 use chrono::{DateTime, Utc};
+use serde::de::{value::StrDeserializer, IntoDeserializer};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 
 use crate::error::Error as AppError;
 use crate::models::{
-    Asset, AssetInfo, AssetInfoUpdate, AssetMetadata, AssetOracleDataEntry, AssetSponsorBalance,
+    Asset, AssetInfo, AssetInfoUpdate, AssetLabel, AssetMetadata, AssetOracleDataEntry,
+    AssetSponsorBalance,
 };
 
 #[derive(Clone, Debug, serde::Deserialize, PartialEq, Eq)]
@@ -14,6 +16,34 @@ pub enum InvalidateCacheMode {
     BlockchainData,
     UserDefinedData,
     AllData,
+    /// Both caches of the assets issued by the address
+    ByIssuer(String),
+    /// Both caches of the given assets
+    ByIds(Vec<String>),
+}
+
+impl InvalidateCacheMode {
+    /// Builds the mode of its flat form, since `ByIssuer` and `ByIds` can't be expressed as a single value:
+    /// `by_issuer` takes the issuer, `by_ids` takes the ids
+    pub fn from_flat(mode: &str, issuer: Option<String>, ids: Vec<String>) -> Result<Self, String> {
+        if mode != "by_ids" && !ids.is_empty() {
+            return Err("ids are allowed for by_ids mode only".to_owned());
+        }
+
+        match (mode, issuer) {
+            ("by_issuer", Some(issuer)) => Ok(Self::ByIssuer(issuer)),
+            ("by_issuer", None) => Err("issuer is required for by_issuer mode".to_owned()),
+            (_, Some(_)) => Err("issuer is allowed for by_issuer mode only".to_owned()),
+            ("by_ids", None) if ids.is_empty() => {
+                Err("ids are required for by_ids mode".to_owned())
+            }
+            ("by_ids", None) => Ok(Self::ByIds(ids)),
+            (mode, None) => {
+                let mode: StrDeserializer<serde::de::value::Error> = mode.into_deserializer();
+                Self::deserialize(mode).map_err(|e| e.to_string())
+            }
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -60,23 +90,23 @@ impl From<&crate::models::AssetInfo> for AssetBlockchainData {
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AssetUserDefinedData {
     pub asset_id: String,
-    pub labels: Vec<String>,
+    pub labels: Vec<AssetLabel>,
 }
 
 impl AssetUserDefinedData {
     pub fn new(asset_id: impl AsRef<str>) -> Self {
         Self {
             asset_id: asset_id.as_ref().to_owned(),
-            labels: Vec::<String>::new(),
+            labels: Vec::<AssetLabel>::new(),
         }
     }
 
-    pub fn add_label(&self, label: &str) -> Self {
+    pub fn add_label(&self, label: &AssetLabel) -> Self {
         let mut labels = self.labels.iter().fold(HashSet::new(), |mut acc, cur| {
             acc.insert(cur.to_owned());
             acc
         });
-        if !label.is_empty() {
+        if !label.as_str().is_empty() {
             labels.insert(label.to_owned());
         };
         Self {
@@ -85,7 +115,7 @@ impl AssetUserDefinedData {
         }
     }
 
-    pub fn delete_label(&self, label: &str) -> Self {
+    pub fn delete_label(&self, label: &AssetLabel) -> Self {
         let labels = self
             .labels
             .iter()
@@ -110,21 +140,7 @@ impl From<(&AssetBlockchainData, &AssetUserDefinedData)> for AssetInfo {
         };
 
         Self {
-            asset: Asset {
-                ticker: blockchain_data.ticker.clone(),
-                id: blockchain_data.id.clone(),
-                name: blockchain_data.name.clone(),
-                precision: blockchain_data.precision.clone(),
-                description: blockchain_data.description.clone(),
-                height: blockchain_data.height.clone(),
-                timestamp: blockchain_data.timestamp.clone(),
-                issuer: blockchain_data.issuer.clone(),
-                quantity: blockchain_data.quantity.clone(),
-                reissuable: blockchain_data.reissuable.clone(),
-                min_sponsored_fee: blockchain_data.min_sponsored_fee.clone(),
-                smart: blockchain_data.smart.clone(),
-                nft: blockchain_data.nft,
-            },
+            asset: Asset::from(blockchain_data),
             metadata: AssetMetadata {
                 labels: user_defined_data.labels.clone(),
                 sponsor_balance,
@@ -134,6 +150,26 @@ impl From<(&AssetBlockchainData, &AssetUserDefinedData)> for AssetInfo {
     }
 }
 
+impl From<&AssetBlockchainData> for Asset {
+    fn from(blockchain_data: &AssetBlockchainData) -> Self {
+        Self {
+            ticker: blockchain_data.ticker.clone(),
+            id: blockchain_data.id.clone(),
+            name: blockchain_data.name.clone(),
+            precision: blockchain_data.precision.clone(),
+            description: blockchain_data.description.clone(),
+            height: blockchain_data.height.clone(),
+            timestamp: blockchain_data.timestamp.clone(),
+            issuer: blockchain_data.issuer.clone(),
+            quantity: blockchain_data.quantity.clone(),
+            reissuable: blockchain_data.reissuable.clone(),
+            min_sponsored_fee: blockchain_data.min_sponsored_fee.clone(),
+            smart: blockchain_data.smart.clone(),
+            nft: blockchain_data.nft,
+        }
+    }
+}
+
 /// Used by consumer for updating cached data
 ///
 /// Generates new AssetBlockchainData via applying sequence of updates on current AssetBlockchainData value
@@ -162,13 +198,7 @@ impl From<(&AssetBlockchainData, &Vec<AssetInfoUpdate>)> for AssetBlockchainData
                     cur
                 }
                 AssetInfoUpdate::Ticker(t) => {
-                    cur.ticker = 
-                    if t.is_empty() {
-                        None
-                    } else {
-                        Some(t.clone())
-                    };
-                    
+                    cur.ticker = t.clone();
                     cur
                 }
                 AssetInfoUpdate::SponsorRegularBalance(regular_balance) => {
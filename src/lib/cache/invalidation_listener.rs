@@ -0,0 +1,139 @@
+use futures::StreamExt;
+use redis::Client;
+use std::sync::Arc;
+use std::time::Duration;
+use wavesexchange_log::{error, info, trace};
+
+use super::invalidation_publisher::EVICT_ALL;
+use super::lru_mem::Evict;
+use crate::error::Error as AppError;
+
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+/// Evicts the in-memory cache entries of the asset ids the consumer publishes to the channel
+///
+/// Resubscribes on connection errors and runs forever.
+pub async fn subscribe(client: Client, channel: String, evictors: Vec<Arc<dyn Evict>>) {
+    loop {
+        if let Err(e) = listen(&client, &channel, &evictors).await {
+            error!("cache invalidation channel error: {:?}", e);
+        }
+
+        // messages published while disconnected are lost
+        evictors.iter().for_each(|evictor| evictor.evict_all());
+
+        tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+    }
+}
+
+async fn listen(
+    client: &Client,
+    channel: &str,
+    evictors: &[Arc<dyn Evict>],
+) -> Result<(), AppError> {
+    let mut pubsub = client.get_async_connection().await?.into_pubsub();
+    pubsub.subscribe(channel).await?;
+
+    info!("subscribed to cache invalidation channel {}", channel);
+
+    let mut messages = pubsub.on_message();
+    while let Some(msg) = messages.next().await {
+        let asset_id: String = msg.get_payload()?;
+        evict(evictors, &asset_id);
+    }
+
+    Ok(())
+}
+
+fn evict(evictors: &[Arc<dyn Evict>], asset_id: &str) {
+    if asset_id == EVICT_ALL {
+        info!("evicting all in-memory cache entries");
+        evictors.iter().for_each(|evictor| evictor.evict_all());
+        return;
+    }
+
+    trace!("evicting in-memory cache entries of asset {}", asset_id);
+    evictors.iter().for_each(|evictor| evictor.evict(asset_id));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::evict;
+    use crate::cache::invalidation_publisher::EVICT_ALL;
+    use crate::cache::lru_mem::{self, Evict};
+    use crate::cache::{AsyncReadCache, CacheKeyFn};
+    use crate::error::Error as AppError;
+
+    /// Prefixed cache counting reads
+    struct CountingCache {
+        prefix: &'static str,
+        reads: Arc<Mutex<u32>>,
+    }
+
+    impl CacheKeyFn for CountingCache {
+        fn key_fn(&self, source_key: &str) -> String {
+            format!("{}:{}", self.prefix, source_key)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AsyncReadCache<String> for CountingCache {
+        async fn get(&self, key: &str) -> Result<Option<String>, AppError> {
+            *self.reads.lock().unwrap() += 1;
+            Ok(Some(format!("{}-value", key)))
+        }
+
+        async fn mget(&self, keys: &[&str]) -> Result<Vec<Option<String>>, AppError> {
+            let mut values = vec![];
+            for key in keys {
+                values.push(self.get(key).await?);
+            }
+            Ok(values)
+        }
+    }
+
+    fn counting_cache(prefix: &'static str) -> (CountingCache, Arc<Mutex<u32>>) {
+        let reads = Arc::new(Mutex::new(0));
+        let cache = CountingCache {
+            prefix,
+            reads: reads.clone(),
+        };
+        (cache, reads)
+    }
+
+    #[tokio::test]
+    async fn should_evict_published_asset_id() {
+        let (assets, asset_reads) = counting_cache("asset");
+        let assets = lru_mem::new(assets, 10, Duration::from_secs(60));
+        let (user_defined_data, user_defined_data_reads) =
+            counting_cache("asset_user_defined_data");
+        let user_defined_data = lru_mem::new(user_defined_data, 10, Duration::from_secs(60));
+        let evictors: Vec<Arc<dyn Evict>> = vec![
+            Arc::new(assets.evictor()),
+            Arc::new(user_defined_data.evictor()),
+        ];
+
+        for key in ["A", "B"] {
+            assets.get(key).await.unwrap();
+            user_defined_data.get(key).await.unwrap();
+        }
+
+        evict(&evictors, "A");
+
+        for key in ["A", "B"] {
+            assets.get(key).await.unwrap();
+            user_defined_data.get(key).await.unwrap();
+        }
+        // only "A" is read again from both caches
+        assert_eq!(*asset_reads.lock().unwrap(), 3);
+        assert_eq!(*user_defined_data_reads.lock().unwrap(), 3);
+
+        evict(&evictors, EVICT_ALL);
+
+        assets.get("B").await.unwrap();
+        assert_eq!(*asset_reads.lock().unwrap(), 4);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::async_redis;
+use crate::error::Error as AppError;
+use crate::sync_redis::RedisPool;
+
+/// Message evicting all the entries of the in-memory caches
+pub const EVICT_ALL: &str = "*";
+
+/// Publisher of the ids of the assets whose cached data was changed
+pub trait InvalidationPublisher {
+    fn publish(&self, asset_ids: &[String]) -> Result<(), AppError>;
+}
+
+#[derive(Clone)]
+pub struct RedisPublisher {
+    redis_pool: RedisPool,
+    channel: String,
+}
+
+pub fn new(redis_pool: RedisPool, channel: impl AsRef<str>) -> RedisPublisher {
+    RedisPublisher {
+        redis_pool,
+        channel: channel.as_ref().to_owned(),
+    }
+}
+
+impl InvalidationPublisher for RedisPublisher {
+    fn publish(&self, asset_ids: &[String]) -> Result<(), AppError> {
+        if asset_ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut pipe = redis::pipe();
+        for asset_id in asset_ids {
+            pipe.publish(&self.channel, asset_id).ignore();
+        }
+
+        let mut con = self.redis_pool.get()?;
+        let () = pipe.query(&mut *con)?;
+
+        Ok(())
+    }
+}
+
+/// Publisher for the admin service changing the cache through the async pool
+#[derive(Clone)]
+pub struct AsyncRedisPublisher {
+    redis_pool: async_redis::RedisPool,
+    channel: String,
+}
+
+pub fn new_async(
+    redis_pool: async_redis::RedisPool,
+    channel: impl AsRef<str>,
+) -> AsyncRedisPublisher {
+    AsyncRedisPublisher {
+        redis_pool,
+        channel: channel.as_ref().to_owned(),
+    }
+}
+
+impl AsyncRedisPublisher {
+    pub async fn publish(&self, asset_ids: &[String]) -> Result<(), AppError> {
+        if asset_ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut pipe = redis::pipe();
+        for asset_id in asset_ids {
+            pipe.publish(&self.channel, asset_id).ignore();
+        }
+
+        let mut con = self
+            .redis_pool
+            .get()
+            .await
+            .map_err(|e| AppError::Bb8RunError(e.to_string()))?;
+        let () = pipe.query_async(&mut *con).await?;
+
+        Ok(())
+    }
+}
@@ -1,12 +1,17 @@
 use anyhow::Result;
 use futures::{stream, StreamExt};
+use std::collections::HashSet;
 use std::sync::Arc;
 use wavesexchange_log::{debug, info, timer};
 
 use super::{AssetBlockchainData, AssetUserDefinedData, AsyncWriteCache, InvalidateCacheMode};
-use crate::services::assets::{MgetOptions, SearchRequest, Service};
+use crate::services::assets::{
+    MgetOptions, MgetResult, SearchRequest, Service, UserDefinedDataPages,
+};
 
 const REDIS_CONCURRENCY_LIMIT: usize = 10;
+const ISSUER_ASSETS_CHUNK_SIZE: usize = 1000;
+const USER_DEFINED_DATA_CHUNK_SIZE: usize = 1000;
 
 pub async fn run<S, BDC, UDDC>(
     assets_service: Arc<S>,
@@ -21,6 +26,77 @@ where
 {
     timer!("cache invalidating");
 
+    if let InvalidateCacheMode::ByIssuer(issuer) = invalidate_cache_mode {
+        info!("starting cache invalidation of the issuer assets"; "issuer" => issuer);
+
+        let asset_ids = assets_service.asset_ids_by_issuers(&[issuer.as_str()])?;
+        // NFTs are cached too
+        let opts = MgetOptions::with_bypass_cache(true).set_include_nfts(true);
+
+        for asset_ids in asset_ids.chunks(ISSUER_ASSETS_CHUNK_SIZE) {
+            let asset_ids = asset_ids.iter().map(String::as_str).collect::<Vec<_>>();
+            let assets = assets_service
+                .mget(&asset_ids, &opts)
+                .await?
+                .into_iter()
+                .filter_map(MgetResult::found)
+                .collect::<Vec<_>>();
+
+            // the assets may vanish after listing them on rollback
+            let found_asset_ids = assets
+                .iter()
+                .map(|a| a.asset.id.as_str())
+                .collect::<HashSet<_>>();
+            for asset_id in asset_ids.iter().filter(|id| !found_asset_ids.contains(*id)) {
+                debug!("deleting asset cache"; "asset_id" => asset_id);
+                assets_blockchain_data_cache.del(asset_id).await?;
+                assets_user_defined_data_cache.del(asset_id).await?;
+            }
+
+            assets_blockchain_data_cache
+                .mset(
+                    assets
+                        .iter()
+                        .map(|a| (a.asset.id.clone(), AssetBlockchainData::from(a)))
+                        .collect(),
+                )
+                .await?;
+            assets_user_defined_data_cache
+                .mset(
+                    assets
+                        .into_iter()
+                        .map(|a| {
+                            let asset_id = a.asset.id;
+                            let data = AssetUserDefinedData {
+                                asset_id: asset_id.clone(),
+                                labels: a.metadata.labels,
+                            };
+                            (asset_id, data)
+                        })
+                        .collect(),
+                )
+                .await?;
+        }
+
+        info!("issuer assets cache succcessfully invalidated"; "assets count" => asset_ids.len());
+    }
+
+    if let InvalidateCacheMode::ByIds(asset_ids) = invalidate_cache_mode {
+        info!("starting cache invalidation of the assets"; "assets count" => asset_ids.len());
+
+        for asset_id in asset_ids {
+            invalidate_asset(
+                assets_service.clone(),
+                assets_blockchain_data_cache.clone(),
+                assets_user_defined_data_cache.clone(),
+                asset_id,
+            )
+            .await?;
+        }
+
+        info!("assets cache succcessfully invalidated");
+    }
+
     if *invalidate_cache_mode == InvalidateCacheMode::AllData
         || *invalidate_cache_mode == InvalidateCacheMode::BlockchainData
     {
@@ -33,10 +109,10 @@ where
 
         loop {
             timer!("fetching assets from the assets service");
-            let assets_blockchain_data_ids = assets_service.search(&req)?;
-            let assets_blockchain_data_ids = assets_blockchain_data_ids
+            let found_assets = assets_service.search(&req)?;
+            let assets_blockchain_data_ids = found_assets
                 .iter()
-                .map(|s| s.as_str())
+                .map(|s| s.id.as_str())
                 .collect::<Vec<_>>();
 
             let mut assets_blockchain_data = assets_service
@@ -46,14 +122,14 @@ where
                 )
                 .await?
                 .into_iter()
-                .filter_map(|o| o)
+                .filter_map(MgetResult::found)
                 .collect::<Vec<_>>();
 
             all_assets_blockchain_data.append(&mut assets_blockchain_data);
 
             if assets_blockchain_data_ids.len() as u32 >= REQUEST_LIMIT {
-                let last = assets_blockchain_data_ids.last().cloned().unwrap();
-                req = req.with_after(last.to_owned());
+                let last = found_assets.last().unwrap();
+                req = req.with_after(last.cursor.clone());
             } else {
                 break;
             }
@@ -85,30 +161,277 @@ where
     {
         info!("starting assets user defined data cache invalidation");
 
-        let assets_user_defined_data = assets_service.user_defined_data()?;
+        let assets_count = set_all_user_defined_data(
+            assets_service.as_ref(),
+            &assets_user_defined_data_cache,
+            true,
+        )
+        .await?;
+
+        debug!("new cache set"; "assets_user_defined_data count" => assets_count);
+    }
+
+    Ok(())
+}
+
+/// Rewrites the cached user defined data of the given assets (all assets if `None`)
+/// with the data of the labels and tickers tables, leaving the blockchain data cache intact
+///
+/// Cached data of the given assets missing in the database is deleted.
+/// Returns the count of rewritten assets.
+pub async fn rebuild_user_defined_data<S, UDDC>(
+    assets_service: Arc<S>,
+    assets_user_defined_data_cache: Arc<UDDC>,
+    asset_ids: Option<&[String]>,
+) -> Result<usize>
+where
+    S: Service,
+    UDDC: AsyncWriteCache<AssetUserDefinedData>,
+{
+    timer!("user defined data cache rebuilding");
+
+    let asset_ids = match asset_ids {
+        Some(asset_ids) => asset_ids,
+        None => {
+            let assets_count = set_all_user_defined_data(
+                assets_service.as_ref(),
+                &assets_user_defined_data_cache,
+                false,
+            )
+            .await?;
+
+            info!("user defined data cache succcessfully rebuilt"; "assets count" => assets_count);
+
+            return Ok(assets_count);
+        }
+    };
+
+    let assets_user_defined_data = {
+        let asset_ids = asset_ids.iter().map(String::as_str).collect::<Vec<_>>();
+        assets_service.user_defined_data_by_ids(&asset_ids)?
+    };
+
+    {
+        let found_asset_ids = assets_user_defined_data
+            .iter()
+            .map(|data| data.asset_id.as_str())
+            .collect::<HashSet<_>>();
+
+        for asset_id in asset_ids
+            .iter()
+            .filter(|id| !found_asset_ids.contains(id.as_str()))
+        {
+            debug!("deleting user defined data cache"; "asset_id" => asset_id);
+            assets_user_defined_data_cache.del(asset_id).await?;
+        }
+    }
+
+    for chunk in assets_user_defined_data.chunks(USER_DEFINED_DATA_CHUNK_SIZE) {
+        assets_user_defined_data_cache
+            .mset(
+                chunk
+                    .iter()
+                    .map(|data| (data.asset_id.clone(), AssetUserDefinedData::from(data)))
+                    .collect(),
+            )
+            .await?;
+    }
+
+    info!("user defined data cache succcessfully rebuilt"; "assets count" => assets_user_defined_data.len());
 
+    Ok(assets_user_defined_data.len())
+}
+
+/// Writes the user defined data of all assets to the cache page by page,
+/// so that only a single page is held in memory
+///
+/// With `clear` the cache is cleared once the first page is read,
+/// so that it is left intact when the database can't be read.
+/// Returns the count of written assets.
+async fn set_all_user_defined_data<S, UDDC>(
+    assets_service: &S,
+    assets_user_defined_data_cache: &Arc<UDDC>,
+    clear: bool,
+) -> Result<usize>
+where
+    S: Service,
+    UDDC: AsyncWriteCache<AssetUserDefinedData>,
+{
+    let mut assets_count = 0;
+    let mut pages =
+        UserDefinedDataPages::new(assets_service, USER_DEFINED_DATA_CHUNK_SIZE as u32, None);
+    let first_page = pages.next().transpose()?;
+
+    if clear {
         debug!("clearing cache");
         assets_user_defined_data_cache.clear().await?;
+    }
 
-        debug!("setting new cache"; "assets_user_defined_data count" => assets_user_defined_data.len());
-
-        stream::iter(&assets_user_defined_data)
-            .for_each_concurrent(REDIS_CONCURRENCY_LIMIT, |asset_user_defined_data| {
-                let cache = assets_user_defined_data_cache.clone();
-                async move {
-                    let asset_user_defined_data =
-                        AssetUserDefinedData::from(asset_user_defined_data);
-                    cache
-                        .set(
-                            asset_user_defined_data.asset_id.clone(),
-                            asset_user_defined_data.clone(),
-                        )
-                        .await
-                        .unwrap();
-                }
-            })
-            .await;
+    for page in first_page.into_iter().map(Ok).chain(pages) {
+        let page = page?;
+        assets_count += page.len();
+
+        assets_user_defined_data_cache
+            .mset(
+                page.iter()
+                    .map(|data| (data.asset_id.clone(), AssetUserDefinedData::from(data)))
+                    .collect(),
+            )
+            .await?;
     }
 
-    Ok(())
+    Ok(assets_count)
+}
+
+/// Recomputes the cached data of a single asset from the database
+///
+/// Cached data of an absent asset is deleted, as the consumer does when rolling back its issue.
+/// Returns whether the asset exists.
+pub async fn invalidate_asset<S, BDC, UDDC>(
+    assets_service: Arc<S>,
+    assets_blockchain_data_cache: Arc<BDC>,
+    assets_user_defined_data_cache: Arc<UDDC>,
+    asset_id: &str,
+) -> Result<bool>
+where
+    S: Service,
+    BDC: AsyncWriteCache<AssetBlockchainData>,
+    UDDC: AsyncWriteCache<AssetUserDefinedData>,
+{
+    // NFTs are cached too
+    let opts = MgetOptions::with_bypass_cache(true).set_include_nfts(true);
+    let asset_info = assets_service
+        .mget(&[asset_id], &opts)
+        .await?
+        .into_iter()
+        .next()
+        .and_then(MgetResult::found);
+
+    match asset_info {
+        Some(asset_info) => {
+            debug!("setting asset cache"; "asset_id" => asset_id);
+            assets_blockchain_data_cache
+                .set(asset_id.to_owned(), AssetBlockchainData::from(&asset_info))
+                .await?;
+            assets_user_defined_data_cache
+                .set(
+                    asset_id.to_owned(),
+                    AssetUserDefinedData {
+                        asset_id: asset_id.to_owned(),
+                        labels: asset_info.metadata.labels,
+                    },
+                )
+                .await?;
+            Ok(true)
+        }
+        None => {
+            debug!("deleting asset cache"; "asset_id" => asset_id);
+            assets_blockchain_data_cache.del(asset_id).await?;
+            assets_user_defined_data_cache.del(asset_id).await?;
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use std::sync::Arc;
+
+    use super::run;
+    use crate::cache::memory_cache::MemoryCache;
+    use crate::cache::{AssetBlockchainData, AssetUserDefinedData, InvalidateCacheMode};
+    use crate::models::{Asset, AssetInfo, AssetLabel, AssetMetadata};
+    use crate::services::assets::mock::MockService;
+
+    fn asset_info(id: &str, labels: &[&str]) -> AssetInfo {
+        AssetInfo {
+            asset: Asset {
+                id: id.to_owned(),
+                name: id.to_owned(),
+                precision: 8,
+                description: "".to_owned(),
+                height: 1,
+                timestamp: Utc::now(),
+                issuer: "issuer".to_owned(),
+                quantity: 100,
+                reissuable: false,
+                min_sponsored_fee: None,
+                smart: false,
+                nft: false,
+                ticker: None,
+            },
+            metadata: AssetMetadata {
+                labels: labels.iter().map(|l| AssetLabel::from(*l)).collect(),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn user_defined_data(id: &str, labels: &[&str]) -> AssetUserDefinedData {
+        AssetUserDefinedData {
+            asset_id: id.to_owned(),
+            labels: labels.iter().map(|l| AssetLabel::from(*l)).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_replace_user_defined_data_cache() {
+        let service = Arc::new(MockService::new(vec![
+            asset_info("a", &["DEFI"]),
+            asset_info("b", &[]),
+        ]));
+        let blockchain_data_cache = Arc::new(MemoryCache::<AssetBlockchainData>::default());
+        let user_defined_data_cache = Arc::new(MemoryCache::new(vec![
+            ("a", user_defined_data("a", &[])),
+            ("stale", user_defined_data("stale", &["GATEWAY"])),
+        ]));
+
+        run(
+            service,
+            blockchain_data_cache,
+            user_defined_data_cache.clone(),
+            &InvalidateCacheMode::UserDefinedData,
+        )
+        .await
+        .unwrap();
+
+        let cached = user_defined_data_cache.values();
+        let mut cached_ids = cached.keys().cloned().collect::<Vec<_>>();
+        cached_ids.sort();
+        assert_eq!(cached_ids, vec!["a".to_owned(), "b".to_owned()]);
+        assert_eq!(cached["a"].labels, vec![AssetLabel::DeFi]);
+    }
+
+    #[tokio::test]
+    async fn should_keep_user_defined_data_cache_if_database_is_unavailable() {
+        let service = Arc::new(MockService {
+            user_defined_data_unavailable: true,
+            ..MockService::new(vec![asset_info("a", &["DEFI"])])
+        });
+        let blockchain_data_cache = Arc::new(MemoryCache::<AssetBlockchainData>::default());
+        let user_defined_data_cache = Arc::new(MemoryCache::new(vec![(
+            "a",
+            user_defined_data("a", &["GATEWAY"]),
+        )]));
+
+        for mode in [
+            InvalidateCacheMode::UserDefinedData,
+            InvalidateCacheMode::AllData,
+        ] {
+            let result = run(
+                service.clone(),
+                blockchain_data_cache.clone(),
+                user_defined_data_cache.clone(),
+                &mode,
+            )
+            .await;
+
+            assert!(result.is_err());
+            assert_eq!(
+                user_defined_data_cache.values()["a"].labels,
+                vec![AssetLabel::Gateway]
+            );
+        }
+    }
 }
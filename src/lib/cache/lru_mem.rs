@@ -0,0 +1,417 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use wavesexchange_log::trace;
+
+use super::{AsyncNegativeCache, AsyncReadCache, CacheKeyFn, Cached};
+use crate::error::Error as AppError;
+
+pub const DEFAULT_CAPACITY: usize = 10_000;
+pub const DEFAULT_TTL: Duration = Duration::from_secs(5);
+
+/// In-process LRU read-through layer in front of another cache.
+///
+/// Values are invalidated by TTL and idle time since the underlying cache
+/// is written by another process, which may also publish the ids of the changed assets
+/// to be evicted through an [`Evictor`].
+pub struct MemCache<T, C> {
+    inner: C,
+    entries: Arc<Mutex<Lru<T>>>,
+}
+
+pub fn new<T, C>(inner: C, capacity: usize, ttl: Duration) -> MemCache<T, C> {
+    MemCache {
+        inner,
+        entries: Arc::new(Mutex::new(Lru::new(capacity, ttl))),
+    }
+}
+
+struct Entry<T> {
+    value: Cached<T>,
+    expires_at: Instant,
+    accessed_at: Instant,
+    tick: u64,
+}
+
+struct Lru<T> {
+    capacity: usize,
+    ttl: Duration,
+    max_idle: Option<Duration>,
+    tick: u64,
+    entries: HashMap<String, Entry<T>>,
+    // tick of the last access -> key
+    order: BTreeMap<u64, String>,
+}
+
+impl<T: Clone> Lru<T> {
+    fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            max_idle: None,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, key: &str, now: Instant) -> Option<Cached<T>> {
+        let expired = match self.entries.get(key) {
+            Some(entry) => entry.expires_at <= now || self.is_idle(entry, now),
+            None => return None,
+        };
+
+        if expired {
+            self.remove(key);
+            return None;
+        }
+
+        self.tick += 1;
+        let tick = self.tick;
+        let entry = self.entries.get_mut(key)?;
+        self.order.remove(&entry.tick);
+        self.order.insert(tick, key.to_owned());
+        entry.tick = tick;
+        entry.accessed_at = now;
+
+        Some(entry.value.clone())
+    }
+
+    fn is_idle(&self, entry: &Entry<T>, now: Instant) -> bool {
+        match self.max_idle {
+            Some(max_idle) => entry.accessed_at + max_idle <= now,
+            None => false,
+        }
+    }
+
+    /// Evicts entries not accessed for `max_idle`, going from the least recently used one
+    fn evict_idle(&mut self, now: Instant) {
+        while let Some((_, key)) = self.order.iter().next() {
+            match self.entries.get(key) {
+                Some(entry) if self.is_idle(entry, now) => {
+                    let key = key.clone();
+                    self.remove(&key);
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn put(&mut self, key: &str, value: Cached<T>, now: Instant) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.remove(key);
+        self.evict_idle(now);
+
+        self.tick += 1;
+        self.order.insert(self.tick, key.to_owned());
+        self.entries.insert(
+            key.to_owned(),
+            Entry {
+                value,
+                expires_at: now + self.ttl,
+                accessed_at: now,
+                tick: self.tick,
+            },
+        );
+
+        while self.entries.len() > self.capacity {
+            let oldest_tick = match self.order.keys().next() {
+                Some(tick) => *tick,
+                None => break,
+            };
+            if let Some(key) = self.order.remove(&oldest_tick) {
+                self.entries.remove(&key);
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.order.remove(&entry.tick);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+/// Eviction of local entries on invalidation messages
+pub trait Evict: Send + Sync {
+    fn evict(&self, key: &str);
+
+    fn evict_all(&self);
+}
+
+/// Handle evicting the entries of the [`MemCache`] it was taken from
+pub struct Evictor<T> {
+    entries: Arc<Mutex<Lru<T>>>,
+}
+
+impl<T: Clone + Send> Evict for Evictor<T> {
+    fn evict(&self, key: &str) {
+        self.entries.lock().unwrap().remove(key);
+    }
+
+    fn evict_all(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+impl<T, C> MemCache<T, C> {
+    /// Evicts entries not accessed for the duration even if their TTL has not expired yet
+    pub fn with_max_idle(self, max_idle: Option<Duration>) -> Self {
+        self.entries.lock().unwrap().max_idle = max_idle;
+        self
+    }
+}
+
+impl<T, C> MemCache<T, C> {
+    pub fn evictor(&self) -> Evictor<T> {
+        Evictor {
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl<T: Clone, C> MemCache<T, C> {
+    fn mem_get(&self, key: &str) -> Option<Cached<T>> {
+        self.entries.lock().unwrap().get(key, Instant::now())
+    }
+
+    fn mem_put(&self, key: &str, value: Cached<T>) {
+        self.entries.lock().unwrap().put(key, value, Instant::now())
+    }
+}
+
+impl<T, C: CacheKeyFn> CacheKeyFn for MemCache<T, C> {
+    fn key_fn(&self, source_key: &str) -> String {
+        self.inner.key_fn(source_key)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T, C> AsyncReadCache<T> for MemCache<T, C>
+where
+    T: Clone + Send + Sync,
+    C: AsyncReadCache<T> + Send + Sync,
+{
+    async fn get(&self, key: &str) -> Result<Option<T>, AppError> {
+        match self.mem_get(key) {
+            Some(Cached::Value(value)) => return Ok(Some(value)),
+            Some(Cached::Absent) => return Ok(None),
+            None => (),
+        }
+
+        trace!("memory cache miss for key {}", key);
+
+        let value = self.inner.get(key).await?;
+        if let Some(value) = &value {
+            self.mem_put(key, Cached::Value(value.clone()));
+        }
+
+        Ok(value)
+    }
+
+    async fn mget(&self, keys: &[&str]) -> Result<Vec<Option<T>>, AppError> {
+        let mut values = keys.iter().map(|k| self.mem_get(k)).collect::<Vec<_>>();
+
+        let missed_keys = keys
+            .iter()
+            .zip(values.iter())
+            .filter_map(|(k, v)| v.is_none().then(|| *k))
+            .collect::<Vec<_>>();
+
+        if !missed_keys.is_empty() {
+            trace!("memory cache miss for keys {:?}", missed_keys);
+
+            let mut inner_values = self.inner.mget(&missed_keys).await?.into_iter();
+            for (key, value) in keys.iter().zip(values.iter_mut()) {
+                if value.is_none() {
+                    if let Some(Some(inner_value)) = inner_values.next() {
+                        self.mem_put(key, Cached::Value(inner_value.clone()));
+                        *value = Some(Cached::Value(inner_value));
+                    }
+                }
+            }
+        }
+
+        Ok(values
+            .into_iter()
+            .map(|v| match v {
+                Some(Cached::Value(value)) => Some(value),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl<T, C> AsyncNegativeCache<T> for MemCache<T, C>
+where
+    T: Clone + Send + Sync,
+    C: AsyncNegativeCache<T> + Send + Sync,
+{
+    async fn get_cached(&self, key: &str) -> Result<Option<Cached<T>>, AppError> {
+        if let Some(value) = self.mem_get(key) {
+            return Ok(Some(value));
+        }
+
+        let value = self.inner.get_cached(key).await?;
+        if let Some(value) = &value {
+            self.mem_put(key, value.clone());
+        }
+
+        Ok(value)
+    }
+
+    async fn mget_cached(&self, keys: &[&str]) -> Result<Vec<Option<Cached<T>>>, AppError> {
+        let mut values = keys.iter().map(|k| self.mem_get(k)).collect::<Vec<_>>();
+
+        let missed_keys = keys
+            .iter()
+            .zip(values.iter())
+            .filter_map(|(k, v)| v.is_none().then(|| *k))
+            .collect::<Vec<_>>();
+
+        if !missed_keys.is_empty() {
+            let mut inner_values = self.inner.mget_cached(&missed_keys).await?.into_iter();
+            for (key, value) in keys.iter().zip(values.iter_mut()) {
+                if value.is_none() {
+                    if let Some(Some(inner_value)) = inner_values.next() {
+                        self.mem_put(key, inner_value.clone());
+                        *value = Some(inner_value);
+                    }
+                }
+            }
+        }
+
+        Ok(values)
+    }
+
+    async fn set_absent(&self, key: &str) -> Result<(), AppError> {
+        self.inner.set_absent(key).await?;
+        // the inner cache keeps the value cached since the miss, the next read takes either
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    use super::{new, Lru};
+    use crate::cache::{AsyncReadCache, CacheKeyFn, Cached};
+    use crate::error::Error as AppError;
+
+    /// Inner cache recording the requested keys
+    struct RecordingCache {
+        values: HashMap<String, String>,
+        requested: Mutex<Vec<String>>,
+    }
+
+    impl CacheKeyFn for RecordingCache {
+        fn key_fn(&self, source_key: &str) -> String {
+            source_key.to_owned()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AsyncReadCache<String> for RecordingCache {
+        async fn get(&self, key: &str) -> Result<Option<String>, AppError> {
+            self.requested.lock().unwrap().push(key.to_owned());
+            Ok(self.values.get(key).cloned())
+        }
+
+        async fn mget(&self, keys: &[&str]) -> Result<Vec<Option<String>>, AppError> {
+            let mut values = vec![];
+            for key in keys {
+                values.push(self.get(key).await?);
+            }
+            Ok(values)
+        }
+    }
+
+    #[test]
+    fn should_evict_least_recently_used() {
+        let now = Instant::now();
+        let mut lru = Lru::new(2, Duration::from_secs(5));
+
+        lru.put("a", Cached::Value(1), now);
+        lru.put("b", Cached::Value(2), now);
+        // touch "a" so "b" becomes the least recently used
+        assert_eq!(lru.get("a", now), Some(Cached::Value(1)));
+        lru.put("c", Cached::Value(3), now);
+
+        assert_eq!(lru.get("a", now), Some(Cached::Value(1)));
+        assert_eq!(lru.get("b", now), None);
+        assert_eq!(lru.get("c", now), Some(Cached::Value(3)));
+    }
+
+    #[test]
+    fn should_expire_by_ttl() {
+        let now = Instant::now();
+        let mut lru = Lru::new(2, Duration::from_secs(5));
+
+        lru.put("a", Cached::Value(1), now);
+        assert_eq!(
+            lru.get("a", now + Duration::from_secs(4)),
+            Some(Cached::Value(1))
+        );
+        assert_eq!(lru.get("a", now + Duration::from_secs(5)), None);
+        assert!(lru.entries.is_empty());
+        assert!(lru.order.is_empty());
+    }
+
+    #[test]
+    fn should_evict_idle_entries() {
+        let now = Instant::now();
+        let mut lru = Lru::new(10, Duration::from_secs(60));
+        lru.max_idle = Some(Duration::from_secs(5));
+
+        lru.put("a", Cached::Value(1), now);
+        lru.put("b", Cached::Value(2), now);
+        assert_eq!(
+            lru.get("a", now + Duration::from_secs(4)),
+            Some(Cached::Value(1))
+        );
+
+        // "b" was not accessed since it was put
+        lru.put("c", Cached::Value(3), now + Duration::from_secs(6));
+        assert!(!lru.entries.contains_key("b"));
+        assert_eq!(
+            lru.get("a", now + Duration::from_secs(8)),
+            Some(Cached::Value(1))
+        );
+        assert_eq!(lru.get("a", now + Duration::from_secs(13)), None);
+    }
+
+    #[tokio::test]
+    async fn should_fall_through_only_for_missed_keys() {
+        let inner = RecordingCache {
+            values: vec![("a", "1"), ("b", "2")]
+                .into_iter()
+                .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                .collect(),
+            requested: Mutex::new(vec![]),
+        };
+        let cache = new(inner, 10, Duration::from_secs(5));
+
+        assert_eq!(cache.get("a").await.unwrap(), Some("1".to_owned()));
+
+        let values = cache.mget(&["a", "b", "c"]).await.unwrap();
+        assert_eq!(
+            values,
+            vec![Some("1".to_owned()), Some("2".to_owned()), None]
+        );
+
+        assert_eq!(*cache.inner.requested.lock().unwrap(), vec!["a", "b", "c"]);
+    }
+}
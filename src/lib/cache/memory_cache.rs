@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::{AsyncNegativeCache, AsyncReadCache, AsyncWriteCache, CacheKeyFn, Cached};
+use crate::error::Error as AppError;
+
+/// Cache of the tests keeping the entries in a map shared by its clones
+#[derive(Clone)]
+pub struct MemoryCache<T> {
+    entries: Arc<Mutex<HashMap<String, Cached<T>>>>,
+}
+
+impl<T> Default for MemoryCache<T> {
+    fn default() -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<T: Clone> MemoryCache<T> {
+    pub fn new(values: Vec<(&str, T)>) -> Self {
+        let cache = Self::default();
+        for (key, value) in values {
+            cache.insert(key, Cached::Value(value));
+        }
+        cache
+    }
+
+    pub fn insert(&self, key: &str, entry: Cached<T>) {
+        self.entries.lock().unwrap().insert(key.to_owned(), entry);
+    }
+
+    /// Cached values without the tombstones
+    pub fn values(&self) -> HashMap<String, T> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(key, entry)| match entry {
+                Cached::Value(value) => Some((key.clone(), value.clone())),
+                Cached::Absent => None,
+            })
+            .collect()
+    }
+}
+
+impl<T> CacheKeyFn for MemoryCache<T> {
+    fn key_fn(&self, source_key: &str) -> String {
+        source_key.to_owned()
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: Clone + Send + Sync> AsyncReadCache<T> for MemoryCache<T> {
+    async fn get(&self, key: &str) -> Result<Option<T>, AppError> {
+        let entries = self.entries.lock().unwrap();
+        Ok(entries.get(key).cloned().and_then(Cached::into_value))
+    }
+
+    async fn mget(&self, keys: &[&str]) -> Result<Vec<Option<T>>, AppError> {
+        let entries = self.entries.lock().unwrap();
+        Ok(keys
+            .iter()
+            .map(|k| entries.get(*k).cloned().and_then(Cached::into_value))
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: Clone + Send + Sync> AsyncWriteCache<T> for MemoryCache<T> {
+    async fn set(&self, key: String, value: T) -> Result<(), AppError> {
+        self.insert(&key, Cached::Value(value));
+        Ok(())
+    }
+
+    async fn mset(&self, entries: Vec<(String, T)>) -> Result<(), AppError> {
+        for (key, value) in entries {
+            self.insert(&key, Cached::Value(value));
+        }
+        Ok(())
+    }
+
+    async fn del(&self, key: &str) -> Result<(), AppError> {
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn clear(&self) -> Result<(), AppError> {
+        self.entries.lock().unwrap().clear();
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: Clone + Send + Sync> AsyncNegativeCache<T> for MemoryCache<T> {
+    async fn get_cached(&self, key: &str) -> Result<Option<Cached<T>>, AppError> {
+        Ok(self.entries.lock().unwrap().get(key).cloned())
+    }
+
+    async fn mget_cached(&self, keys: &[&str]) -> Result<Vec<Option<Cached<T>>>, AppError> {
+        let entries = self.entries.lock().unwrap();
+        Ok(keys.iter().map(|k| entries.get(*k).cloned()).collect())
+    }
+
+    async fn set_absent(&self, key: &str) -> Result<(), AppError> {
+        self.entries
+            .lock()
+            .unwrap()
+            .entry(key.to_owned())
+            .or_insert(Cached::Absent);
+        Ok(())
+    }
+}
@@ -1,15 +1,45 @@
 pub mod async_redis_cache;
+pub mod codec;
 mod dtos;
+pub mod invalidation_listener;
+pub mod invalidation_publisher;
 pub mod invalidator;
+pub mod lru_mem;
+#[cfg(test)]
+pub mod memory_cache;
 pub mod sync_redis_cache;
 
 pub use dtos::{AssetBlockchainData, AssetUserDefinedData, InvalidateCacheMode};
 
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use crate::error::Error as AppError;
+use crate::metrics;
 
 pub const KEY_SEPARATOR: &str = ":";
 pub const ASSET_BLOCKCHAIN_DATA_KEY_PREFIX: &str = "asset";
 pub const ASSET_USER_DEFINED_DATA_KEY_PREFIX: &str = "asset_user_defined_data";
+pub const DEFAULT_ABSENT_TTL_SECS: usize = 60;
+/// Has to be bumped on incompatible changes of `AssetBlockchainData`
+pub const ASSET_BLOCKCHAIN_DATA_SCHEMA_VERSION: u32 = 2;
+/// Has to be bumped on incompatible changes of `AssetUserDefinedData`
+pub const ASSET_USER_DEFINED_DATA_SCHEMA_VERSION: u32 = 1;
+
+/// Builds `<prefix>:v<version>:<key>`, or `<prefix>:<key>` if the version is unset
+pub fn versioned_key(
+    key_prefix: &str,
+    key_separator: &str,
+    schema_version: Option<u32>,
+    source_key: &str,
+) -> String {
+    match schema_version {
+        Some(version) => format!(
+            "{}{}v{}{}{}",
+            key_prefix, key_separator, version, key_separator, source_key
+        ),
+        None => format!("{}{}{}", key_prefix, key_separator, source_key),
+    }
+}
 
 pub trait CacheKeyFn {
     fn key_fn(&self, source_key: &str) -> String;
@@ -24,6 +54,10 @@ pub trait SyncReadCache<T>: CacheKeyFn {
 pub trait SyncWriteCache<T>: SyncReadCache<T> {
     fn set(&self, key: &str, value: T) -> Result<(), AppError>;
 
+    fn mset(&self, entries: Vec<(String, T)>) -> Result<(), AppError>;
+
+    fn del(&self, key: &str) -> Result<(), AppError>;
+
     fn clear(&self) -> Result<(), AppError>;
 }
 
@@ -38,48 +72,207 @@ pub trait AsyncReadCache<T>: CacheKeyFn {
 pub trait AsyncWriteCache<T>: AsyncReadCache<T> {
     async fn set(&self, key: String, value: T) -> Result<(), AppError>;
 
+    async fn mset(&self, entries: Vec<(String, T)>) -> Result<(), AppError>;
+
+    async fn del(&self, key: &str) -> Result<(), AppError>;
+
     async fn clear(&self) -> Result<(), AppError>;
 }
 
+/// Cached value or tombstone of the value known to be absent
+#[derive(Clone, Debug, PartialEq)]
+pub enum Cached<T> {
+    Absent,
+    Value(T),
+}
+
+impl<T> Cached<T> {
+    pub fn into_value(self) -> Option<T> {
+        match self {
+            Self::Absent => None,
+            Self::Value(value) => Some(value),
+        }
+    }
+}
+
+/// Cache remembering confirmed misses for a short time
+#[async_trait::async_trait]
+pub trait AsyncNegativeCache<T>: AsyncReadCache<T> {
+    async fn get_cached(&self, key: &str) -> Result<Option<Cached<T>>, AppError>;
+
+    async fn mget_cached(&self, keys: &[&str]) -> Result<Vec<Option<Cached<T>>>, AppError>;
+
+    /// Remembers the miss unless the key holds a value already
+    async fn set_absent(&self, key: &str) -> Result<(), AppError>;
+}
+
+/// Read statistics of the cache
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// tombstones of the values known to be absent
+    pub absent: u64,
+    /// mget calls having both hits and misses
+    pub mget_partial_hits: u64,
+    /// cached values which could not be decoded
+    pub decode_errors: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    absent: AtomicU64,
+    mget_partial_hits: AtomicU64,
+    decode_errors: AtomicU64,
+    /// `cache` label of the exported `CACHE_READS` metric, reads are not exported if not set
+    metrics_label: Option<String>,
+}
+
+impl CacheCounters {
+    pub fn with_metrics_label(metrics_label: impl AsRef<str>) -> Self {
+        Self {
+            metrics_label: Some(metrics_label.as_ref().to_owned()),
+            ..Self::default()
+        }
+    }
+
+    pub fn record_get<T>(&self, value: &Option<Cached<T>>) {
+        self.record_mget(std::slice::from_ref(value));
+    }
+
+    pub fn record_mget<T>(&self, values: &[Option<Cached<T>>]) {
+        let hits = values
+            .iter()
+            .filter(|v| matches!(v, Some(Cached::Value(_))))
+            .count() as u64;
+        let absent = values
+            .iter()
+            .filter(|v| matches!(v, Some(Cached::Absent)))
+            .count() as u64;
+        let misses = values.len() as u64 - hits - absent;
+
+        self.hits.fetch_add(hits, Ordering::Relaxed);
+        self.misses.fetch_add(misses, Ordering::Relaxed);
+        self.absent.fetch_add(absent, Ordering::Relaxed);
+        if hits > 0 && misses > 0 {
+            self.mget_partial_hits.fetch_add(1, Ordering::Relaxed);
+        }
+        self.export("hit", hits);
+        self.export("miss", misses);
+        self.export("absent", absent);
+    }
+
+    /// Counts the result of decoding a cached value
+    pub fn record_decode<T>(&self, result: Result<T, AppError>) -> Result<T, AppError> {
+        if result.is_err() {
+            self.decode_errors.fetch_add(1, Ordering::Relaxed);
+            self.export("decode_error", 1);
+        }
+        result
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            absent: self.absent.load(Ordering::Relaxed),
+            mget_partial_hits: self.mget_partial_hits.load(Ordering::Relaxed),
+            decode_errors: self.decode_errors.load(Ordering::Relaxed),
+        }
+    }
+
+    fn export(&self, result: &str, count: u64) {
+        if let Some(label) = &self.metrics_label {
+            if count > 0 {
+                metrics::CACHE_READS
+                    .with_label_values(&[label, result])
+                    .inc_by(count);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::AssetUserDefinedData;
+    use super::{versioned_key, AssetUserDefinedData, CacheCounters, CacheStats, Cached};
+    use crate::error::Error as AppError;
+    use crate::models::AssetLabel;
+
+    #[test]
+    fn should_not_collide_keys_of_different_schema_versions() {
+        let v1 = versioned_key("asset", ":", Some(1), "WAVES");
+        let v2 = versioned_key("asset", ":", Some(2), "WAVES");
+
+        assert_eq!(v1, "asset:v1:WAVES");
+        assert_eq!(v2, "asset:v2:WAVES");
+        assert_ne!(v1, v2);
+        assert_eq!(versioned_key("asset", ":", None, "WAVES"), "asset:WAVES");
+    }
+
+    #[test]
+    fn should_count_hits_and_misses() {
+        let counters = CacheCounters::default();
+
+        counters.record_get(&Some(Cached::Value(1)));
+        counters.record_get::<i32>(&None);
+        counters.record_get::<i32>(&Some(Cached::Absent));
+        counters.record_mget(&[Some(Cached::Value(1)), None, Some(Cached::Value(2))]);
+        counters.record_mget(&[Some(Cached::Value(1)), Some(Cached::Value(2))]);
+        counters.record_mget::<i32>(&[None, None, Some(Cached::Absent)]);
+        counters
+            .record_decode::<i32>(Err(AppError::CacheError("invalid".to_owned())))
+            .unwrap_err();
+        counters.record_decode(Ok(1)).unwrap();
+
+        assert_eq!(
+            counters.stats(),
+            CacheStats {
+                hits: 5,
+                misses: 4,
+                absent: 2,
+                mget_partial_hits: 1,
+                decode_errors: 1,
+            }
+        );
+    }
 
     #[test]
     fn should_add_label() {
         let udd = AssetUserDefinedData::new("asset_id");
-        let udd_with_new_label = udd.add_label("WA_VERIFIED");
-        assert_eq!(udd_with_new_label.labels, vec!["WA_VERIFIED"]);
+        let udd_with_new_label = udd.add_label(&AssetLabel::WaVerified);
+        assert_eq!(udd_with_new_label.labels, vec![AssetLabel::WaVerified]);
     }
 
     #[test]
     fn should_add_label_exactly_once() {
         let udd = AssetUserDefinedData::new("asset_id");
-        let udd_with_new_label = udd.add_label("WA_VERIFIED");
-        let udd_with_new_label = udd_with_new_label.add_label("WA_VERIFIED");
-        assert_eq!(udd_with_new_label.labels, vec!["WA_VERIFIED"]);
+        let udd_with_new_label = udd.add_label(&AssetLabel::WaVerified);
+        let udd_with_new_label = udd_with_new_label.add_label(&AssetLabel::WaVerified);
+        assert_eq!(udd_with_new_label.labels, vec![AssetLabel::WaVerified]);
     }
 
     #[test]
     fn should_skip_empty_label_addition() {
         let udd = AssetUserDefinedData::new("asset_id");
-        let udd_with_new_label = udd.add_label("");
-        assert_eq!(udd_with_new_label.labels, [] as [&str; 0]);
+        let udd_with_new_label = udd.add_label(&AssetLabel::from(" "));
+        assert_eq!(udd_with_new_label.labels, Vec::<AssetLabel>::new());
     }
 
     #[test]
     fn should_delete_label() {
         let udd = AssetUserDefinedData::new("asset_id");
-        let udd_with_new_label = udd.delete_label("WA_VERIFIED");
-        assert_eq!(udd_with_new_label.labels, Vec::<String>::new());
+        let udd_with_new_label = udd.delete_label(&AssetLabel::WaVerified);
+        assert_eq!(udd_with_new_label.labels, Vec::<AssetLabel>::new());
     }
 
     #[test]
     fn should_delete_label_exactly_once() {
         let udd = AssetUserDefinedData::new("asset_id");
-        let udd_with_new_label = udd.delete_label("WA_VERIFIED");
+        let udd_with_new_label = udd.delete_label(&AssetLabel::WaVerified);
         // should not fail while deleting non-existing label
-        udd_with_new_label.delete_label("WA_VERIFIED");
-        assert_eq!(udd_with_new_label.labels, Vec::<String>::new());
+        udd_with_new_label.delete_label(&AssetLabel::WaVerified);
+        assert_eq!(udd_with_new_label.labels, Vec::<AssetLabel>::new());
     }
 }
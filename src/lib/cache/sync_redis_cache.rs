@@ -1,10 +1,12 @@
-use itertools::Itertools;
 use redis::Commands;
 use serde::{de::DeserializeOwned, Serialize};
 use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
 use wavesexchange_log::{debug, trace};
 
-use super::{CacheKeyFn, SyncReadCache, SyncWriteCache};
+use super::codec::{CacheCodec, Codec};
+use super::{versioned_key, CacheCounters, CacheKeyFn, Cached, SyncReadCache, SyncWriteCache};
 use crate::{error::Error as AppError, sync_redis::RedisPool};
 
 #[derive(Clone)]
@@ -12,17 +14,58 @@ pub struct SyncRedisCache {
     redis_pool: RedisPool,
     key_prefix: String,
     key_separator: String,
+    schema_version: Option<u32>,
+    ttl: Option<Duration>,
+    counters: Arc<CacheCounters>,
+    codec: Codec,
 }
 
 pub fn new(
     redis_pool: RedisPool,
     key_prefix: impl AsRef<str>,
     key_separator: impl AsRef<str>,
+    ttl: Option<Duration>,
 ) -> SyncRedisCache {
     SyncRedisCache {
         redis_pool,
         key_prefix: key_prefix.as_ref().to_string(),
         key_separator: key_separator.as_ref().to_string(),
+        schema_version: None,
+        ttl,
+        counters: Arc::new(CacheCounters::default()),
+        codec: Codec::default(),
+    }
+}
+
+impl SyncRedisCache {
+    /// Versions the keys, so the values cached with other versions are not read
+    pub fn with_schema_version(mut self, schema_version: u32) -> Self {
+        self.schema_version = Some(schema_version);
+        self
+    }
+
+    pub fn with_codec(mut self, codec: Codec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Tombstones are stored as encoded `None`
+    fn parse<T: DeserializeOwned>(
+        &self,
+        value: Option<Vec<u8>>,
+    ) -> Result<Option<Cached<T>>, AppError> {
+        match value {
+            Some(bytes) => Ok(Some(
+                match self
+                    .counters
+                    .record_decode(self.codec.decode::<Option<T>>(&bytes))?
+                {
+                    Some(value) => Cached::Value(value),
+                    None => Cached::Absent,
+                },
+            )),
+            _ => Ok(None),
+        }
     }
 }
 
@@ -36,14 +79,15 @@ where
         trace!("get value from redis cache for key {}", key);
 
         let mut con = self.redis_pool.get()?;
-        let value: Option<String> = con.get(key)?;
-        debug!("value: {:?}", value);
-        match value {
-            Some(s) => serde_json::from_str(&s)
-                .map(|v| Some(v))
-                .map_err(|e| AppError::from(e)),
-            _ => Ok(None),
-        }
+        let value: Option<Vec<u8>> = con.get(key)?;
+        debug!(
+            "value: {:?}",
+            value.as_ref().map(|v| String::from_utf8_lossy(v))
+        );
+        let value = self.parse(value)?;
+        self.counters.record_get(&value);
+
+        Ok(value.and_then(Cached::into_value))
     }
 
     fn mget(&self, keys: &[&str]) -> Result<Vec<Option<T>>, AppError> {
@@ -52,34 +96,21 @@ where
         trace!("mget values from redis cache for keys {:?}", keys);
 
         let mut con = self.redis_pool.get()?;
-        match keys.len() {
-            0 => Ok(vec![]),
-            1 => {
-                con.get(keys)
-                    .map_err(|e| AppError::from(e))
-                    .and_then(|m: Option<String>| match m {
-                        Some(s) => {
-                            let v = serde_json::from_str(&s)?;
-                            Ok(vec![v])
-                        }
-                        _ => Ok(vec![None]),
-                    })
-            }
-            _ => {
-                con.get(keys)
-                    .map_err(|e| AppError::from(e))
-                    .and_then(|ms: Vec<Option<String>>| {
-                        ms.into_iter()
-                            .map(|m| match m {
-                                Some(s) => serde_json::from_str(&s)
-                                    .map(|v| Some(v))
-                                    .map_err(|e| AppError::from(e)),
-                                _ => Ok(None),
-                            })
-                            .try_collect()
-                    })
-            }
-        }
+        let values: Vec<Option<Vec<u8>>> = match keys.len() {
+            0 => return Ok(vec![]),
+            1 => vec![con.get(keys)?],
+            _ => con.get(keys)?,
+        };
+        let values = values
+            .into_iter()
+            .map(|m| self.parse(m))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.counters.record_mget(&values);
+
+        Ok(values
+            .into_iter()
+            .map(|v| v.and_then(Cached::into_value))
+            .collect())
     }
 }
 
@@ -93,9 +124,50 @@ where
         trace!("set redis cache value for key {}: {:?}", key, value);
 
         let mut con = self.redis_pool.get()?;
-        let value = serde_json::to_string(&value)?;
+        let value = self.codec.encode(&value)?;
 
-        con.set(key, value).map_err(|e| AppError::from(e))?;
+        match self.ttl {
+            Some(ttl) => con.set_ex(key, value, ttl.as_secs() as usize),
+            None => con.set(key, value),
+        }
+        .map_err(|e| AppError::from(e))?;
+
+        Ok(())
+    }
+
+    fn mset(&self, entries: Vec<(String, T)>) -> Result<(), AppError> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        trace!("mset {} redis cache values", entries.len());
+
+        // MSET does not support expiration, so SETs are pipelined instead
+        let mut pipe = redis::pipe();
+        for (key, value) in entries {
+            let key = self.key_fn(&key);
+            let value = self.codec.encode(&value)?;
+
+            match self.ttl {
+                Some(ttl) => pipe.set_ex(key, value, ttl.as_secs() as usize),
+                None => pipe.set(key, value),
+            }
+            .ignore();
+        }
+
+        let mut con = self.redis_pool.get()?;
+        let () = pipe.query(&mut *con)?;
+
+        Ok(())
+    }
+
+    fn del(&self, key: &str) -> Result<(), AppError> {
+        let key = self.key_fn(key);
+
+        trace!("delete redis cache value for key {}", key);
+
+        let mut con = self.redis_pool.get()?;
+        con.del(key).map_err(|e| AppError::from(e))?;
 
         Ok(())
     }
@@ -125,6 +197,61 @@ where
 
 impl CacheKeyFn for SyncRedisCache {
     fn key_fn(&self, source_key: &str) -> String {
-        format!("{}{}{}", self.key_prefix, self.key_separator, source_key)
+        versioned_key(
+            &self.key_prefix,
+            &self.key_separator,
+            self.schema_version,
+            source_key,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::new;
+    use crate::cache::{SyncReadCache, SyncWriteCache};
+    use crate::{config, sync_redis};
+
+    #[test]
+    #[ignore = "requires running Redis configured with REDIS__* env variables"]
+    fn should_expire_value_after_ttl() {
+        let config = config::redis::load().unwrap();
+        let pool = sync_redis::pool(&config).unwrap();
+        let cache = new(pool, "ttl_test", ":", Some(Duration::from_secs(1)));
+
+        cache.set("key", "value".to_owned()).unwrap();
+        let value: Option<String> = cache.get("key").unwrap();
+        assert_eq!(value, Some("value".to_owned()));
+
+        std::thread::sleep(Duration::from_millis(1500));
+
+        let value: Option<String> = cache.get("key").unwrap();
+        assert_eq!(value, None);
+    }
+
+    #[test]
+    #[ignore = "requires running Redis configured with REDIS__* env variables"]
+    fn should_mset_all_entries() {
+        let config = config::redis::load().unwrap();
+        let pool = sync_redis::pool(&config).unwrap();
+        let cache = new(pool, "mset_test", ":", None);
+
+        // a block updating 500 assets
+        let entries = (0..500)
+            .map(|i| (format!("asset{}", i), format!("value{}", i)))
+            .collect::<Vec<_>>();
+        cache.mset(entries.clone()).unwrap();
+
+        let keys = entries.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>();
+        let values: Vec<Option<String>> = cache.mget(&keys).unwrap();
+        assert_eq!(
+            values,
+            entries
+                .into_iter()
+                .map(|(_, v)| Some(v))
+                .collect::<Vec<_>>()
+        );
     }
 }
@@ -1,15 +1,20 @@
 use serde::Deserialize;
 
+use super::split_addresses;
 use crate::error::Error;
 
 #[derive(Deserialize)]
 pub struct ConfigFlat {
     pub api_key: String,
+    #[serde(default)]
+    pub allowed_labels: String,
 }
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub api_key: String,
+    /// Labels allowed to be added to assets, any label is allowed if empty
+    pub allowed_labels: Vec<String>,
 }
 
 pub fn load() -> Result<Config, Error> {
@@ -17,5 +22,6 @@ pub fn load() -> Result<Config, Error> {
 
     Ok(Config {
         api_key: admin_config_flat.api_key,
+        allowed_labels: split_addresses(&admin_config_flat.allowed_labels),
     })
 }
@@ -1,7 +1,13 @@
 use serde::Deserialize;
+use std::time::Duration;
 
 use crate::error::Error;
 
+/// Page size of the lists if not requested and not configured lower
+pub const DEFAULT_LIMIT: u32 = 100;
+/// Hard maximum of the page size, the configured one can not exceed it
+pub const MAX_LIMIT: u32 = 1000;
+
 fn default_port() -> u16 {
     8080
 }
@@ -10,6 +16,14 @@ fn default_metrics_port() -> u16 {
     9090
 }
 
+fn default_max_limit() -> u32 {
+    DEFAULT_LIMIT
+}
+
+fn default_consumer_liveness_window_secs() -> u64 {
+    300
+}
+
 #[derive(Deserialize)]
 struct ConfigFlat {
     #[serde(default = "default_port")]
@@ -20,6 +34,20 @@ struct ConfigFlat {
     image_service_url: String,
     #[serde(default)]
     image_service_bypass: bool,
+    #[serde(default)]
+    debug_absence_reasons: bool,
+    #[serde(default)]
+    cache_fallback_to_db: bool,
+    #[serde(default = "default_max_limit")]
+    max_limit: u32,
+    #[serde(default = "default_consumer_liveness_window_secs")]
+    consumer_liveness_window_secs: u64,
+    chain_id: u8,
+    rate_limit_per_second: Option<u32>,
+    rate_limit_burst: Option<u32>,
+    #[serde(default)]
+    trust_forwarded_for: bool,
+    image_cache_ttl_secs: Option<u64>,
 }
 
 #[derive(Debug, Clone)]
@@ -28,6 +56,24 @@ pub struct Config {
     pub metrics_port: u16,
     pub image_service_url: String,
     pub image_service_bypass: bool,
+    /// Explains why a single asset is missing instead of responding with 404
+    pub debug_absence_reasons: bool,
+    /// Reads assets from the database when Redis is unavailable instead of failing requests
+    pub cache_fallback_to_db: bool,
+    /// Maximal page size of the lists, `MAX_LIMIT` at most
+    pub max_limit: u32,
+    /// The consumer is reported stalled if it has not saved its state for longer
+    pub consumer_liveness_window: Duration,
+    /// Chain of the addresses derived from the issuer public keys
+    pub chain_id: u8,
+    /// Requests per second of a client, not limited if unset
+    pub rate_limit_per_second: Option<u32>,
+    /// Requests of a client at once, `rate_limit_per_second` by default
+    pub rate_limit_burst: Option<u32>,
+    /// Tells the clients apart by `X-Forwarded-For`, only safe behind a proxy setting it
+    pub trust_forwarded_for: bool,
+    /// Caches whether the images exist for this long, not cached if unset
+    pub image_cache_ttl: Option<Duration>,
 }
 
 pub fn load() -> Result<Config, Error> {
@@ -38,5 +84,40 @@ pub fn load() -> Result<Config, Error> {
         metrics_port: api_config_flat.metrics_port,
         image_service_url: api_config_flat.image_service_url,
         image_service_bypass: api_config_flat.image_service_bypass,
+        debug_absence_reasons: api_config_flat.debug_absence_reasons,
+        cache_fallback_to_db: api_config_flat.cache_fallback_to_db,
+        max_limit: validate_max_limit(api_config_flat.max_limit)?,
+        consumer_liveness_window: Duration::from_secs(
+            api_config_flat.consumer_liveness_window_secs,
+        ),
+        chain_id: api_config_flat.chain_id,
+        rate_limit_per_second: api_config_flat.rate_limit_per_second,
+        rate_limit_burst: api_config_flat.rate_limit_burst,
+        trust_forwarded_for: api_config_flat.trust_forwarded_for,
+        image_cache_ttl: api_config_flat
+            .image_cache_ttl_secs
+            .map(Duration::from_secs),
     })
 }
+
+/// Caps the configured page size at `MAX_LIMIT`, no page could be requested with 0
+fn validate_max_limit(max_limit: u32) -> Result<u32, Error> {
+    if max_limit == 0 {
+        return Err(Error::LoadConfigFailed(envy::Error::Custom(
+            "API__MAX_LIMIT has to be positive".to_owned(),
+        )));
+    }
+    Ok(max_limit.min(MAX_LIMIT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_max_limit, MAX_LIMIT};
+
+    #[test]
+    fn should_validate_max_limit() {
+        assert_eq!(validate_max_limit(500).unwrap(), 500);
+        assert_eq!(validate_max_limit(MAX_LIMIT + 1).unwrap(), MAX_LIMIT);
+        assert!(validate_max_limit(0).is_err());
+    }
+}
@@ -1,30 +1,212 @@
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
 
-use crate::cache::InvalidateCacheMode;
+use super::split_addresses;
+use crate::cache::{lru_mem, InvalidateCacheMode};
 use crate::error::Error;
+use crate::models::{AssetLabel, SearchWeights};
 
-fn default_invalidate_entire_cache() -> InvalidateCacheMode {
-    InvalidateCacheMode::UserDefinedData
+fn default_invalidate_entire_cache() -> String {
+    "user_defined_data".to_owned()
+}
+
+fn default_mem_cache_capacity() -> usize {
+    lru_mem::DEFAULT_CAPACITY
+}
+
+fn default_mem_cache_ttl_secs() -> u64 {
+    lru_mem::DEFAULT_TTL.as_secs()
 }
 
 #[derive(Deserialize)]
 pub struct ConfigFlat {
     pub waves_association_address: String,
     #[serde(default = "default_invalidate_entire_cache")]
-    pub invalidate_cache_mode: InvalidateCacheMode,
+    pub invalidate_cache_mode: String,
+    /// Address of the `by_issuer` mode
+    pub invalidate_cache_issuer: Option<String>,
+    /// Comma-separated asset ids of the `by_ids` mode
+    pub invalidate_cache_ids: Option<String>,
+    #[serde(default = "default_mem_cache_capacity")]
+    pub mem_cache_capacity: usize,
+    #[serde(default = "default_mem_cache_ttl_secs")]
+    pub mem_cache_ttl_secs: u64,
+    pub mem_cache_max_idle_secs: Option<u64>,
+    pub search_weight_exact_ticker: Option<u32>,
+    pub search_weight_exact_name: Option<u32>,
+    pub search_weight_id: Option<u32>,
+    pub search_weight_id_with_ticker: Option<u32>,
+    pub search_weight_metadata: Option<u32>,
+    pub search_weight_metadata_with_ticker: Option<u32>,
+    pub search_weight_ticker: Option<u32>,
+    pub search_weight_name: Option<u32>,
+    pub search_weight_name_with_ticker: Option<u32>,
+    pub search_label_priorities: Option<String>,
+    #[serde(default)]
+    pub search_materialized_view: bool,
+    pub label_display_names: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Config {
-    pub waves_association_address: String,
+    pub waves_association_addresses: Vec<String>,
     pub invalidate_cache_mode: InvalidateCacheMode,
+    pub mem_cache_capacity: usize,
+    pub mem_cache_ttl: Duration,
+    /// Entries not accessed for this long are evicted before their TTL expires
+    pub mem_cache_max_idle: Option<Duration>,
+    pub search_weights: SearchWeights,
+    /// Search the `searchable_assets` materialized view refreshed by the consumer
+    pub search_materialized_view: bool,
+    /// Display names of the label codes, returned with `label_display=true`
+    pub label_display_names: HashMap<String, String>,
 }
 
 pub fn load() -> Result<Config, Error> {
     let app_config_flat = envy::from_env::<ConfigFlat>()?;
 
+    let default_weights = SearchWeights::default();
+    let search_weights = SearchWeights {
+        exact_ticker: app_config_flat
+            .search_weight_exact_ticker
+            .unwrap_or(default_weights.exact_ticker),
+        exact_name: app_config_flat
+            .search_weight_exact_name
+            .unwrap_or(default_weights.exact_name),
+        id: app_config_flat
+            .search_weight_id
+            .unwrap_or(default_weights.id),
+        id_with_ticker: app_config_flat
+            .search_weight_id_with_ticker
+            .unwrap_or(default_weights.id_with_ticker),
+        metadata: app_config_flat
+            .search_weight_metadata
+            .unwrap_or(default_weights.metadata),
+        metadata_with_ticker: app_config_flat
+            .search_weight_metadata_with_ticker
+            .unwrap_or(default_weights.metadata_with_ticker),
+        ticker: app_config_flat
+            .search_weight_ticker
+            .unwrap_or(default_weights.ticker),
+        name: app_config_flat
+            .search_weight_name
+            .unwrap_or(default_weights.name),
+        name_with_ticker: app_config_flat
+            .search_weight_name_with_ticker
+            .unwrap_or(default_weights.name_with_ticker),
+        label_priorities: match app_config_flat.search_label_priorities.as_deref() {
+            Some(priorities) => parse_label_priorities(priorities)?,
+            None => default_weights.label_priorities,
+        },
+    };
+
+    let invalidate_cache_mode = InvalidateCacheMode::from_flat(
+        &app_config_flat.invalidate_cache_mode,
+        app_config_flat.invalidate_cache_issuer,
+        app_config_flat
+            .invalidate_cache_ids
+            .as_deref()
+            .map(split_addresses)
+            .unwrap_or_default(),
+    )
+    .map_err(|e| Error::LoadConfigFailed(envy::Error::Custom(e)))?;
+
+    let label_display_names = match app_config_flat.label_display_names.as_deref() {
+        Some(display_names) => parse_label_display_names(display_names)?,
+        None => HashMap::new(),
+    };
+
     Ok(Config {
-        waves_association_address: app_config_flat.waves_association_address,
-        invalidate_cache_mode: app_config_flat.invalidate_cache_mode,
+        waves_association_addresses: split_addresses(&app_config_flat.waves_association_address),
+        invalidate_cache_mode,
+        mem_cache_capacity: app_config_flat.mem_cache_capacity,
+        mem_cache_ttl: Duration::from_secs(app_config_flat.mem_cache_ttl_secs),
+        mem_cache_max_idle: app_config_flat
+            .mem_cache_max_idle_secs
+            .map(Duration::from_secs),
+        search_weights,
+        search_materialized_view: app_config_flat.search_materialized_view,
+        label_display_names,
     })
 }
+
+/// Parses comma-separated `LABEL:priority` pairs
+fn parse_label_priorities(priorities: &str) -> Result<Vec<(AssetLabel, u32)>, Error> {
+    priorities
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            pair.split_once(':')
+                .and_then(|(label, priority)| {
+                    let priority = priority.trim().parse::<u32>().ok()?;
+                    Some((AssetLabel::from(label), priority))
+                })
+                .ok_or_else(|| {
+                    Error::LoadConfigFailed(envy::Error::Custom(format!(
+                        "invalid label priority '{}', expected LABEL:priority",
+                        pair
+                    )))
+                })
+        })
+        .collect()
+}
+
+/// Parses comma-separated `LABEL:display name` pairs
+fn parse_label_display_names(display_names: &str) -> Result<HashMap<String, String>, Error> {
+    display_names
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            pair.split_once(':')
+                .map(|(label, display)| {
+                    (
+                        AssetLabel::from(label).as_str().to_owned(),
+                        display.trim().to_owned(),
+                    )
+                })
+                .filter(|(_, display)| !display.is_empty())
+                .ok_or_else(|| {
+                    Error::LoadConfigFailed(envy::Error::Custom(format!(
+                        "invalid label display name '{}', expected LABEL:display name",
+                        pair
+                    )))
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{parse_label_display_names, parse_label_priorities};
+    use crate::models::AssetLabel;
+
+    #[test]
+    fn should_parse_label_priorities() {
+        assert_eq!(
+            parse_label_priorities("WA_VERIFIED:3, defi:1,").unwrap(),
+            vec![(AssetLabel::WaVerified, 3), (AssetLabel::DeFi, 1)]
+        );
+        assert!(parse_label_priorities("WA_VERIFIED").is_err());
+        assert!(parse_label_priorities("WA_VERIFIED:high").is_err());
+    }
+
+    #[test]
+    fn should_parse_label_display_names() {
+        assert_eq!(
+            parse_label_display_names("WA_VERIFIED:Verified, defi: DeFi ,").unwrap(),
+            vec![
+                ("WA_VERIFIED".to_owned(), "Verified".to_owned()),
+                ("DEFI".to_owned(), "DeFi".to_owned()),
+            ]
+            .into_iter()
+            .collect::<HashMap<_, _>>()
+        );
+        assert!(parse_label_display_names("WA_VERIFIED").is_err());
+        assert!(parse_label_display_names("WA_VERIFIED:").is_err());
+    }
+}
@@ -1,5 +1,8 @@
 use serde::Deserialize;
+use std::time::Duration;
 
+use super::split_addresses;
+use crate::consumer::ReconnectBackoff;
 use crate::error::Error;
 
 fn default_updates_per_request() -> usize {
@@ -14,8 +17,38 @@ fn default_metrics_port() -> u16 {
     9090
 }
 
+fn default_metrics_enabled() -> bool {
+    true
+}
+
+fn default_health_port() -> u16 {
+    8080
+}
+
+fn default_max_handled_lag() -> u32 {
+    10
+}
+
+fn default_liveness_max_idle_secs() -> u64 {
+    600
+}
+
+fn default_stream_reconnect_initial_delay_ms() -> u64 {
+    1000
+}
+
+fn default_stream_reconnect_max_delay_secs() -> u64 {
+    60
+}
+
+fn default_stream_reconnect_max_attempts() -> u32 {
+    10
+}
+
 #[derive(Deserialize)]
 struct ConfigFlat {
+    #[serde(default = "default_metrics_enabled")]
+    metrics_enabled: bool,
     #[serde(default = "default_metrics_port")]
     metrics_port: u16,
     blockchain_updates_url: String,
@@ -24,31 +57,91 @@ struct ConfigFlat {
     updates_per_request: usize,
     #[serde(default = "default_max_wait_time_in_secs")]
     max_wait_time_in_secs: u64,
+    #[serde(default)]
+    adaptive_batch_size: bool,
     chain_id: u8,
     waves_association_address: String,
+    #[serde(default)]
+    empty_ticker_as_deleted: bool,
+    #[serde(default)]
+    debug_asset_ids: String,
+    searchable_assets_refresh_interval_secs: Option<u64>,
+    #[serde(default = "default_stream_reconnect_initial_delay_ms")]
+    stream_reconnect_initial_delay_ms: u64,
+    #[serde(default = "default_stream_reconnect_max_delay_secs")]
+    stream_reconnect_max_delay_secs: u64,
+    #[serde(default = "default_stream_reconnect_max_attempts")]
+    stream_reconnect_max_attempts: u32,
+    #[serde(default)]
+    redis_checkpoint: bool,
+    node_url: Option<String>,
+    #[serde(default = "default_health_port")]
+    health_port: u16,
+    #[serde(default = "default_max_handled_lag")]
+    max_handled_lag: u32,
+    #[serde(default = "default_liveness_max_idle_secs")]
+    liveness_max_idle_secs: u64,
 }
 
 #[derive(Debug, Clone)]
 pub struct Config {
+    /// Whether the metrics HTTP endpoint is started
+    pub metrics_enabled: bool,
     pub metrics_port: u16,
     pub blockchain_updates_url: String,
     pub starting_height: u32,
     pub updates_per_request: usize,
     pub max_wait_time_in_secs: u64,
+    /// Grows the batches up to `updates_per_request` while syncing and shrinks them near the chain head
+    pub adaptive_batch_size: bool,
     pub chain_id: u8,
-    pub waves_association_address: String,
+    pub waves_association_addresses: Vec<String>,
+    /// Drops the ticker explicitly set to an empty string like a deleted one
+    pub empty_ticker_as_deleted: bool,
+    /// Assets whose raw updates are logged for debugging ingestion
+    pub debug_asset_ids: Vec<String>,
+    /// Minimal interval between refreshes of the `searchable_assets` materialized view,
+    /// the view is refreshed after every handled batch when zero and never when not set
+    pub searchable_assets_refresh_interval: Option<std::time::Duration>,
+    pub stream_reconnect: ReconnectBackoff,
+    /// Copies the consumer state to Redis, so that the state query is skipped on start
+    pub redis_checkpoint: bool,
+    /// Node REST API the readiness probe takes the chain height from, no health server without it
+    pub node_url: Option<String>,
+    pub health_port: u16,
+    /// The consumer is not ready while it is more blocks behind the node
+    pub max_handled_lag: u32,
+    /// The consumer is not live when it has not handled a batch for longer
+    pub liveness_max_idle: Duration,
 }
 
 pub fn load() -> Result<Config, Error> {
     let config_flat = envy::from_env::<ConfigFlat>()?;
 
     Ok(Config {
+        metrics_enabled: config_flat.metrics_enabled,
         metrics_port: config_flat.metrics_port,
         blockchain_updates_url: config_flat.blockchain_updates_url,
         starting_height: config_flat.starting_height,
         updates_per_request: config_flat.updates_per_request,
         max_wait_time_in_secs: config_flat.max_wait_time_in_secs,
+        adaptive_batch_size: config_flat.adaptive_batch_size,
         chain_id: config_flat.chain_id,
-        waves_association_address: config_flat.waves_association_address,
+        waves_association_addresses: split_addresses(&config_flat.waves_association_address),
+        empty_ticker_as_deleted: config_flat.empty_ticker_as_deleted,
+        debug_asset_ids: split_addresses(&config_flat.debug_asset_ids),
+        searchable_assets_refresh_interval: config_flat
+            .searchable_assets_refresh_interval_secs
+            .map(std::time::Duration::from_secs),
+        stream_reconnect: ReconnectBackoff {
+            initial_delay: Duration::from_millis(config_flat.stream_reconnect_initial_delay_ms),
+            max_delay: Duration::from_secs(config_flat.stream_reconnect_max_delay_secs),
+            max_attempts: config_flat.stream_reconnect_max_attempts,
+        },
+        redis_checkpoint: config_flat.redis_checkpoint,
+        node_url: config_flat.node_url,
+        health_port: config_flat.health_port,
+        max_handled_lag: config_flat.max_handled_lag,
+        liveness_max_idle: Duration::from_secs(config_flat.liveness_max_idle_secs),
     })
 }
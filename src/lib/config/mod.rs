@@ -96,3 +96,27 @@ pub async fn load_invalidate_cache_config() -> Result<InvalidateCacheConfig, Err
 pub fn load_migration_config() -> Result<migration::Config, Error> {
     migration::load()
 }
+
+/// Splits comma-separated list of addresses, a single address is also accepted
+pub fn split_addresses(addresses: &str) -> Vec<String> {
+    addresses
+        .split(',')
+        .map(|a| a.trim())
+        .filter(|a| !a.is_empty())
+        .map(|a| a.to_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_addresses;
+
+    #[test]
+    fn should_split_addresses() {
+        assert_eq!(split_addresses("3PAddr1"), vec!["3PAddr1".to_owned()]);
+        assert_eq!(
+            split_addresses("3PAddr1, 3PAddr2,"),
+            vec!["3PAddr1".to_owned(), "3PAddr2".to_owned()]
+        );
+    }
+}
@@ -20,6 +20,8 @@ pub struct ConfigFlat {
     pub password: String,
     #[serde(default = "default_poolsize")]
     pub poolsize: u32,
+    pub replica_url: Option<String>,
+    pub replica_poolsize: Option<u32>,
 }
 
 #[derive(Debug, Clone)]
@@ -30,6 +32,9 @@ pub struct Config {
     pub user: String,
     pub password: String,
     pub pool_size: u32,
+    /// Replica serving the api reads, the primary is used if not set
+    pub replica_url: Option<String>,
+    pub replica_pool_size: u32,
 }
 
 pub fn load() -> Result<Config, Error> {
@@ -42,5 +47,7 @@ pub fn load() -> Result<Config, Error> {
         user: config_flat.user,
         password: config_flat.password,
         pool_size: config_flat.poolsize,
+        replica_url: config_flat.replica_url,
+        replica_pool_size: config_flat.replica_poolsize.unwrap_or(config_flat.poolsize),
     })
 }
@@ -1,5 +1,7 @@
 use serde::Deserialize;
+use std::time::Duration;
 
+use crate::cache::{async_redis_cache, codec::Codec};
 use crate::error::Error;
 
 fn default_port() -> u16 {
@@ -16,6 +18,10 @@ fn default_poolsize() -> u32 {
     1
 }
 
+fn default_mget_chunk_size() -> usize {
+    async_redis_cache::DEFAULT_MGET_CHUNK_SIZE
+}
+
 #[derive(Deserialize)]
 pub struct ConfigFlat {
     pub host: String,
@@ -26,6 +32,12 @@ pub struct ConfigFlat {
     pub password: String,
     #[serde(default = "default_poolsize")]
     pub poolsize: u32,
+    pub ttl_secs: Option<u64>,
+    #[serde(default = "default_mget_chunk_size")]
+    pub mget_chunk_size: usize,
+    #[serde(default)]
+    pub codec: Codec,
+    pub invalidation_channel: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -35,6 +47,13 @@ pub struct Config {
     pub user: String,
     pub password: String,
     pub poolsize: u32,
+    /// Values do not expire if unset
+    pub ttl: Option<Duration>,
+    pub mget_chunk_size: usize,
+    /// Has to be the same for all the services sharing the cache
+    pub codec: Codec,
+    /// Pub/sub channel of the ids of the changed assets, the in-memory caches are not evicted if unset
+    pub invalidation_channel: Option<String>,
 }
 
 pub fn load() -> Result<Config, Error> {
@@ -46,5 +65,13 @@ pub fn load() -> Result<Config, Error> {
         user: config_flat.user,
         password: config_flat.password,
         poolsize: config_flat.poolsize,
+        // redis rejects the zero expiration time, the values are kept forever instead
+        ttl: config_flat
+            .ttl_secs
+            .filter(|ttl_secs| *ttl_secs > 0)
+            .map(Duration::from_secs),
+        mget_chunk_size: config_flat.mget_chunk_size,
+        codec: config_flat.codec,
+        invalidation_channel: config_flat.invalidation_channel,
     })
 }
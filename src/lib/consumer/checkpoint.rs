@@ -0,0 +1,57 @@
+use redis::Commands;
+
+use super::models::consumer_state::ConsumerState;
+use crate::cache::KEY_SEPARATOR;
+use crate::error::Error as AppError;
+use crate::sync_redis::RedisPool;
+
+pub const CONSUMER_CHECKPOINT_KEY_PREFIX: &str = "consumer_checkpoint";
+
+/// Key of the checkpoint of the chain, so that the consumers of other chains sharing the Redis keep their own
+pub fn key(chain_id: u8) -> String {
+    format!(
+        "{}{}{}",
+        CONSUMER_CHECKPOINT_KEY_PREFIX, KEY_SEPARATOR, chain_id as char
+    )
+}
+
+/// Copy of the consumer state saved after every committed batch
+///
+/// The state in Postgres stays authoritative, the checkpoint is checked against the last stored block
+/// before the first streamed batch is handled
+pub trait Checkpoint {
+    fn get(&self) -> Result<Option<ConsumerState>, AppError>;
+
+    fn set(&self, state: &ConsumerState) -> Result<(), AppError>;
+}
+
+#[derive(Clone)]
+pub struct RedisCheckpoint {
+    redis_pool: RedisPool,
+    key: String,
+}
+
+pub fn new(redis_pool: RedisPool, key: impl AsRef<str>) -> RedisCheckpoint {
+    RedisCheckpoint {
+        redis_pool,
+        key: key.as_ref().to_owned(),
+    }
+}
+
+impl Checkpoint for RedisCheckpoint {
+    fn get(&self) -> Result<Option<ConsumerState>, AppError> {
+        let mut con = self.redis_pool.get()?;
+        let value: Option<String> = con.get(&self.key)?;
+
+        value
+            .map(|value| serde_json::from_str(&value).map_err(AppError::from))
+            .transpose()
+    }
+
+    fn set(&self, state: &ConsumerState) -> Result<(), AppError> {
+        let mut con = self.redis_pool.get()?;
+        let () = con.set(&self.key, serde_json::to_string(state)?)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use warp::{http::StatusCode, reply::Response, Filter, Rejection, Reply};
+use wavesexchange_log::{info, warn};
+
+use crate::api_clients::node;
+
+/// Whether the consumer keeps up with the node
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct Readiness {
+    ready: bool,
+    handled_height: u32,
+    node_height: Option<u32>,
+}
+
+/// Height and time of the last handled batch, shared by the consumer with its health server
+#[derive(Debug)]
+pub struct Progress {
+    handled_height: AtomicU32,
+    handled_at: Mutex<Instant>,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self {
+            handled_height: AtomicU32::new(0),
+            handled_at: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+impl Progress {
+    pub fn set_handled(&self, height: u32) {
+        self.handled_height.store(height, Ordering::Relaxed);
+        *self.handled_at.lock().unwrap() = Instant::now();
+    }
+
+    pub fn handled_height(&self) -> u32 {
+        self.handled_height.load(Ordering::Relaxed)
+    }
+
+    fn idle(&self) -> Duration {
+        self.handled_at.lock().unwrap().elapsed()
+    }
+}
+
+/// Serves `/livez` and `/readyz` of the consumer, the former failing when no batch was handled
+/// for `max_idle`, the latter while it lags behind the node
+pub async fn start<C>(
+    port: u16,
+    progress: Arc<Progress>,
+    node_client: C,
+    max_lag: u32,
+    max_idle: Duration,
+) where
+    C: node::Client + Send + Sync + 'static,
+{
+    info!("Starting consumer health server at 0.0.0.0:{}", port);
+
+    warp::serve(routes(progress, node_client, max_lag, max_idle))
+        .run(([0, 0, 0, 0], port))
+        .await;
+}
+
+fn routes<C>(
+    progress: Arc<Progress>,
+    node_client: C,
+    max_lag: u32,
+    max_idle: Duration,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone
+where
+    C: node::Client + Send + Sync + 'static,
+{
+    let with_node_client = {
+        let node_client = Arc::new(node_client);
+        warp::any().map(move || node_client.clone())
+    };
+
+    let with_progress = warp::any().map(move || progress.clone());
+
+    let livez_handler = warp::path!("livez")
+        .and(warp::get())
+        .and(with_progress.clone())
+        .map(move |progress: Arc<Progress>| livez_controller(&progress, max_idle));
+
+    let readyz_handler = warp::path!("readyz")
+        .and(warp::get())
+        .and(with_progress)
+        .and(with_node_client)
+        .and(warp::any().map(move || max_lag))
+        .and_then(readyz_controller);
+
+    livez_handler.or(readyz_handler)
+}
+
+/// A stream stalled without failing leaves the consumer running, but handling nothing
+fn livez_controller(progress: &Progress, max_idle: Duration) -> Response {
+    let idle = progress.idle();
+    if idle <= max_idle {
+        return warp::reply::json(&"ok").into_response();
+    }
+
+    warn!(
+        "no batch handled for {}s, the last handled height is {}",
+        idle.as_secs(),
+        progress.handled_height()
+    );
+    warp::reply::with_status(
+        warp::reply::json(&"stalled"),
+        StatusCode::SERVICE_UNAVAILABLE,
+    )
+    .into_response()
+}
+
+async fn readyz_controller(
+    progress: Arc<Progress>,
+    node_client: Arc<impl node::Client>,
+    max_lag: u32,
+) -> Result<Response, Rejection> {
+    let handled_height = progress.handled_height();
+    let node_height = match node_client.height().await {
+        Ok(node_height) => Some(node_height),
+        Err(e) => {
+            warn!("failed to get the node height: {}", e);
+            None
+        }
+    };
+    let ready = node_height.map_or(false, |node_height| {
+        is_ready(handled_height, node_height, max_lag)
+    });
+
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&Readiness {
+            ready,
+            handled_height,
+            node_height,
+        }),
+        status,
+    )
+    .into_response())
+}
+
+/// The node may be behind the consumer for a moment after a reconnection
+fn is_ready(handled_height: u32, node_height: u32, max_lag: u32) -> bool {
+    node_height.saturating_sub(handled_height) <= max_lag
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use warp::http::StatusCode;
+
+    use super::{routes, Progress};
+    use crate::api_clients::{node, ApiBaseUrl, Error};
+
+    struct FixedHeightNode(Arc<AtomicU32>);
+
+    impl ApiBaseUrl for FixedHeightNode {
+        fn base_url(&self) -> String {
+            "http://node".to_owned()
+        }
+    }
+
+    #[async_trait]
+    impl node::Client for FixedHeightNode {
+        async fn height(&self) -> Result<u32, Error> {
+            Ok(self.0.load(Ordering::Relaxed))
+        }
+    }
+
+    #[tokio::test]
+    async fn should_not_be_ready_while_lag_exceeds_threshold() {
+        let progress = Arc::new(Progress::default());
+        progress.set_handled(100);
+        let node_height = Arc::new(AtomicU32::new(105));
+        let routes = routes(
+            progress.clone(),
+            FixedHeightNode(node_height.clone()),
+            10,
+            Duration::from_secs(600),
+        );
+
+        let readyz = || warp::test::request().path("/readyz");
+
+        assert_eq!(readyz().reply(&routes).await.status(), StatusCode::OK);
+
+        node_height.store(111, Ordering::Relaxed);
+        let response = readyz().reply(&routes).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"ready": false, "handled_height": 100, "node_height": 111})
+        );
+
+        // the consumer caught up
+        progress.set_handled(111);
+        assert_eq!(readyz().reply(&routes).await.status(), StatusCode::OK);
+
+        let livez = warp::test::request().path("/livez").reply(&routes).await;
+        assert_eq!(livez.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_not_be_live_when_no_batch_handled_within_window() {
+        let progress = Arc::new(Progress::default());
+        progress.set_handled(100);
+        let routes = routes(
+            progress.clone(),
+            FixedHeightNode(Arc::new(AtomicU32::new(100))),
+            10,
+            Duration::from_millis(50),
+        );
+
+        let livez = || warp::test::request().path("/livez");
+
+        assert_eq!(livez().reply(&routes).await.status(), StatusCode::OK);
+
+        // the stream is connected, but delivers nothing
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(
+            livez().reply(&routes).await.status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        progress.set_handled(101);
+        assert_eq!(livez().reply(&routes).await.status(), StatusCode::OK);
+    }
+}
@@ -1,8 +1,11 @@
+pub mod checkpoint;
+pub mod health;
 pub mod models;
+pub mod pending_cache;
 pub mod repo;
 pub mod updates;
 
-use anyhow::{Error, Result};
+use anyhow::{bail, Error, Result};
 use bigdecimal::ToPrimitive;
 use chrono::{DateTime, Duration, NaiveDateTime, Utc};
 use fragstrings::frag_parse;
@@ -13,20 +16,32 @@ use std::str;
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::mpsc::Receiver;
+use tokio::sync::watch;
 use waves_protobuf_schemas::waves::{
     data_transaction_data::data_entry::Value,
-    events::{StateUpdate, TransactionMetadata},
+    events::{
+        state_update::AssetDetails,
+        transaction_metadata::{EthereumMetadata, Metadata as TxMetadata},
+        StateUpdate, TransactionMetadata,
+    },
     signed_transaction::Transaction,
     SignedTransaction, Transaction as WavesTx,
 };
-use wavesexchange_log::{debug, info, timer};
+use wavesexchange_log::{debug, error, info, timer, warn};
 
-use self::models::asset::{AssetOverride, DeletedAsset, InsertableAsset};
+use self::checkpoint::Checkpoint;
+use self::models::asset::{AssetOverride, CurrentAsset, DeletedAsset, InsertableAsset};
+use self::models::asset_descriptions::{
+    AssetDescriptionOverride, DeletedAssetDescription, InsertableAssetDescription,
+};
 use self::models::asset_labels::{AssetLabelsOverride, DeletedAssetLabels, InsertableAssetLabels};
+use self::models::asset_names::{AssetNameOverride, DeletedAssetName, InsertableAssetName};
 use self::models::asset_tickers::{AssetTickerOverride, DeletedAssetTicker, InsertableAssetTicker};
 use self::models::block_microblock::BlockMicroblock;
+use self::models::consumer_state::ConsumerState;
 use self::models::data_entry::{
-    DataEntryOverride, DataEntryUpdate, DataEntryValue, DeletedDataEntry, InsertableDataEntry,
+    CurrentDataEntry, DataEntryOverride, DataEntryUpdate, DataEntryValue, DeletedDataEntry,
+    InsertableDataEntry,
 };
 use self::models::issuer_balance::{
     DeletedIssuerBalance, InsertableIssuerBalance, IssuerBalanceOverride, IssuerBalanceUpdate,
@@ -34,10 +49,13 @@ use self::models::issuer_balance::{
 use self::models::out_leasing::{
     DeletedOutLeasing, InsertableOutLeasing, OutLeasingOverride, OutLeasingUpdate,
 };
+use self::models::supply_change::InsertableSupplyChange;
+use self::pending_cache::PendingWritesCache;
+use crate::cache::invalidation_publisher::InvalidationPublisher;
 use crate::cache::{AssetBlockchainData, AssetUserDefinedData, SyncReadCache, SyncWriteCache};
-use crate::db::enums::DataEntryValueType;
 use crate::error::Error as AppError;
-use crate::models::{AssetInfoUpdate, AssetOracleDataEntry, BaseAssetInfoUpdate, DataEntryType};
+use crate::metrics;
+use crate::models::{AssetInfoUpdate, AssetLabel, AssetOracleDataEntry, BaseAssetInfoUpdate};
 use crate::waves::{
     get_asset_id, is_waves_asset_id, parse_waves_association_key, Address,
     KNOWN_WAVES_ASSOCIATION_ASSET_ATTRIBUTES, WAVES_ID,
@@ -55,11 +73,22 @@ pub struct BlockMicroblockAppend {
     id: String,
     time_stamp: Option<i64>,
     height: u32,
+    /// Id of the parent block, set for key blocks only
+    reference: Option<String>,
     updated_waves_amount: Option<i64>,
     state_update: StateUpdate,
     txs: Vec<Tx>,
 }
 
+impl BlockMicroblockAppend {
+    /// Whether the append carries no transactions and no state changes
+    fn is_empty(&self) -> bool {
+        self.txs.is_empty()
+            && self.updated_waves_amount.is_none()
+            && self.state_update == StateUpdate::default()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Tx {
     pub id: String,
@@ -90,19 +119,51 @@ enum UpdatesItem {
 #[derive(Debug)]
 pub struct AssetLabelsUpdate {
     pub asset_id: String,
-    pub labels: Vec<String>,
+    pub labels: Vec<AssetLabel>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct AssetNameUpdate {
+    pub asset_id: String,
+    pub name: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct AssetDescriptionUpdate {
+    pub asset_id: String,
+    pub description: String,
 }
 
 #[derive(Debug)]
 pub struct AssetTickerUpdate {
     pub asset_id: String,
-    pub ticker: String,
+    /// `None` if the ticker was deleted, explicitly set empty ticker is `Some("")`
+    pub ticker: Option<String>,
 }
 
 #[derive(Clone, Debug)]
 pub enum AssetLabelUpdate {
-    SetLabel(String),
-    DeleteLabel(String),
+    SetLabel(AssetLabel),
+    DeleteLabel(AssetLabel),
+}
+
+/// Reconnection policy of the closed updates stream
+#[derive(Clone, Debug)]
+pub struct ReconnectBackoff {
+    pub initial_delay: std::time::Duration,
+    pub max_delay: std::time::Duration,
+    /// Consecutive failed reconnections before giving up
+    pub max_attempts: u32,
+}
+
+impl ReconnectBackoff {
+    /// Delay before the reconnection attempt, doubled after each failed one
+    fn delay(&self, attempt: u32) -> std::time::Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
 }
 
 #[async_trait::async_trait]
@@ -115,8 +176,9 @@ pub trait UpdatesSource {
     ) -> Result<Receiver<BlockchainUpdatesWithLastHeight>, AppError>;
 }
 
-// TODO: handle shutdown signals -> rollback current transaction
-pub async fn start<T, R, CBD, CUDD>(
+/// Handles the updates until the stream fails for good or the shutdown is signalled,
+/// the batch being handled at the shutdown is rolled back
+pub async fn start<T, P, C, R, CBD, CUDD>(
     starting_height: u32,
     updates_src: T,
     repo: Arc<R>,
@@ -125,78 +187,524 @@ pub async fn start<T, R, CBD, CUDD>(
     updates_per_request: usize,
     max_wait_time_in_secs: u64,
     chain_id: u8,
-    waves_association_address: &str,
+    waves_association_addresses: &[String],
+    empty_ticker_as_deleted: bool,
+    debug_asset_ids: &[String],
+    searchable_assets_refresh_interval: Option<std::time::Duration>,
+    reconnect_backoff: &ReconnectBackoff,
+    invalidation_publisher: Option<P>,
+    checkpoint: Option<C>,
+    progress: Arc<health::Progress>,
+    mut shutdown: watch::Receiver<bool>,
 ) -> Result<()>
 where
-    T: UpdatesSource + Send + Sync + 'static,
+    T: UpdatesSource + Clone + Send + Sync + 'static,
+    P: InvalidationPublisher + Clone + Send + 'static,
+    C: Checkpoint,
+    R: repo::Repo,
+    CBD: SyncReadCache<AssetBlockchainData> + SyncWriteCache<AssetBlockchainData> + Clone,
+    CUDD: SyncReadCache<AssetUserDefinedData> + SyncWriteCache<AssetUserDefinedData> + Clone,
+{
+    // the lock is held by the session of the repo connection until the consumer exits
+    if !repo.try_lock_consumer()? {
+        bail!("another consumer is running");
+    }
+
+    let max_duration = Duration::seconds(max_wait_time_in_secs.to_i64().unwrap());
+
+    let mut last_view_refresh: Option<Instant> = None;
+    let mut failed_attempts = 0;
+    let mut force_rollback = false;
+    let mut skip_checkpoint = false;
+    let batch_metrics = metrics::BatchMetrics::default();
+
+    loop {
+        if *shutdown.borrow() {
+            return Ok(());
+        }
+
+        // unfinished height is handled again after reconnecting, as on restart
+        let ResumePoint {
+            from_height: starting_from_height,
+            expected_reference: mut expected_reference,
+            rollback: startup_rollback,
+            checkpointed: mut checkpointed,
+        } = resume_height(
+            repo.clone(),
+            blockchain_data_cache.clone(),
+            user_defined_data_cache.clone(),
+            waves_association_addresses,
+            starting_height,
+            force_rollback,
+            checkpoint.as_ref().filter(|_| !skip_checkpoint),
+        )?;
+        force_rollback = false;
+        skip_checkpoint = false;
+        progress.set_handled(starting_from_height.saturating_sub(1));
+
+        if let (Some(publisher), Some(rollback)) =
+            (invalidation_publisher.clone(), startup_rollback)
+        {
+            publish_invalidations(publisher, rollback.invalidated_asset_ids);
+        }
+
+        info!(
+            "Start fetching updates from height {}",
+            starting_from_height
+        );
+
+        let stream = updates_src
+            .clone()
+            .stream(starting_from_height, updates_per_request, max_duration)
+            .await;
+
+        let closed_error = match stream {
+            Ok(mut rx) => {
+                loop {
+                    let start = Instant::now();
+
+                    // the stream is closed once the receiver is dropped on return
+                    let updates_with_height = tokio::select! {
+                        biased;
+                        _ = shutdown_signalled(&mut shutdown) => {
+                            info!("shutdown signalled, stop fetching updates");
+                            return Ok(());
+                        }
+                        updates_with_height = rx.recv() => match updates_with_height {
+                            Some(updates_with_height) => updates_with_height,
+                            None => break,
+                        },
+                    };
+
+                    info!(
+                        "{} updates were received in {:?}",
+                        updates_with_height.updates.len(),
+                        start.elapsed()
+                    );
+
+                    failed_attempts = 0;
+
+                    // nothing is written before the checkpoint is verified
+                    if let Some(state) = checkpointed.take() {
+                        if repo.get_last_block()?.as_ref() != Some(&state) {
+                            warn!(
+                                "checkpoint of block {} is stale, resuming from the state in Postgres",
+                                state.block_id
+                            );
+                            skip_checkpoint = true;
+                            break;
+                        }
+                    }
+
+                    if let Some(reference) = expected_reference.take() {
+                        if !continues_from(&reference, &updates_with_height.updates) {
+                            warn!(
+                                "block {} was rolled back while the consumer was stopped",
+                                reference
+                            );
+                            force_rollback = true;
+                            break;
+                        }
+                    }
+
+                    let last_height = updates_with_height.last_height;
+                    let (changed_asset_ids, state) = match handle_updates_batch(
+                        updates_with_height,
+                        repo.clone(),
+                        blockchain_data_cache.clone(),
+                        user_defined_data_cache.clone(),
+                        chain_id,
+                        waves_association_addresses,
+                        empty_ticker_as_deleted,
+                        debug_asset_ids,
+                        &shutdown,
+                        &batch_metrics,
+                    ) {
+                        Ok(handled) => handled,
+                        Err(e) if is_shutdown_requested(&e) => {
+                            info!("shutdown signalled, the handled batch was rolled back");
+                            return Ok(());
+                        }
+                        Err(e) => return Err(e),
+                    };
+                    progress.set_handled(last_height);
+
+                    if let (Some(checkpoint), Some(state)) = (&checkpoint, state) {
+                        if let Err(e) = checkpoint.set(&state) {
+                            warn!("failed to save the checkpoint: {}", e);
+                        }
+                    }
+
+                    // published after the commit, so the subscribers re-read the committed data
+                    if let Some(publisher) = invalidation_publisher.clone() {
+                        publish_invalidations(publisher, changed_asset_ids);
+                    }
+
+                    // the view is refreshed outside of the batch transaction, so it sees the handled updates
+                    if let Some(interval) = searchable_assets_refresh_interval {
+                        if last_view_refresh.map_or(true, |at| at.elapsed() >= interval) {
+                            let start = Instant::now();
+                            repo.refresh_searchable_assets()?;
+                            info!("searchable assets were refreshed in {:?}", start.elapsed());
+                            last_view_refresh = Some(start);
+                        }
+                    }
+                }
+
+                if force_rollback || skip_checkpoint {
+                    continue;
+                }
+
+                AppError::StreamClosed("GRPC Stream was closed by the server".to_string())
+            }
+            Err(e) => e,
+        };
+
+        failed_attempts += 1;
+        if failed_attempts > reconnect_backoff.max_attempts {
+            return Err(Error::new(closed_error));
+        }
+
+        let delay = reconnect_backoff.delay(failed_attempts);
+        warn!(
+            "updates stream failed: {}, reconnecting in {:?} (attempt {} of {})",
+            closed_error, delay, failed_attempts, reconnect_backoff.max_attempts
+        );
+        tokio::select! {
+            _ = shutdown_signalled(&mut shutdown) => return Ok(()),
+            _ = tokio::time::sleep(delay) => {}
+        }
+    }
+}
+
+/// Resolves once the shutdown is signalled, never if the sender is dropped without signalling
+async fn shutdown_signalled(shutdown: &mut watch::Receiver<bool>) {
+    while !*shutdown.borrow() {
+        if shutdown.changed().await.is_err() {
+            futures::future::pending::<()>().await;
+        }
+    }
+}
+
+fn is_shutdown_requested(err: &Error) -> bool {
+    matches!(
+        err.downcast_ref::<AppError>(),
+        Some(AppError::ShutdownRequested)
+    )
+}
+
+/// Publishes the ids in background, failures are logged only
+/// since the subscribers' local caches expire anyway
+fn publish_invalidations<P>(publisher: P, asset_ids: Vec<String>)
+where
+    P: InvalidationPublisher + Send + 'static,
+{
+    if asset_ids.is_empty() {
+        return;
+    }
+
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = publisher.publish(&asset_ids) {
+            warn!(
+                "failed to publish {} invalidated asset ids: {}",
+                asset_ids.len(),
+                e
+            );
+        }
+    });
+}
+
+/// Height to stream the updates from
+#[derive(Debug, PartialEq)]
+struct ResumePoint {
+    from_height: u32,
+    /// Id of the last handled block the streamed updates have to continue
+    expected_reference: Option<String>,
+    rollback: Option<StartupRollback>,
+    /// State of the checkpoint the stream starts from, unverified until the first batch
+    checkpointed: Option<ConsumerState>,
+}
+
+/// Scope of the rollback of the last handled height
+#[derive(Debug, PartialEq)]
+struct StartupRollback {
+    rolled_back_to_height: i32,
+    affected_assets: usize,
+    invalidated_asset_ids: Vec<String>,
+}
+
+/// Returns the height to stream from and the expected parent id of its block.
+///
+/// Streaming continues after the checkpointed block, which is verified along with the first batch,
+/// or after the last handled block if it matches the persisted consumer state,
+/// otherwise the last handled height, which may be incomplete, is rolled back.
+fn resume_height<R, CBD, CUDD>(
+    repo: Arc<R>,
+    blockchain_data_cache: CBD,
+    user_defined_data_cache: CUDD,
+    waves_association_addresses: &[String],
+    starting_height: u32,
+    force_rollback: bool,
+    checkpoint: Option<&impl Checkpoint>,
+) -> Result<ResumePoint>
+where
     R: repo::Repo,
     CBD: SyncReadCache<AssetBlockchainData> + SyncWriteCache<AssetBlockchainData> + Clone,
     CUDD: SyncReadCache<AssetUserDefinedData> + SyncWriteCache<AssetUserDefinedData> + Clone,
 {
-    let starting_from_height = match repo.get_prev_handled_height()? {
+    let mut unflushed_height = None;
+    if !force_rollback {
+        // Postgres is not read on start with the checkpoint
+        let checkpointed = checkpoint.and_then(|checkpoint| match checkpoint.get() {
+            Ok(state) => state,
+            Err(e) => {
+                warn!("failed to read the checkpoint: {}", e);
+                None
+            }
+        });
+        if let Some(state) = checkpointed {
+            return Ok(ResumePoint {
+                from_height: state.height as u32 + 1,
+                expected_reference: Some(state.block_id.clone()),
+                rollback: None,
+                checkpointed: Some(state),
+            });
+        }
+
+        let last_block = repo.get_last_block()?;
+        let state = repo.get_consumer_state()?;
+        unflushed_height = unflushed_batch_height(state.as_ref(), last_block.as_ref());
+
+        if let Some(state) = clean_state(state, last_block.as_ref()) {
+            return Ok(ResumePoint {
+                from_height: state.height as u32 + 1,
+                expected_reference: Some(state.block_id),
+                rollback: None,
+                checkpointed: None,
+            });
+        }
+    }
+
+    let prev_handled_height = match unflushed_height {
+        Some(height) => repo.get_handled_height(height)?,
+        None => repo.get_prev_handled_height()?,
+    };
+
+    match prev_handled_height {
         Some(prev_handled_height) => {
+            let mut summary = None;
             repo.transaction(|| {
-                rollback(
+                summary = Some(rollback(
                     repo.clone(),
-                    blockchain_data_cache.clone(),
-                    user_defined_data_cache.clone(),
-                    waves_association_address,
+                    blockchain_data_cache,
+                    user_defined_data_cache,
+                    waves_association_addresses,
                     prev_handled_height.uid,
-                )
+                )?);
+                save_consumer_state(repo.as_ref()).map(drop)
             })?;
-            prev_handled_height.height as u32 + 1
+            let summary = summary.unwrap();
+
+            let rollback = StartupRollback {
+                rolled_back_to_height: prev_handled_height.height,
+                affected_assets: summary.affected_assets,
+                invalidated_asset_ids: summary.invalidated_asset_ids,
+            };
+            info!(
+                "rolled back the last handled height";
+                "rolled_back_to_height" => rollback.rolled_back_to_height,
+                "affected_assets" => rollback.affected_assets
+            );
+
+            Ok(ResumePoint {
+                from_height: prev_handled_height.height as u32 + 1,
+                expected_reference: None,
+                rollback: Some(rollback),
+                checkpointed: None,
+            })
         }
-        None => starting_height,
+        None => Ok(ResumePoint {
+            from_height: starting_height,
+            expected_reference: None,
+            rollback: None,
+            checkpointed: None,
+        }),
+    }
+}
+
+/// Rolls back the blocks of the height and above like `rollback_to`, refusing to run along with the consumer
+pub fn rollback_to_height<R, CBD, CUDD>(
+    repo: Arc<R>,
+    blockchain_data_cache: CBD,
+    user_defined_data_cache: CUDD,
+    waves_association_addresses: &[String],
+    height: i32,
+) -> Result<RollbackSummary>
+where
+    R: repo::Repo,
+    CBD: SyncReadCache<AssetBlockchainData> + SyncWriteCache<AssetBlockchainData> + Clone,
+    CUDD: SyncReadCache<AssetUserDefinedData> + SyncWriteCache<AssetUserDefinedData> + Clone,
+{
+    if !repo.try_lock_consumer()? {
+        bail!("the consumer is running, stop it before the rollback");
+    }
+
+    let block_uid = match repo.get_last_block_uid_below(height)? {
+        Some(block_uid) => block_uid,
+        None => bail!("there is no block below height {}", height),
     };
 
-    info!(
-        "Start fetching updates from height {}",
-        starting_from_height
-    );
-    let max_duration = Duration::seconds(max_wait_time_in_secs.to_i64().unwrap());
+    let mut summary = None;
+    repo.transaction(|| {
+        summary = Some(rollback(
+            repo.clone(),
+            blockchain_data_cache,
+            user_defined_data_cache,
+            waves_association_addresses,
+            block_uid,
+        )?);
+        save_consumer_state(repo.as_ref()).map(drop)
+    })?;
+
+    Ok(summary.unwrap())
+}
 
-    let mut rx = updates_src
-        .stream(starting_from_height, updates_per_request, max_duration)
-        .await?;
+/// The persisted state if it points to the last stored block
+fn clean_state(
+    state: Option<ConsumerState>,
+    last_block: Option<&ConsumerState>,
+) -> Option<ConsumerState> {
+    state.filter(|state| Some(state) == last_block)
+}
 
-    loop {
-        let mut start = Instant::now();
+/// Height of the state saved before the blocks of a batch whose cache writes were not flushed,
+/// if the batch reaches the later heights, so that the whole batch is handled again
+fn unflushed_batch_height(
+    state: Option<&ConsumerState>,
+    last_block: Option<&ConsumerState>,
+) -> Option<i32> {
+    match (state, last_block) {
+        (Some(state), Some(last_block))
+            if state.block_uid < last_block.block_uid && state.height < last_block.height =>
+        {
+            Some(state.height)
+        }
+        _ => None,
+    }
+}
+
+/// Whether the streamed updates continue the chain ending with the `reference` block
+fn continues_from(reference: &str, updates: &[BlockchainUpdate]) -> bool {
+    match updates.first() {
+        Some(BlockchainUpdate::Block(BlockMicroblockAppend {
+            reference: Some(parent_id),
+            ..
+        })) => parent_id == reference,
+        _ => true,
+    }
+}
+
+/// Returns the saved state, none without blocks
+fn save_consumer_state<R: repo::Repo>(repo: &R) -> Result<Option<ConsumerState>> {
+    match repo.get_last_block()? {
+        Some(last_block) => {
+            repo.set_consumer_state(&last_block)?;
+            Ok(Some(last_block))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Returns the ids of the changed assets and the consumer state saved with the batch
+fn handle_updates_batch<R, CBD, CUDD>(
+    updates_with_height: BlockchainUpdatesWithLastHeight,
+    repo: Arc<R>,
+    blockchain_data_cache: CBD,
+    user_defined_data_cache: CUDD,
+    chain_id: u8,
+    waves_association_addresses: &[String],
+    empty_ticker_as_deleted: bool,
+    debug_asset_ids: &[String],
+    shutdown: &watch::Receiver<bool>,
+    batch_metrics: &metrics::BatchMetrics,
+) -> Result<(Vec<String>, Option<ConsumerState>)>
+where
+    R: repo::Repo,
+    CBD: SyncReadCache<AssetBlockchainData> + SyncWriteCache<AssetBlockchainData> + Clone,
+    CUDD: SyncReadCache<AssetUserDefinedData> + SyncWriteCache<AssetUserDefinedData> + Clone,
+{
+    let start = Instant::now();
+    let mut changed_asset_ids = vec![];
+
+    let updates_count = updates_with_height.updates.len();
+    let last_height = updates_with_height.last_height;
+    let last_block_time_stamp =
+        updates_with_height
+            .updates
+            .iter()
+            .rev()
+            .find_map(|update| match update {
+                BlockchainUpdate::Block(block) | BlockchainUpdate::Microblock(block) => {
+                    block.time_stamp
+                }
+                _ => None,
+            });
+
+    // the cache is written after the commit, so a rolled back batch leaves it intact,
+    // and the state is saved after the cache, so a batch failing to write it is handled again
+    let blockchain_data_cache = PendingWritesCache::new(blockchain_data_cache);
+    let user_defined_data_cache = PendingWritesCache::new(user_defined_data_cache);
 
-        let updates_with_height = rx.recv().await.ok_or(Error::new(AppError::StreamClosed(
-            "GRPC Stream was closed by the server".to_string(),
-        )))?;
+    repo.transaction(|| {
+        changed_asset_ids = handle_updates(
+            updates_with_height,
+            repo.clone(),
+            blockchain_data_cache.clone(),
+            user_defined_data_cache.clone(),
+            chain_id,
+            waves_association_addresses,
+            empty_ticker_as_deleted,
+            debug_asset_ids,
+            shutdown,
+        )?;
+
+        // the batch is handled again after restart
+        if *shutdown.borrow() {
+            return Err(Error::new(AppError::ShutdownRequested));
+        }
+
+        let elapsed = start.elapsed();
 
-        let updates_count = updates_with_height.updates.len();
         info!(
-            "{} updates were received in {:?}",
+            "{} updates were handled in {:?} ms. Last updated height is {}.",
             updates_count,
-            start.elapsed()
+            elapsed.as_millis(),
+            last_height
         );
 
-        let last_height = updates_with_height.last_height;
+        batch_metrics.last_handled_height.set(last_height as i64);
+        batch_metrics
+            .updates_per_batch
+            .observe(updates_count as f64);
+        batch_metrics.updates_processed.inc_by(updates_count as u64);
+        batch_metrics
+            .batch_handle_duration
+            .observe(elapsed.as_secs_f64());
+        if let Some(time_stamp) = last_block_time_stamp {
+            batch_metrics
+                .last_handled_block_timestamp
+                .set(time_stamp / 1000);
+        }
 
-        start = Instant::now();
+        Ok(())
+    })?;
 
-        repo.transaction(|| {
-            handle_updates(
-                updates_with_height,
-                repo.clone(),
-                blockchain_data_cache.clone(),
-                user_defined_data_cache.clone(),
-                chain_id,
-                waves_association_address,
-            )?;
+    blockchain_data_cache.flush()?;
+    user_defined_data_cache.flush()?;
 
-            info!(
-                "{} updates were handled in {:?} ms. Last updated height is {}.",
-                updates_count,
-                start.elapsed().as_millis(),
-                last_height
-            );
+    let state = save_consumer_state(repo.as_ref())?;
 
-            Ok(())
-        })?;
-    }
+    Ok((changed_asset_ids, state))
 }
 
 fn handle_updates<'a, R, CBD, CUDD>(
@@ -205,13 +713,19 @@ fn handle_updates<'a, R, CBD, CUDD>(
     blockchain_data_cache: CBD,
     user_defined_data_cache: CUDD,
     chain_id: u8,
-    waves_association_address: &str,
-) -> Result<()>
+    waves_association_addresses: &[String],
+    empty_ticker_as_deleted: bool,
+    debug_asset_ids: &[String],
+    shutdown: &watch::Receiver<bool>,
+) -> Result<Vec<String>>
 where
     R: repo::Repo,
     CBD: SyncReadCache<AssetBlockchainData> + SyncWriteCache<AssetBlockchainData> + Clone,
     CUDD: SyncReadCache<AssetUserDefinedData> + SyncWriteCache<AssetUserDefinedData> + Clone,
 {
+    // ids of the assets whose cached data was changed by the appends
+    let mut changed_asset_ids = HashSet::new();
+
     updates_with_height
         .updates
         .into_iter()
@@ -247,183 +761,312 @@ where
             }
         })
         .into_iter()
-        .try_fold((), |_, update_item| match update_item {
-            UpdatesItem::Blocks(bs) => {
-                squash_microblocks(repo.clone())?;
-                handle_appends(
-                    repo.clone(),
-                    blockchain_data_cache.clone(),
-                    user_defined_data_cache.clone(),
-                    chain_id,
-                    bs.as_ref(),
-                    waves_association_address,
-                )
+        .try_fold((), |_, update_item| {
+            // the batch transaction is rolled back and handled again after restart
+            if *shutdown.borrow() {
+                return Err(Error::new(AppError::ShutdownRequested));
             }
-            UpdatesItem::Microblock(mba) => handle_appends(
-                repo.clone(),
-                blockchain_data_cache.clone(),
-                user_defined_data_cache.clone(),
-                chain_id,
-                &vec![mba.to_owned()],
-                waves_association_address,
-            ),
-            UpdatesItem::Rollback(sig) => {
-                let block_uid = repo.clone().get_block_uid(&sig)?;
-                rollback(
-                    repo.clone(),
-                    blockchain_data_cache.clone(),
-                    user_defined_data_cache.clone(),
-                    waves_association_address,
-                    block_uid,
-                )
+
+            match update_item {
+                UpdatesItem::Blocks(bs) => {
+                    squash_microblocks(repo.clone())?;
+                    let asset_ids = handle_appends(
+                        repo.clone(),
+                        blockchain_data_cache.clone(),
+                        user_defined_data_cache.clone(),
+                        chain_id,
+                        bs.as_ref(),
+                        waves_association_addresses,
+                        empty_ticker_as_deleted,
+                        debug_asset_ids,
+                    )?;
+                    changed_asset_ids.extend(asset_ids);
+                    Ok(())
+                }
+                UpdatesItem::Microblock(mba) => {
+                    let asset_ids = handle_appends(
+                        repo.clone(),
+                        blockchain_data_cache.clone(),
+                        user_defined_data_cache.clone(),
+                        chain_id,
+                        &vec![mba.to_owned()],
+                        waves_association_addresses,
+                        empty_ticker_as_deleted,
+                        debug_asset_ids,
+                    )?;
+                    changed_asset_ids.extend(asset_ids);
+                    Ok(())
+                }
+                UpdatesItem::Rollback(sig) => {
+                    metrics::ROLLBACKS.inc();
+                    let block_uid = repo.clone().get_block_uid(&sig)?;
+                    let summary = rollback(
+                        repo.clone(),
+                        blockchain_data_cache.clone(),
+                        user_defined_data_cache.clone(),
+                        waves_association_addresses,
+                        block_uid,
+                    )?;
+                    changed_asset_ids.extend(summary.invalidated_asset_ids);
+                    Ok(())
+                }
             }
         })?;
 
-    Ok(())
+    Ok(changed_asset_ids.into_iter().sorted().collect())
 }
 
-fn handle_appends<'a, R, CBD, CUDD>(
-    repo: Arc<R>,
-    blockchain_data_cache: CBD,
-    user_defined_data_cache: CUDD,
-    chain_id: u8,
-    appends: &Vec<BlockMicroblockAppend>,
-    waves_association_address: &str,
-) -> Result<()>
-where
-    R: repo::Repo,
-    CBD: SyncReadCache<AssetBlockchainData> + SyncWriteCache<AssetBlockchainData> + Clone,
-    CUDD: SyncReadCache<AssetUserDefinedData> + SyncWriteCache<AssetUserDefinedData> + Clone,
-{
-    let block_uids = repo.insert_blocks_or_microblocks(
-        &appends
-            .into_iter()
-            .map(|append| BlockMicroblock {
-                id: append.id.clone(),
-                height: append.height as i32,
-                time_stamp: append.time_stamp,
-            })
-            .collect_vec(),
-    )?;
-
-    let block_uids_with_appends = block_uids.into_iter().zip(appends).collect_vec();
-
-    // Handle base asset info updates
-    let base_asset_info_updates_with_block_uids = {
-        timer!("assets updates handling");
+/// Updates of the appends paired with the uids of their blocks
+struct ExtractedUpdates<'a> {
+    base_asset_info: Vec<(&'a i64, BaseAssetInfoUpdate)>,
+    data_entries: Vec<(&'a i64, DataEntryUpdate)>,
+    asset_labels: Vec<(&'a i64, AssetLabelsUpdate)>,
+    asset_tickers: Vec<(&'a i64, AssetTickerUpdate)>,
+    out_leasing: Vec<(&'a i64, OutLeasingUpdate)>,
+}
 
-        let base_asset_info_updates_with_block_uids: Vec<(&i64, BaseAssetInfoUpdate)> =
-            block_uids_with_appends
+/// The passes depend neither on each other nor on the db, so they run in parallel
+fn extract_updates<'a>(
+    chain_id: u8,
+    block_uids_with_appends: &'a [(i64, &BlockMicroblockAppend)],
+    waves_association_addresses: &[String],
+    empty_ticker_as_deleted: bool,
+    debug_asset_ids: &[String],
+) -> ExtractedUpdates<'a> {
+    let mut base_asset_info = vec![];
+    let mut data_entries = vec![];
+    let mut asset_labels = vec![];
+    let mut asset_tickers = vec![];
+    let mut out_leasing = vec![];
+
+    rayon::scope(|s| {
+        s.spawn(|_| {
+            base_asset_info = block_uids_with_appends
                 .iter()
                 .flat_map(|(block_uid, append)| {
-                    extract_base_asset_info_updates(chain_id, append)
+                    extract_base_asset_info_updates(chain_id, append, debug_asset_ids)
                         .into_iter()
-                        .map(|au| (block_uid, au))
-                        .collect_vec()
+                        .map(move |au| (block_uid, au))
                 })
                 .collect();
+        });
 
-        handle_base_asset_info_updates(repo.clone(), &base_asset_info_updates_with_block_uids)?;
-
-        info!(
-            "handled {} assets updates",
-            base_asset_info_updates_with_block_uids.len()
-        );
-
-        base_asset_info_updates_with_block_uids
-    };
-
-    // Handle data entries updates
-    let data_entries_updates_with_block_uids = {
-        timer!("data entries updates handling");
-
-        let data_entries_updates_with_block_uids: Vec<(&i64, DataEntryUpdate)> =
-            block_uids_with_appends
+        s.spawn(|_| {
+            data_entries = block_uids_with_appends
                 .iter()
                 .flat_map(|(block_uid, append)| {
                     append
                         .txs
                         .iter()
-                        .flat_map(|tx| {
+                        .flat_map(move |tx| {
                             extract_asset_related_data_entries_updates(
                                 append.height as i32,
+                                append.time_stamp,
                                 tx,
-                                waves_association_address,
+                                waves_association_addresses,
                             )
                         })
-                        .map(|u| (block_uid, u))
-                        .collect_vec()
+                        .map(move |u| (block_uid, u))
                 })
                 .collect();
+        });
 
-        handle_asset_related_data_entries_updates(
-            repo.clone(),
-            &data_entries_updates_with_block_uids,
-        )?;
-
-        info!(
-            "handled {} data entries updates",
-            data_entries_updates_with_block_uids.len()
-        );
-
-        data_entries_updates_with_block_uids
-    };
-
-    // Handle asset labels updates
-    let asset_labels_updates_with_block_uids = {
-        timer!("asset label updates handling");
-
-        let asset_labels_updates_with_block_uids: Vec<(&i64, AssetLabelsUpdate)> =
-            block_uids_with_appends
+        s.spawn(|_| {
+            asset_labels = block_uids_with_appends
                 .iter()
                 .flat_map(|(block_uid, append)| {
                     append
                         .txs
                         .iter()
-                        .flat_map(|tx| {
+                        .flat_map(move |tx| {
                             extract_asset_labels_updates(
                                 append.height as i32,
                                 tx,
-                                waves_association_address,
+                                waves_association_addresses,
                             )
                         })
-                        .map(|u| (block_uid, u))
-                        .collect_vec()
+                        .map(move |u| (block_uid, u))
                 })
                 .collect();
+        });
 
-        handle_asset_labels_updates(repo.clone(), &asset_labels_updates_with_block_uids)?;
-
-        info!(
-            "handled {} asset label updates",
-            asset_labels_updates_with_block_uids.len()
-        );
-
-        asset_labels_updates_with_block_uids
-    };
-
-    // Handle asset tickers updates
-    let asset_tickers_updates_with_block_uids = {
-        timer!("asset tickers updates handling");
-
-        let asset_tickers_updates_with_block_uids: Vec<(&i64, AssetTickerUpdate)> =
-            block_uids_with_appends
+        s.spawn(|_| {
+            asset_tickers = block_uids_with_appends
                 .iter()
                 .flat_map(|(block_uid, append)| {
                     append
                         .txs
                         .iter()
-                        .flat_map(|tx| {
+                        .flat_map(move |tx| {
                             extract_asset_tickers_updates(
                                 append.height as i32,
                                 tx,
-                                waves_association_address, // wich address
+                                waves_association_addresses,
+                                empty_ticker_as_deleted,
                             )
                         })
-                        .map(|u| (block_uid, u))
-                        .collect_vec()
+                        .map(move |u| (block_uid, u))
+                })
+                .collect();
+        });
+
+        s.spawn(|_| {
+            out_leasing = block_uids_with_appends
+                .iter()
+                .flat_map(|(block_uid, append)| {
+                    extract_out_leasing_updates(append)
+                        .into_iter()
+                        .map(move |u| (block_uid, u))
                 })
                 .collect();
+        });
+    });
+
+    ExtractedUpdates {
+        base_asset_info,
+        data_entries,
+        asset_labels,
+        asset_tickers,
+        out_leasing,
+    }
+}
+
+fn handle_appends<'a, R, CBD, CUDD>(
+    repo: Arc<R>,
+    blockchain_data_cache: CBD,
+    user_defined_data_cache: CUDD,
+    chain_id: u8,
+    appends: &Vec<BlockMicroblockAppend>,
+    waves_association_addresses: &[String],
+    empty_ticker_as_deleted: bool,
+    debug_asset_ids: &[String],
+) -> Result<Vec<String>>
+where
+    R: repo::Repo,
+    CBD: SyncReadCache<AssetBlockchainData> + SyncWriteCache<AssetBlockchainData> + Clone,
+    CUDD: SyncReadCache<AssetUserDefinedData> + SyncWriteCache<AssetUserDefinedData> + Clone,
+{
+    let block_uids = repo.insert_blocks_or_microblocks(
+        &appends
+            .into_iter()
+            .map(|append| BlockMicroblock {
+                id: append.id.clone(),
+                height: append.height as i32,
+                time_stamp: append.time_stamp,
+            })
+            .collect_vec(),
+    )?;
+
+    // there is nothing to extract from empty appends except blocks themselves
+    if appends.iter().all(|append| append.is_empty()) {
+        debug!("skip handling of {} empty appends", appends.len());
+        return Ok(vec![]);
+    }
+
+    let block_uids_with_appends = block_uids.into_iter().zip(appends).collect_vec();
+
+    let ExtractedUpdates {
+        base_asset_info: base_asset_info_updates_with_block_uids,
+        data_entries: data_entries_updates_with_block_uids,
+        asset_labels: asset_labels_updates_with_block_uids,
+        asset_tickers: asset_tickers_updates_with_block_uids,
+        out_leasing: out_leasing_updates_with_block_uids,
+    } = {
+        timer!("updates extraction");
+
+        extract_updates(
+            chain_id,
+            &block_uids_with_appends,
+            waves_association_addresses,
+            empty_ticker_as_deleted,
+            debug_asset_ids,
+        )
+    };
+
+    // Handle base asset info updates
+    {
+        timer!("assets updates handling");
+
+        handle_base_asset_info_updates(repo.clone(), &base_asset_info_updates_with_block_uids)?;
+
+        info!(
+            "handled {} assets updates",
+            base_asset_info_updates_with_block_uids.len()
+        );
+    }
+
+    // Handle asset names and descriptions updates
+    {
+        timer!("asset names and descriptions updates handling");
+
+        let asset_ids = base_asset_info_updates_with_block_uids
+            .iter()
+            .map(|(_, update)| update.id.as_str())
+            .unique()
+            .collect_vec();
+
+        let current_names = repo
+            .mget_asset_names(&asset_ids)?
+            .into_iter()
+            .map(|n| (n.asset_id, n.name))
+            .collect();
+        let asset_names_updates_with_block_uids =
+            extract_asset_name_updates(&current_names, &base_asset_info_updates_with_block_uids);
+
+        handle_asset_names_updates(repo.clone(), &asset_names_updates_with_block_uids)?;
+
+        let current_descriptions = repo
+            .mget_asset_descriptions(&asset_ids)?
+            .into_iter()
+            .map(|d| (d.asset_id, d.description))
+            .collect();
+        let asset_descriptions_updates_with_block_uids = extract_asset_description_updates(
+            &current_descriptions,
+            &base_asset_info_updates_with_block_uids,
+        );
+
+        handle_asset_descriptions_updates(
+            repo.clone(),
+            &asset_descriptions_updates_with_block_uids,
+        )?;
+
+        info!(
+            "handled {} asset names and {} asset descriptions updates",
+            asset_names_updates_with_block_uids.len(),
+            asset_descriptions_updates_with_block_uids.len()
+        );
+    }
+
+    // Handle data entries updates
+    {
+        timer!("data entries updates handling");
+
+        handle_asset_related_data_entries_updates(
+            repo.clone(),
+            &data_entries_updates_with_block_uids,
+        )?;
+
+        info!(
+            "handled {} data entries updates",
+            data_entries_updates_with_block_uids.len()
+        );
+    }
+
+    // Handle asset labels updates
+    {
+        timer!("asset label updates handling");
+
+        handle_asset_labels_updates(repo.clone(), &asset_labels_updates_with_block_uids)?;
+
+        info!(
+            "handled {} asset label updates",
+            asset_labels_updates_with_block_uids.len()
+        );
+    }
+
+    // Handle asset tickers updates
+    {
+        timer!("asset tickers updates handling");
 
         handle_asset_tickers_updates(repo.clone(), &asset_tickers_updates_with_block_uids)?;
 
@@ -431,9 +1074,7 @@ where
             "handled {} asset tickers updates",
             asset_tickers_updates_with_block_uids.len()
         );
-
-        asset_tickers_updates_with_block_uids
-    };
+    }
 
     // Handle issuer balances updates
     let issuer_balances_updates_with_block_uids = {
@@ -477,29 +1118,16 @@ where
     };
 
     // Handle out leasing updates
-    let out_leasing_updates_with_block_uids = {
+    {
         timer!("out leasing updates handling");
 
-        let out_leasing_updates_with_block_uids: Vec<(&i64, OutLeasingUpdate)> =
-            block_uids_with_appends
-                .iter()
-                .flat_map(|(block_uid, append)| {
-                    extract_out_leasing_updates(&append)
-                        .into_iter()
-                        .map(|u| (block_uid, u))
-                        .collect_vec()
-                })
-                .collect();
-
         handle_out_leasing_updates(repo.clone(), &out_leasing_updates_with_block_uids)?;
 
         info!(
             "handled {} out leasing updates",
             out_leasing_updates_with_block_uids.len()
         );
-
-        out_leasing_updates_with_block_uids
-    };
+    }
 
     // Invalidate assets cache
     // 1. Collect asset info updates grouped by asset id
@@ -544,8 +1172,11 @@ where
         asset_info_updates_from_asset_tickers_update(&asset_tickers_updates_with_block_uids)?;
 
     // 3.
-    let assets_info_updates_by_data_entries =
-        asset_info_updates_from_data_entries_updates(&data_entries_updates_with_block_uids)?;
+    let assets_info_updates_by_data_entries = asset_info_updates_from_data_entries_updates(
+        repo.clone(),
+        &data_entries_updates_with_block_uids,
+        waves_association_addresses,
+    )?;
 
     // 4.
     let assets_info_updates_by_issuer_balances = asset_info_updates_from_issuer_balances_updates(
@@ -613,6 +1244,9 @@ where
         );
 
     // 8.
+    let mut blockchain_data_updates = Vec::with_capacity(assets_info_updates.len());
+    let mut user_defined_data_updates = vec![];
+
     assets_info_updates
         .iter()
         .try_for_each::<_, Result<(), AppError>>(|(asset_id, asset_info_updates)| {
@@ -621,21 +1255,14 @@ where
                 asset_id, asset_info_updates
             );
             // Invalidate cached blockchain data
-            match cached_blockhain_data
+            let new_asset_blockchain_data = match cached_blockhain_data
                 .get(asset_id.as_str())
                 .and_then(|o| o.as_ref())
             {
-                Some(cached) => {
-                    let new_asset_blockchain_data =
-                        AssetBlockchainData::from((cached, asset_info_updates));
-                    blockchain_data_cache.set(&asset_id, new_asset_blockchain_data)?;
-                }
-                _ => {
-                    let new_asset_blockchain_data =
-                        AssetBlockchainData::try_from(asset_info_updates)?;
-                    blockchain_data_cache.set(&asset_id, new_asset_blockchain_data)?;
-                }
-            }
+                Some(cached) => AssetBlockchainData::from((cached, asset_info_updates)),
+                _ => AssetBlockchainData::try_from(asset_info_updates)?,
+            };
+            blockchain_data_updates.push((asset_id.clone(), new_asset_blockchain_data));
 
             let asset_labels_update = asset_info_updates
                 .iter()
@@ -661,13 +1288,13 @@ where
                 let asset_labels_update = asset_labels_update
                     .clone()
                     .into_iter()
-                    .collect::<HashSet<String>>();
+                    .collect::<HashSet<AssetLabel>>();
 
                 let current_asset_labels = current_asset_user_defined_data
                     .labels
                     .clone()
                     .into_iter()
-                    .collect::<HashSet<String>>();
+                    .collect::<HashSet<AssetLabel>>();
 
                 // Labels to add to asset
                 let settings = asset_labels_update
@@ -687,26 +1314,35 @@ where
                     },
                 );
 
-                user_defined_data_cache.set(&asset_id, new_asset_user_defined_data)?;
+                user_defined_data_updates.push((asset_id.clone(), new_asset_user_defined_data));
             }
 
             Ok(())
         })?;
 
-    Ok(())
+    blockchain_data_cache
+        .mset(blockchain_data_updates)
+        .map_err(count_cache_set_failure)?;
+
+    user_defined_data_cache
+        .mset(user_defined_data_updates)
+        .map_err(count_cache_set_failure)?;
+
+    Ok(assets_info_updates
+        .into_iter()
+        .map(|(asset_id, _)| asset_id)
+        .collect())
 }
 
 fn extract_base_asset_info_updates(
     chain_id: u8,
     append: &BlockMicroblockAppend,
+    debug_asset_ids: &[String],
 ) -> Vec<BaseAssetInfoUpdate> {
     let mut asset_updates = vec![];
 
     let update_time_stamp = match append.time_stamp {
-        Some(time_stamp) => DateTime::from_utc(
-            NaiveDateTime::from_timestamp(time_stamp / 1000, time_stamp as u32 % 1000 * 1000),
-            Utc,
-        ),
+        Some(time_stamp) => millis_to_datetime(time_stamp),
         None => Utc::now(),
     };
 
@@ -727,31 +1363,30 @@ fn extract_base_asset_info_updates(
                 .iter()
                 .filter_map(|asset_update| {
                     if let Some(asset_details) = &asset_update.after {
-                        let time_stamp = match tx.data.transaction.as_ref() {
-                            Some(stx) => match stx {
-                                Transaction::WavesTransaction(WavesTx { timestamp, .. }) => {
-                                    DateTime::from_utc(
-                                        NaiveDateTime::from_timestamp(
-                                            timestamp / 1000,
-                                            *timestamp as u32 % 1000 * 1000,
-                                        ),
-                                        Utc,
-                                    )
-                                }
-                                Transaction::EthereumTransaction(_) => return None,
-                            },
-                            _ => Utc::now(),
+                        let time_stamp = match tx_time_stamp(tx) {
+                            Some(timestamp) => millis_to_datetime(timestamp),
+                            None => update_time_stamp,
                         };
 
                         let asset_id = get_asset_id(&asset_details.asset_id);
                         let issuer =
-                            Address::from((asset_details.issuer.as_slice(), chain_id)).into();
-                        Some(BaseAssetInfoUpdate {
+                            match Address::try_from((asset_details.issuer.as_slice(), chain_id)) {
+                                Ok(issuer) => issuer.into(),
+                                Err(err) => {
+                                    error!(
+                                        "skipping asset update with invalid issuer: {}", err;
+                                        "asset_id" => &asset_id,
+                                        "tx_id" => &tx.id
+                                    );
+                                    return None;
+                                }
+                            };
+                        let update = BaseAssetInfoUpdate {
                             update_height: append.height as i32,
                             updated_at: time_stamp,
                             id: asset_id,
-                            name: escape_unicode_null(&asset_details.name),
-                            description: escape_unicode_null(&asset_details.description),
+                            name: escape_control_chars(&asset_details.name),
+                            description: escape_control_chars(&asset_details.description),
                             issuer,
                             precision: asset_details.decimals,
                             smart: asset_details
@@ -767,7 +1402,19 @@ fn extract_base_asset_info_updates(
                                 None
                             },
                             quantity: asset_details.volume.to_owned(),
-                        })
+                        };
+
+                        if debug_asset_ids.contains(&update.id) {
+                            info!(
+                                "ingestion debug: asset update";
+                                "tx_id" => &tx.id,
+                                "height" => append.height,
+                                "raw" => format!("{:?}", RawAssetDetails::from(asset_details)),
+                                "update" => format!("{:?}", update)
+                            );
+                        }
+
+                        Some(update)
                     } else {
                         None
                     }
@@ -780,6 +1427,99 @@ fn extract_base_asset_info_updates(
     asset_updates
 }
 
+/// Asset fields as received from the blockchain updates, before any transformation
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawAssetDetails {
+    pub asset_id: String,
+    pub issuer: String,
+    pub decimals: i32,
+    pub name: String,
+    pub description: String,
+    pub reissuable: bool,
+    pub volume: i64,
+    pub script: Option<String>,
+    pub sponsorship: i64,
+    pub nft: bool,
+}
+
+impl From<&AssetDetails> for RawAssetDetails {
+    fn from(details: &AssetDetails) -> Self {
+        Self {
+            asset_id: bs58::encode(&details.asset_id).into_string(),
+            issuer: bs58::encode(&details.issuer).into_string(),
+            decimals: details.decimals,
+            name: details.name.clone(),
+            description: details.description.clone(),
+            reissuable: details.reissuable,
+            volume: details.volume,
+            script: details
+                .script_info
+                .as_ref()
+                .map(|s| base64::encode(&s.script)),
+            sponsorship: details.sponsorship,
+            nft: details.nft,
+        }
+    }
+}
+
+/// Names differing from the previous ones of the assets, starting with the `current_names`
+fn extract_asset_name_updates<'a>(
+    current_names: &HashMap<String, String>,
+    updates: &[(&'a i64, BaseAssetInfoUpdate)],
+) -> Vec<(&'a i64, AssetNameUpdate)> {
+    changed_values(current_names, updates, |update| &update.name)
+        .into_iter()
+        .map(|(block_uid, asset_id, name)| (block_uid, AssetNameUpdate { asset_id, name }))
+        .collect()
+}
+
+/// Descriptions differing from the previous ones of the assets, starting with the `current_descriptions`
+fn extract_asset_description_updates<'a>(
+    current_descriptions: &HashMap<String, String>,
+    updates: &[(&'a i64, BaseAssetInfoUpdate)],
+) -> Vec<(&'a i64, AssetDescriptionUpdate)> {
+    changed_values(current_descriptions, updates, |update| &update.description)
+        .into_iter()
+        .map(|(block_uid, asset_id, description)| {
+            (
+                block_uid,
+                AssetDescriptionUpdate {
+                    asset_id,
+                    description,
+                },
+            )
+        })
+        .collect()
+}
+
+fn changed_values<'a, F>(
+    current: &HashMap<String, String>,
+    updates: &[(&'a i64, BaseAssetInfoUpdate)],
+    value: F,
+) -> Vec<(&'a i64, String, String)>
+where
+    F: Fn(&BaseAssetInfoUpdate) -> &String,
+{
+    let mut last_values: HashMap<&str, &str> = current
+        .iter()
+        .map(|(asset_id, value)| (asset_id.as_str(), value.as_str()))
+        .collect();
+
+    updates
+        .iter()
+        .filter(|(_, update)| update.id != WAVES_ID)
+        .filter_map(|(block_uid, update)| {
+            let value = value(update);
+            if last_values.get(update.id.as_str()) == Some(&value.as_str()) {
+                None
+            } else {
+                last_values.insert(&update.id, value);
+                Some((*block_uid, update.id.clone(), value.clone()))
+            }
+        })
+        .collect()
+}
+
 fn handle_base_asset_info_updates<R: repo::Repo>(
     repo: Arc<R>,
     updates: &[(&i64, BaseAssetInfoUpdate)],
@@ -788,22 +1528,27 @@ fn handle_base_asset_info_updates<R: repo::Repo>(
         return Ok(());
     }
 
-    let updates_count = updates.len();
+    let asset_ids = updates
+        .iter()
+        .map(|(_, update)| update.id.as_str())
+        .unique()
+        .collect_vec();
+    let current_assets: HashMap<String, CurrentAsset> = repo
+        .get_current_assets(&asset_ids)?
+        .into_iter()
+        .map(|asset| (asset.id.clone(), asset))
+        .collect();
+    let current_quantities = current_assets
+        .iter()
+        .map(|(id, asset)| (id.clone(), asset.quantity))
+        .collect();
+    let supply_changes = supply_changes(&current_quantities, updates);
 
     let assets_next_uid = repo.get_next_assets_uid()?;
 
-    let current_waves_quantity = repo.get_current_waves_quantity()?;
-
-    let asset_updates = updates
-        .iter()
-        .filter(|(_, update)| {
-            // save only not-waves assets or waves quantity updates
-            if update.id != WAVES_ID || update.quantity != current_waves_quantity {
-                true
-            } else {
-                false
-            }
-        })
+    // waves updates come with every block, mostly without changing the quantity
+    let asset_updates = changed_asset_updates(current_assets, updates)
+        .into_iter()
         .enumerate()
         .map(|(update_idx, (block_uid, update))| InsertableAsset {
             uid: assets_next_uid + update_idx as i64,
@@ -823,6 +1568,8 @@ fn handle_base_asset_info_updates<R: repo::Repo>(
         })
         .collect_vec();
 
+    let updates_count = asset_updates.len();
+
     let mut assets_grouped: HashMap<InsertableAsset, Vec<InsertableAsset>> = HashMap::new();
 
     asset_updates.into_iter().for_each(|update| {
@@ -880,34 +1627,106 @@ fn handle_base_asset_info_updates<R: repo::Repo>(
 
     repo.insert_assets(assets_with_uids_superseded_by)?;
 
+    if !supply_changes.is_empty() {
+        repo.insert_supply_changes(&supply_changes)?;
+    }
+
     repo.set_assets_next_update_uid(assets_next_uid + updates_count as i64)
 }
 
+/// Drops the updates not changing the asset, compared with its previous update or its current version
+fn changed_asset_updates<'a, 'b>(
+    mut latest_assets: HashMap<String, CurrentAsset>,
+    updates: &'a [(&'b i64, BaseAssetInfoUpdate)],
+) -> Vec<&'a (&'b i64, BaseAssetInfoUpdate)> {
+    updates
+        .iter()
+        .filter(|(_, update)| {
+            let asset = CurrentAsset::from(update);
+            if latest_assets.get(&update.id) == Some(&asset) {
+                false
+            } else {
+                latest_assets.insert(update.id.clone(), asset);
+                true
+            }
+        })
+        .collect()
+}
+
+/// Diffs quantities of the consecutive updates of each asset starting from its current quantity,
+/// quantities of newly issued assets are diffed against zero
+fn supply_changes(
+    current_quantities: &HashMap<String, i64>,
+    updates: &[(&i64, BaseAssetInfoUpdate)],
+) -> Vec<InsertableSupplyChange> {
+    let mut quantities = current_quantities.clone();
+
+    updates
+        .iter()
+        .filter_map(|(block_uid, update)| {
+            let prev_quantity = quantities
+                .insert(update.id.clone(), update.quantity)
+                .unwrap_or(0);
+
+            if prev_quantity == update.quantity {
+                return None;
+            }
+
+            Some(InsertableSupplyChange {
+                block_uid: **block_uid,
+                asset_id: update.id.clone(),
+                height: update.update_height,
+                prev_quantity,
+                new_quantity: update.quantity,
+                change: update.quantity - prev_quantity,
+            })
+        })
+        .collect()
+}
+
+/// Converts the blockchain timestamp in millis keeping the millis
+fn millis_to_datetime(ts: i64) -> DateTime<Utc> {
+    let naive = NaiveDateTime::from_timestamp(
+        ts.div_euclid(1000),
+        (ts.rem_euclid(1000) * 1_000_000) as u32,
+    );
+    DateTime::from_utc(naive, Utc)
+}
+
+/// Timestamp of the transaction in millis, Ethereum transactions carry it in the metadata
+fn tx_time_stamp(tx: &Tx) -> Option<i64> {
+    match tx.data.transaction.as_ref()? {
+        Transaction::WavesTransaction(WavesTx { timestamp, .. }) => Some(*timestamp),
+        Transaction::EthereumTransaction(_) => match tx.meta.metadata.as_ref()? {
+            TxMetadata::Ethereum(EthereumMetadata { timestamp, .. }) => Some(*timestamp),
+            _ => None,
+        },
+    }
+}
+
 fn extract_asset_related_data_entries_updates(
     height: i32,
+    block_time_stamp: Option<i64>,
     tx: &Tx,
-    waves_association_address: &str,
+    waves_association_addresses: &[String],
 ) -> Vec<DataEntryUpdate> {
+    // the state update is there for any kind of the transaction, e.g. invokes made by Ethereum ones
+    let time_stamp = match tx_time_stamp(tx).or(block_time_stamp) {
+        Some(timestamp) => millis_to_datetime(timestamp),
+        None => Utc::now(),
+    };
+
     tx.state_update
         .data_entries
         .iter()
         .filter_map(|data_entry_update| {
-            let transaction = match tx.data.transaction.as_ref() {
-                Some(Transaction::WavesTransaction(wtx)) => wtx,
-                Some(Transaction::EthereumTransaction(_)) | None => return None,
-            };
             data_entry_update.data_entry.as_ref().and_then(|de| {
                 let oracle_address = bs58::encode(&data_entry_update.address).into_string();
-                if waves_association_address == &oracle_address {
+                if waves_association_addresses.contains(&oracle_address) {
                     let parsed_key = parse_waves_association_key(
                         &KNOWN_WAVES_ASSOCIATION_ASSET_ATTRIBUTES,
                         &de.key,
                     );
-                    let time_stamp = DateTime::from_utc(
-                        NaiveDateTime::from_timestamp(transaction.timestamp / 1000, 0),
-                        Utc,
-                    );
-
                     Some(DataEntryUpdate {
                         update_height: height,
                         updated_at: time_stamp,
@@ -918,7 +1737,7 @@ fn extract_asset_related_data_entries_updates(
                             Value::BoolValue(value) => DataEntryValue::BoolVal(value.to_owned()),
                             Value::IntValue(value) => DataEntryValue::IntVal(value.to_owned()),
                             Value::StringValue(value) => {
-                                DataEntryValue::StrVal(escape_unicode_null(value))
+                                DataEntryValue::StrVal(escape_control_chars(value))
                             }
                         }),
                         related_asset_id: parsed_key.map(|k| k.asset_id),
@@ -939,57 +1758,44 @@ fn handle_asset_related_data_entries_updates<R: repo::Repo>(
         return Ok(());
     }
 
+    let updates = last_data_entries_updates_of_blocks(updates);
+
+    let address_keys = updates
+        .iter()
+        .map(|(_, update)| (update.address.as_str(), update.key.as_str()))
+        .unique()
+        .collect_vec();
+    let current_data_entries = repo
+        .get_current_data_entries(&address_keys)?
+        .into_iter()
+        .map(|data_entry| {
+            let key = (data_entry.address.clone(), data_entry.key.clone());
+            (key, data_entry)
+        })
+        .collect::<HashMap<_, _>>();
+    let updates = changed_data_entries_updates(current_data_entries, updates);
+
     let updates_count = updates.len();
 
     let data_entries_next_uid = repo.get_next_data_entries_uid()?;
 
     let data_entries_updates = updates
-        .iter()
+        .into_iter()
         .enumerate()
         .map(|(update_idx, (block_uid, update))| {
-            let (data_type, bin_val, bool_val, int_val, str_val) = match &update.value {
-                Some(DataEntryValue::BinVal(v)) => (
-                    Some(DataEntryValueType::Bin),
-                    Some(v.to_owned()),
-                    None,
-                    None,
-                    None,
-                ),
-                Some(DataEntryValue::BoolVal(v)) => (
-                    Some(DataEntryValueType::Bool),
-                    None,
-                    Some(v.to_owned()),
-                    None,
-                    None,
-                ),
-                Some(DataEntryValue::IntVal(v)) => (
-                    Some(DataEntryValueType::Int),
-                    None,
-                    None,
-                    Some(v.to_owned()),
-                    None,
-                ),
-                Some(DataEntryValue::StrVal(v)) => (
-                    Some(DataEntryValueType::Str),
-                    None,
-                    None,
-                    None,
-                    Some(v.to_owned()),
-                ),
-                None => (None, None, None, None, None),
-            };
+            let data_entry = CurrentDataEntry::from(update);
             InsertableDataEntry {
                 uid: data_entries_next_uid + update_idx as i64,
                 superseded_by: -1,
                 block_uid: *block_uid.clone(),
-                address: update.address.clone(),
-                key: update.key.clone(),
-                data_type,
-                bool_val,
-                bin_val,
-                int_val,
-                str_val,
-                related_asset_id: update.related_asset_id.clone(),
+                address: data_entry.address,
+                key: data_entry.key,
+                data_type: data_entry.data_type,
+                bool_val: data_entry.bool_val,
+                bin_val: data_entry.bin_val,
+                int_val: data_entry.int_val,
+                str_val: data_entry.str_val,
+                related_asset_id: data_entry.related_asset_id,
             }
         })
         .collect_vec();
@@ -1057,28 +1863,70 @@ fn handle_asset_related_data_entries_updates<R: repo::Repo>(
     repo.set_data_entries_next_update_uid(data_entries_next_uid + updates_count as i64)
 }
 
-fn extract_asset_tickers_updates(
-    _height: i32,
-    tx: &Tx,
-    waves_association_address: &str,
-) -> Vec<AssetTickerUpdate> {
-    tx.state_update
-        .data_entries
-        .iter()
-        .filter_map(|data_entry_update| {
-            data_entry_update.data_entry.as_ref().and_then(|de| {
-                let oracle_address = bs58::encode(&data_entry_update.address).into_string();
-                if waves_association_address == &oracle_address
-                    && is_asset_ticker_data_entry(&de.key)
+/// Drops the updates not changing the data entry, compared with its previous update or its current version
+fn changed_data_entries_updates<'a, 'b>(
+    mut latest_data_entries: HashMap<(String, String), CurrentDataEntry>,
+    updates: Vec<&'a (&'b i64, DataEntryUpdate)>,
+) -> Vec<&'a (&'b i64, DataEntryUpdate)> {
+    updates
+        .into_iter()
+        .filter(|(_, update)| {
+            let data_entry = CurrentDataEntry::from(update);
+            let key = (update.address.clone(), update.key.clone());
+            if latest_data_entries.get(&key) == Some(&data_entry) {
+                false
+            } else {
+                latest_data_entries.insert(key, data_entry);
+                true
+            }
+        })
+        .collect()
+}
+
+/// Keeps the last update of every data entry within a block, the previous ones are never visible
+fn last_data_entries_updates_of_blocks<'a, 'b>(
+    updates: &'a [(&'b i64, DataEntryUpdate)],
+) -> Vec<&'a (&'b i64, DataEntryUpdate)> {
+    let mut last_update_idxs = HashMap::new();
+    for (idx, (block_uid, update)) in updates.iter().enumerate() {
+        last_update_idxs.insert((**block_uid, &update.address, &update.key), idx);
+    }
+
+    updates
+        .iter()
+        .enumerate()
+        .filter(|(idx, (block_uid, update))| {
+            last_update_idxs[&(**block_uid, &update.address, &update.key)] == *idx
+        })
+        .map(|(_, update)| update)
+        .collect_vec()
+}
+
+/// Deleted ticker keys drop the ticker, empty tickers are kept unless `empty_ticker_as_deleted`
+fn extract_asset_tickers_updates(
+    _height: i32,
+    tx: &Tx,
+    waves_association_addresses: &[String],
+    empty_ticker_as_deleted: bool,
+) -> Vec<AssetTickerUpdate> {
+    tx.state_update
+        .data_entries
+        .iter()
+        .filter_map(|data_entry_update| {
+            data_entry_update.data_entry.as_ref().and_then(|de| {
+                let oracle_address = bs58::encode(&data_entry_update.address).into_string();
+                if waves_association_addresses.contains(&oracle_address)
+                    && is_asset_ticker_data_entry(&de.key)
                 {
                     match de.value.as_ref() {
                         Some(value) => match value {
                             Value::StringValue(value)
-                                if waves_association_address == &oracle_address =>
+                                if waves_association_addresses.contains(&oracle_address) =>
                             {
                                 frag_parse!("%s%s", de.key).map(|(_, asset_id)| AssetTickerUpdate {
                                     asset_id: asset_id,
-                                    ticker: value.clone(),
+                                    ticker: Some(value.clone())
+                                        .filter(|t| !(empty_ticker_as_deleted && t.is_empty())),
                                 })
                             }
                             _ => None,
@@ -1087,7 +1935,7 @@ fn extract_asset_tickers_updates(
                         None => {
                             frag_parse!("%s%s", de.key).map(|(_, asset_id)| AssetTickerUpdate {
                                 asset_id,
-                                ticker: "".into(),
+                                ticker: None,
                             })
                         }
                     }
@@ -1103,7 +1951,7 @@ fn extract_asset_tickers_updates(
 fn extract_asset_labels_updates(
     _height: i32,
     tx: &Tx,
-    waves_association_address: &str,
+    waves_association_addresses: &[String],
 ) -> Vec<AssetLabelsUpdate> {
     tx.state_update
         .data_entries
@@ -1111,13 +1959,13 @@ fn extract_asset_labels_updates(
         .filter_map(|data_entry_update| {
             data_entry_update.data_entry.as_ref().and_then(|de| {
                 let oracle_address = bs58::encode(&data_entry_update.address).into_string();
-                if waves_association_address == &oracle_address
+                if waves_association_addresses.contains(&oracle_address)
                     && is_asset_labels_data_entry(&de.key)
                 {
                     match de.value.as_ref() {
                         Some(value) => match value {
                             Value::StringValue(value)
-                                if waves_association_address == &oracle_address =>
+                                if waves_association_addresses.contains(&oracle_address) =>
                             {
                                 frag_parse!("%s%s", de.key).map(|(_, asset_id)| {
                                     let labels = parse_asset_labels(&value);
@@ -1163,7 +2011,11 @@ fn handle_asset_labels_updates<R: repo::Repo>(
                 superseded_by: -1,
                 block_uid: *block_uid.clone(),
                 asset_id: labels_update.asset_id.clone(),
-                labels: labels_update.labels.clone(),
+                labels: labels_update
+                    .labels
+                    .iter()
+                    .map(|label| label.as_str().to_owned())
+                    .collect(),
             },
         )
         .collect_vec();
@@ -1320,6 +2172,187 @@ fn handle_asset_tickers_updates<R: repo::Repo>(
     repo.set_asset_tickers_next_update_uid(asset_tickers_next_uid + updates_count as i64)
 }
 
+fn handle_asset_names_updates<R: repo::Repo>(
+    repo: Arc<R>,
+    updates: &[(&i64, AssetNameUpdate)],
+) -> Result<()> {
+    if updates.is_empty() {
+        return Ok(());
+    }
+
+    let updates_count = updates.len();
+
+    let asset_names_next_uid = repo.get_next_asset_names_uid()?;
+
+    let asset_names_updates = updates
+        .iter()
+        .enumerate()
+        .map(
+            |(update_idx, (block_uid, name_update))| InsertableAssetName {
+                uid: asset_names_next_uid + update_idx as i64,
+                superseded_by: -1,
+                block_uid: *block_uid.clone(),
+                asset_id: name_update.asset_id.clone(),
+                name: name_update.name.clone(),
+            },
+        )
+        .collect_vec();
+
+    let mut asset_names_grouped: HashMap<InsertableAssetName, Vec<InsertableAssetName>> =
+        HashMap::new();
+
+    asset_names_updates.into_iter().for_each(|update| {
+        let group = asset_names_grouped.entry(update.clone()).or_insert(vec![]);
+        group.push(update);
+    });
+
+    let asset_names_grouped = asset_names_grouped.into_iter().collect_vec();
+
+    let asset_names_grouped_with_uids_superseded_by = asset_names_grouped
+        .into_iter()
+        .map(|(group_key, group)| {
+            let mut updates = group
+                .into_iter()
+                .sorted_by_key(|item| item.uid)
+                .collect::<Vec<InsertableAssetName>>();
+
+            let mut last_uid = std::i64::MAX - 1;
+            (
+                group_key,
+                updates
+                    .as_mut_slice()
+                    .iter_mut()
+                    .rev()
+                    .map(|cur| {
+                        cur.superseded_by = last_uid;
+                        last_uid = cur.uid;
+                        cur.to_owned()
+                    })
+                    .sorted_by_key(|item| item.uid)
+                    .collect(),
+            )
+        })
+        .collect::<Vec<(InsertableAssetName, Vec<InsertableAssetName>)>>();
+
+    let asset_names_first_uids: Vec<AssetNameOverride> =
+        asset_names_grouped_with_uids_superseded_by
+            .iter()
+            .map(|(_, group)| {
+                let first = group.iter().next().unwrap().clone();
+                AssetNameOverride {
+                    superseded_by: first.uid,
+                    asset_id: first.asset_id,
+                }
+            })
+            .collect();
+
+    repo.close_asset_names_superseded_by(&asset_names_first_uids)?;
+
+    let asset_names_with_uids_superseded_by = &asset_names_grouped_with_uids_superseded_by
+        .clone()
+        .into_iter()
+        .flat_map(|(_, v)| v)
+        .sorted_by_key(|asset_names| asset_names.uid)
+        .collect_vec();
+
+    repo.insert_asset_names(asset_names_with_uids_superseded_by)?;
+
+    repo.set_asset_names_next_update_uid(asset_names_next_uid + updates_count as i64)
+}
+
+fn handle_asset_descriptions_updates<R: repo::Repo>(
+    repo: Arc<R>,
+    updates: &[(&i64, AssetDescriptionUpdate)],
+) -> Result<()> {
+    if updates.is_empty() {
+        return Ok(());
+    }
+
+    let updates_count = updates.len();
+
+    let asset_descriptions_next_uid = repo.get_next_asset_descriptions_uid()?;
+
+    let asset_descriptions_updates = updates
+        .iter()
+        .enumerate()
+        .map(
+            |(update_idx, (block_uid, description_update))| InsertableAssetDescription {
+                uid: asset_descriptions_next_uid + update_idx as i64,
+                superseded_by: -1,
+                block_uid: *block_uid.clone(),
+                asset_id: description_update.asset_id.clone(),
+                description: description_update.description.clone(),
+            },
+        )
+        .collect_vec();
+
+    let mut asset_descriptions_grouped: HashMap<
+        InsertableAssetDescription,
+        Vec<InsertableAssetDescription>,
+    > = HashMap::new();
+
+    asset_descriptions_updates.into_iter().for_each(|update| {
+        let group = asset_descriptions_grouped
+            .entry(update.clone())
+            .or_insert(vec![]);
+        group.push(update);
+    });
+
+    let asset_descriptions_grouped = asset_descriptions_grouped.into_iter().collect_vec();
+
+    let asset_descriptions_grouped_with_uids_superseded_by = asset_descriptions_grouped
+        .into_iter()
+        .map(|(group_key, group)| {
+            let mut updates = group
+                .into_iter()
+                .sorted_by_key(|item| item.uid)
+                .collect::<Vec<InsertableAssetDescription>>();
+
+            let mut last_uid = std::i64::MAX - 1;
+            (
+                group_key,
+                updates
+                    .as_mut_slice()
+                    .iter_mut()
+                    .rev()
+                    .map(|cur| {
+                        cur.superseded_by = last_uid;
+                        last_uid = cur.uid;
+                        cur.to_owned()
+                    })
+                    .sorted_by_key(|item| item.uid)
+                    .collect(),
+            )
+        })
+        .collect::<Vec<(InsertableAssetDescription, Vec<InsertableAssetDescription>)>>();
+
+    let asset_descriptions_first_uids: Vec<AssetDescriptionOverride> =
+        asset_descriptions_grouped_with_uids_superseded_by
+            .iter()
+            .map(|(_, group)| {
+                let first = group.iter().next().unwrap().clone();
+                AssetDescriptionOverride {
+                    superseded_by: first.uid,
+                    asset_id: first.asset_id,
+                }
+            })
+            .collect();
+
+    repo.close_asset_descriptions_superseded_by(&asset_descriptions_first_uids)?;
+
+    let asset_descriptions_with_uids_superseded_by =
+        &asset_descriptions_grouped_with_uids_superseded_by
+            .clone()
+            .into_iter()
+            .flat_map(|(_, v)| v)
+            .sorted_by_key(|asset_descriptions| asset_descriptions.uid)
+            .collect_vec();
+
+    repo.insert_asset_descriptions(asset_descriptions_with_uids_superseded_by)?;
+
+    repo.set_asset_descriptions_next_update_uid(asset_descriptions_next_uid + updates_count as i64)
+}
+
 fn extract_issuers_balance_updates(
     append: &BlockMicroblockAppend,
     issuers: &HashSet<&str>,
@@ -1336,15 +2369,9 @@ fn extract_issuers_balance_updates(
         .iter()
         .map(|balance_update| (append.time_stamp, balance_update))
         .chain(append.txs.iter().flat_map(|tx| {
-            tx.state_update
-                .balances
-                .iter()
-                .map(move |balance_update| match tx.data.transaction {
-                    Some(Transaction::WavesTransaction(WavesTx { timestamp, .. })) => {
-                        (Some(timestamp), balance_update)
-                    }
-                    _ => (None, balance_update),
-                })
+            tx.state_update.balances.iter().map(move |balance_update| {
+                (tx_time_stamp(tx).or(append.time_stamp), balance_update)
+            })
         }))
         .filter_map(move |(time_stamp, balance_update)| {
             let address = bs58::encode(&balance_update.address).into_string();
@@ -1359,13 +2386,7 @@ fn extract_issuers_balance_updates(
                             && balance_update.amount_before != amount_after.amount
                         {
                             let updated_at = match &time_stamp {
-                                Some(timestamp) => DateTime::from_utc(
-                                    NaiveDateTime::from_timestamp(
-                                        timestamp / 1000,
-                                        *timestamp as u32 % 1000 * 1000,
-                                    ),
-                                    Utc,
-                                ),
+                                Some(timestamp) => millis_to_datetime(*timestamp),
                                 _ => Utc::now(),
                             };
 
@@ -1495,47 +2516,35 @@ fn extract_out_leasing_updates(append: &BlockMicroblockAppend) -> Vec<OutLeasing
     append
         .state_update
         .leasing_for_address
-        .clone()
         .iter()
-        .chain(
-            append
-                .txs
+        .map(|leasing_update| (append.time_stamp, leasing_update))
+        .chain(append.txs.iter().flat_map(|tx| {
+            tx.state_update
+                .leasing_for_address
                 .iter()
-                .flat_map(|tx| tx.state_update.leasing_for_address.iter()),
-        )
-        .for_each(|leasing_update| {
+                .map(move |leasing_update| {
+                    (tx_time_stamp(tx).or(append.time_stamp), leasing_update)
+                })
+        }))
+        .for_each(|(time_stamp, leasing_update)| {
             // handle out leasing changes only
             if leasing_update.out_after != leasing_update.out_before {
-                let updated_at = match append.time_stamp {
-                    Some(time_stamp) => DateTime::from_utc(
-                        NaiveDateTime::from_timestamp(
-                            time_stamp / 1000,
-                            time_stamp as u32 % 1000 * 1000,
-                        ),
-                        Utc,
-                    ),
+                let updated_at = match time_stamp {
+                    Some(time_stamp) => millis_to_datetime(time_stamp),
                     _ => Utc::now(),
                 };
 
                 let address = bs58::encode(&leasing_update.address).into_string();
 
-                if out_leasing_updates.contains_key(&address) {
-                    out_leasing_updates.entry(address.clone()).and_modify(
-                        |u: &mut OutLeasingUpdate| {
-                            u.new_amount = leasing_update.out_after;
-                        },
-                    );
-                } else {
-                    out_leasing_updates.insert(
-                        address.clone(),
-                        OutLeasingUpdate {
-                            updated_at,
-                            update_height: append.height as i32,
-                            address: address.clone(),
-                            new_amount: leasing_update.out_after,
-                        },
-                    );
-                }
+                out_leasing_updates.insert(
+                    address.clone(),
+                    OutLeasingUpdate {
+                        updated_at,
+                        update_height: append.height as i32,
+                        address,
+                        new_amount: leasing_update.out_after,
+                    },
+                );
             }
         });
 
@@ -1628,6 +2637,11 @@ fn handle_out_leasing_updates<R: repo::Repo>(
     repo.set_out_leasings_next_update_uid(out_leasings_next_uid + updates_count as i64)
 }
 
+fn count_cache_set_failure(err: AppError) -> AppError {
+    metrics::CACHE_SET_FAILURES.inc();
+    err
+}
+
 fn squash_microblocks<R: repo::Repo>(storage: Arc<R>) -> Result<()> {
     let total_block_id = storage.get_total_block_id()?;
 
@@ -1641,12 +2655,18 @@ fn squash_microblocks<R: repo::Repo>(storage: Arc<R>) -> Result<()> {
 
             storage.update_asset_tickers_block_references(&key_block_uid)?;
 
+            storage.update_asset_names_block_references(&key_block_uid)?;
+
+            storage.update_asset_descriptions_block_references(&key_block_uid)?;
+
             storage.update_data_entries_block_references(&key_block_uid)?;
 
             storage.update_issuer_balances_block_references(&key_block_uid)?;
 
             storage.update_out_leasings_block_references(&key_block_uid)?;
 
+            storage.update_supply_changes_block_references(&key_block_uid)?;
+
             storage.delete_microblocks()?;
 
             storage.change_block_id(&key_block_uid, &total_block_id)?;
@@ -1657,13 +2677,24 @@ fn squash_microblocks<R: repo::Repo>(storage: Arc<R>) -> Result<()> {
     Ok(())
 }
 
+/// Outcome of a rollback
+#[derive(Clone, Debug)]
+pub struct RollbackSummary {
+    /// Count of the assets changed after the block
+    pub affected_assets: usize,
+    /// Ids of the assets whose cached data was rewritten or deleted
+    pub invalidated_asset_ids: Vec<String>,
+    /// Deleted rows per table
+    pub deleted_rows: Vec<(&'static str, usize)>,
+}
+
 fn rollback<R, CBD, CUDD>(
     repo: Arc<R>,
     blockchain_data_cache: CBD,
     user_defined_data_cache: CUDD,
-    waves_association_address: &str,
+    waves_association_addresses: &[String],
     block_uid: i64,
-) -> Result<()>
+) -> Result<RollbackSummary>
 where
     R: repo::Repo,
     CBD: SyncReadCache<AssetBlockchainData> + SyncWriteCache<AssetBlockchainData> + Clone,
@@ -1674,19 +2705,44 @@ where
     // which assets have to be updated after rollback
     let assets_to_rollback = repo.assets_gt_block_uid(&block_uid)?;
 
-    rollback_assets(repo.clone(), block_uid)?;
-
-    rollback_asset_labels(repo.clone(), block_uid)?;
-
-    rollback_asset_tickers(repo.clone(), block_uid)?;
-
-    rollback_data_entries(repo.clone(), block_uid)?;
+    let (rolled_back_asset_ids, deleted_assets) = rollback_assets(repo.clone(), block_uid)?;
 
-    rollback_issuer_balances(repo.clone(), block_uid)?;
-
-    rollback_out_leasings(repo.clone(), block_uid)?;
-
-    repo.rollback_blocks_microblocks(&block_uid)?;
+    let deleted_rows = vec![
+        ("assets", deleted_assets),
+        (
+            "asset_labels",
+            rollback_asset_labels(repo.clone(), block_uid)?,
+        ),
+        (
+            "asset_tickers",
+            rollback_asset_tickers(repo.clone(), block_uid)?,
+        ),
+        (
+            "asset_names",
+            rollback_asset_names(repo.clone(), block_uid)?,
+        ),
+        (
+            "asset_descriptions",
+            rollback_asset_descriptions(repo.clone(), block_uid)?,
+        ),
+        (
+            "data_entries",
+            rollback_data_entries(repo.clone(), block_uid)?,
+        ),
+        (
+            "issuer_balances",
+            rollback_issuer_balances(repo.clone(), block_uid)?,
+        ),
+        (
+            "out_leasings",
+            rollback_out_leasings(repo.clone(), block_uid)?,
+        ),
+        ("supply_changes", repo.rollback_supply_changes(&block_uid)?),
+        (
+            "blocks_microblocks",
+            repo.rollback_blocks_microblocks(&block_uid)?,
+        ),
+    ];
 
     // Invalidate cache
     let assets = repo.mget_assets(&assets_to_rollback)?;
@@ -1701,7 +2757,7 @@ where
 
     // Current assets oracles data
     let assets_oracles_data =
-        repo.assets_oracle_data_entries(&asset_ids, waves_association_address)?;
+        repo.assets_oracle_data_entries(&asset_ids, waves_association_addresses)?;
 
     let assets_oracles_data =
         assets_oracles_data
@@ -1732,7 +2788,9 @@ where
             _ => None,
         })
         .try_for_each(|asset_blockchain_data| {
-            blockchain_data_cache.set(&asset_blockchain_data.id.clone(), asset_blockchain_data)
+            blockchain_data_cache
+                .set(&asset_blockchain_data.id.clone(), asset_blockchain_data)
+                .map_err(count_cache_set_failure)
         })?;
 
     let cached_user_defined_data = user_defined_data_cache.mget(&asset_ids)?.into_iter().fold(
@@ -1749,8 +2807,15 @@ where
     let assets_labels = repo
         .mget_asset_labels(asset_ids)?
         .into_iter()
-        .map(|asset_labels| (asset_labels.asset_id, asset_labels.labels))
-        .collect::<HashMap<String, Vec<String>>>();
+        .map(|asset_labels| {
+            let labels = asset_labels
+                .labels
+                .iter()
+                .map(|label| AssetLabel::from(label.as_str()))
+                .collect();
+            (asset_labels.asset_id, labels)
+        })
+        .collect::<HashMap<String, Vec<AssetLabel>>>();
 
     asset_ids.iter().try_for_each(|asset_id| {
         let asset_labels_update = assets_labels.get(asset_id.to_owned());
@@ -1767,13 +2832,13 @@ where
             let asset_labels_update = asset_labels_update
                 .clone()
                 .into_iter()
-                .collect::<HashSet<String>>();
+                .collect::<HashSet<AssetLabel>>();
 
             let current_asset_labels = current_asset_user_defined_data
                 .labels
                 .clone()
                 .into_iter()
-                .collect::<HashSet<String>>();
+                .collect::<HashSet<AssetLabel>>();
 
             // Labels to add to asset
             let settings = asset_labels_update
@@ -1796,17 +2861,53 @@ where
                         },
                     );
 
-            user_defined_data_cache.set(&asset_id, rollbacked_asset_user_defined_data)
+            user_defined_data_cache
+                .set(&asset_id, rollbacked_asset_user_defined_data)
+                .map_err(count_cache_set_failure)
         } else {
             Ok(())
         }
     })?;
 
-    Ok(())
+    // Delete cached data of the assets issued in the rolled back blocks
+    let rolled_back_asset_ids = rolled_back_asset_ids
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let current_asset_ids = repo.current_asset_ids(&rolled_back_asset_ids)?;
+    let deleted_asset_ids = rolled_back_asset_ids
+        .into_iter()
+        .filter(|asset_id| !current_asset_ids.iter().any(|id| id == asset_id))
+        .collect::<Vec<_>>();
+
+    deleted_asset_ids
+        .iter()
+        .try_for_each(|asset_id| {
+            debug!("delete cached data of rolled back asset {}", asset_id);
+            blockchain_data_cache.del(asset_id)?;
+            user_defined_data_cache.del(asset_id)
+        })
+        .map_err(count_cache_set_failure)?;
+
+    let invalidated_asset_ids = asset_ids
+        .iter()
+        .chain(deleted_asset_ids.iter())
+        .map(|asset_id| asset_id.to_string())
+        .sorted()
+        .dedup()
+        .collect();
+
+    Ok(RollbackSummary {
+        affected_assets: assets_to_rollback.len(),
+        invalidated_asset_ids,
+        deleted_rows,
+    })
 }
 
-fn rollback_assets<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<()> {
+/// Returns ids of the rolled back assets and the number of the deleted rows
+fn rollback_assets<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<(Vec<String>, usize)> {
     let deleted = repo.rollback_assets(&block_uid)?;
+    let deleted_count = deleted.len();
 
     let mut grouped_deleted: HashMap<DeletedAsset, Vec<DeletedAsset>> = HashMap::new();
 
@@ -1815,16 +2916,24 @@ fn rollback_assets<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<()> {
         group.push(item);
     });
 
+    let deleted_ids = grouped_deleted
+        .keys()
+        .map(|asset| asset.id.clone())
+        .collect();
+
     let lowest_deleted_uids: Vec<i64> = grouped_deleted
         .into_iter()
         .filter_map(|(_, group)| group.into_iter().min_by_key(|i| i.uid).map(|i| i.uid))
         .collect();
 
-    repo.reopen_assets_superseded_by(&lowest_deleted_uids)
+    repo.reopen_assets_superseded_by(&lowest_deleted_uids)?;
+
+    Ok((deleted_ids, deleted_count))
 }
 
-fn rollback_asset_labels<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<()> {
+fn rollback_asset_labels<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<usize> {
     let deleted = repo.rollback_asset_labels(&block_uid)?;
+    let deleted_count = deleted.len();
 
     let mut grouped_deleted: HashMap<DeletedAssetLabels, Vec<DeletedAssetLabels>> = HashMap::new();
 
@@ -1838,11 +2947,14 @@ fn rollback_asset_labels<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<
         .filter_map(|(_, group)| group.into_iter().min_by_key(|i| i.uid).map(|i| i.uid))
         .collect();
 
-    repo.reopen_asset_labels_superseded_by(&lowest_deleted_uids)
+    repo.reopen_asset_labels_superseded_by(&lowest_deleted_uids)?;
+
+    Ok(deleted_count)
 }
 
-fn rollback_asset_tickers<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<()> {
+fn rollback_asset_tickers<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<usize> {
     let deleted = repo.rollback_asset_tickers(&block_uid)?;
+    let deleted_count = deleted.len();
 
     let mut grouped_deleted: HashMap<DeletedAssetTicker, Vec<DeletedAssetTicker>> = HashMap::new();
 
@@ -1856,13 +2968,16 @@ fn rollback_asset_tickers<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result
         .filter_map(|(_, group)| group.into_iter().min_by_key(|i| i.uid).map(|i| i.uid))
         .collect();
 
-    repo.reopen_asset_tickers_superseded_by(&lowest_deleted_uids)
+    repo.reopen_asset_tickers_superseded_by(&lowest_deleted_uids)?;
+
+    Ok(deleted_count)
 }
 
-fn rollback_data_entries<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<()> {
-    let deleted = repo.rollback_data_entries(&block_uid)?;
+fn rollback_asset_names<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<usize> {
+    let deleted = repo.rollback_asset_names(&block_uid)?;
+    let deleted_count = deleted.len();
 
-    let mut grouped_deleted: HashMap<DeletedDataEntry, Vec<DeletedDataEntry>> = HashMap::new();
+    let mut grouped_deleted: HashMap<DeletedAssetName, Vec<DeletedAssetName>> = HashMap::new();
 
     deleted.into_iter().for_each(|item| {
         let group = grouped_deleted.entry(item.clone()).or_insert(vec![]);
@@ -1874,13 +2989,16 @@ fn rollback_data_entries<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<
         .filter_map(|(_, group)| group.into_iter().min_by_key(|i| i.uid).map(|i| i.uid))
         .collect();
 
-    repo.reopen_data_entries_superseded_by(&lowest_deleted_uids)
+    repo.reopen_asset_names_superseded_by(&lowest_deleted_uids)?;
+
+    Ok(deleted_count)
 }
 
-fn rollback_issuer_balances<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<()> {
-    let deleted = repo.rollback_issuer_balances(&block_uid)?;
+fn rollback_asset_descriptions<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<usize> {
+    let deleted = repo.rollback_asset_descriptions(&block_uid)?;
+    let deleted_count = deleted.len();
 
-    let mut grouped_deleted: HashMap<DeletedIssuerBalance, Vec<DeletedIssuerBalance>> =
+    let mut grouped_deleted: HashMap<DeletedAssetDescription, Vec<DeletedAssetDescription>> =
         HashMap::new();
 
     deleted.into_iter().for_each(|item| {
@@ -1893,13 +3011,16 @@ fn rollback_issuer_balances<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Resu
         .filter_map(|(_, group)| group.into_iter().min_by_key(|i| i.uid).map(|i| i.uid))
         .collect();
 
-    repo.reopen_issuer_balances_superseded_by(&lowest_deleted_uids)
+    repo.reopen_asset_descriptions_superseded_by(&lowest_deleted_uids)?;
+
+    Ok(deleted_count)
 }
 
-fn rollback_out_leasings<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<()> {
-    let deleted = repo.rollback_out_leasings(&block_uid)?;
+fn rollback_data_entries<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<usize> {
+    let deleted = repo.rollback_data_entries(&block_uid)?;
+    let deleted_count = deleted.len();
 
-    let mut grouped_deleted: HashMap<DeletedOutLeasing, Vec<DeletedOutLeasing>> = HashMap::new();
+    let mut grouped_deleted: HashMap<DeletedDataEntry, Vec<DeletedDataEntry>> = HashMap::new();
 
     deleted.into_iter().for_each(|item| {
         let group = grouped_deleted.entry(item.clone()).or_insert(vec![]);
@@ -1911,77 +3032,96 @@ fn rollback_out_leasings<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<
         .filter_map(|(_, group)| group.into_iter().min_by_key(|i| i.uid).map(|i| i.uid))
         .collect();
 
-    repo.reopen_out_leasings_superseded_by(&lowest_deleted_uids)
-}
-
-fn escape_unicode_null(s: &str) -> String {
-    s.replace("\0", "\\0")
-}
-
-impl From<&models::data_entry::DataEntryUpdate> for Option<AssetOracleDataEntry> {
-    fn from(v: &models::data_entry::DataEntryUpdate) -> Self {
-        v.related_asset_id.as_ref().and_then(|related_asset_id| {
-            let (data_type, bin_val, bool_val, int_val, str_val) = match &v.value {
-                Some(DataEntryValue::BinVal(v)) => (
-                    Some(DataEntryValueType::Bin),
-                    Some(v.to_owned()),
-                    None,
-                    None,
-                    None,
-                ),
-                Some(DataEntryValue::BoolVal(v)) => (
-                    Some(DataEntryValueType::Bool),
-                    None,
-                    Some(v.to_owned()),
-                    None,
-                    None,
-                ),
-                Some(DataEntryValue::IntVal(v)) => (
-                    Some(DataEntryValueType::Int),
-                    None,
-                    None,
-                    Some(v.to_owned()),
-                    None,
-                ),
-                Some(DataEntryValue::StrVal(v)) => (
-                    Some(DataEntryValueType::Str),
-                    None,
-                    None,
-                    None,
-                    Some(v.to_owned()),
-                ),
-                None => (None, None, None, None, None),
-            };
-
-            data_type.map(|data_type| AssetOracleDataEntry {
-                asset_id: related_asset_id.to_owned(),
-                oracle_address: v.address.to_owned(),
-                key: v.key.to_owned(),
-                data_type: DataEntryType::from(&data_type),
-                bin_val,
-                bool_val,
-                int_val,
-                str_val,
-            })
-        })
-    }
-}
+    repo.reopen_data_entries_superseded_by(&lowest_deleted_uids)?;
 
-fn is_asset_labels_data_entry(key: &str) -> bool {
-    key.starts_with("%s%s__labels__")
+    Ok(deleted_count)
 }
 
-fn is_asset_ticker_data_entry(key: &str) -> bool {
-    key.starts_with("%s%s__assetId2ticker__")
-}
+fn rollback_issuer_balances<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<usize> {
+    let deleted = repo.rollback_issuer_balances(&block_uid)?;
+    let deleted_count = deleted.len();
 
-fn parse_asset_labels(value: &str) -> Vec<String> {
-    value
-        .split("__")
-        .map(|l| l.to_owned())
-        .filter(|l| !l.is_empty())
-        .collect()
-}
+    let mut grouped_deleted: HashMap<DeletedIssuerBalance, Vec<DeletedIssuerBalance>> =
+        HashMap::new();
+
+    deleted.into_iter().for_each(|item| {
+        let group = grouped_deleted.entry(item.clone()).or_insert(vec![]);
+        group.push(item);
+    });
+
+    let lowest_deleted_uids: Vec<i64> = grouped_deleted
+        .into_iter()
+        .filter_map(|(_, group)| group.into_iter().min_by_key(|i| i.uid).map(|i| i.uid))
+        .collect();
+
+    repo.reopen_issuer_balances_superseded_by(&lowest_deleted_uids)?;
+
+    Ok(deleted_count)
+}
+
+fn rollback_out_leasings<R: repo::Repo>(repo: Arc<R>, block_uid: i64) -> Result<usize> {
+    let deleted = repo.rollback_out_leasings(&block_uid)?;
+    let deleted_count = deleted.len();
+
+    let mut grouped_deleted: HashMap<DeletedOutLeasing, Vec<DeletedOutLeasing>> = HashMap::new();
+
+    deleted.into_iter().for_each(|item| {
+        let group = grouped_deleted.entry(item.clone()).or_insert(vec![]);
+        group.push(item);
+    });
+
+    let lowest_deleted_uids: Vec<i64> = grouped_deleted
+        .into_iter()
+        .filter_map(|(_, group)| group.into_iter().min_by_key(|i| i.uid).map(|i| i.uid))
+        .collect();
+
+    repo.reopen_out_leasings_superseded_by(&lowest_deleted_uids)?;
+
+    Ok(deleted_count)
+}
+
+/// Escapes the C0 control characters as `\xNN`, except NUL escaped as `\0`
+///
+/// Tabs and line breaks are kept, since they are common in the descriptions.
+fn escape_control_chars(s: &str) -> String {
+    if !s.chars().any(is_escaped_control_char) {
+        return s.to_owned();
+    }
+
+    s.chars()
+        .fold(String::with_capacity(s.len()), |mut escaped, c| {
+            match c {
+                '\0' => escaped.push_str("\\0"),
+                c if is_escaped_control_char(c) => {
+                    escaped.push_str(&format!("\\x{:02x}", c as u32))
+                }
+                c => escaped.push(c),
+            }
+            escaped
+        })
+}
+
+fn is_escaped_control_char(c: char) -> bool {
+    c.is_ascii_control() && !matches!(c, '\t' | '\n' | '\r' | '\x7f')
+}
+
+fn is_asset_labels_data_entry(key: &str) -> bool {
+    key.starts_with("%s%s__labels__")
+}
+
+fn is_asset_ticker_data_entry(key: &str) -> bool {
+    key.starts_with("%s%s__assetId2ticker__")
+}
+
+/// Labels are normalized and unique, in the order of the first occurrence
+fn parse_asset_labels(value: &str) -> Vec<AssetLabel> {
+    value
+        .split("__")
+        .map(AssetLabel::from)
+        .filter(|l| !l.as_str().is_empty())
+        .unique()
+        .collect()
+}
 
 fn asset_info_updates_from_asset_labels_update(
     updates: &[(&i64, AssetLabelsUpdate)],
@@ -2019,50 +3159,47 @@ fn asset_info_updates_from_asset_tickers_update(
     Ok(asset_info_updates)
 }
 
-fn asset_info_updates_from_data_entries_updates(
+/// Current oracles data of the updated assets, read back with the uids of the entries
+fn asset_info_updates_from_data_entries_updates<R>(
+    repo: Arc<R>,
     updates: &[(&i64, DataEntryUpdate)],
-) -> Result<HashMap<String, AssetInfoUpdate>, AppError> {
-    // Group updates by asset id
-    let data_entries_updates_by_asset_ids = updates
-        .clone()
-        .into_iter()
-        .filter_map(|(_, de_update)| {
-            de_update
-                .related_asset_id
-                .as_ref()
-                .map(|related_asset_id| (related_asset_id.to_owned(), de_update.to_owned()))
-        })
-        .into_group_map();
-
-    let updates_count = data_entries_updates_by_asset_ids.len();
+    waves_association_addresses: &[String],
+) -> Result<HashMap<String, AssetInfoUpdate>, AppError>
+where
+    R: repo::Repo,
+{
+    let asset_ids = updates
+        .iter()
+        .filter_map(|(_, de_update)| de_update.related_asset_id.as_deref())
+        .unique()
+        .collect_vec();
 
-    // Build asset info updates from data entries updates
-    let asset_info_updates = data_entries_updates_by_asset_ids
-        .into_iter()
-        .map(|(related_asset_id, de_updates)| {
-            let asset_oracles_data = de_updates
-                .iter()
-                .filter_map(|de_update| {
-                    let asset_oracle_data_entry: Option<AssetOracleDataEntry> = de_update.into();
-                    asset_oracle_data_entry.map(|asset_oracle_data_entry| {
-                        (de_update.address.clone(), asset_oracle_data_entry)
-                    })
-                })
-                .into_group_map();
+    if asset_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
 
-            let update = AssetInfoUpdate::OraclesData(asset_oracles_data);
+    // the assets whose entries are all deleted are updated with no oracles data
+    let mut assets_oracles_data = asset_ids
+        .iter()
+        .map(|asset_id| (asset_id.to_string(), HashMap::new()))
+        .collect::<HashMap<String, HashMap<String, Vec<AssetOracleDataEntry>>>>();
 
-            (related_asset_id, update)
-        })
-        .fold(
-            HashMap::with_capacity(updates_count),
-            |mut acc, (related_asset_id, asset_info_update)| {
-                acc.insert(related_asset_id, asset_info_update);
-                acc
-            },
-        );
+    repo.assets_oracle_data_entries(&asset_ids, waves_association_addresses)
+        .map_err(|e| AppError::DbError(e.to_string()))?
+        .iter()
+        .for_each(|de| {
+            if let Some(asset_oracles_data) = assets_oracles_data.get_mut(&de.asset_id) {
+                asset_oracles_data
+                    .entry(de.oracle_address.clone())
+                    .or_insert_with(Vec::new)
+                    .push(AssetOracleDataEntry::from(de));
+            }
+        });
 
-    Ok(asset_info_updates)
+    Ok(assets_oracles_data
+        .into_iter()
+        .map(|(asset_id, oracles_data)| (asset_id, AssetInfoUpdate::OraclesData(oracles_data)))
+        .collect())
 }
 
 fn asset_info_updates_from_issuer_balances_updates<R>(
@@ -2139,28 +3276,2432 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::escape_unicode_null;
-    use super::parse_asset_labels;
+    use anyhow::Result;
+    use prometheus::{Histogram, HistogramOpts, IntCounter, IntGauge};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use waves_protobuf_schemas::waves::{
+        data_transaction_data::{data_entry::Value, DataEntry},
+        events::{
+            state_update::{AssetDetails, AssetStateUpdate, DataEntryUpdate, LeasingUpdate},
+            transaction_metadata::{EthereumMetadata, Metadata as TxMetadata},
+            StateUpdate, TransactionMetadata,
+        },
+        signed_transaction::Transaction,
+        SignedTransaction, Transaction as WavesTx,
+    };
 
-    #[test]
-    fn should_escape_unicode_null() {
-        assert!("asd\0".contains("\0"));
-        assert_eq!(escape_unicode_null("asd\0"), "asd\\0");
+    use super::checkpoint::Checkpoint;
+    use super::models::asset::{
+        AssetOverride, CurrentAsset, DeletedAsset, InsertableAsset, OracleDataEntry, QueryableAsset,
+    };
+    use super::models::asset_descriptions::{
+        AssetDescription, AssetDescriptionOverride, DeletedAssetDescription,
+        InsertableAssetDescription,
+    };
+    use super::models::asset_labels::{
+        AssetLabels, AssetLabelsOverride, DeletedAssetLabels, InsertableAssetLabels,
+    };
+    use super::models::asset_names::{
+        AssetName, AssetNameOverride, DeletedAssetName, InsertableAssetName,
+    };
+    use super::models::asset_tickers::{
+        AssetTicker, AssetTickerOverride, DeletedAssetTicker, InsertableAssetTicker,
+    };
+    use super::models::block_microblock::BlockMicroblock;
+    use super::models::consumer_state::ConsumerState;
+    use super::models::data_entry::{
+        CurrentDataEntry, DataEntryOverride, DataEntryUpdate as ConsumerDataEntryUpdate,
+        DataEntryValue, DeletedDataEntry, InsertableDataEntry,
+    };
+    use super::models::issuer_balance::{
+        CurrentIssuerBalance, DeletedIssuerBalance, InsertableIssuerBalance, IssuerBalanceOverride,
+    };
+    use super::models::out_leasing::{DeletedOutLeasing, InsertableOutLeasing, OutLeasingOverride};
+    use super::models::supply_change::InsertableSupplyChange;
+    use super::repo::Repo;
+    use super::{
+        asset_info_updates_from_asset_tickers_update, clean_state, continues_from,
+        escape_control_chars, extract_asset_description_updates, extract_asset_labels_updates,
+        extract_asset_name_updates, extract_asset_related_data_entries_updates,
+        extract_asset_tickers_updates, extract_base_asset_info_updates,
+        extract_out_leasing_updates, extract_updates, handle_asset_descriptions_updates,
+        handle_asset_names_updates, handle_asset_related_data_entries_updates,
+        handle_base_asset_info_updates, handle_updates, handle_updates_batch, health,
+        millis_to_datetime, parse_asset_labels, resume_height, rollback, rollback_to_height, start,
+        supply_changes, AssetDescriptionUpdate, AssetNameUpdate, BlockMicroblockAppend,
+        BlockchainUpdate, BlockchainUpdatesWithLastHeight, ExtractedUpdates, PrevHandledHeight,
+        RawAssetDetails, ReconnectBackoff, ResumePoint, StartupRollback, Tx, UpdatesSource,
+    };
+    use crate::cache::invalidation_publisher::InvalidationPublisher;
+    use crate::cache::{CacheKeyFn, SyncReadCache, SyncWriteCache};
+    use crate::error::Error as AppError;
+    use crate::metrics::BatchMetrics;
+    use crate::models::{AssetInfoUpdate, AssetLabel, BaseAssetInfoUpdate};
+
+    /// Repo recording names of called methods
+    #[derive(Default)]
+    struct RecordingRepo {
+        calls: Mutex<Vec<&'static str>>,
+        rolled_back_assets: Vec<DeletedAsset>,
+        current_asset_ids: Vec<String>,
+        changed_asset_uids: Vec<i64>,
+        /// Current versions are the last inserted ones
+        inserted_assets: Mutex<Vec<InsertableAsset>>,
+        inserted_asset_names: Mutex<Vec<InsertableAssetName>>,
+        inserted_data_entries: Mutex<Vec<InsertableDataEntry>>,
+        inserted_asset_descriptions: Mutex<Vec<InsertableAssetDescription>>,
+        /// Signals the shutdown in the middle of the batch
+        shutdown_on_insert_blocks: Option<tokio::sync::watch::Sender<bool>>,
+        prev_handled_height: Option<i32>,
+        consumer_state: Option<ConsumerState>,
+        last_block: Option<ConsumerState>,
+        last_block_uid_below: Option<i64>,
+        /// Another consumer holds the lock
+        locked_elsewhere: bool,
     }
 
-    #[test]
-    fn should_filter_empty_labels() {
-        assert_eq!(parse_asset_labels(""), [] as [&str; 0]);
-        assert_eq!(parse_asset_labels("__"), [] as [&str; 0]);
-        assert_eq!(parse_asset_labels("____"), [] as [&str; 0]);
-        assert_eq!(parse_asset_labels("DEFO"), ["DEFO"]);
-        assert_eq!(parse_asset_labels("__DEFO"), ["DEFO"]);
-        assert_eq!(parse_asset_labels("DEFO__"), ["DEFO"]);
-        assert_eq!(parse_asset_labels("__DEFO__"), ["DEFO"]);
-        assert_eq!(parse_asset_labels("DEFO__GATEWAY"), ["DEFO", "GATEWAY"]);
-        assert_eq!(parse_asset_labels("DEFO__GATEWAY__"), ["DEFO", "GATEWAY"]);
-        assert_eq!(parse_asset_labels("__DEFO__GATEWAY"), ["DEFO", "GATEWAY"]);
-        assert_eq!(parse_asset_labels("__DEFO__GATEWAY__"), ["DEFO", "GATEWAY"]);
-        assert_eq!(parse_asset_labels("DEFO____GATEWAY"), ["DEFO", "GATEWAY"]);
+    impl RecordingRepo {
+        fn record(&self, call: &'static str) {
+            self.calls.lock().unwrap().push(call);
+        }
+
+        fn calls(&self) -> Vec<&'static str> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl Repo for RecordingRepo {
+        fn transaction(&self, f: impl FnOnce() -> Result<()>) -> Result<()> {
+            let result = f();
+            self.record(if result.is_ok() { "commit" } else { "rollback" });
+            result
+        }
+
+        fn get_prev_handled_height(&self) -> Result<Option<PrevHandledHeight>> {
+            self.record("get_prev_handled_height");
+            Ok(self
+                .prev_handled_height
+                .map(|height| PrevHandledHeight { uid: 1, height }))
+        }
+
+        fn get_handled_height(&self, height: i32) -> Result<Option<PrevHandledHeight>> {
+            self.record("get_handled_height");
+            Ok(Some(PrevHandledHeight { uid: 1, height }))
+        }
+
+        fn get_last_block_uid_below(&self, _height: i32) -> Result<Option<i64>> {
+            self.record("get_last_block_uid_below");
+            Ok(self.last_block_uid_below)
+        }
+
+        fn try_lock_consumer(&self) -> Result<bool> {
+            self.record("try_lock_consumer");
+            Ok(!self.locked_elsewhere)
+        }
+
+        fn get_consumer_state(&self) -> Result<Option<ConsumerState>> {
+            self.record("get_consumer_state");
+            Ok(self.consumer_state.clone())
+        }
+
+        fn set_consumer_state(&self, _state: &ConsumerState) -> Result<()> {
+            self.record("set_consumer_state");
+            Ok(())
+        }
+
+        fn get_last_block(&self) -> Result<Option<ConsumerState>> {
+            self.record("get_last_block");
+            Ok(self.last_block.clone())
+        }
+
+        fn refresh_searchable_assets(&self) -> Result<()> {
+            self.record("refresh_searchable_assets");
+            Ok(())
+        }
+
+        fn get_block_uid(&self, _block_id: &str) -> Result<i64> {
+            self.record("get_block_uid");
+            Ok(1)
+        }
+
+        fn get_key_block_uid(&self) -> Result<i64> {
+            self.record("get_key_block_uid");
+            Ok(1)
+        }
+
+        fn get_total_block_id(&self) -> Result<Option<String>> {
+            self.record("get_total_block_id");
+            Ok(None)
+        }
+
+        fn insert_blocks_or_microblocks(&self, blocks: &Vec<BlockMicroblock>) -> Result<Vec<i64>> {
+            self.record("insert_blocks_or_microblocks");
+            if let Some(shutdown) = self.shutdown_on_insert_blocks.as_ref() {
+                shutdown.send(true).unwrap();
+            }
+            Ok((1..=blocks.len() as i64).collect())
+        }
+
+        fn change_block_id(&self, _block_uid: &i64, _new_block_id: &str) -> Result<()> {
+            self.record("change_block_id");
+            Ok(())
+        }
+
+        fn delete_microblocks(&self) -> Result<()> {
+            self.record("delete_microblocks");
+            Ok(())
+        }
+
+        fn rollback_blocks_microblocks(&self, _block_uid: &i64) -> Result<usize> {
+            self.record("rollback_blocks_microblocks");
+            Ok(0)
+        }
+
+        fn get_current_assets(&self, ids: &[&str]) -> Result<Vec<CurrentAsset>> {
+            self.record("get_current_assets");
+            let inserted = self.inserted_assets.lock().unwrap();
+            Ok(ids
+                .iter()
+                .filter_map(|id| inserted.iter().rev().find(|a| a.id == *id))
+                .map(|a| CurrentAsset {
+                    id: a.id.clone(),
+                    name: a.name.clone(),
+                    description: a.description.clone(),
+                    issuer: a.issuer.clone(),
+                    precision: a.precision,
+                    smart: a.smart,
+                    nft: a.nft,
+                    quantity: a.quantity,
+                    reissuable: a.reissuable,
+                    min_sponsored_fee: a.min_sponsored_fee,
+                })
+                .collect())
+        }
+
+        fn get_next_assets_uid(&self) -> Result<i64> {
+            self.record("get_next_assets_uid");
+            Ok(1)
+        }
+
+        fn insert_assets(&self, assets: &Vec<InsertableAsset>) -> Result<()> {
+            self.record("insert_assets");
+            self.inserted_assets
+                .lock()
+                .unwrap()
+                .extend_from_slice(assets);
+            Ok(())
+        }
+
+        fn update_assets_block_references(&self, _block_uid: &i64) -> Result<()> {
+            self.record("update_assets_block_references");
+            Ok(())
+        }
+
+        fn close_assets_superseded_by(&self, _updates: &Vec<AssetOverride>) -> Result<()> {
+            self.record("close_assets_superseded_by");
+            Ok(())
+        }
+
+        fn reopen_assets_superseded_by(&self, _current_superseded_by: &Vec<i64>) -> Result<()> {
+            self.record("reopen_assets_superseded_by");
+            Ok(())
+        }
+
+        fn set_assets_next_update_uid(&self, _new_uid: i64) -> Result<()> {
+            self.record("set_assets_next_update_uid");
+            Ok(())
+        }
+
+        fn rollback_assets(&self, _block_uid: &i64) -> Result<Vec<DeletedAsset>> {
+            self.record("rollback_assets");
+            Ok(self.rolled_back_assets.clone())
+        }
+
+        fn current_asset_ids(&self, ids: &[&str]) -> Result<Vec<String>> {
+            self.record("current_asset_ids");
+            Ok(self
+                .current_asset_ids
+                .iter()
+                .filter(|id| ids.contains(&id.as_str()))
+                .cloned()
+                .collect())
+        }
+
+        fn assets_gt_block_uid(&self, _block_uid: &i64) -> Result<Vec<i64>> {
+            self.record("assets_gt_block_uid");
+            Ok(self.changed_asset_uids.clone())
+        }
+
+        fn mget_assets(&self, uids: &[i64]) -> Result<Vec<Option<QueryableAsset>>> {
+            self.record("mget_assets");
+            Ok(vec![None; uids.len()])
+        }
+
+        fn assets_oracle_data_entries(
+            &self,
+            _asset_ids: &[&str],
+            _oracle_addresses: &[String],
+        ) -> Result<Vec<OracleDataEntry>> {
+            self.record("assets_oracle_data_entries");
+            Ok(vec![])
+        }
+
+        fn issuer_assets(&self, _issuer_address: impl AsRef<str>) -> Result<Vec<QueryableAsset>> {
+            self.record("issuer_assets");
+            Ok(vec![])
+        }
+
+        fn mget_asset_labels(&self, _asset_ids: &[&str]) -> Result<Vec<AssetLabels>> {
+            self.record("mget_asset_labels");
+            Ok(vec![])
+        }
+
+        fn get_next_asset_labels_uid(&self) -> Result<i64> {
+            self.record("get_next_asset_labels_uid");
+            Ok(1)
+        }
+
+        fn insert_asset_labels(&self, _balances: &Vec<InsertableAssetLabels>) -> Result<()> {
+            self.record("insert_asset_labels");
+            Ok(())
+        }
+
+        fn update_asset_labels_block_references(&self, _block_uid: &i64) -> Result<()> {
+            self.record("update_asset_labels_block_references");
+            Ok(())
+        }
+
+        fn close_asset_labels_superseded_by(
+            &self,
+            _updates: &Vec<AssetLabelsOverride>,
+        ) -> Result<()> {
+            self.record("close_asset_labels_superseded_by");
+            Ok(())
+        }
+
+        fn reopen_asset_labels_superseded_by(
+            &self,
+            _current_superseded_by: &Vec<i64>,
+        ) -> Result<()> {
+            self.record("reopen_asset_labels_superseded_by");
+            Ok(())
+        }
+
+        fn set_asset_labels_next_update_uid(&self, _new_uid: i64) -> Result<()> {
+            self.record("set_asset_labels_next_update_uid");
+            Ok(())
+        }
+
+        fn rollback_asset_labels(&self, _block_uid: &i64) -> Result<Vec<DeletedAssetLabels>> {
+            self.record("rollback_asset_labels");
+            Ok(vec![])
+        }
+
+        fn mget_asset_tickers(&self, _asset_ids: &[&str]) -> Result<Vec<AssetTicker>> {
+            self.record("mget_asset_tickers");
+            Ok(vec![])
+        }
+
+        fn get_next_asset_tickers_uid(&self) -> Result<i64> {
+            self.record("get_next_asset_tickers_uid");
+            Ok(1)
+        }
+
+        fn insert_asset_tickers(&self, _updates: &Vec<InsertableAssetTicker>) -> Result<()> {
+            self.record("insert_asset_tickers");
+            Ok(())
+        }
+
+        fn update_asset_tickers_block_references(&self, _block_uid: &i64) -> Result<()> {
+            self.record("update_asset_tickers_block_references");
+            Ok(())
+        }
+
+        fn close_asset_tickers_superseded_by(
+            &self,
+            _updates: &Vec<AssetTickerOverride>,
+        ) -> Result<()> {
+            self.record("close_asset_tickers_superseded_by");
+            Ok(())
+        }
+
+        fn reopen_asset_tickers_superseded_by(
+            &self,
+            _current_superseded_by: &Vec<i64>,
+        ) -> Result<()> {
+            self.record("reopen_asset_tickers_superseded_by");
+            Ok(())
+        }
+
+        fn set_asset_tickers_next_update_uid(&self, _new_uid: i64) -> Result<()> {
+            self.record("set_asset_tickers_next_update_uid");
+            Ok(())
+        }
+
+        fn rollback_asset_tickers(&self, _block_uid: &i64) -> Result<Vec<DeletedAssetTicker>> {
+            self.record("rollback_asset_tickers");
+            Ok(vec![])
+        }
+
+        fn mget_asset_names(&self, _asset_ids: &[&str]) -> Result<Vec<AssetName>> {
+            self.record("mget_asset_names");
+            Ok(vec![])
+        }
+
+        fn get_next_asset_names_uid(&self) -> Result<i64> {
+            self.record("get_next_asset_names_uid");
+            Ok(1)
+        }
+
+        fn insert_asset_names(&self, updates: &Vec<InsertableAssetName>) -> Result<()> {
+            self.record("insert_asset_names");
+            self.inserted_asset_names
+                .lock()
+                .unwrap()
+                .extend_from_slice(updates);
+            Ok(())
+        }
+
+        fn update_asset_names_block_references(&self, _block_uid: &i64) -> Result<()> {
+            self.record("update_asset_names_block_references");
+            Ok(())
+        }
+
+        fn close_asset_names_superseded_by(&self, _updates: &Vec<AssetNameOverride>) -> Result<()> {
+            self.record("close_asset_names_superseded_by");
+            Ok(())
+        }
+
+        fn reopen_asset_names_superseded_by(
+            &self,
+            _current_superseded_by: &Vec<i64>,
+        ) -> Result<()> {
+            self.record("reopen_asset_names_superseded_by");
+            Ok(())
+        }
+
+        fn set_asset_names_next_update_uid(&self, _new_uid: i64) -> Result<()> {
+            self.record("set_asset_names_next_update_uid");
+            Ok(())
+        }
+
+        fn rollback_asset_names(&self, _block_uid: &i64) -> Result<Vec<DeletedAssetName>> {
+            self.record("rollback_asset_names");
+            Ok(vec![])
+        }
+
+        fn mget_asset_descriptions(&self, _asset_ids: &[&str]) -> Result<Vec<AssetDescription>> {
+            self.record("mget_asset_descriptions");
+            Ok(vec![])
+        }
+
+        fn get_next_asset_descriptions_uid(&self) -> Result<i64> {
+            self.record("get_next_asset_descriptions_uid");
+            Ok(1)
+        }
+
+        fn insert_asset_descriptions(
+            &self,
+            updates: &Vec<InsertableAssetDescription>,
+        ) -> Result<()> {
+            self.record("insert_asset_descriptions");
+            self.inserted_asset_descriptions
+                .lock()
+                .unwrap()
+                .extend_from_slice(updates);
+            Ok(())
+        }
+
+        fn update_asset_descriptions_block_references(&self, _block_uid: &i64) -> Result<()> {
+            self.record("update_asset_descriptions_block_references");
+            Ok(())
+        }
+
+        fn close_asset_descriptions_superseded_by(
+            &self,
+            _updates: &Vec<AssetDescriptionOverride>,
+        ) -> Result<()> {
+            self.record("close_asset_descriptions_superseded_by");
+            Ok(())
+        }
+
+        fn reopen_asset_descriptions_superseded_by(
+            &self,
+            _current_superseded_by: &Vec<i64>,
+        ) -> Result<()> {
+            self.record("reopen_asset_descriptions_superseded_by");
+            Ok(())
+        }
+
+        fn set_asset_descriptions_next_update_uid(&self, _new_uid: i64) -> Result<()> {
+            self.record("set_asset_descriptions_next_update_uid");
+            Ok(())
+        }
+
+        fn rollback_asset_descriptions(
+            &self,
+            _block_uid: &i64,
+        ) -> Result<Vec<DeletedAssetDescription>> {
+            self.record("rollback_asset_descriptions");
+            Ok(vec![])
+        }
+
+        fn get_current_data_entries(
+            &self,
+            address_keys: &[(&str, &str)],
+        ) -> Result<Vec<CurrentDataEntry>> {
+            self.record("get_current_data_entries");
+            let inserted = self.inserted_data_entries.lock().unwrap();
+            Ok(address_keys
+                .iter()
+                .filter_map(|(address, key)| {
+                    inserted
+                        .iter()
+                        .rev()
+                        .find(|de| de.address == *address && de.key == *key)
+                })
+                .map(|de| CurrentDataEntry {
+                    address: de.address.clone(),
+                    key: de.key.clone(),
+                    data_type: de.data_type.clone(),
+                    bin_val: de.bin_val.clone(),
+                    bool_val: de.bool_val,
+                    int_val: de.int_val,
+                    str_val: de.str_val.clone(),
+                    related_asset_id: de.related_asset_id.clone(),
+                })
+                .collect())
+        }
+
+        fn get_next_data_entries_uid(&self) -> Result<i64> {
+            self.record("get_next_data_entries_uid");
+            Ok(1)
+        }
+
+        fn insert_data_entries(&self, updates: &Vec<InsertableDataEntry>) -> Result<()> {
+            self.record("insert_data_entries");
+            self.inserted_data_entries
+                .lock()
+                .unwrap()
+                .extend_from_slice(updates);
+            Ok(())
+        }
+
+        fn update_data_entries_block_references(&self, _block_uid: &i64) -> Result<()> {
+            self.record("update_data_entries_block_references");
+            Ok(())
+        }
+
+        fn close_data_entries_superseded_by(
+            &self,
+            _updates: &Vec<DataEntryOverride>,
+        ) -> Result<()> {
+            self.record("close_data_entries_superseded_by");
+            Ok(())
+        }
+
+        fn reopen_data_entries_superseded_by(
+            &self,
+            _current_superseded_by: &Vec<i64>,
+        ) -> Result<()> {
+            self.record("reopen_data_entries_superseded_by");
+            Ok(())
+        }
+
+        fn set_data_entries_next_update_uid(&self, _new_uid: i64) -> Result<()> {
+            self.record("set_data_entries_next_update_uid");
+            Ok(())
+        }
+
+        fn rollback_data_entries(&self, _block_uid: &i64) -> Result<Vec<DeletedDataEntry>> {
+            self.record("rollback_data_entries");
+            Ok(vec![])
+        }
+
+        fn get_current_issuer_balances(&self) -> Result<Vec<CurrentIssuerBalance>> {
+            self.record("get_current_issuer_balances");
+            Ok(vec![])
+        }
+
+        fn get_next_issuer_balances_uid(&self) -> Result<i64> {
+            self.record("get_next_issuer_balances_uid");
+            Ok(1)
+        }
+
+        fn insert_issuer_balances(&self, _balances: &Vec<InsertableIssuerBalance>) -> Result<()> {
+            self.record("insert_issuer_balances");
+            Ok(())
+        }
+
+        fn update_issuer_balances_block_references(&self, _block_uid: &i64) -> Result<()> {
+            self.record("update_issuer_balances_block_references");
+            Ok(())
+        }
+
+        fn close_issuer_balances_superseded_by(
+            &self,
+            _updates: &Vec<IssuerBalanceOverride>,
+        ) -> Result<()> {
+            self.record("close_issuer_balances_superseded_by");
+            Ok(())
+        }
+
+        fn reopen_issuer_balances_superseded_by(
+            &self,
+            _current_superseded_by: &Vec<i64>,
+        ) -> Result<()> {
+            self.record("reopen_issuer_balances_superseded_by");
+            Ok(())
+        }
+
+        fn set_issuer_balances_next_update_uid(&self, _new_uid: i64) -> Result<()> {
+            self.record("set_issuer_balances_next_update_uid");
+            Ok(())
+        }
+
+        fn rollback_issuer_balances(&self, _block_uid: &i64) -> Result<Vec<DeletedIssuerBalance>> {
+            self.record("rollback_issuer_balances");
+            Ok(vec![])
+        }
+
+        fn get_next_out_leasings_uid(&self) -> Result<i64> {
+            self.record("get_next_out_leasings_uid");
+            Ok(1)
+        }
+
+        fn insert_out_leasings(&self, _balances: &Vec<InsertableOutLeasing>) -> Result<()> {
+            self.record("insert_out_leasings");
+            Ok(())
+        }
+
+        fn update_out_leasings_block_references(&self, _block_uid: &i64) -> Result<()> {
+            self.record("update_out_leasings_block_references");
+            Ok(())
+        }
+
+        fn close_out_leasings_superseded_by(
+            &self,
+            _updates: &Vec<OutLeasingOverride>,
+        ) -> Result<()> {
+            self.record("close_out_leasings_superseded_by");
+            Ok(())
+        }
+
+        fn reopen_out_leasings_superseded_by(
+            &self,
+            _current_superseded_by: &Vec<i64>,
+        ) -> Result<()> {
+            self.record("reopen_out_leasings_superseded_by");
+            Ok(())
+        }
+
+        fn set_out_leasings_next_update_uid(&self, _new_uid: i64) -> Result<()> {
+            self.record("set_out_leasings_next_update_uid");
+            Ok(())
+        }
+
+        fn rollback_out_leasings(&self, _block_uid: &i64) -> Result<Vec<DeletedOutLeasing>> {
+            self.record("rollback_out_leasings");
+            Ok(vec![])
+        }
+
+        fn insert_supply_changes(&self, _changes: &Vec<InsertableSupplyChange>) -> Result<()> {
+            self.record("insert_supply_changes");
+            Ok(())
+        }
+
+        fn update_supply_changes_block_references(&self, _block_uid: &i64) -> Result<()> {
+            self.record("update_supply_changes_block_references");
+            Ok(())
+        }
+
+        fn rollback_supply_changes(&self, _block_uid: &i64) -> Result<usize> {
+            self.record("rollback_supply_changes");
+            Ok(0)
+        }
+    }
+
+    /// Cache failing on any access
+    #[derive(Clone)]
+    struct UnreachableCache;
+
+    impl CacheKeyFn for UnreachableCache {
+        fn key_fn(&self, source_key: &str) -> String {
+            source_key.to_owned()
+        }
+    }
+
+    impl<T> SyncReadCache<T> for UnreachableCache {
+        fn get(&self, key: &str) -> Result<Option<T>, AppError> {
+            unreachable!("unexpected cache get {}", key)
+        }
+
+        fn mget(&self, keys: &[&str]) -> Result<Vec<Option<T>>, AppError> {
+            unreachable!("unexpected cache mget {:?}", keys)
+        }
+    }
+
+    impl<T> SyncWriteCache<T> for UnreachableCache {
+        fn set(&self, key: &str, _value: T) -> Result<(), AppError> {
+            unreachable!("unexpected cache set {}", key)
+        }
+
+        fn mset(&self, entries: Vec<(String, T)>) -> Result<(), AppError> {
+            unreachable!("unexpected cache mset of {} entries", entries.len())
+        }
+
+        fn del(&self, key: &str) -> Result<(), AppError> {
+            unreachable!("unexpected cache del {}", key)
+        }
+
+        fn clear(&self) -> Result<(), AppError> {
+            unreachable!("unexpected cache clear")
+        }
+    }
+
+    /// Empty cache failing the writes, as with Redis unavailable
+    #[derive(Clone)]
+    struct FailingWritesCache;
+
+    impl CacheKeyFn for FailingWritesCache {
+        fn key_fn(&self, source_key: &str) -> String {
+            source_key.to_owned()
+        }
+    }
+
+    impl<T> SyncReadCache<T> for FailingWritesCache {
+        fn get(&self, _key: &str) -> Result<Option<T>, AppError> {
+            Ok(None)
+        }
+
+        fn mget(&self, keys: &[&str]) -> Result<Vec<Option<T>>, AppError> {
+            Ok(keys.iter().map(|_| None).collect())
+        }
+    }
+
+    impl<T> SyncWriteCache<T> for FailingWritesCache {
+        fn set(&self, _key: &str, _value: T) -> Result<(), AppError> {
+            Err(AppError::CacheError("unavailable".to_owned()))
+        }
+
+        fn mset(&self, _entries: Vec<(String, T)>) -> Result<(), AppError> {
+            Err(AppError::CacheError("unavailable".to_owned()))
+        }
+
+        fn del(&self, _key: &str) -> Result<(), AppError> {
+            Err(AppError::CacheError("unavailable".to_owned()))
+        }
+
+        fn clear(&self) -> Result<(), AppError> {
+            Err(AppError::CacheError("unavailable".to_owned()))
+        }
+    }
+
+    /// Empty cache recording deleted keys
+    #[derive(Clone, Default)]
+    pub(super) struct DeletionsCache {
+        pub(super) deleted: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl CacheKeyFn for DeletionsCache {
+        fn key_fn(&self, source_key: &str) -> String {
+            source_key.to_owned()
+        }
+    }
+
+    impl<T> SyncReadCache<T> for DeletionsCache {
+        fn get(&self, _key: &str) -> Result<Option<T>, AppError> {
+            Ok(None)
+        }
+
+        fn mget(&self, keys: &[&str]) -> Result<Vec<Option<T>>, AppError> {
+            Ok(keys.iter().map(|_| None).collect())
+        }
+    }
+
+    impl<T> SyncWriteCache<T> for DeletionsCache {
+        fn set(&self, _key: &str, _value: T) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        fn mset(&self, _entries: Vec<(String, T)>) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        fn del(&self, key: &str) -> Result<(), AppError> {
+            self.deleted.lock().unwrap().push(key.to_owned());
+            Ok(())
+        }
+
+        fn clear(&self) -> Result<(), AppError> {
+            Ok(())
+        }
+    }
+
+    fn empty_block(height: u32) -> BlockMicroblockAppend {
+        BlockMicroblockAppend {
+            id: format!("block{}", height),
+            time_stamp: Some(1_600_000_000_000),
+            height,
+            reference: Some(format!("block{}", height - 1)),
+            updated_waves_amount: None,
+            state_update: StateUpdate::default(),
+            txs: vec![],
+        }
+    }
+
+    #[test]
+    fn should_handle_empty_blocks_with_blocks_insertion_only() {
+        let repo = Arc::new(RecordingRepo::default());
+
+        let updates = BlockchainUpdatesWithLastHeight {
+            last_height: 3,
+            updates: (1..=3)
+                .map(|h| BlockchainUpdate::Block(empty_block(h)))
+                .collect(),
+        };
+
+        handle_updates(
+            updates,
+            repo.clone(),
+            UnreachableCache,
+            UnreachableCache,
+            b'W',
+            &["3PAddr".to_owned()],
+            false,
+            &[],
+            &tokio::sync::watch::channel(false).1,
+        )
+        .unwrap();
+
+        assert_eq!(
+            repo.calls(),
+            vec!["get_total_block_id", "insert_blocks_or_microblocks"]
+        );
+    }
+
+    #[test]
+    fn should_stop_handling_updates_items_at_shutdown() {
+        let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+        let repo = Arc::new(RecordingRepo {
+            shutdown_on_insert_blocks: Some(shutdown_tx),
+            ..Default::default()
+        });
+
+        let updates = BlockchainUpdatesWithLastHeight {
+            last_height: 1,
+            updates: vec![
+                BlockchainUpdate::Block(empty_block(1)),
+                BlockchainUpdate::Rollback("block0".to_owned()),
+            ],
+        };
+
+        let result = handle_updates(
+            updates,
+            repo.clone(),
+            UnreachableCache,
+            UnreachableCache,
+            b'W',
+            &[],
+            false,
+            &[],
+            &shutdown_rx,
+        );
+
+        // the rollback following the blocks is not handled
+        assert!(is_shutdown_requested(&result.unwrap_err()));
+        assert!(!repo.calls().contains(&"get_block_uid"));
+    }
+
+    #[test]
+    fn should_update_metrics_after_handled_batch() {
+        let batch_metrics = BatchMetrics {
+            last_handled_height: IntGauge::new("last_handled_height", "test").unwrap(),
+            updates_per_batch: Histogram::with_opts(HistogramOpts::new(
+                "updates_per_batch",
+                "test",
+            ))
+            .unwrap(),
+            updates_processed: IntCounter::new("updates_processed", "test").unwrap(),
+            batch_handle_duration: Histogram::with_opts(HistogramOpts::new(
+                "batch_handle_duration",
+                "test",
+            ))
+            .unwrap(),
+            last_handled_block_timestamp: IntGauge::new("last_handled_block_timestamp", "test")
+                .unwrap(),
+        };
+        let last_height = 3;
+
+        let updates = BlockchainUpdatesWithLastHeight {
+            last_height,
+            updates: (1..=last_height)
+                .map(|h| BlockchainUpdate::Block(empty_block(h)))
+                .collect(),
+        };
+
+        handle_updates_batch(
+            updates,
+            Arc::new(RecordingRepo::default()),
+            UnreachableCache,
+            UnreachableCache,
+            b'W',
+            &["3PAddr".to_owned()],
+            false,
+            &[],
+            &tokio::sync::watch::channel(false).1,
+            &batch_metrics,
+        )
+        .unwrap();
+
+        assert_eq!(batch_metrics.last_handled_height.get(), last_height as i64);
+        assert_eq!(batch_metrics.updates_processed.get(), 3);
+        assert_eq!(batch_metrics.updates_per_batch.get_sample_count(), 1);
+    }
+
+    #[test]
+    fn should_not_save_state_of_batch_failing_to_write_cache() {
+        let repo = Arc::new(RecordingRepo {
+            rolled_back_assets: vec![DeletedAsset {
+                uid: 10,
+                id: "issued".to_owned(),
+            }],
+            ..Default::default()
+        });
+
+        let result = handle_updates_batch(
+            BlockchainUpdatesWithLastHeight {
+                last_height: 1,
+                updates: vec![BlockchainUpdate::Rollback("block1".to_owned())],
+            },
+            repo.clone(),
+            FailingWritesCache,
+            DeletionsCache::default(),
+            b'W',
+            &[],
+            false,
+            &[],
+            &tokio::sync::watch::channel(false).1,
+            &BatchMetrics::default(),
+        );
+
+        // the rows are committed, the state stays at the previous batch to handle this one again
+        assert!(result.is_err());
+        let calls = repo.calls();
+        assert!(calls.contains(&"commit"));
+        assert!(!calls.contains(&"set_consumer_state"));
+    }
+
+    #[test]
+    fn should_rollback_whole_batch_of_unflushed_cache() {
+        // the state of the previous batch is kept since the cache of the last one failed to be written
+        let repo = Arc::new(RecordingRepo {
+            prev_handled_height: Some(9),
+            consumer_state: Some(consumer_state(5, "block3", 3)),
+            last_block: Some(consumer_state(12, "block10", 10)),
+            ..Default::default()
+        });
+
+        let resumed = resume_height(
+            repo.clone(),
+            DeletionsCache::default(),
+            DeletionsCache::default(),
+            &[],
+            1,
+            false,
+            None::<&RecordingCheckpoint>,
+        )
+        .unwrap();
+
+        assert_eq!(resumed.from_height, 4);
+        assert_eq!(resumed.rollback.unwrap().rolled_back_to_height, 3);
+        let calls = repo.calls();
+        assert!(calls.contains(&"get_handled_height"));
+        assert!(!calls.contains(&"get_prev_handled_height"));
+    }
+
+    #[test]
+    fn should_not_consider_block_with_waves_amount_update_empty() {
+        let mut block = empty_block(1);
+        assert!(block.is_empty());
+
+        block.updated_waves_amount = Some(1);
+        assert!(!block.is_empty());
+    }
+
+    #[test]
+    fn should_keep_millis_of_timestamp() {
+        let dt = millis_to_datetime(1_600_000_000_123);
+        assert_eq!(dt.timestamp(), 1_600_000_000);
+        assert_eq!(dt.timestamp_subsec_millis(), 123);
+        assert_eq!(dt.timestamp_millis(), 1_600_000_000_123);
+
+        // before the epoch the millis are still counted forward from the second
+        assert_eq!(millis_to_datetime(-1).timestamp_millis(), -1);
+    }
+
+    #[test]
+    fn should_escape_unicode_null() {
+        assert!("asd\0".contains("\0"));
+        assert_eq!(escape_control_chars("asd\0"), "asd\\0");
+    }
+
+    #[test]
+    fn should_escape_control_chars() {
+        assert_eq!(escape_control_chars("asd\x01"), "asd\\x01");
+        assert_eq!(escape_control_chars("\x1b[31mred"), "\\x1b[31mred");
+        assert_eq!(escape_control_chars("a\x1fb\0"), "a\\x1fb\\0");
+        // line breaks, tabs and non-ASCII text are kept
+        assert_eq!(escape_control_chars("a\tb\r\nc"), "a\tb\r\nc");
+        assert_eq!(escape_control_chars("Токен ✓"), "Токен ✓");
+        assert_eq!(escape_control_chars("a\u{7f}"), "a\u{7f}");
+    }
+
+    #[test]
+    fn should_filter_empty_labels() {
+        let parse_asset_labels = |value: &str| {
+            parse_asset_labels(value)
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(parse_asset_labels(""), [] as [&str; 0]);
+        assert_eq!(parse_asset_labels("__"), [] as [&str; 0]);
+        assert_eq!(parse_asset_labels("____"), [] as [&str; 0]);
+        assert_eq!(parse_asset_labels("DEFO"), ["DEFO"]);
+        assert_eq!(parse_asset_labels("__DEFO"), ["DEFO"]);
+        assert_eq!(parse_asset_labels("DEFO__"), ["DEFO"]);
+        assert_eq!(parse_asset_labels("__DEFO__"), ["DEFO"]);
+        assert_eq!(parse_asset_labels("DEFO__GATEWAY"), ["DEFO", "GATEWAY"]);
+        assert_eq!(parse_asset_labels("DEFO__GATEWAY__"), ["DEFO", "GATEWAY"]);
+        assert_eq!(parse_asset_labels("__DEFO__GATEWAY"), ["DEFO", "GATEWAY"]);
+        assert_eq!(parse_asset_labels("__DEFO__GATEWAY__"), ["DEFO", "GATEWAY"]);
+        assert_eq!(parse_asset_labels("DEFO____GATEWAY"), ["DEFO", "GATEWAY"]);
+        assert_eq!(parse_asset_labels("DEFO__DEFO"), ["DEFO"]);
+        assert_eq!(parse_asset_labels("defo__ DEFO __  "), ["DEFO"]);
+        assert_eq!(
+            parse_asset_labels("GATEWAY__DEFO__GATEWAY"),
+            ["GATEWAY", "DEFO"]
+        );
+    }
+
+    fn ticker_tx(oracle_address: &[u8], asset_id: &str, ticker: Option<&str>) -> Tx {
+        Tx {
+            id: "tx".to_owned(),
+            data: SignedTransaction::default(),
+            meta: TransactionMetadata::default(),
+            state_update: StateUpdate {
+                data_entries: vec![DataEntryUpdate {
+                    address: oracle_address.to_vec(),
+                    data_entry: Some(DataEntry {
+                        key: format!("%s%s__assetId2ticker__{}", asset_id),
+                        value: ticker.map(|t| Value::StringValue(t.to_owned())),
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn should_extract_updates_in_parallel_like_sequentially() {
+        let oracle_address = vec![1; 26];
+        let oracle_addresses = vec![bs58::encode(&oracle_address).into_string()];
+        let mut append = empty_block(1);
+        append.txs = (0..1_000)
+            .map(|i| ticker_tx(&oracle_address, &format!("asset{}", i), Some("TKR")))
+            .collect();
+        let block_uids_with_appends = vec![(1, &append)];
+
+        let extract = || {
+            extract_updates(
+                b'W',
+                &block_uids_with_appends,
+                &oracle_addresses,
+                false,
+                &[],
+            )
+        };
+        let single_thread = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+
+        let asset_ids = |updates: ExtractedUpdates| {
+            updates
+                .asset_tickers
+                .into_iter()
+                .map(|(_, u)| u.asset_id)
+                .collect::<Vec<_>>()
+        };
+
+        let sequential = asset_ids(single_thread.install(extract));
+        assert_eq!(sequential.len(), 1_000);
+        assert_eq!(sequential, asset_ids(extract()));
+    }
+
+    #[test]
+    fn should_clear_deleted_ticker() {
+        let oracle_address = vec![1; 26];
+        let oracle_addresses = vec![bs58::encode(&oracle_address).into_string()];
+
+        let set = extract_asset_tickers_updates(
+            1,
+            &ticker_tx(&oracle_address, "asset", Some("TKR")),
+            &oracle_addresses,
+            false,
+        );
+        assert_eq!(set.len(), 1);
+        assert_eq!(set[0].asset_id, "asset");
+        assert_eq!(set[0].ticker, Some("TKR".to_owned()));
+
+        let deleted = extract_asset_tickers_updates(
+            2,
+            &ticker_tx(&oracle_address, "asset", None),
+            &oracle_addresses,
+            false,
+        );
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].ticker, None);
+
+        let updates = set
+            .into_iter()
+            .map(|u| (&1, u))
+            .chain(deleted.into_iter().map(|u| (&2, u)))
+            .collect::<Vec<_>>();
+        let asset_info_updates = asset_info_updates_from_asset_tickers_update(&updates).unwrap();
+        assert!(matches!(
+            asset_info_updates.get("asset"),
+            Some(AssetInfoUpdate::Ticker(None))
+        ));
+    }
+
+    #[test]
+    fn should_tell_empty_ticker_from_deleted_one() {
+        let oracle_address = vec![1; 26];
+        let oracle_addresses = vec![bs58::encode(&oracle_address).into_string()];
+        let empty = ticker_tx(&oracle_address, "asset", Some(""));
+        let deleted = ticker_tx(&oracle_address, "asset", None);
+
+        let updates = extract_asset_tickers_updates(1, &empty, &oracle_addresses, false);
+        assert_eq!(updates[0].ticker, Some("".to_owned()));
+        let updates = extract_asset_tickers_updates(1, &deleted, &oracle_addresses, false);
+        assert_eq!(updates[0].ticker, None);
+
+        // the legacy handling drops the ticker in both cases
+        let updates = extract_asset_tickers_updates(1, &empty, &oracle_addresses, true);
+        assert_eq!(updates[0].ticker, None);
+        let updates = extract_asset_tickers_updates(1, &deleted, &oracle_addresses, true);
+        assert_eq!(updates[0].ticker, None);
+
+        let updates = extract_asset_tickers_updates(1, &empty, &oracle_addresses, false)
+            .into_iter()
+            .map(|u| (&1, u))
+            .collect::<Vec<_>>();
+        let asset_info_updates = asset_info_updates_from_asset_tickers_update(&updates).unwrap();
+        assert!(matches!(
+            asset_info_updates.get("asset"),
+            Some(AssetInfoUpdate::Ticker(Some(ticker))) if ticker.is_empty()
+        ));
+    }
+
+    #[test]
+    fn should_keep_raw_asset_details() {
+        let asset_details = AssetDetails {
+            asset_id: vec![2; 32],
+            issuer: vec![3; 32],
+            decimals: 8,
+            name: "Name\u{0}".to_owned(),
+            description: "Description".to_owned(),
+            reissuable: true,
+            volume: 1_000_000,
+            sponsorship: 0,
+            nft: false,
+            ..Default::default()
+        };
+        let tx = Tx {
+            id: "tx".to_owned(),
+            data: SignedTransaction::default(),
+            meta: TransactionMetadata::default(),
+            state_update: StateUpdate {
+                assets: vec![AssetStateUpdate {
+                    before: None,
+                    after: Some(asset_details.clone()),
+                }],
+                ..Default::default()
+            },
+        };
+        let mut append = empty_block(1);
+        append.txs = vec![tx];
+
+        let raw = RawAssetDetails::from(&asset_details);
+        assert_eq!(
+            raw,
+            RawAssetDetails {
+                asset_id: bs58::encode(vec![2; 32]).into_string(),
+                issuer: bs58::encode(vec![3; 32]).into_string(),
+                decimals: 8,
+                name: "Name\u{0}".to_owned(),
+                description: "Description".to_owned(),
+                reissuable: true,
+                volume: 1_000_000,
+                script: None,
+                sponsorship: 0,
+                nft: false,
+            }
+        );
+
+        let updates = extract_base_asset_info_updates(b'W', &append, &[raw.asset_id.clone()]);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].id, raw.asset_id);
+        assert_eq!(updates[0].precision, raw.decimals);
+        assert_eq!(updates[0].quantity, raw.volume);
+        assert_eq!(updates[0].min_sponsored_fee, None);
+        assert_ne!(updates[0].name, raw.name);
+    }
+
+    #[test]
+    fn should_diff_supply_changes() {
+        let update = |id: &str, height: i32, quantity: i64| BaseAssetInfoUpdate {
+            id: id.to_owned(),
+            issuer: "issuer".to_owned(),
+            precision: 8,
+            nft: false,
+            updated_at: chrono::Utc::now(),
+            update_height: height,
+            name: id.to_owned(),
+            description: "".to_owned(),
+            smart: false,
+            quantity,
+            reissuable: true,
+            min_sponsored_fee: None,
+        };
+        let change =
+            |block_uid, id: &str, height, prev_quantity, new_quantity| InsertableSupplyChange {
+                block_uid,
+                asset_id: id.to_owned(),
+                height,
+                prev_quantity,
+                new_quantity,
+                change: new_quantity - prev_quantity,
+            };
+
+        let current_quantities = vec![("A".to_owned(), 100)].into_iter().collect();
+        let updates = vec![
+            // reissue
+            (&1, update("A", 1, 150)),
+            // issue
+            (&1, update("B", 1, 10)),
+            // renaming keeps the quantity
+            (&2, update("A", 2, 150)),
+            // burn
+            (&2, update("A", 2, 120)),
+        ];
+
+        assert_eq!(
+            supply_changes(&current_quantities, &updates),
+            vec![
+                change(1, "A", 1, 100, 150),
+                change(1, "B", 1, 0, 10),
+                change(2, "A", 2, 150, 120),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_extract_changed_asset_names_and_descriptions() {
+        let update = |id: &str, name: &str, description: &str| BaseAssetInfoUpdate {
+            id: id.to_owned(),
+            issuer: "issuer".to_owned(),
+            precision: 8,
+            nft: false,
+            updated_at: chrono::Utc::now(),
+            update_height: 1,
+            name: name.to_owned(),
+            description: description.to_owned(),
+            smart: false,
+            quantity: 100,
+            reissuable: true,
+            min_sponsored_fee: None,
+        };
+
+        let current = |id: &str, value: &str| {
+            vec![(id.to_owned(), value.to_owned())]
+                .into_iter()
+                .collect::<HashMap<_, _>>()
+        };
+        let updates = vec![
+            // reissue keeps the name and the description
+            (&1, update("A", "Old", "About")),
+            // issue
+            (&1, update("B", "New", "")),
+            (&2, update("A", "Renamed", "About")),
+            (&3, update("A", "Renamed", "Described")),
+        ];
+
+        let names = extract_asset_name_updates(&current("A", "Old"), &updates);
+        let name = |asset_id: &str, name: &str| AssetNameUpdate {
+            asset_id: asset_id.to_owned(),
+            name: name.to_owned(),
+        };
+        assert_eq!(
+            names,
+            vec![(&1, name("B", "New")), (&2, name("A", "Renamed"))]
+        );
+
+        let descriptions = extract_asset_description_updates(&current("A", "About"), &updates);
+        let description = |asset_id: &str, description: &str| AssetDescriptionUpdate {
+            asset_id: asset_id.to_owned(),
+            description: description.to_owned(),
+        };
+        assert_eq!(
+            descriptions,
+            vec![
+                (&1, description("B", "")),
+                (&3, description("A", "Described"))
+            ]
+        );
+    }
+
+    #[test]
+    fn should_supersede_previous_asset_names() {
+        let repo = Arc::new(RecordingRepo::default());
+        let name = |asset_id: &str, name: &str| AssetNameUpdate {
+            asset_id: asset_id.to_owned(),
+            name: name.to_owned(),
+        };
+
+        handle_asset_names_updates(
+            repo.clone(),
+            &[
+                (&1, name("A", "First")),
+                (&2, name("B", "Other")),
+                (&2, name("A", "Second")),
+            ],
+        )
+        .unwrap();
+
+        let inserted = repo
+            .inserted_asset_names
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|n| (n.uid, n.superseded_by, n.asset_id.clone(), n.name.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            inserted,
+            vec![
+                (1, 3, "A".to_owned(), "First".to_owned()),
+                (2, i64::MAX - 1, "B".to_owned(), "Other".to_owned()),
+                (3, i64::MAX - 1, "A".to_owned(), "Second".to_owned()),
+            ]
+        );
+        assert!(repo.calls().contains(&"close_asset_names_superseded_by"));
+    }
+
+    #[test]
+    fn should_insert_last_data_entry_update_of_block() {
+        let repo = Arc::new(RecordingRepo::default());
+        let update = |key: &str, value: i64| ConsumerDataEntryUpdate {
+            update_height: 1,
+            updated_at: millis_to_datetime(1_600_000_000_000),
+            address: "3PEgG7eZHLFhcfsTSaYxgRhZsh4AxMvA4Ms".to_owned(),
+            key: key.to_owned(),
+            value: Some(DataEntryValue::IntVal(value)),
+            related_asset_id: None,
+        };
+
+        handle_asset_related_data_entries_updates(
+            repo.clone(),
+            &[
+                (&1, update("a", 1)),
+                (&1, update("b", 2)),
+                (&1, update("a", 3)),
+                (&2, update("a", 4)),
+            ],
+        )
+        .unwrap();
+
+        let inserted = repo
+            .inserted_data_entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|de| {
+                (
+                    de.uid,
+                    de.superseded_by,
+                    de.block_uid,
+                    de.key.clone(),
+                    de.int_val,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            inserted,
+            vec![
+                (1, i64::MAX - 1, 1, "b".to_owned(), Some(2)),
+                (2, 3, 1, "a".to_owned(), Some(3)),
+                (3, i64::MAX - 1, 2, "a".to_owned(), Some(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_insert_unchanged_asset_version_once() {
+        let repo = Arc::new(RecordingRepo::default());
+        let update = |height: i32, quantity: i64| BaseAssetInfoUpdate {
+            id: "A".to_owned(),
+            issuer: "issuer".to_owned(),
+            precision: 8,
+            nft: false,
+            updated_at: millis_to_datetime(1_600_000_000_000 + height as i64),
+            update_height: height,
+            name: "A".to_owned(),
+            description: "".to_owned(),
+            smart: false,
+            quantity,
+            reissuable: true,
+            min_sponsored_fee: None,
+        };
+        let inserted_assets = || {
+            repo.inserted_assets
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|a| (a.uid, a.superseded_by, a.block_uid, a.quantity))
+                .collect::<Vec<_>>()
+        };
+
+        // reissue of zero amount within the batch
+        handle_base_asset_info_updates(repo.clone(), &[(&1, update(1, 100)), (&2, update(2, 100))])
+            .unwrap();
+        assert_eq!(inserted_assets(), vec![(1, i64::MAX - 1, 1, 100)]);
+
+        // and in the next batch
+        handle_base_asset_info_updates(repo.clone(), &[(&3, update(3, 100))]).unwrap();
+        assert_eq!(inserted_assets(), vec![(1, i64::MAX - 1, 1, 100)]);
+
+        // uids of the changed versions stay consecutive
+        handle_base_asset_info_updates(
+            repo.clone(),
+            &[
+                (&4, update(4, 100)),
+                (&4, update(4, 150)),
+                (&5, update(5, 150)),
+                (&5, update(5, 120)),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            inserted_assets(),
+            vec![
+                (1, i64::MAX - 1, 1, 100),
+                (1, 2, 4, 150),
+                (2, i64::MAX - 1, 5, 120),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_insert_waves_quantity_changes_only() {
+        let repo = Arc::new(RecordingRepo::default());
+        let update = |height: i32, quantity: i64| {
+            BaseAssetInfoUpdate::waves_update(
+                height,
+                millis_to_datetime(1_600_000_000_000 + height as i64),
+                quantity,
+            )
+        };
+
+        for height in 1..=3 {
+            handle_base_asset_info_updates(repo.clone(), &[(&(height as i64), update(height, 10))])
+                .unwrap();
+        }
+        handle_base_asset_info_updates(repo.clone(), &[(&4, update(4, 11))]).unwrap();
+
+        let quantities = repo
+            .inserted_assets
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|a| a.quantity)
+            .collect::<Vec<_>>();
+        assert_eq!(quantities, vec![10, 11]);
+    }
+
+    #[test]
+    fn should_insert_unchanged_data_entry_version_once() {
+        let repo = Arc::new(RecordingRepo::default());
+        let update_of = |address: &str, height: i32| ConsumerDataEntryUpdate {
+            update_height: height,
+            updated_at: millis_to_datetime(1_600_000_000_000),
+            address: address.to_owned(),
+            key: "description_<en>_A".to_owned(),
+            value: Some(DataEntryValue::StrVal("Token".to_owned())),
+            related_asset_id: Some("A".to_owned()),
+        };
+        let update = |height: i32| update_of("3PEgG7eZHLFhcfsTSaYxgRhZsh4AxMvA4Ms", height);
+
+        handle_asset_related_data_entries_updates(
+            repo.clone(),
+            &[(&1, update(1)), (&2, update(2))],
+        )
+        .unwrap();
+        handle_asset_related_data_entries_updates(
+            repo.clone(),
+            &[
+                (&3, update(3)),
+                (&3, update_of("3PC4roN512iugc6xGVTTM2XkoWKEdSiiscd", 3)),
+            ],
+        )
+        .unwrap();
+
+        let inserted = repo
+            .inserted_data_entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|de| (de.uid, de.superseded_by, de.block_uid))
+            .collect::<Vec<_>>();
+        // the entry of the other address is new, the uids of the recording repo restart from 1
+        assert_eq!(inserted, vec![(1, i64::MAX - 1, 1), (1, i64::MAX - 1, 3)]);
+
+        // the current entries of all the addresses are read at once
+        let reads = repo
+            .calls()
+            .into_iter()
+            .filter(|call| *call == "get_current_data_entries")
+            .count();
+        assert_eq!(reads, 2);
+    }
+
+    #[test]
+    fn should_supersede_previous_asset_descriptions() {
+        let repo = Arc::new(RecordingRepo::default());
+        let description = |asset_id: &str, description: &str| AssetDescriptionUpdate {
+            asset_id: asset_id.to_owned(),
+            description: description.to_owned(),
+        };
+
+        handle_asset_descriptions_updates(
+            repo.clone(),
+            &[
+                (&1, description("A", "First")),
+                (&1, description("B", "")),
+                (&2, description("A", "Second")),
+                (&3, description("A", "Third")),
+            ],
+        )
+        .unwrap();
+
+        let inserted = repo
+            .inserted_asset_descriptions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|d| {
+                (
+                    d.uid,
+                    d.superseded_by,
+                    d.block_uid,
+                    d.asset_id.clone(),
+                    d.description.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            inserted,
+            vec![
+                (1, 3, 1, "A".to_owned(), "First".to_owned()),
+                (2, i64::MAX - 1, 1, "B".to_owned(), "".to_owned()),
+                (3, 4, 2, "A".to_owned(), "Second".to_owned()),
+                (4, i64::MAX - 1, 3, "A".to_owned(), "Third".to_owned()),
+            ]
+        );
+        assert!(repo
+            .calls()
+            .contains(&"close_asset_descriptions_superseded_by"));
+    }
+
+    #[test]
+    fn should_rollback_asset_names_and_descriptions() {
+        let repo = Arc::new(RecordingRepo::default());
+
+        rollback(
+            repo.clone(),
+            DeletionsCache::default(),
+            DeletionsCache::default(),
+            &[],
+            1,
+        )
+        .unwrap();
+
+        let calls = repo.calls();
+        assert!(calls.contains(&"rollback_asset_names"));
+        assert!(calls.contains(&"reopen_asset_names_superseded_by"));
+        assert!(calls.contains(&"rollback_asset_descriptions"));
+        assert!(calls.contains(&"reopen_asset_descriptions_superseded_by"));
+    }
+
+    #[test]
+    fn should_skip_asset_with_invalid_issuer() {
+        let asset_details = |asset_id: u8, issuer: Vec<u8>| AssetDetails {
+            asset_id: vec![asset_id; 32],
+            issuer,
+            decimals: 8,
+            name: "Name".to_owned(),
+            volume: 1_000_000,
+            ..Default::default()
+        };
+        let tx = Tx {
+            id: "tx".to_owned(),
+            data: SignedTransaction::default(),
+            meta: TransactionMetadata::default(),
+            state_update: StateUpdate {
+                assets: vec![
+                    AssetStateUpdate {
+                        before: None,
+                        after: Some(asset_details(1, vec![3; 5])),
+                    },
+                    AssetStateUpdate {
+                        before: None,
+                        after: Some(asset_details(2, vec![3; 32])),
+                    },
+                ],
+                ..Default::default()
+            },
+        };
+        let mut append = empty_block(1);
+        append.txs = vec![tx];
+
+        let updates = extract_base_asset_info_updates(b'W', &append, &[]);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].id, bs58::encode(vec![2; 32]).into_string());
+    }
+
+    #[test]
+    fn should_take_out_leasing_timestamp_of_the_last_tx() {
+        let leasing_tx = |id: &str, timestamp: i64, out_before: i64, out_after: i64| Tx {
+            id: id.to_owned(),
+            data: SignedTransaction {
+                transaction: Some(Transaction::WavesTransaction(WavesTx {
+                    timestamp,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            },
+            meta: TransactionMetadata::default(),
+            state_update: StateUpdate {
+                leasing_for_address: vec![LeasingUpdate {
+                    address: vec![1; 26],
+                    out_before,
+                    out_after,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        };
+
+        let mut append = empty_block(1);
+        append.txs = vec![
+            leasing_tx("lease", 1_600_000_001_000, 0, 100),
+            leasing_tx("lease_cancel", 1_600_000_002_000, 100, 40),
+        ];
+
+        let updates = extract_out_leasing_updates(&append);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].new_amount, 40);
+        assert_eq!(updates[0].updated_at, millis_to_datetime(1_600_000_002_000));
+    }
+
+    #[test]
+    fn should_handle_asset_updates_of_ethereum_transactions() {
+        // issue made by an invoke of the Ethereum transaction
+        let ethereum_tx = |meta: TransactionMetadata| Tx {
+            id: "ethereum_tx".to_owned(),
+            data: SignedTransaction {
+                transaction: Some(Transaction::EthereumTransaction(vec![0xf8, 0x6b, 0x86])),
+                ..Default::default()
+            },
+            meta,
+            state_update: StateUpdate {
+                assets: vec![AssetStateUpdate {
+                    before: None,
+                    after: Some(AssetDetails {
+                        asset_id: vec![1; 32],
+                        issuer: vec![3; 32],
+                        decimals: 8,
+                        name: "Name".to_owned(),
+                        volume: 1_000_000,
+                        ..Default::default()
+                    }),
+                }],
+                ..Default::default()
+            },
+        };
+        let with_timestamp = TransactionMetadata {
+            metadata: Some(TxMetadata::Ethereum(EthereumMetadata {
+                timestamp: 1_650_000_000_000,
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+
+        let mut append = empty_block(1);
+        append.txs = vec![ethereum_tx(with_timestamp)];
+
+        let updates = extract_base_asset_info_updates(b'W', &append, &[]);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].id, bs58::encode(vec![1; 32]).into_string());
+        assert_eq!(updates[0].updated_at.timestamp(), 1_650_000_000);
+
+        // falls back to the block timestamp without the metadata
+        append.txs = vec![ethereum_tx(TransactionMetadata::default())];
+
+        let updates = extract_base_asset_info_updates(b'W', &append, &[]);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].updated_at.timestamp(), 1_600_000_000);
+    }
+
+    #[test]
+    fn should_extract_all_updates_of_ethereum_transactions() {
+        let oracle_address = vec![1; 26];
+        let oracle_addresses = vec![bs58::encode(&oracle_address).into_string()];
+        let asset_id = bs58::encode(vec![2; 32]).into_string();
+        let data_entry = |key: String, value: &str| DataEntryUpdate {
+            address: oracle_address.clone(),
+            data_entry: Some(DataEntry {
+                key,
+                value: Some(Value::StringValue(value.to_owned())),
+            }),
+            ..Default::default()
+        };
+
+        // invoke of the Ethereum transaction issuing the asset and setting its oracle data
+        let tx = Tx {
+            id: "ethereum_tx".to_owned(),
+            data: SignedTransaction {
+                transaction: Some(Transaction::EthereumTransaction(vec![0xf8, 0x6b, 0x86])),
+                ..Default::default()
+            },
+            meta: TransactionMetadata::default(),
+            state_update: StateUpdate {
+                assets: vec![AssetStateUpdate {
+                    before: None,
+                    after: Some(AssetDetails {
+                        asset_id: vec![2; 32],
+                        issuer: vec![3; 32],
+                        name: "Name".to_owned(),
+                        ..Default::default()
+                    }),
+                }],
+                data_entries: vec![
+                    data_entry(format!("description_<en>_<{}>", asset_id), "Description"),
+                    data_entry(format!("%s%s__assetId2ticker__{}", asset_id), "TKN"),
+                    data_entry(format!("%s%s__labels__{}", asset_id), "DEFI__GATEWAY"),
+                ],
+                ..Default::default()
+            },
+        };
+
+        let mut append = empty_block(1);
+        append.txs = vec![tx.clone()];
+        let asset_updates = extract_base_asset_info_updates(b'W', &append, &[]);
+        assert_eq!(asset_updates.len(), 1);
+        assert_eq!(asset_updates[0].id, asset_id);
+
+        let data_entries = extract_asset_related_data_entries_updates(
+            1,
+            append.time_stamp,
+            &tx,
+            &oracle_addresses,
+        );
+        assert_eq!(data_entries.len(), 3);
+        assert_eq!(data_entries[0].related_asset_id, Some(asset_id.clone()));
+        // without the metadata the block timestamp is taken
+        assert_eq!(data_entries[0].updated_at.timestamp(), 1_600_000_000);
+
+        let tickers = extract_asset_tickers_updates(1, &tx, &oracle_addresses, false);
+        assert_eq!(tickers.len(), 1);
+        assert_eq!(tickers[0].ticker, Some("TKN".to_owned()));
+
+        let labels = extract_asset_labels_updates(1, &tx, &oracle_addresses);
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].asset_id, asset_id);
+        assert_eq!(
+            labels[0].labels,
+            vec![AssetLabel::DeFi, AssetLabel::Gateway]
+        );
+    }
+
+    #[test]
+    fn should_delete_cached_data_of_vanished_assets_on_rollback() {
+        let deleted_asset = |uid, id: &str| DeletedAsset {
+            uid,
+            id: id.to_owned(),
+        };
+        let repo = Arc::new(RecordingRepo {
+            // "issued" was issued in the rolled back block, "updated" was only updated there
+            rolled_back_assets: vec![deleted_asset(10, "issued"), deleted_asset(11, "updated")],
+            current_asset_ids: vec!["updated".to_owned()],
+            ..Default::default()
+        });
+        let blockchain_data_cache = DeletionsCache::default();
+        let user_defined_data_cache = DeletionsCache::default();
+
+        let summary = rollback(
+            repo,
+            blockchain_data_cache.clone(),
+            user_defined_data_cache.clone(),
+            &[],
+            1,
+        )
+        .unwrap();
+
+        // published to the subscribers along with the assets of the handled updates
+        assert_eq!(summary.invalidated_asset_ids, vec!["issued"]);
+        assert_eq!(
+            *blockchain_data_cache.deleted.lock().unwrap(),
+            vec!["issued"]
+        );
+        assert_eq!(
+            *user_defined_data_cache.deleted.lock().unwrap(),
+            vec!["issued"]
+        );
+    }
+
+    #[test]
+    fn should_return_assets_invalidated_by_rollback() {
+        let repo = Arc::new(RecordingRepo {
+            rolled_back_assets: vec![DeletedAsset {
+                uid: 10,
+                id: "issued".to_owned(),
+            }],
+            ..Default::default()
+        });
+
+        let changed_asset_ids = handle_updates(
+            BlockchainUpdatesWithLastHeight {
+                last_height: 1,
+                updates: vec![BlockchainUpdate::Rollback("block1".to_owned())],
+            },
+            repo,
+            DeletionsCache::default(),
+            DeletionsCache::default(),
+            b'W',
+            &[],
+            false,
+            &[],
+            &tokio::sync::watch::channel(false).1,
+        )
+        .unwrap();
+
+        assert_eq!(changed_asset_ids, vec!["issued"]);
+    }
+
+    #[test]
+    fn should_rollback_to_height_in_transaction() {
+        let repo = Arc::new(RecordingRepo {
+            rolled_back_assets: vec![DeletedAsset {
+                uid: 10,
+                id: "issued".to_owned(),
+            }],
+            last_block_uid_below: Some(5),
+            last_block: Some(consumer_state(5, "block3", 3)),
+            ..Default::default()
+        });
+        let blockchain_data_cache = DeletionsCache::default();
+
+        let summary = rollback_to_height(
+            repo.clone(),
+            blockchain_data_cache.clone(),
+            DeletionsCache::default(),
+            &[],
+            3,
+        )
+        .unwrap();
+
+        assert_eq!(summary.deleted_rows[0], ("assets", 1));
+        assert_eq!(summary.deleted_rows.len(), 10);
+        assert_eq!(
+            *blockchain_data_cache.deleted.lock().unwrap(),
+            vec!["issued"]
+        );
+
+        let calls = repo.calls();
+        assert_eq!(
+            calls[..2],
+            ["try_lock_consumer", "get_last_block_uid_below"]
+        );
+        assert!(calls.contains(&"set_consumer_state"));
+        assert_eq!(calls.last(), Some(&"commit"));
+    }
+
+    #[test]
+    fn should_not_rollback_to_height_while_consumer_runs() {
+        let repo = Arc::new(RecordingRepo {
+            last_block_uid_below: Some(5),
+            locked_elsewhere: true,
+            ..Default::default()
+        });
+
+        let result = rollback_to_height(repo.clone(), UnreachableCache, UnreachableCache, &[], 3);
+
+        assert!(result.is_err());
+        assert_eq!(repo.calls(), vec!["try_lock_consumer"]);
+
+        // a missing height is not rolled back either
+        let repo = Arc::new(RecordingRepo::default());
+        let result = rollback_to_height(repo.clone(), UnreachableCache, UnreachableCache, &[], 3);
+
+        assert!(result.is_err());
+        assert!(!repo.calls().contains(&"commit"));
+    }
+
+    /// Source streaming a single empty batch per connection, failing after `connections` ones
+    #[derive(Clone)]
+    struct FlakySource {
+        connections: usize,
+        requested_heights: Arc<Mutex<Vec<u32>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl UpdatesSource for FlakySource {
+        async fn stream(
+            self,
+            from_height: u32,
+            _batch_max_size: usize,
+            _batch_max_time: chrono::Duration,
+        ) -> Result<tokio::sync::mpsc::Receiver<BlockchainUpdatesWithLastHeight>, AppError>
+        {
+            let mut requested_heights = self.requested_heights.lock().unwrap();
+            requested_heights.push(from_height);
+            if requested_heights.len() > self.connections {
+                return Err(AppError::StreamError("connection refused".to_owned()));
+            }
+
+            let (tx, rx) = tokio::sync::mpsc::channel(1);
+            tx.send(BlockchainUpdatesWithLastHeight {
+                last_height: from_height,
+                updates: vec![],
+            })
+            .await
+            .unwrap();
+            // the stream is closed once the sender is dropped
+            Ok(rx)
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordingPublisher {
+        published: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl InvalidationPublisher for RecordingPublisher {
+        fn publish(&self, asset_ids: &[String]) -> Result<(), AppError> {
+            self.published.lock().unwrap().extend_from_slice(asset_ids);
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordingCheckpoint {
+        state: Arc<Mutex<Option<ConsumerState>>>,
+    }
+
+    impl Checkpoint for RecordingCheckpoint {
+        fn get(&self) -> Result<Option<ConsumerState>, AppError> {
+            Ok(self.state.lock().unwrap().clone())
+        }
+
+        fn set(&self, state: &ConsumerState) -> Result<(), AppError> {
+            *self.state.lock().unwrap() = Some(state.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn should_return_changed_asset_ids() {
+        let asset_details = |asset_id: u8| AssetDetails {
+            asset_id: vec![asset_id; 32],
+            issuer: vec![3; 32],
+            decimals: 8,
+            name: "Name".to_owned(),
+            volume: 1_000_000,
+            ..Default::default()
+        };
+        let tx = Tx {
+            id: "tx".to_owned(),
+            data: SignedTransaction::default(),
+            meta: TransactionMetadata::default(),
+            state_update: StateUpdate {
+                assets: vec![2, 1]
+                    .into_iter()
+                    .map(|asset_id| AssetStateUpdate {
+                        before: None,
+                        after: Some(asset_details(asset_id)),
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+        };
+        let mut block = empty_block(2);
+        block.txs = vec![tx];
+
+        let updates = BlockchainUpdatesWithLastHeight {
+            last_height: 2,
+            updates: vec![
+                BlockchainUpdate::Block(empty_block(1)),
+                BlockchainUpdate::Block(block),
+            ],
+        };
+
+        let changed_asset_ids = handle_updates(
+            updates,
+            Arc::new(RecordingRepo::default()),
+            DeletionsCache::default(),
+            DeletionsCache::default(),
+            b'W',
+            &["3PAddr".to_owned()],
+            false,
+            &[],
+            &tokio::sync::watch::channel(false).1,
+        )
+        .unwrap();
+
+        assert_eq!(
+            changed_asset_ids,
+            vec![
+                bs58::encode(vec![1; 32]).into_string(),
+                bs58::encode(vec![2; 32]).into_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_double_reconnect_delay_up_to_cap() {
+        let backoff = ReconnectBackoff {
+            initial_delay: std::time::Duration::from_secs(1),
+            max_delay: std::time::Duration::from_secs(5),
+            max_attempts: 10,
+        };
+
+        let delays = (1..=5).map(|attempt| backoff.delay(attempt).as_secs());
+        assert_eq!(delays.collect::<Vec<_>>(), vec![1, 2, 4, 5, 5]);
+        assert_eq!(backoff.delay(100).as_secs(), 5);
+    }
+
+    #[tokio::test]
+    async fn should_reconnect_closed_stream() {
+        let repo = Arc::new(RecordingRepo {
+            prev_handled_height: Some(9),
+            ..Default::default()
+        });
+        let source = FlakySource {
+            connections: 2,
+            requested_heights: Arc::new(Mutex::new(vec![])),
+        };
+        let backoff = ReconnectBackoff {
+            initial_delay: std::time::Duration::from_millis(1),
+            max_delay: std::time::Duration::from_millis(2),
+            max_attempts: 2,
+        };
+
+        let publisher = RecordingPublisher::default();
+
+        let result = start(
+            1,
+            source.clone(),
+            repo.clone(),
+            DeletionsCache::default(),
+            DeletionsCache::default(),
+            10,
+            1,
+            b'W',
+            &[],
+            false,
+            &[],
+            None,
+            &backoff,
+            Some(publisher.clone()),
+            None::<RecordingCheckpoint>,
+            Default::default(),
+            tokio::sync::watch::channel(false).1,
+        )
+        .await;
+
+        // both streams were resumed after the rolled back height,
+        // the error is returned after 2 failed reconnections following the second closure
+        assert!(result.is_err());
+        assert_eq!(*source.requested_heights.lock().unwrap(), vec![10; 4]);
+        let rollbacks = repo
+            .calls()
+            .into_iter()
+            .filter(|call| *call == "rollback_blocks_microblocks")
+            .count();
+        assert_eq!(rollbacks, 4);
+    }
+
+    #[tokio::test]
+    async fn should_resubscribe_after_last_handled_block() {
+        let repo = Arc::new(RecordingRepo {
+            consumer_state: Some(consumer_state(3, "block3", 3)),
+            last_block: Some(consumer_state(3, "block3", 3)),
+            prev_handled_height: Some(2),
+            ..Default::default()
+        });
+        let source = FlakySource {
+            connections: 2,
+            requested_heights: Arc::new(Mutex::new(vec![])),
+        };
+        let backoff = ReconnectBackoff {
+            initial_delay: std::time::Duration::from_millis(1),
+            max_delay: std::time::Duration::from_millis(2),
+            max_attempts: 1,
+        };
+
+        let result = start(
+            1,
+            source.clone(),
+            repo.clone(),
+            DeletionsCache::default(),
+            DeletionsCache::default(),
+            10,
+            1,
+            b'W',
+            &[],
+            false,
+            &[],
+            None,
+            &backoff,
+            None::<RecordingPublisher>,
+            None::<RecordingCheckpoint>,
+            Default::default(),
+            tokio::sync::watch::channel(false).1,
+        )
+        .await;
+
+        // the clean state is resumed after every closure without rolling back the handled height
+        assert!(result.is_err());
+        assert_eq!(*source.requested_heights.lock().unwrap(), vec![4; 3]);
+        assert!(!repo.calls().contains(&"rollback_blocks_microblocks"));
+    }
+
+    /// Source streaming a batch of the blocks from the requested height, closing the stream after it
+    #[derive(Clone)]
+    struct BlocksSource {
+        blocks_count: u32,
+    }
+
+    #[async_trait::async_trait]
+    impl UpdatesSource for BlocksSource {
+        async fn stream(
+            self,
+            from_height: u32,
+            _batch_max_size: usize,
+            _batch_max_time: chrono::Duration,
+        ) -> Result<tokio::sync::mpsc::Receiver<BlockchainUpdatesWithLastHeight>, AppError>
+        {
+            let last_height = from_height + self.blocks_count - 1;
+            let (tx, rx) = tokio::sync::mpsc::channel(1);
+            tx.send(BlockchainUpdatesWithLastHeight {
+                last_height,
+                updates: (from_height..=last_height)
+                    .map(|h| BlockchainUpdate::Block(empty_block(h)))
+                    .collect(),
+            })
+            .await
+            .unwrap();
+            Ok(rx)
+        }
+    }
+
+    #[tokio::test]
+    async fn should_rollback_batch_handled_at_shutdown() {
+        let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+        let repo = Arc::new(RecordingRepo {
+            shutdown_on_insert_blocks: Some(shutdown_tx),
+            ..Default::default()
+        });
+        let backoff = ReconnectBackoff {
+            initial_delay: std::time::Duration::from_millis(1),
+            max_delay: std::time::Duration::from_millis(1),
+            max_attempts: 0,
+        };
+
+        let result = start(
+            1,
+            BlocksSource { blocks_count: 3 },
+            repo.clone(),
+            DeletionsCache::default(),
+            DeletionsCache::default(),
+            10,
+            1,
+            b'W',
+            &[],
+            false,
+            &[],
+            None,
+            &backoff,
+            None::<RecordingPublisher>,
+            None::<RecordingCheckpoint>,
+            Default::default(),
+            shutdown_rx,
+        )
+        .await;
+
+        // stopped cleanly without committing the batch, so the last committed height is kept
+        assert!(result.is_ok());
+        let calls = repo.calls();
+        assert!(calls.contains(&"insert_blocks_or_microblocks"));
+        assert!(calls.contains(&"rollback"));
+        assert!(!calls.contains(&"commit"));
+    }
+
+    #[tokio::test]
+    async fn should_checkpoint_last_handled_block() {
+        let repo = Arc::new(RecordingRepo {
+            last_block: Some(consumer_state(3, "block3", 3)),
+            ..Default::default()
+        });
+        let backoff = ReconnectBackoff {
+            initial_delay: std::time::Duration::from_millis(1),
+            max_delay: std::time::Duration::from_millis(1),
+            max_attempts: 0,
+        };
+        let checkpoint = RecordingCheckpoint::default();
+
+        let _ = start(
+            1,
+            BlocksSource { blocks_count: 3 },
+            repo.clone(),
+            DeletionsCache::default(),
+            DeletionsCache::default(),
+            10,
+            1,
+            b'W',
+            &[],
+            false,
+            &[],
+            None,
+            &backoff,
+            None::<RecordingPublisher>,
+            Some(checkpoint.clone()),
+            Default::default(),
+            tokio::sync::watch::channel(false).1,
+        )
+        .await;
+
+        assert!(repo.calls().contains(&"commit"));
+        assert_eq!(
+            *checkpoint.state.lock().unwrap(),
+            Some(consumer_state(3, "block3", 3))
+        );
+    }
+
+    #[test]
+    fn should_resume_from_checkpoint_without_reading_postgres() {
+        let repo = Arc::new(RecordingRepo::default());
+        let checkpoint = RecordingCheckpoint::default();
+        checkpoint
+            .set(&consumer_state(12, "microblock10", 10))
+            .unwrap();
+
+        let resumed = resume_height(
+            repo.clone(),
+            UnreachableCache,
+            UnreachableCache,
+            &[],
+            1,
+            false,
+            Some(&checkpoint),
+        );
+
+        assert_eq!(
+            resumed.unwrap(),
+            ResumePoint {
+                from_height: 11,
+                expected_reference: Some("microblock10".to_owned()),
+                rollback: None,
+                checkpointed: Some(consumer_state(12, "microblock10", 10)),
+            }
+        );
+        assert!(repo.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_resume_from_postgres_if_checkpoint_is_stale() {
+        let last_block = consumer_state(3, "block3", 3);
+        let repo = Arc::new(RecordingRepo {
+            consumer_state: Some(last_block.clone()),
+            last_block: Some(last_block),
+            ..Default::default()
+        });
+        let backoff = ReconnectBackoff {
+            initial_delay: std::time::Duration::from_millis(1),
+            max_delay: std::time::Duration::from_millis(1),
+            max_attempts: 0,
+        };
+        // e.g. saved by the consumer of a database restored from a backup since
+        let checkpoint = RecordingCheckpoint::default();
+        checkpoint.set(&consumer_state(5, "block5", 5)).unwrap();
+        let progress = Arc::new(health::Progress::default());
+
+        let _ = start(
+            1,
+            BlocksSource { blocks_count: 3 },
+            repo.clone(),
+            DeletionsCache::default(),
+            DeletionsCache::default(),
+            10,
+            1,
+            b'W',
+            &[],
+            false,
+            &[],
+            None,
+            &backoff,
+            None::<RecordingPublisher>,
+            Some(checkpoint),
+            progress.clone(),
+            tokio::sync::watch::channel(false).1,
+        )
+        .await;
+
+        // the batch streamed after the checkpoint is dropped, the one after the last stored block is handled
+        let calls = repo.calls();
+        assert!(calls.contains(&"get_consumer_state"));
+        assert_eq!(calls.iter().filter(|c| **c == "commit").count(), 1);
+        assert_eq!(progress.handled_height(), 6);
+    }
+
+    fn consumer_state(block_uid: i64, block_id: &str, height: i32) -> ConsumerState {
+        ConsumerState {
+            block_uid,
+            block_id: block_id.to_owned(),
+            height,
+        }
+    }
+
+    #[test]
+    fn should_resume_after_last_handled_block_if_state_is_clean() {
+        let repo = Arc::new(RecordingRepo {
+            prev_handled_height: Some(9),
+            consumer_state: Some(consumer_state(12, "microblock10", 10)),
+            last_block: Some(consumer_state(12, "microblock10", 10)),
+            ..Default::default()
+        });
+
+        let resumed = resume_height(
+            repo.clone(),
+            UnreachableCache,
+            UnreachableCache,
+            &[],
+            1,
+            false,
+            None::<&RecordingCheckpoint>,
+        );
+
+        assert_eq!(
+            resumed.unwrap(),
+            ResumePoint {
+                from_height: 11,
+                expected_reference: Some("microblock10".to_owned()),
+                rollback: None,
+                checkpointed: None,
+            }
+        );
+        assert!(!repo.calls().contains(&"rollback_blocks_microblocks"));
+    }
+
+    #[test]
+    fn should_rollback_last_height_if_state_is_dirty() {
+        // crashed after storing blocks without the state or the state is missing at all
+        for state in [Some(consumer_state(11, "block10", 10)), None] {
+            let repo = Arc::new(RecordingRepo {
+                prev_handled_height: Some(9),
+                consumer_state: state,
+                last_block: Some(consumer_state(12, "microblock10", 10)),
+                changed_asset_uids: vec![101, 102],
+                ..Default::default()
+            });
+
+            let resumed = resume_height(
+                repo.clone(),
+                DeletionsCache::default(),
+                DeletionsCache::default(),
+                &[],
+                1,
+                false,
+                None::<&RecordingCheckpoint>,
+            );
+
+            // the rollback scope is reported on start
+            assert_eq!(
+                resumed.unwrap(),
+                ResumePoint {
+                    from_height: 10,
+                    expected_reference: None,
+                    rollback: Some(StartupRollback {
+                        rolled_back_to_height: 9,
+                        affected_assets: 2,
+                        invalidated_asset_ids: vec![],
+                    }),
+                    checkpointed: None,
+                }
+            );
+            assert!(repo.calls().contains(&"rollback_blocks_microblocks"));
+            assert!(repo.calls().contains(&"set_consumer_state"));
+        }
+
+        assert_eq!(clean_state(None, None), None);
+    }
+
+    #[test]
+    fn should_rollback_last_height_after_chain_rollback() {
+        let state = consumer_state(12, "microblock10", 10);
+        assert_eq!(
+            clean_state(Some(state.clone()), Some(&state)),
+            Some(state.clone())
+        );
+
+        // the node switched to a fork while the consumer was stopped
+        let mut next_block = empty_block(11);
+        next_block.reference = Some("fork10".to_owned());
+        assert!(!continues_from(
+            &state.block_id,
+            &[BlockchainUpdate::Block(next_block.clone())]
+        ));
+
+        next_block.reference = Some(state.block_id.clone());
+        assert!(continues_from(
+            &state.block_id,
+            &[BlockchainUpdate::Block(next_block)]
+        ));
+
+        let repo = Arc::new(RecordingRepo {
+            prev_handled_height: Some(9),
+            consumer_state: Some(state.clone()),
+            last_block: Some(state),
+            ..Default::default()
+        });
+
+        let resumed = resume_height(
+            repo.clone(),
+            DeletionsCache::default(),
+            DeletionsCache::default(),
+            &[],
+            1,
+            true,
+            None::<&RecordingCheckpoint>,
+        );
+
+        assert_eq!(resumed.unwrap().from_height, 10);
+        assert!(!repo.calls().contains(&"get_consumer_state"));
+        assert!(repo.calls().contains(&"rollback_blocks_microblocks"));
     }
 }
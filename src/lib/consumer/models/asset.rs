@@ -45,6 +45,39 @@ impl Hash for InsertableAsset {
     }
 }
 
+/// Values of the current version of an asset, the ones compared with the updates
+#[derive(Clone, Debug, PartialEq, Queryable)]
+pub struct CurrentAsset {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub issuer: String,
+    pub precision: i32,
+    pub smart: bool,
+    pub nft: bool,
+    pub quantity: i64,
+    pub reissuable: bool,
+    pub min_sponsored_fee: Option<i64>,
+}
+
+/// The time of the update is not compared, a version written at another time is still the same
+impl From<&BaseAssetInfoUpdate> for CurrentAsset {
+    fn from(update: &BaseAssetInfoUpdate) -> Self {
+        Self {
+            id: update.id.clone(),
+            name: update.name.clone(),
+            description: update.description.clone(),
+            issuer: update.issuer.clone(),
+            precision: update.precision,
+            smart: update.smart,
+            nft: update.nft,
+            quantity: update.quantity,
+            reissuable: update.reissuable,
+            min_sponsored_fee: update.min_sponsored_fee,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct AssetOverride {
     pub superseded_by: i64,
@@ -134,6 +167,7 @@ pub struct OracleDataEntry {
     pub bool_val: Option<bool>,
     pub int_val: Option<i64>,
     pub str_val: Option<String>,
+    pub uid: i64,
 }
 
 impl From<&OracleDataEntry> for AssetOracleDataEntry {
@@ -147,6 +181,7 @@ impl From<&OracleDataEntry> for AssetOracleDataEntry {
             bool_val: de.bool_val,
             int_val: de.int_val,
             str_val: de.str_val.clone(),
+            uid: de.uid,
         }
     }
 }
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+use crate::schema::consumer_state;
+
+/// The last block or microblock handled by the consumer
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, Insertable, Queryable)]
+#[table_name = "consumer_state"]
+pub struct ConsumerState {
+    pub block_uid: i64,
+    pub block_id: String,
+    pub height: i32,
+}
@@ -37,6 +37,58 @@ impl Hash for InsertableDataEntry {
     }
 }
 
+/// Values of the current version of a data entry, the ones compared with the updates
+#[derive(Clone, Debug, PartialEq, Queryable, QueryableByName)]
+#[table_name = "data_entries"]
+pub struct CurrentDataEntry {
+    pub address: String,
+    pub key: String,
+    pub data_type: Option<DataEntryValueType>,
+    pub bin_val: Option<Vec<u8>>,
+    pub bool_val: Option<bool>,
+    pub int_val: Option<i64>,
+    pub str_val: Option<String>,
+    pub related_asset_id: Option<String>,
+}
+
+impl From<&DataEntryUpdate> for CurrentDataEntry {
+    fn from(update: &DataEntryUpdate) -> Self {
+        let (data_type, bin_val, bool_val, int_val, str_val) = match &update.value {
+            Some(DataEntryValue::BinVal(v)) => (
+                Some(DataEntryValueType::Bin),
+                Some(v.to_owned()),
+                None,
+                None,
+                None,
+            ),
+            Some(DataEntryValue::BoolVal(v)) => {
+                (Some(DataEntryValueType::Bool), None, Some(*v), None, None)
+            }
+            Some(DataEntryValue::IntVal(v)) => {
+                (Some(DataEntryValueType::Int), None, None, Some(*v), None)
+            }
+            Some(DataEntryValue::StrVal(v)) => (
+                Some(DataEntryValueType::Str),
+                None,
+                None,
+                None,
+                Some(v.to_owned()),
+            ),
+            None => (None, None, None, None, None),
+        };
+        Self {
+            address: update.address.clone(),
+            key: update.key.clone(),
+            data_type,
+            bin_val,
+            bool_val,
+            int_val,
+            str_val,
+            related_asset_id: update.related_asset_id.clone(),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct DataEntryOverride {
     pub superseded_by: i64,
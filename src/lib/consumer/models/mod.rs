@@ -1,7 +1,11 @@
 pub mod asset;
+pub mod asset_descriptions;
 pub mod asset_labels;
+pub mod asset_names;
 pub mod asset_tickers;
 pub mod block_microblock;
+pub mod consumer_state;
 pub mod data_entry;
 pub mod issuer_balance;
 pub mod out_leasing;
+pub mod supply_change;
@@ -0,0 +1,13 @@
+use crate::schema::supply_changes;
+
+/// Change of the asset quantity by issue, reissue or burn
+#[derive(Clone, Debug, Insertable, PartialEq)]
+#[table_name = "supply_changes"]
+pub struct InsertableSupplyChange {
+    pub block_uid: i64,
+    pub asset_id: String,
+    pub height: i32,
+    pub prev_quantity: i64,
+    pub new_quantity: i64,
+    pub change: i64,
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::cache::{CacheKeyFn, SyncReadCache, SyncWriteCache};
+use crate::error::Error as AppError;
+
+/// Cache keeping the writes of the batch transaction until it is committed,
+/// so that the rolled back batches leave the wrapped cache intact
+///
+/// Reads see the pending writes. Clones share them.
+#[derive(Clone)]
+pub struct PendingWritesCache<C, T> {
+    cache: C,
+    /// Written values by key, `None` for the deleted keys
+    pending: Arc<Mutex<HashMap<String, Option<T>>>>,
+}
+
+impl<C, T> PendingWritesCache<C, T>
+where
+    C: SyncWriteCache<T>,
+    T: Clone,
+{
+    pub fn new(cache: C) -> Self {
+        Self {
+            cache,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Applies the pending writes to the wrapped cache
+    pub fn flush(&self) -> Result<(), AppError> {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+
+        let mut values = vec![];
+        for (key, value) in pending {
+            match value {
+                Some(value) => values.push((key, value)),
+                None => self.cache.del(&key)?,
+            }
+        }
+
+        if !values.is_empty() {
+            self.cache.mset(values)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<C: CacheKeyFn, T> CacheKeyFn for PendingWritesCache<C, T> {
+    fn key_fn(&self, source_key: &str) -> String {
+        self.cache.key_fn(source_key)
+    }
+}
+
+impl<C, T> SyncReadCache<T> for PendingWritesCache<C, T>
+where
+    C: SyncReadCache<T>,
+    T: Clone,
+{
+    fn get(&self, key: &str) -> Result<Option<T>, AppError> {
+        if let Some(value) = self.pending.lock().unwrap().get(key) {
+            return Ok(value.clone());
+        }
+        self.cache.get(key)
+    }
+
+    fn mget(&self, keys: &[&str]) -> Result<Vec<Option<T>>, AppError> {
+        let pending = self.pending.lock().unwrap();
+
+        let missing_keys = keys
+            .iter()
+            .filter(|key| !pending.contains_key(**key))
+            .copied()
+            .collect::<Vec<_>>();
+        let mut cached = if missing_keys.is_empty() {
+            vec![]
+        } else {
+            self.cache.mget(&missing_keys)?
+        }
+        .into_iter();
+
+        Ok(keys
+            .iter()
+            .map(|key| match pending.get(*key) {
+                Some(value) => value.clone(),
+                None => cached.next().flatten(),
+            })
+            .collect())
+    }
+}
+
+impl<C, T> SyncWriteCache<T> for PendingWritesCache<C, T>
+where
+    C: SyncWriteCache<T>,
+    T: Clone,
+{
+    fn set(&self, key: &str, value: T) -> Result<(), AppError> {
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(key.to_owned(), Some(value));
+        Ok(())
+    }
+
+    fn mset(&self, entries: Vec<(String, T)>) -> Result<(), AppError> {
+        let mut pending = self.pending.lock().unwrap();
+        for (key, value) in entries {
+            pending.insert(key, Some(value));
+        }
+        Ok(())
+    }
+
+    fn del(&self, key: &str) -> Result<(), AppError> {
+        self.pending.lock().unwrap().insert(key.to_owned(), None);
+        Ok(())
+    }
+
+    /// Not deferred, the consumer never clears the cache
+    fn clear(&self) -> Result<(), AppError> {
+        self.pending.lock().unwrap().clear();
+        self.cache.clear()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PendingWritesCache;
+    use crate::cache::{SyncReadCache, SyncWriteCache};
+    use crate::consumer::tests::DeletionsCache;
+
+    #[test]
+    fn should_write_to_cache_on_flush_only() {
+        let cache = DeletionsCache::default();
+        let pending = PendingWritesCache::new(cache.clone());
+
+        pending.set("a", "value".to_owned()).unwrap();
+        pending.del("b").unwrap();
+
+        // the pending writes are read back before the flush
+        assert_eq!(
+            pending.mget(&["a", "b", "c"]).unwrap(),
+            vec![Some("value".to_owned()), None, None]
+        );
+        assert!(cache.deleted.lock().unwrap().is_empty());
+
+        // a rolled back batch drops its cache along with the writes
+        let rolled_back = PendingWritesCache::<_, String>::new(cache.clone());
+        rolled_back.del("c").unwrap();
+        drop(rolled_back);
+
+        pending.flush().unwrap();
+        assert_eq!(*cache.deleted.lock().unwrap(), vec!["b".to_owned()]);
+    }
+}
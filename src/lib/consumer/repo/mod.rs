@@ -3,20 +3,30 @@ pub mod pg;
 use anyhow::Result;
 
 use super::models::asset::{
-    AssetOverride, DeletedAsset, InsertableAsset, OracleDataEntry, QueryableAsset,
+    AssetOverride, CurrentAsset, DeletedAsset, InsertableAsset, OracleDataEntry, QueryableAsset,
+};
+use super::models::asset_descriptions::{
+    AssetDescription, AssetDescriptionOverride, DeletedAssetDescription, InsertableAssetDescription,
 };
 use super::models::asset_labels::{
     AssetLabels, AssetLabelsOverride, DeletedAssetLabels, InsertableAssetLabels,
 };
+use super::models::asset_names::{
+    AssetName, AssetNameOverride, DeletedAssetName, InsertableAssetName,
+};
 use super::models::asset_tickers::{
     AssetTicker, AssetTickerOverride, DeletedAssetTicker, InsertableAssetTicker,
 };
 use super::models::block_microblock::BlockMicroblock;
-use super::models::data_entry::{DataEntryOverride, DeletedDataEntry, InsertableDataEntry};
+use super::models::consumer_state::ConsumerState;
+use super::models::data_entry::{
+    CurrentDataEntry, DataEntryOverride, DeletedDataEntry, InsertableDataEntry,
+};
 use super::models::issuer_balance::{
     CurrentIssuerBalance, DeletedIssuerBalance, InsertableIssuerBalance, IssuerBalanceOverride,
 };
 use super::models::out_leasing::{DeletedOutLeasing, InsertableOutLeasing, OutLeasingOverride};
+use super::models::supply_change::InsertableSupplyChange;
 use super::PrevHandledHeight;
 
 #[async_trait::async_trait]
@@ -29,6 +39,25 @@ pub trait Repo {
 
     fn get_prev_handled_height(&self) -> Result<Option<PrevHandledHeight>>;
 
+    /// First block of the height, carrying its squashed microblocks
+    fn get_handled_height(&self, height: i32) -> Result<Option<PrevHandledHeight>>;
+
+    /// Uid of the last block or microblock below the height
+    fn get_last_block_uid_below(&self, height: i32) -> Result<Option<i64>>;
+
+    /// Takes the session advisory lock of the consumer, returns `false` if another session holds it
+    fn try_lock_consumer(&self) -> Result<bool>;
+
+    fn get_consumer_state(&self) -> Result<Option<ConsumerState>>;
+
+    fn set_consumer_state(&self, state: &ConsumerState) -> Result<()>;
+
+    /// The latest row of `blocks_microblocks` as the consumer state
+    fn get_last_block(&self) -> Result<Option<ConsumerState>>;
+
+    /// Refreshes the `searchable_assets` materialized view without blocking the search
+    fn refresh_searchable_assets(&self) -> Result<()>;
+
     fn get_block_uid(&self, block_id: &str) -> Result<i64>;
 
     fn get_key_block_uid(&self) -> Result<i64>;
@@ -41,13 +70,14 @@ pub trait Repo {
 
     fn delete_microblocks(&self) -> Result<()>;
 
-    fn rollback_blocks_microblocks(&self, block_uid: &i64) -> Result<()>;
+    fn rollback_blocks_microblocks(&self, block_uid: &i64) -> Result<usize>;
 
     //
     // ASSETS
     //
 
-    fn get_current_waves_quantity(&self) -> Result<i64>;
+    /// Current versions of the existing assets among the given ones
+    fn get_current_assets(&self, ids: &[&str]) -> Result<Vec<CurrentAsset>>;
 
     fn get_next_assets_uid(&self) -> Result<i64>;
 
@@ -65,12 +95,15 @@ pub trait Repo {
 
     fn assets_gt_block_uid(&self, block_uid: &i64) -> Result<Vec<i64>>;
 
+    /// Returns those of `ids` having the current version
+    fn current_asset_ids(&self, ids: &[&str]) -> Result<Vec<String>>;
+
     fn mget_assets(&self, uids: &[i64]) -> Result<Vec<Option<QueryableAsset>>>;
 
     fn assets_oracle_data_entries(
         &self,
         asset_ids: &[&str],
-        oracle_address: &str,
+        oracle_addresses: &[String],
     ) -> Result<Vec<OracleDataEntry>>;
 
     fn issuer_assets(&self, issuer_address: impl AsRef<str>) -> Result<Vec<QueryableAsset>>;
@@ -115,10 +148,62 @@ pub trait Repo {
 
     fn rollback_asset_tickers(&self, block_uid: &i64) -> Result<Vec<DeletedAssetTicker>>;
 
+    //
+    // ASSET NAMES
+    //
+
+    fn mget_asset_names(&self, asset_ids: &[&str]) -> Result<Vec<AssetName>>;
+
+    fn get_next_asset_names_uid(&self) -> Result<i64>;
+
+    fn insert_asset_names(&self, updates: &Vec<InsertableAssetName>) -> Result<()>;
+
+    fn update_asset_names_block_references(&self, block_uid: &i64) -> Result<()>;
+
+    fn close_asset_names_superseded_by(&self, updates: &Vec<AssetNameOverride>) -> Result<()>;
+
+    fn reopen_asset_names_superseded_by(&self, current_superseded_by: &Vec<i64>) -> Result<()>;
+
+    fn set_asset_names_next_update_uid(&self, new_uid: i64) -> Result<()>;
+
+    fn rollback_asset_names(&self, block_uid: &i64) -> Result<Vec<DeletedAssetName>>;
+
+    //
+    // ASSET DESCRIPTIONS
+    //
+
+    fn mget_asset_descriptions(&self, asset_ids: &[&str]) -> Result<Vec<AssetDescription>>;
+
+    fn get_next_asset_descriptions_uid(&self) -> Result<i64>;
+
+    fn insert_asset_descriptions(&self, updates: &Vec<InsertableAssetDescription>) -> Result<()>;
+
+    fn update_asset_descriptions_block_references(&self, block_uid: &i64) -> Result<()>;
+
+    fn close_asset_descriptions_superseded_by(
+        &self,
+        updates: &Vec<AssetDescriptionOverride>,
+    ) -> Result<()>;
+
+    fn reopen_asset_descriptions_superseded_by(
+        &self,
+        current_superseded_by: &Vec<i64>,
+    ) -> Result<()>;
+
+    fn set_asset_descriptions_next_update_uid(&self, new_uid: i64) -> Result<()>;
+
+    fn rollback_asset_descriptions(&self, block_uid: &i64) -> Result<Vec<DeletedAssetDescription>>;
+
     //
     // DATA ENTRIES
     //
 
+    /// Current versions of the existing data entries among the given address and key pairs
+    fn get_current_data_entries(
+        &self,
+        address_keys: &[(&str, &str)],
+    ) -> Result<Vec<CurrentDataEntry>>;
+
     fn get_next_data_entries_uid(&self) -> Result<i64>;
 
     fn insert_data_entries(&self, balances: &Vec<InsertableDataEntry>) -> Result<()>;
@@ -173,4 +258,14 @@ pub trait Repo {
     fn set_out_leasings_next_update_uid(&self, new_uid: i64) -> Result<()>;
 
     fn rollback_out_leasings(&self, block_uid: &i64) -> Result<Vec<DeletedOutLeasing>>;
+
+    //
+    // SUPPLY CHANGES
+    //
+
+    fn insert_supply_changes(&self, changes: &Vec<InsertableSupplyChange>) -> Result<()>;
+
+    fn update_supply_changes_block_references(&self, block_uid: &i64) -> Result<()>;
+
+    fn rollback_supply_changes(&self, block_uid: &i64) -> Result<usize>;
 }
@@ -1,4 +1,5 @@
 use anyhow::{Error, Result};
+use chrono::Utc;
 use diesel::dsl::sql;
 use diesel::pg::PgConnection;
 use diesel::sql_types::{Array, BigInt, Bool, Text, VarChar};
@@ -9,31 +10,41 @@ use super::super::models::asset_labels::{
     AssetLabels, AssetLabelsOverride, DeletedAssetLabels, InsertableAssetLabels,
 };
 use super::super::models::{
-    asset::{AssetOverride, DeletedAsset, InsertableAsset, QueryableAsset},
+    asset::{AssetOverride, CurrentAsset, DeletedAsset, InsertableAsset, QueryableAsset},
     block_microblock::BlockMicroblock,
-    data_entry::{DataEntryOverride, DeletedDataEntry, InsertableDataEntry},
+    consumer_state::ConsumerState,
+    data_entry::{CurrentDataEntry, DataEntryOverride, DeletedDataEntry, InsertableDataEntry},
     issuer_balance::{
         CurrentIssuerBalance, DeletedIssuerBalance, InsertableIssuerBalance, IssuerBalanceOverride,
     },
     out_leasing::{DeletedOutLeasing, InsertableOutLeasing, OutLeasingOverride},
+    supply_change::InsertableSupplyChange,
 };
 use super::super::PrevHandledHeight;
 use super::Repo;
+use crate::consumer::models::asset_descriptions::{
+    AssetDescription, AssetDescriptionOverride, DeletedAssetDescription, InsertableAssetDescription,
+};
+use crate::consumer::models::asset_names::{
+    AssetName, AssetNameOverride, DeletedAssetName, InsertableAssetName,
+};
 use crate::consumer::models::asset_tickers::{
     AssetTicker, AssetTickerOverride, DeletedAssetTicker, InsertableAssetTicker,
 };
 use crate::db::enums::DataEntryValueTypeMapping;
 use crate::error::Error as AppError;
 use crate::schema::{
-    asset_labels, asset_labels_uid_seq, asset_tickers, asset_tickers_uid_seq, assets,
-    assets_uid_seq, blocks_microblocks, data_entries, data_entries_uid_seq, issuer_balances,
-    issuer_balances_uid_seq, out_leasings, out_leasings_uid_seq,
+    asset_descriptions, asset_descriptions_uid_seq, asset_labels, asset_labels_uid_seq,
+    asset_names, asset_names_uid_seq, asset_tickers, asset_tickers_uid_seq, assets, assets_uid_seq,
+    blocks_microblocks, consumer_state, data_entries, data_entries_uid_seq, issuer_balances,
+    issuer_balances_uid_seq, out_leasings, out_leasings_uid_seq, supply_changes,
 };
 use crate::tuple_len::TupleLen;
-use crate::waves::WAVES_ID;
 
 const MAX_UID: i64 = std::i64::MAX - 1;
 const PG_MAX_INSERT_FIELDS_COUNT: usize = 65535;
+/// Key of the advisory lock held by the running consumer
+const CONSUMER_LOCK_KEY: i64 = 0x6173_7365_7473;
 
 pub struct PgRepoImpl {
     conn: PgConnection,
@@ -67,6 +78,92 @@ impl Repo for PgRepoImpl {
             .map_err(|err| Error::new(AppError::DbDieselError(err)))
     }
 
+    fn get_handled_height(&self, height: i32) -> Result<Option<PrevHandledHeight>> {
+        blocks_microblocks::table
+            .select((blocks_microblocks::uid, blocks_microblocks::height))
+            .filter(blocks_microblocks::height.eq(height))
+            .order(blocks_microblocks::uid.asc())
+            .first(&self.conn)
+            .optional()
+            .map_err(|err| Error::new(AppError::DbDieselError(err)))
+    }
+
+    fn get_last_block_uid_below(&self, height: i32) -> Result<Option<i64>> {
+        blocks_microblocks::table
+            .select(blocks_microblocks::uid)
+            .filter(blocks_microblocks::height.lt(height))
+            .order(blocks_microblocks::uid.desc())
+            .first(&self.conn)
+            .optional()
+            .map_err(|err| Error::new(AppError::DbDieselError(err)))
+    }
+
+    fn try_lock_consumer(&self) -> Result<bool> {
+        diesel::select(sql::<Bool>(&format!(
+            "pg_try_advisory_lock({})",
+            CONSUMER_LOCK_KEY
+        )))
+        .get_result(&self.conn)
+        .map_err(|err| {
+            let context = format!("Cannot lock consumer: {}", err);
+            Error::new(AppError::DbDieselError(err)).context(context)
+        })
+    }
+
+    fn get_consumer_state(&self) -> Result<Option<ConsumerState>> {
+        consumer_state::table
+            .select((
+                consumer_state::block_uid,
+                consumer_state::block_id,
+                consumer_state::height,
+            ))
+            .first(&self.conn)
+            .optional()
+            .map_err(|err| Error::new(AppError::DbDieselError(err)))
+    }
+
+    fn set_consumer_state(&self, state: &ConsumerState) -> Result<()> {
+        diesel::insert_into(consumer_state::table)
+            .values(state)
+            .on_conflict(consumer_state::id)
+            .do_update()
+            .set((
+                consumer_state::block_uid.eq(state.block_uid),
+                consumer_state::block_id.eq(&state.block_id),
+                consumer_state::height.eq(state.height),
+                consumer_state::updated_at.eq(Utc::now()),
+            ))
+            .execute(&self.conn)
+            .map(|_| ())
+            .map_err(|err| {
+                let context = format!("Cannot set consumer state: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+
+    fn get_last_block(&self) -> Result<Option<ConsumerState>> {
+        blocks_microblocks::table
+            .select((
+                blocks_microblocks::uid,
+                blocks_microblocks::id,
+                blocks_microblocks::height,
+            ))
+            .order(blocks_microblocks::uid.desc())
+            .first(&self.conn)
+            .optional()
+            .map_err(|err| Error::new(AppError::DbDieselError(err)))
+    }
+
+    fn refresh_searchable_assets(&self) -> Result<()> {
+        sql_query("REFRESH MATERIALIZED VIEW CONCURRENTLY searchable_assets")
+            .execute(&self.conn)
+            .map(|_| ())
+            .map_err(|err| {
+                let context = format!("Cannot refresh searchable assets: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+
     fn get_block_uid(&self, block_id: &str) -> Result<i64> {
         blocks_microblocks::table
             .select(blocks_microblocks::uid)
@@ -136,11 +233,10 @@ impl Repo for PgRepoImpl {
             })
     }
 
-    fn rollback_blocks_microblocks(&self, block_uid: &i64) -> Result<()> {
+    fn rollback_blocks_microblocks(&self, block_uid: &i64) -> Result<usize> {
         diesel::delete(blocks_microblocks::table)
             .filter(blocks_microblocks::uid.gt(block_uid))
             .execute(&self.conn)
-            .map(|_| ())
             .map_err(|err| {
                 let context = format!("Cannot rollback blocks/microblocks: {}", err);
                 Error::new(AppError::DbDieselError(err)).context(context)
@@ -151,14 +247,25 @@ impl Repo for PgRepoImpl {
     // ASSETS
     //
 
-    fn get_current_waves_quantity(&self) -> Result<i64> {
+    fn get_current_assets(&self, ids: &[&str]) -> Result<Vec<CurrentAsset>> {
         assets::table
-            .select(assets::quantity)
+            .select((
+                assets::id,
+                assets::name,
+                assets::description,
+                assets::issuer,
+                assets::precision,
+                assets::smart,
+                assets::nft,
+                assets::quantity,
+                assets::reissuable,
+                assets::min_sponsored_fee,
+            ))
             .filter(assets::superseded_by.eq(MAX_UID))
-            .filter(assets::id.eq(WAVES_ID))
-            .first(&self.conn)
+            .filter(assets::id.eq_any(ids))
+            .get_results(&self.conn)
             .map_err(|err| {
-                let context = format!("Cannot get current waves quantity: {}", err);
+                let context = format!("Cannot get current assets: {}", err);
                 Error::new(AppError::DbDieselError(err)).context(context)
             })
     }
@@ -279,6 +386,19 @@ impl Repo for PgRepoImpl {
             })
     }
 
+    fn current_asset_ids(&self, ids: &[&str]) -> Result<Vec<String>> {
+        assets::table
+            .select(assets::id)
+            .distinct()
+            .filter(assets::superseded_by.eq(MAX_UID))
+            .filter(assets::id.eq_any(ids))
+            .get_results(&self.conn)
+            .map_err(|err| {
+                let context = format!("Cannot get current assets ids: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+
     fn mget_assets(&self, uids: &[i64]) -> Result<Vec<Option<QueryableAsset>>> {
         let q = sql_query("SELECT 
             a.id,
@@ -313,7 +433,7 @@ impl Repo for PgRepoImpl {
     fn assets_oracle_data_entries(
         &self,
         asset_ids: &[&str],
-        oracle_address: &str,
+        oracle_addresses: &[String],
     ) -> Result<Vec<OracleDataEntry>> {
         let q = data_entries::table
             .select((
@@ -325,9 +445,10 @@ impl Repo for PgRepoImpl {
                 data_entries::bool_val,
                 data_entries::int_val,
                 data_entries::str_val,
+                data_entries::uid,
             ))
             .filter(data_entries::superseded_by.eq(MAX_UID))
-            .filter(data_entries::address.eq(oracle_address))
+            .filter(data_entries::address.eq_any(oracle_addresses))
             .filter(data_entries::related_asset_id.eq_any(asset_ids))
             .filter(data_entries::data_type.is_not_null());
 
@@ -607,10 +728,272 @@ impl Repo for PgRepoImpl {
             })
     }
 
+    //
+    // ASSET NAMES
+    //
+
+    fn mget_asset_names(&self, asset_ids: &[&str]) -> Result<Vec<AssetName>> {
+        let q = asset_names::table
+            .select((asset_names::asset_id, asset_names::name))
+            .filter(asset_names::superseded_by.eq(MAX_UID))
+            .filter(asset_names::asset_id.eq_any(asset_ids));
+
+        q.load(&self.conn).map_err(|err| {
+            let context = format!("Cannot get asset names: {}", err);
+            Error::new(AppError::DbDieselError(err)).context(context)
+        })
+    }
+
+    fn get_next_asset_names_uid(&self) -> Result<i64> {
+        asset_names_uid_seq::table
+            .select(asset_names_uid_seq::last_value)
+            .first(&self.conn)
+            .map_err(|err| {
+                let context = format!("Cannot get next asset names update uid: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+
+    fn close_asset_names_superseded_by(&self, updates: &Vec<AssetNameOverride>) -> Result<()> {
+        let mut asset_ids = vec![];
+        let mut superseded_by_uids = vec![];
+
+        updates.iter().for_each(|u| {
+            asset_ids.push(&u.asset_id);
+            superseded_by_uids.push(&u.superseded_by);
+        });
+
+        let q = diesel::sql_query("UPDATE asset_names SET superseded_by = updates.superseded_by FROM (SELECT UNNEST($1::text[]) as asset_id, UNNEST($2::int8[]) as superseded_by) AS updates WHERE asset_names.asset_id = updates.asset_id AND asset_names.superseded_by = $3;")
+            .bind::<Array<VarChar>, _>(asset_ids)
+            .bind::<Array<BigInt>, _>(superseded_by_uids)
+            .bind::<BigInt, _>(MAX_UID);
+
+        q.execute(&self.conn).map(|_| ()).map_err(|err| {
+            let context = format!("Cannot close asset_names superseded_by: {}", err);
+            Error::new(AppError::DbDieselError(err)).context(context)
+        })
+    }
+
+    fn insert_asset_names(&self, updates: &Vec<InsertableAssetName>) -> Result<()> {
+        let columns_count = asset_names::table::all_columns().len();
+        let chunk_size = (PG_MAX_INSERT_FIELDS_COUNT / columns_count) / 10 * 10;
+        updates
+            .to_owned()
+            .chunks(chunk_size)
+            .into_iter()
+            .try_fold((), |_, chunk| {
+                diesel::insert_into(asset_names::table)
+                    .values(chunk)
+                    .execute(&self.conn)
+                    .map(|_| ())
+            })
+            .map_err(|err| {
+                let context = format!("Cannot insert new asset names: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+
+    fn set_asset_names_next_update_uid(&self, new_uid: i64) -> Result<()> {
+        diesel::sql_query(format!(
+            "select setval('asset_names_uid_seq', {}, false);", // 3rd param - is called; in case of true, value'll be incremented before returning
+            new_uid
+        ))
+        .execute(&self.conn)
+        .map(|_| ())
+        .map_err(|err| {
+            let context = format!("Cannot set asset_names next update uid: {}", err);
+            Error::new(AppError::DbDieselError(err)).context(context)
+        })
+    }
+
+    fn rollback_asset_names(&self, block_uid: &i64) -> Result<Vec<DeletedAssetName>> {
+        diesel::delete(asset_names::table)
+            .filter(asset_names::block_uid.gt(block_uid))
+            .returning((asset_names::uid, asset_names::asset_id))
+            .get_results(&self.conn)
+            .map(|bs| {
+                bs.into_iter()
+                    .map(|(uid, asset_id)| DeletedAssetName { uid, asset_id })
+                    .collect()
+            })
+            .map_err(|err| {
+                let context = format!("Cannot rollback asset_names: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+
+    fn reopen_asset_names_superseded_by(&self, current_superseded_by: &Vec<i64>) -> Result<()> {
+        diesel::sql_query("UPDATE asset_names SET superseded_by = $1 FROM (SELECT UNNEST($2) AS superseded_by) AS current WHERE asset_names.superseded_by = current.superseded_by;")
+            .bind::<BigInt, _>(MAX_UID)
+            .bind::<Array<BigInt>, _>(current_superseded_by)
+            .execute(&self.conn)
+            .map(|_| ())
+            .map_err(|err| {
+                let context = format!("Cannot reopen asset_names superseded_by: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+
+    fn update_asset_names_block_references(&self, block_uid: &i64) -> Result<()> {
+        diesel::update(asset_names::table)
+            .set((asset_names::block_uid.eq(block_uid),))
+            .filter(asset_names::block_uid.gt(block_uid))
+            .execute(&self.conn)
+            .map(|_| ())
+            .map_err(|err| {
+                let context = format!("Cannot update asset_names block references: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+
+    //
+    // ASSET DESCRIPTIONS
+    //
+
+    fn mget_asset_descriptions(&self, asset_ids: &[&str]) -> Result<Vec<AssetDescription>> {
+        let q = asset_descriptions::table
+            .select((
+                asset_descriptions::asset_id,
+                asset_descriptions::description,
+            ))
+            .filter(asset_descriptions::superseded_by.eq(MAX_UID))
+            .filter(asset_descriptions::asset_id.eq_any(asset_ids));
+
+        q.load(&self.conn).map_err(|err| {
+            let context = format!("Cannot get asset descriptions: {}", err);
+            Error::new(AppError::DbDieselError(err)).context(context)
+        })
+    }
+
+    fn get_next_asset_descriptions_uid(&self) -> Result<i64> {
+        asset_descriptions_uid_seq::table
+            .select(asset_descriptions_uid_seq::last_value)
+            .first(&self.conn)
+            .map_err(|err| {
+                let context = format!("Cannot get next asset descriptions update uid: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+
+    fn close_asset_descriptions_superseded_by(
+        &self,
+        updates: &Vec<AssetDescriptionOverride>,
+    ) -> Result<()> {
+        let mut asset_ids = vec![];
+        let mut superseded_by_uids = vec![];
+
+        updates.iter().for_each(|u| {
+            asset_ids.push(&u.asset_id);
+            superseded_by_uids.push(&u.superseded_by);
+        });
+
+        let q = diesel::sql_query("UPDATE asset_descriptions SET superseded_by = updates.superseded_by FROM (SELECT UNNEST($1::text[]) as asset_id, UNNEST($2::int8[]) as superseded_by) AS updates WHERE asset_descriptions.asset_id = updates.asset_id AND asset_descriptions.superseded_by = $3;")
+            .bind::<Array<VarChar>, _>(asset_ids)
+            .bind::<Array<BigInt>, _>(superseded_by_uids)
+            .bind::<BigInt, _>(MAX_UID);
+
+        q.execute(&self.conn).map(|_| ()).map_err(|err| {
+            let context = format!("Cannot close asset_descriptions superseded_by: {}", err);
+            Error::new(AppError::DbDieselError(err)).context(context)
+        })
+    }
+
+    fn insert_asset_descriptions(&self, updates: &Vec<InsertableAssetDescription>) -> Result<()> {
+        let columns_count = asset_descriptions::table::all_columns().len();
+        let chunk_size = (PG_MAX_INSERT_FIELDS_COUNT / columns_count) / 10 * 10;
+        updates
+            .to_owned()
+            .chunks(chunk_size)
+            .into_iter()
+            .try_fold((), |_, chunk| {
+                diesel::insert_into(asset_descriptions::table)
+                    .values(chunk)
+                    .execute(&self.conn)
+                    .map(|_| ())
+            })
+            .map_err(|err| {
+                let context = format!("Cannot insert new asset descriptions: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+
+    fn set_asset_descriptions_next_update_uid(&self, new_uid: i64) -> Result<()> {
+        diesel::sql_query(format!(
+            "select setval('asset_descriptions_uid_seq', {}, false);", // 3rd param - is called; in case of true, value'll be incremented before returning
+            new_uid
+        ))
+        .execute(&self.conn)
+        .map(|_| ())
+        .map_err(|err| {
+            let context = format!("Cannot set asset_descriptions next update uid: {}", err);
+            Error::new(AppError::DbDieselError(err)).context(context)
+        })
+    }
+
+    fn rollback_asset_descriptions(&self, block_uid: &i64) -> Result<Vec<DeletedAssetDescription>> {
+        diesel::delete(asset_descriptions::table)
+            .filter(asset_descriptions::block_uid.gt(block_uid))
+            .returning((asset_descriptions::uid, asset_descriptions::asset_id))
+            .get_results(&self.conn)
+            .map(|bs| {
+                bs.into_iter()
+                    .map(|(uid, asset_id)| DeletedAssetDescription { uid, asset_id })
+                    .collect()
+            })
+            .map_err(|err| {
+                let context = format!("Cannot rollback asset_descriptions: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+
+    fn reopen_asset_descriptions_superseded_by(
+        &self,
+        current_superseded_by: &Vec<i64>,
+    ) -> Result<()> {
+        diesel::sql_query("UPDATE asset_descriptions SET superseded_by = $1 FROM (SELECT UNNEST($2) AS superseded_by) AS current WHERE asset_descriptions.superseded_by = current.superseded_by;")
+            .bind::<BigInt, _>(MAX_UID)
+            .bind::<Array<BigInt>, _>(current_superseded_by)
+            .execute(&self.conn)
+            .map(|_| ())
+            .map_err(|err| {
+                let context = format!("Cannot reopen asset_descriptions superseded_by: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+
+    fn update_asset_descriptions_block_references(&self, block_uid: &i64) -> Result<()> {
+        diesel::update(asset_descriptions::table)
+            .set((asset_descriptions::block_uid.eq(block_uid),))
+            .filter(asset_descriptions::block_uid.gt(block_uid))
+            .execute(&self.conn)
+            .map(|_| ())
+            .map_err(|err| {
+                let context = format!("Cannot update asset_descriptions block references: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+
     //
     // DATA ENTRIES
     //
 
+    fn get_current_data_entries(
+        &self,
+        address_keys: &[(&str, &str)],
+    ) -> Result<Vec<CurrentDataEntry>> {
+        let (addresses, keys): (Vec<&str>, Vec<&str>) = address_keys.iter().copied().unzip();
+
+        diesel::sql_query("SELECT address, key, data_type, bin_val, bool_val, int_val, str_val, related_asset_id FROM data_entries WHERE superseded_by = $3 AND (address, key) IN (SELECT UNNEST($1::text[]), UNNEST($2::text[]));")
+            .bind::<Array<VarChar>, _>(addresses)
+            .bind::<Array<VarChar>, _>(keys)
+            .bind::<BigInt, _>(MAX_UID)
+            .load(&self.conn)
+            .map_err(|err| {
+                let context = format!("Cannot get current data entries: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+
     fn get_next_data_entries_uid(&self) -> Result<i64> {
         data_entries_uid_seq::table
             .select(data_entries_uid_seq::last_value)
@@ -941,4 +1324,151 @@ impl Repo for PgRepoImpl {
                 Error::new(AppError::DbDieselError(err)).context(context)
             })
     }
+
+    //
+    // SUPPLY CHANGES
+    //
+
+    fn insert_supply_changes(&self, changes: &Vec<InsertableSupplyChange>) -> Result<()> {
+        let columns_count = supply_changes::table::all_columns().len();
+        let chunk_size = (PG_MAX_INSERT_FIELDS_COUNT / columns_count) / 10 * 10;
+        changes
+            .chunks(chunk_size)
+            .into_iter()
+            .try_fold((), |_, chunk| {
+                diesel::insert_into(supply_changes::table)
+                    .values(chunk)
+                    .execute(&self.conn)
+                    .map(|_| ())
+            })
+            .map_err(|err| {
+                let context = format!("Cannot insert new supply changes: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+
+    fn update_supply_changes_block_references(&self, block_uid: &i64) -> Result<()> {
+        diesel::update(supply_changes::table)
+            .set((supply_changes::block_uid.eq(block_uid),))
+            .filter(supply_changes::block_uid.gt(block_uid))
+            .execute(&self.conn)
+            .map(|_| ())
+            .map_err(|err| {
+                let context = format!("Cannot update supply changes block references: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+
+    fn rollback_supply_changes(&self, block_uid: &i64) -> Result<usize> {
+        diesel::delete(supply_changes::table)
+            .filter(supply_changes::block_uid.gt(block_uid))
+            .execute(&self.conn)
+            .map_err(|err| {
+                let context = format!("Cannot rollback supply changes: {}", err);
+                Error::new(AppError::DbDieselError(err)).context(context)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Error;
+    use diesel::dsl::sql;
+    use diesel::sql_types::BigInt;
+    use diesel::{sql_query, Connection, RunQueryDsl};
+    use std::sync::Arc;
+
+    use super::{new, MAX_UID};
+    use crate::consumer::rollback_to_height;
+    use crate::consumer::tests::DeletionsCache;
+    use crate::{config, db};
+
+    /// Uids and heights above the ones of the real data, so that only the fixtures are rolled back
+    const KEPT_BLOCK_UID: i64 = 9_000_000_000_000_000_001;
+    const ROLLED_BACK_BLOCK_UID: i64 = 9_000_000_000_000_000_002;
+    const KEPT_HEIGHT: i32 = 2_000_000_001;
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_delete_rows_of_blocks_from_height() {
+        let config = config::postgres::load().unwrap();
+        let repo = Arc::new(new(db::unpooled(&config).unwrap()));
+        let conn = &repo.conn;
+
+        conn.test_transaction::<_, Error, _>(|| {
+            sql_query(format!(
+                "INSERT INTO blocks_microblocks (uid, id, height, time_stamp) VALUES
+                    ({kept}, 'test_rollback_block_1', {height}, 0),
+                    ({rolled_back}, 'test_rollback_block_2', {height} + 1, 0)",
+                kept = KEPT_BLOCK_UID,
+                rolled_back = ROLLED_BACK_BLOCK_UID,
+                height = KEPT_HEIGHT,
+            ))
+            .execute(conn)?;
+
+            let row = |columns: &str| {
+                format!(
+                    "{uid}, {max_uid}, {uid}, {columns}",
+                    uid = ROLLED_BACK_BLOCK_UID,
+                    max_uid = MAX_UID,
+                    columns = columns
+                )
+            };
+            let fixtures = [
+                ("assets (uid, superseded_by, block_uid, id, name, description, time_stamp, issuer, precision, smart, nft, quantity, reissuable, min_sponsored_fee)",
+                    row("'test_rollback_asset', 'name', '', now(), 'issuer', 0, false, false, 1, false, NULL")),
+                ("asset_labels (uid, superseded_by, block_uid, asset_id, labels)",
+                    row("'test_rollback_asset', ARRAY['TEST']")),
+                ("asset_tickers (uid, superseded_by, block_uid, asset_id, ticker)",
+                    row("'test_rollback_asset', 'TST'")),
+                ("asset_names (uid, superseded_by, block_uid, asset_id, name)",
+                    row("'test_rollback_asset', 'name'")),
+                ("asset_descriptions (uid, superseded_by, block_uid, asset_id, description)",
+                    row("'test_rollback_asset', 'description'")),
+                ("data_entries (uid, superseded_by, block_uid, address, key, data_type, int_val)",
+                    row("'test_rollback_address', 'key', 'int', 1")),
+                ("issuer_balances (uid, superseded_by, block_uid, address, regular_balance)",
+                    row("'issuer', 1")),
+                ("out_leasings (uid, superseded_by, block_uid, address, amount)",
+                    row("'issuer', 1")),
+            ];
+            for (table, values) in fixtures.iter() {
+                sql_query(format!("INSERT INTO {} VALUES ({})", table, values)).execute(conn)?;
+            }
+            sql_query(format!(
+                "INSERT INTO supply_changes (uid, block_uid, asset_id, height, prev_quantity, new_quantity, change)
+                    VALUES ({uid}, {uid}, 'test_rollback_asset', {height} + 1, 0, 1, 1)",
+                uid = ROLLED_BACK_BLOCK_UID,
+                height = KEPT_HEIGHT,
+            ))
+            .execute(conn)?;
+
+            let summary = rollback_to_height(
+                repo.clone(),
+                DeletionsCache::default(),
+                DeletionsCache::default(),
+                &[],
+                KEPT_HEIGHT + 1,
+            )?;
+
+            assert_eq!(summary.affected_assets, 1);
+            for (table, deleted) in summary.deleted_rows {
+                assert_eq!(deleted, 1, "deleted rows of {}", table);
+
+                let uid_column = if table == "blocks_microblocks" {
+                    "uid"
+                } else {
+                    "block_uid"
+                };
+                let left: i64 = diesel::select(sql::<BigInt>(&format!(
+                    "(SELECT count(*) FROM {} WHERE {} > {})",
+                    table, uid_column, KEPT_BLOCK_UID
+                )))
+                .get_result(conn)?;
+                assert_eq!(left, 0, "rows of {} left", table);
+            }
+
+            Ok(())
+        });
+    }
 }
@@ -23,7 +23,7 @@ use waves_protobuf_schemas::waves::{
     Block as BlockPB, SignedMicroBlock as SignedMicroBlockPB,
     SignedTransaction as SignedTransactionPB,
 };
-use wavesexchange_log::error;
+use wavesexchange_log::{error, info};
 
 use super::{
     BlockMicroblockAppend, BlockchainUpdate, BlockchainUpdatesWithLastHeight, Tx, UpdatesSource,
@@ -95,12 +95,17 @@ impl UpdatesSourceImpl {
         let batch_max_wait_time = batch_max_wait_time.to_std().unwrap();
 
         loop {
-            if let Some(SubscribeEventPB {
-                update: Some(update),
-            }) = stream
+            let message = stream
                 .message()
                 .await
                 .map_err(|s| AppError::StreamError(s.to_string()))?
+                .ok_or_else(|| {
+                    AppError::StreamClosed("GRPC Stream was closed by the server".to_string())
+                })?;
+
+            if let SubscribeEventPB {
+                update: Some(update),
+            } = message
             {
                 last_height = update.height as u32;
                 match BlockchainUpdate::try_from(update) {
@@ -138,6 +143,125 @@ impl UpdatesSourceImpl {
     }
 }
 
+/// Effective size of the updates batches
+///
+/// Doubles while the batches arrive full, up to the max size, and halves down to 1
+/// after the short batches, which arrive once the node catches up with the chain head.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdaptiveBatchSize {
+    current: usize,
+    max: usize,
+}
+
+impl AdaptiveBatchSize {
+    pub fn new(max: usize) -> Self {
+        Self {
+            current: 1,
+            max: max.max(1),
+        }
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Adjusts the size to the length of the last batch
+    pub fn observe(&mut self, batch_len: usize) {
+        self.current = if batch_len >= self.current {
+            (self.current * 2).min(self.max)
+        } else {
+            (self.current / 2).max(1)
+        };
+    }
+}
+
+/// Updates source merging the single-update batches of the wrapped one into the batches
+/// of the adaptive size, the requested batch size being the max one
+///
+/// Passes the batches of the wrapped source through when disabled.
+#[derive(Clone)]
+pub struct AdaptiveBatchesSource<S> {
+    source: S,
+    enabled: bool,
+}
+
+impl<S> AdaptiveBatchesSource<S> {
+    pub fn new(source: S, enabled: bool) -> Self {
+        Self { source, enabled }
+    }
+}
+
+#[async_trait]
+impl<S> UpdatesSource for AdaptiveBatchesSource<S>
+where
+    S: UpdatesSource + Send + Sync + 'static,
+{
+    async fn stream(
+        self,
+        from_height: u32,
+        batch_max_size: usize,
+        batch_max_wait_time: Duration,
+    ) -> Result<Receiver<BlockchainUpdatesWithLastHeight>, AppError> {
+        if !self.enabled {
+            return self
+                .source
+                .stream(from_height, batch_max_size, batch_max_wait_time)
+                .await;
+        }
+
+        let rx = self
+            .source
+            .stream(from_height, 1, batch_max_wait_time)
+            .await?;
+        let (tx, merged_rx) = channel::<BlockchainUpdatesWithLastHeight>(1);
+
+        tokio::spawn(merge_batches(
+            rx,
+            tx,
+            AdaptiveBatchSize::new(batch_max_size),
+            batch_max_wait_time.to_std().unwrap(),
+        ));
+
+        Ok(merged_rx)
+    }
+}
+
+/// Merges the received batches until the current size is reached, the max wait time passes
+/// or a microblock or rollback is received
+async fn merge_batches(
+    mut rx: Receiver<BlockchainUpdatesWithLastHeight>,
+    tx: Sender<BlockchainUpdatesWithLastHeight>,
+    mut size: AdaptiveBatchSize,
+    max_wait_time: std::time::Duration,
+) {
+    while let Some(mut merged) = rx.recv().await {
+        let deadline = tokio::time::Instant::now() + max_wait_time;
+
+        while merged.updates.len() < size.current()
+            && matches!(merged.updates.last(), Some(BlockchainUpdate::Block(_)))
+        {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(batch)) => {
+                    merged.last_height = batch.last_height;
+                    merged.updates.extend(batch.updates);
+                }
+                // the stream is closed or the node is not ahead anymore
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        let previous_size = size.current();
+        size.observe(merged.updates.len());
+        if size.current() != previous_size {
+            info!("updates batch size changed to {}", size.current());
+        }
+
+        if tx.send(merged).await.is_err() {
+            return;
+        }
+    }
+}
+
 impl TryFrom<BlockchainUpdatedPB> for BlockchainUpdate {
     type Error = AppError;
 
@@ -192,7 +316,12 @@ impl TryFrom<BlockchainUpdatedPB> for BlockchainUpdate {
                     Some(BodyPB::Block(BlockAppendPB {
                         block:
                             Some(BlockPB {
-                                header: Some(HeaderPB { timestamp, .. }),
+                                header:
+                                    Some(HeaderPB {
+                                        timestamp,
+                                        reference,
+                                        ..
+                                    }),
                                 ..
                             }),
                         updated_waves_amount,
@@ -200,6 +329,7 @@ impl TryFrom<BlockchainUpdatedPB> for BlockchainUpdate {
                         id: bs58::encode(&value.id).into_string(),
                         time_stamp: Some(timestamp),
                         height: height as u32,
+                        reference: Some(bs58::encode(&reference).into_string()),
                         updated_waves_amount: if updated_waves_amount > 0 {
                             Some(updated_waves_amount)
                         } else {
@@ -215,6 +345,7 @@ impl TryFrom<BlockchainUpdatedPB> for BlockchainUpdate {
                         id: bs58::encode(&total_block_id).into_string(),
                         time_stamp: None,
                         height: height as u32,
+                        reference: None,
                         updated_waves_amount: None,
                         state_update,
                         txs,
@@ -231,3 +362,91 @@ impl TryFrom<BlockchainUpdatedPB> for BlockchainUpdate {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::channel;
+
+    use super::{merge_batches, AdaptiveBatchSize};
+    use crate::consumer::{
+        BlockMicroblockAppend, BlockchainUpdate, BlockchainUpdatesWithLastHeight,
+    };
+
+    fn block(height: u32) -> BlockchainUpdate {
+        BlockchainUpdate::Block(BlockMicroblockAppend {
+            id: format!("block{}", height),
+            time_stamp: Some(1_600_000_000_000),
+            height,
+            reference: Some(format!("block{}", height - 1)),
+            updated_waves_amount: None,
+            state_update: Default::default(),
+            txs: vec![],
+        })
+    }
+
+    fn batch(heights: std::ops::RangeInclusive<u32>) -> BlockchainUpdatesWithLastHeight {
+        BlockchainUpdatesWithLastHeight {
+            last_height: *heights.end(),
+            updates: heights.map(block).collect(),
+        }
+    }
+
+    #[test]
+    fn should_grow_batch_size_while_batches_are_full() {
+        let mut size = AdaptiveBatchSize::new(8);
+
+        // historical sync fills every batch, then the node catches up with the chain head
+        let sizes = [1, 2, 4, 8, 8, 3, 1, 1, 0]
+            .iter()
+            .map(|batch_len| {
+                size.observe(*batch_len);
+                size.current()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(sizes, vec![2, 4, 8, 8, 4, 2, 1, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn should_merge_batches_up_to_adaptive_size() {
+        let microblock = BlockchainUpdate::Microblock(BlockMicroblockAppend {
+            id: "microblock8".to_owned(),
+            time_stamp: None,
+            height: 8,
+            reference: None,
+            updated_waves_amount: None,
+            state_update: Default::default(),
+            txs: vec![],
+        });
+
+        let (tx, rx) = channel(16);
+        for height in 1..=8 {
+            tx.send(batch(height..=height)).await.unwrap();
+        }
+        tx.send(BlockchainUpdatesWithLastHeight {
+            last_height: 8,
+            updates: vec![microblock],
+        })
+        .await
+        .unwrap();
+        tx.send(batch(9..=9)).await.unwrap();
+        drop(tx);
+
+        let (merged_tx, mut merged_rx) = channel(16);
+        merge_batches(
+            rx,
+            merged_tx,
+            AdaptiveBatchSize::new(4),
+            std::time::Duration::from_secs(1),
+        )
+        .await;
+
+        let mut merged = vec![];
+        while let Some(batch) = merged_rx.recv().await {
+            merged.push((batch.updates.len(), batch.last_height));
+        }
+
+        // the microblock flushes the batch, the last one is flushed on close
+        assert_eq!(merged, vec![(1, 1), (2, 3), (4, 7), (2, 8), (1, 9)]);
+    }
+}
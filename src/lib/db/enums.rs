@@ -1,6 +1,6 @@
 use diesel_derive_enum::DbEnum;
 
-#[derive(DbEnum, Clone, Debug)]
+#[derive(DbEnum, Clone, Debug, PartialEq)]
 pub enum DataEntryValueType {
     Bin,
     Bool,
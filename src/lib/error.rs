@@ -24,6 +24,8 @@ pub enum Error {
     StreamClosed(String),
     #[error("StreamError: {0}")]
     StreamError(String),
+    #[error("ShutdownRequested")]
+    ShutdownRequested,
     #[error("ConsistencyError: {0}")]
     ConsistencyError(String),
     #[error("UpstreamAPIBadResponse: {0}")]
@@ -52,6 +54,26 @@ pub enum Error {
     Bb8RunError(String),
     #[error("Request error: {0}")]
     ApiCustomError(String),
+    #[error("NotFound: {0}")]
+    NotFound(String),
+    #[error("UnresolvedAssets: {0:?}")]
+    UnresolvedAssets(std::collections::HashMap<String, String>),
+}
+
+impl Error {
+    /// Whether the cache could not be reached, as opposed to holding an undecodable value
+    pub fn is_cache_unavailable(&self) -> bool {
+        match self {
+            Error::Bb8RunError(_) => true,
+            Error::RedisError(e) => {
+                e.is_io_error()
+                    || e.is_connection_refusal()
+                    || e.is_connection_dropped()
+                    || e.is_timeout()
+            }
+            _ => false,
+        }
+    }
 }
 
 impl Reject for Error {}
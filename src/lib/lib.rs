@@ -10,6 +10,7 @@ pub mod config;
 pub mod consumer;
 pub mod db;
 pub mod error;
+pub mod metrics;
 pub mod models;
 pub mod schema;
 pub mod services;
@@ -0,0 +1,98 @@
+use lazy_static::lazy_static;
+use prometheus::{Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts};
+
+use crate::db::PgPool;
+
+lazy_static! {
+    // Consumer
+    pub static ref LAST_HANDLED_HEIGHT: IntGauge = IntGauge::new(
+        "asset_search_consumer_last_handled_height",
+        "Last handled blockchain height"
+    )
+    .unwrap();
+    pub static ref UPDATES_PER_BATCH: Histogram = Histogram::with_opts(
+        HistogramOpts::new(
+            "asset_search_consumer_updates_per_batch",
+            "Blockchain updates count per handled batch"
+        )
+        .buckets(vec![1.0, 4.0, 16.0, 64.0, 256.0, 1024.0])
+    )
+    .unwrap();
+    pub static ref BATCH_HANDLE_DURATION: Histogram = Histogram::with_opts(HistogramOpts::new(
+        "asset_search_consumer_batch_handle_duration_seconds",
+        "Duration of blockchain updates batch handling"
+    ))
+    .unwrap();
+    pub static ref UPDATES_PROCESSED: IntCounter = IntCounter::new(
+        "asset_search_consumer_updates_total",
+        "Handled blockchain updates"
+    )
+    .unwrap();
+    pub static ref LAST_HANDLED_BLOCK_TIMESTAMP: IntGauge = IntGauge::new(
+        "asset_search_consumer_last_handled_block_timestamp_seconds",
+        "Timestamp of the last handled block, the lag is `time() - value`"
+    )
+    .unwrap();
+    pub static ref ROLLBACKS: IntCounter =
+        IntCounter::new("asset_search_consumer_rollbacks_total", "Handled rollbacks").unwrap();
+    pub static ref CACHE_SET_FAILURES: IntCounter = IntCounter::new(
+        "asset_search_consumer_cache_set_failures_total",
+        "Failed cache updates"
+    )
+    .unwrap();
+
+    // API
+    pub static ref API_REQUESTS: IntCounterVec = IntCounterVec::new(
+        Opts::new("asset_search_api_requests_total", "API requests per route"),
+        &["route"]
+    )
+    .unwrap();
+    pub static ref DB_POOL_CONNECTIONS: IntGauge = IntGauge::new(
+        "asset_search_db_pool_connections",
+        "Connections currently held by the DB pool"
+    )
+    .unwrap();
+    pub static ref CACHE_READS: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "asset_search_cache_reads_total",
+            "Cache reads per key by result (hit, miss, absent, decode_error)"
+        ),
+        &["cache", "result"]
+    )
+    .unwrap();
+    pub static ref DB_POOL_IDLE_CONNECTIONS: IntGauge = IntGauge::new(
+        "asset_search_db_pool_idle_connections",
+        "Idle connections of the DB pool"
+    )
+    .unwrap();
+}
+
+/// Metrics updated after each handled batch of blockchain updates
+#[derive(Clone)]
+pub struct BatchMetrics {
+    pub last_handled_height: IntGauge,
+    pub updates_per_batch: Histogram,
+    pub updates_processed: IntCounter,
+    pub batch_handle_duration: Histogram,
+    pub last_handled_block_timestamp: IntGauge,
+}
+
+/// The registered consumer metrics
+impl Default for BatchMetrics {
+    fn default() -> Self {
+        Self {
+            last_handled_height: LAST_HANDLED_HEIGHT.clone(),
+            updates_per_batch: UPDATES_PER_BATCH.clone(),
+            updates_processed: UPDATES_PROCESSED.clone(),
+            batch_handle_duration: BATCH_HANDLE_DURATION.clone(),
+            last_handled_block_timestamp: LAST_HANDLED_BLOCK_TIMESTAMP.clone(),
+        }
+    }
+}
+
+/// Updates DB pool utilization gauges with the current pool state
+pub fn observe_db_pool(pg_pool: &PgPool) {
+    let state = pg_pool.state();
+    DB_POOL_CONNECTIONS.set(state.connections as i64);
+    DB_POOL_IDLE_CONNECTIONS.set(state.idle_connections as i64);
+}
@@ -1,6 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 
 use crate::db::enums::DataEntryValueType;
 use crate::waves::{WAVES_ID, WAVES_NAME, WAVES_PRECISION};
@@ -28,13 +29,170 @@ pub struct Asset {
     pub ticker: Option<String>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct AssetMetadata {
-    pub labels: Vec<String>,
+    pub labels: Vec<AssetLabel>,
     pub sponsor_balance: Option<AssetSponsorBalance>,
     pub oracles_data: HashMap<String, Vec<AssetOracleDataEntry>>,
 }
 
+impl AssetMetadata {
+    /// Status of the last written status entry of the oracles, unknown if there is none
+    ///
+    /// The same rule as the `verified_status__in` filter of the search.
+    pub fn verification_status(&self) -> VerificationStatus {
+        self.oracles_data
+            .values()
+            .flatten()
+            .filter(|entry| entry.key.starts_with(VerificationStatus::KEY_PREFIX))
+            .max_by_key(|entry| entry.uid)
+            .and_then(|entry| entry.int_val)
+            .map(VerificationStatus::from_code)
+            .unwrap_or(VerificationStatus::Unknown)
+    }
+}
+
+/// Asset verification status published by the oracle as the `status_<asset_id>` integer entry
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationStatus {
+    Verified,
+    Declined,
+    Unknown,
+}
+
+impl VerificationStatus {
+    pub const KEY_PREFIX: &'static str = "status_";
+
+    /// Codes other than the verified and declined ones are unknown
+    pub fn from_code(code: i64) -> Self {
+        match code {
+            2 => Self::Verified,
+            -2 => Self::Declined,
+            _ => Self::Unknown,
+        }
+    }
+
+    pub fn code(&self) -> i64 {
+        match self {
+            Self::Verified => 2,
+            Self::Declined => -2,
+            Self::Unknown => 0,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Verified => "verified",
+            Self::Declined => "declined",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+/// Label of the asset set by the admins or by the labels oracle
+///
+/// Labels unknown to the service are kept as `Other`. Serialized as the uppercase label, e.g. `DEFI`.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(from = "String", into = "String")]
+pub enum AssetLabel {
+    CommunityVerified,
+    DeFi,
+    Gateway,
+    Qualified,
+    Stablecoin,
+    WaVerified,
+    Other(String),
+}
+
+impl AssetLabel {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::CommunityVerified => "COMMUNITY_VERIFIED",
+            Self::DeFi => "DEFI",
+            Self::Gateway => "GATEWAY",
+            Self::Qualified => "QUALIFIED",
+            Self::Stablecoin => "STABLECOIN",
+            Self::WaVerified => "WA_VERIFIED",
+            Self::Other(label) => label,
+        }
+    }
+}
+
+/// The only place the labels are normalized: trimmed and uppercased
+impl From<&str> for AssetLabel {
+    fn from(label: &str) -> Self {
+        let label = label.trim().to_uppercase();
+        match label.as_str() {
+            "COMMUNITY_VERIFIED" => Self::CommunityVerified,
+            "DEFI" => Self::DeFi,
+            "GATEWAY" => Self::Gateway,
+            "QUALIFIED" => Self::Qualified,
+            "STABLECOIN" => Self::Stablecoin,
+            "WA_VERIFIED" => Self::WaVerified,
+            _ => Self::Other(label),
+        }
+    }
+}
+
+impl From<String> for AssetLabel {
+    fn from(label: String) -> Self {
+        Self::from(label.as_str())
+    }
+}
+
+impl From<AssetLabel> for String {
+    fn from(label: AssetLabel) -> Self {
+        match label {
+            AssetLabel::Other(label) => label,
+            label => label.as_str().to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for AssetLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Rank multipliers of the text search matches, and priorities of the labels for `label_priority` sort
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchWeights {
+    pub exact_ticker: u32,
+    pub exact_name: u32,
+    pub id: u32,
+    pub id_with_ticker: u32,
+    pub metadata: u32,
+    pub metadata_with_ticker: u32,
+    pub ticker: u32,
+    pub name: u32,
+    pub name_with_ticker: u32,
+    /// Assets are prioritized by their label of the highest priority, unlisted labels have none
+    pub label_priorities: Vec<(AssetLabel, u32)>,
+}
+
+impl Default for SearchWeights {
+    fn default() -> Self {
+        Self {
+            exact_ticker: 1024,
+            exact_name: 512,
+            id: 128,
+            id_with_ticker: 256,
+            metadata: 64,
+            metadata_with_ticker: 128,
+            ticker: 32,
+            name: 16,
+            name_with_ticker: 32,
+            label_priorities: vec![
+                (AssetLabel::WaVerified, 3),
+                (AssetLabel::Gateway, 2),
+                (AssetLabel::CommunityVerified, 1),
+            ],
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AssetSponsorBalance {
     pub regular_balance: i64,
@@ -51,6 +209,8 @@ pub struct AssetOracleDataEntry {
     pub bool_val: Option<bool>,
     pub int_val: Option<i64>,
     pub str_val: Option<String>,
+    /// Uid of the data entry row, the greater one is written later
+    pub uid: i64,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -78,8 +238,8 @@ pub enum AssetInfoUpdate {
     SponsorRegularBalance(i64),
     SponsorOutLeasing(i64),
     OraclesData(HashMap<String, Vec<AssetOracleDataEntry>>),
-    Labels(Vec<String>),
-    Ticker(String),
+    Labels(Vec<AssetLabel>),
+    Ticker(Option<String>),
 }
 
 #[derive(Clone, Debug)]
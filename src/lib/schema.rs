@@ -36,7 +36,7 @@ table! {
         superseded_by -> Int8,
         block_uid -> Int8,
         asset_id -> Text,
-        ticker -> Text,
+        ticker -> Nullable<Text>,
     }
 }
 
@@ -46,6 +46,38 @@ table! {
     }
 }
 
+table! {
+    asset_names (superseded_by, asset_id) {
+        uid -> Int8,
+        superseded_by -> Int8,
+        block_uid -> Int8,
+        asset_id -> Text,
+        name -> Text,
+    }
+}
+
+table! {
+    asset_names_uid_seq (last_value) {
+        last_value -> BigInt,
+    }
+}
+
+table! {
+    asset_descriptions (superseded_by, asset_id) {
+        uid -> Int8,
+        superseded_by -> Int8,
+        block_uid -> Int8,
+        asset_id -> Text,
+        description -> Text,
+    }
+}
+
+table! {
+    asset_descriptions_uid_seq (last_value) {
+        last_value -> BigInt,
+    }
+}
+
 table! {
     assets (superseded_by, id) {
         uid -> Int8,
@@ -80,6 +112,16 @@ table! {
     }
 }
 
+table! {
+    consumer_state (id) {
+        id -> Bool,
+        block_uid -> Int8,
+        block_id -> Text,
+        height -> Int4,
+        updated_at -> Timestamptz,
+    }
+}
+
 table! {
     data_entries (superseded_by, address, key) {
         uid -> Int8,
@@ -134,12 +176,26 @@ table! {
     }
 }
 
+table! {
+    supply_changes (uid) {
+        uid -> Int8,
+        block_uid -> Int8,
+        asset_id -> Text,
+        height -> Int4,
+        prev_quantity -> Int8,
+        new_quantity -> Int8,
+        change -> Int8,
+    }
+}
+
 allow_tables_to_appear_in_same_query!(
     asset_metadatas,
     asset_wx_labels,
     assets,
     blocks_microblocks,
+    consumer_state,
     data_entries,
     issuer_balances,
     out_leasings,
+    supply_changes,
 );
@@ -1,16 +1,53 @@
 pub mod repo;
 
-use std::collections::HashSet;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use crate::cache::{AssetUserDefinedData, AsyncWriteCache};
 use crate::error::Error as AppError;
+use crate::models::AssetLabel;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelUpdateStatus {
+    Added,
+    Removed,
+    NotFound,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LabelUpdateResult {
+    pub id: String,
+    pub status: LabelUpdateStatus,
+}
+
+/// Number of the assets carrying the label
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LabelCount {
+    pub label: AssetLabel,
+    pub count: i64,
+}
 
 #[async_trait::async_trait]
 pub trait Service {
-    async fn add_label(&self, id: &str, label: &str) -> Result<(), AppError>;
+    async fn add_label(&self, id: &str, label: &AssetLabel) -> Result<(), AppError>;
+
+    async fn delete_label(&self, id: &str, label: &AssetLabel) -> Result<(), AppError>;
 
-    async fn delete_label(&self, id: &str, label: &str) -> Result<(), AppError>;
+    /// Adds the label to the `add` assets and deletes it from the `remove` ones.
+    ///
+    /// Missing assets are reported in the results, or fail the whole update if `strict` is set.
+    async fn update_label_bulk(
+        &self,
+        label: &AssetLabel,
+        add: &[String],
+        remove: &[String],
+        strict: bool,
+    ) -> Result<Vec<LabelUpdateResult>, AppError>;
+
+    /// Labels of the current assets, the most frequent first
+    async fn label_counts(&self) -> Result<Vec<LabelCount>, AppError>;
 }
 
 pub struct AdminAssetsService {
@@ -32,10 +69,10 @@ impl AdminAssetsService {
 
 #[async_trait::async_trait]
 impl Service for AdminAssetsService {
-    async fn add_label(&self, id: &str, label: &str) -> Result<(), AppError> {
+    async fn add_label(&self, id: &str, label: &AssetLabel) -> Result<(), AppError> {
         if self
             .repo
-            .add_label(id, label)
+            .add_label(id, label.as_str())
             .map_err(|err| AppError::DbError(err.to_string()))?
         {
             let asset_id = id.to_owned();
@@ -47,8 +84,7 @@ impl Service for AdminAssetsService {
                 .await
                 .map_err(|e| AppError::CacheError(format!("{}", e)))?
             {
-                let mut labels: HashSet<String> =
-                    cached_data.labels.into_iter().collect::<HashSet<String>>();
+                let mut labels = cached_data.labels.into_iter().collect::<HashSet<_>>();
                 labels.insert(label);
 
                 AssetUserDefinedData {
@@ -72,10 +108,10 @@ impl Service for AdminAssetsService {
         }
     }
 
-    async fn delete_label(&self, id: &str, label: &str) -> Result<(), AppError> {
+    async fn delete_label(&self, id: &str, label: &AssetLabel) -> Result<(), AppError> {
         if self
             .repo
-            .delete_label(id, label)
+            .delete_label(id, label.as_str())
             .map_err(|err| AppError::DbError(err.to_string()))?
         {
             let asset_id = id.to_owned();
@@ -110,4 +146,262 @@ impl Service for AdminAssetsService {
             Err(AppError::ConsistencyError("Asset not found".to_owned()))
         }
     }
+
+    async fn update_label_bulk(
+        &self,
+        label: &AssetLabel,
+        add: &[String],
+        remove: &[String],
+        strict: bool,
+    ) -> Result<Vec<LabelUpdateResult>, AppError> {
+        if label.as_str().is_empty() {
+            return Err(AppError::ValidationError(
+                "label".to_owned(),
+                Some(validation_details("label is empty")),
+            ));
+        }
+
+        if add.iter().any(|id| remove.contains(id)) {
+            return Err(AppError::ValidationError(
+                "add".to_owned(),
+                Some(validation_details(
+                    "the same asset cannot be both added and removed",
+                )),
+            ));
+        }
+
+        let add = add.iter().map(String::as_str).collect::<Vec<_>>();
+        let remove = remove.iter().map(String::as_str).collect::<Vec<_>>();
+
+        let update = self
+            .repo
+            .update_label_bulk(label.as_str(), &add, &remove, strict)
+            .map_err(|err| AppError::DbError(err.to_string()))?;
+
+        if strict && !update.not_found.is_empty() {
+            let mut details = validation_details("assets not found");
+            details.insert("not_found".to_owned(), update.not_found.join(","));
+            return Err(AppError::ValidationError("add".to_owned(), Some(details)));
+        }
+
+        let affected_ids = update
+            .added
+            .iter()
+            .chain(update.removed.iter())
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+
+        if !affected_ids.is_empty() {
+            let cached = self
+                .user_defined_data_cache
+                .mget(&affected_ids)
+                .await
+                .map_err(|e| AppError::CacheError(format!("{}", e)))?;
+
+            let entries = affected_ids
+                .iter()
+                .zip(cached.into_iter())
+                .enumerate()
+                .map(|(i, (id, cached_data))| {
+                    let data = cached_data.unwrap_or_else(|| AssetUserDefinedData::new(id));
+                    let data = if i < update.added.len() {
+                        data.add_label(label)
+                    } else {
+                        data.delete_label(label)
+                    };
+                    (id.to_string(), data)
+                })
+                .collect::<Vec<_>>();
+
+            self.user_defined_data_cache.mset(entries).await?;
+        }
+
+        let results = update
+            .added
+            .into_iter()
+            .map(|id| (id, LabelUpdateStatus::Added))
+            .chain(
+                update
+                    .removed
+                    .into_iter()
+                    .map(|id| (id, LabelUpdateStatus::Removed)),
+            )
+            .chain(
+                update
+                    .not_found
+                    .into_iter()
+                    .map(|id| (id, LabelUpdateStatus::NotFound)),
+            )
+            .map(|(id, status)| LabelUpdateResult { id, status })
+            .collect();
+
+        Ok(results)
+    }
+
+    async fn label_counts(&self) -> Result<Vec<LabelCount>, AppError> {
+        let counts = self
+            .repo
+            .label_counts()
+            .map_err(|err| AppError::DbError(err.to_string()))?;
+
+        Ok(counts
+            .into_iter()
+            .map(|(label, count)| LabelCount {
+                label: AssetLabel::from(label.as_str()),
+                count,
+            })
+            .collect())
+    }
+}
+
+fn validation_details(reason: &str) -> HashMap<String, String> {
+    let mut details = HashMap::new();
+    details.insert("reason".to_owned(), reason.to_owned());
+    details
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use super::repo::{LabelBulkUpdate, Repo};
+    use super::{AdminAssetsService, LabelUpdateResult, LabelUpdateStatus, Service};
+    use crate::cache::{memory_cache::MemoryCache, AssetUserDefinedData};
+    use crate::error::Error as AppError;
+    use crate::models::AssetLabel;
+
+    struct MockRepo {
+        existing: HashSet<String>,
+    }
+
+    impl Repo for MockRepo {
+        fn add_label(&self, _id: &str, _label: &str) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        fn delete_label(&self, _id: &str, _label: &str) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        fn update_label_bulk(
+            &self,
+            _label: &str,
+            add: &[&str],
+            remove: &[&str],
+            strict: bool,
+        ) -> anyhow::Result<LabelBulkUpdate> {
+            let (found_add, missing_add): (Vec<_>, Vec<_>) =
+                add.iter().partition(|id| self.existing.contains(**id));
+            let (found_remove, missing_remove): (Vec<_>, Vec<_>) =
+                remove.iter().partition(|id| self.existing.contains(**id));
+            let not_found = missing_add
+                .into_iter()
+                .chain(missing_remove)
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>();
+
+            if strict && !not_found.is_empty() {
+                return Ok(LabelBulkUpdate {
+                    not_found,
+                    ..Default::default()
+                });
+            }
+
+            Ok(LabelBulkUpdate {
+                added: found_add.into_iter().map(|id| id.to_string()).collect(),
+                removed: found_remove.into_iter().map(|id| id.to_string()).collect(),
+                not_found,
+            })
+        }
+
+        fn label_counts(&self) -> anyhow::Result<Vec<(String, i64)>> {
+            Ok(vec![])
+        }
+    }
+
+    fn service(existing: &[&str]) -> (AdminAssetsService, MemoryCache<AssetUserDefinedData>) {
+        let repo = MockRepo {
+            existing: existing.iter().map(|id| id.to_string()).collect(),
+        };
+        let cache = MemoryCache::new(vec![(
+            "b",
+            AssetUserDefinedData {
+                asset_id: "b".to_owned(),
+                labels: vec![AssetLabel::from("SCAM"), AssetLabel::Gateway],
+            },
+        )]);
+
+        (
+            AdminAssetsService::new(Arc::new(repo), Box::new(cache.clone())),
+            cache,
+        )
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn should_skip_missing_assets() {
+        let (service, cache) = service(&["a", "b"]);
+
+        let results = service
+            .update_label_bulk(
+                &AssetLabel::from("SCAM"),
+                &ids(&["a", "missing"]),
+                &ids(&["b"]),
+                false,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            results,
+            vec![
+                LabelUpdateResult {
+                    id: "a".to_owned(),
+                    status: LabelUpdateStatus::Added
+                },
+                LabelUpdateResult {
+                    id: "b".to_owned(),
+                    status: LabelUpdateStatus::Removed
+                },
+                LabelUpdateResult {
+                    id: "missing".to_owned(),
+                    status: LabelUpdateStatus::NotFound
+                },
+            ]
+        );
+
+        let values = cache.values();
+        assert_eq!(values["a"].labels, vec![AssetLabel::from("SCAM")]);
+        assert_eq!(values["b"].labels, vec![AssetLabel::Gateway]);
+        assert!(!values.contains_key("missing"));
+    }
+
+    #[tokio::test]
+    async fn should_fail_on_missing_asset_when_strict() {
+        let (service, cache) = service(&["a", "b"]);
+
+        let result = service
+            .update_label_bulk(
+                &AssetLabel::from("SCAM"),
+                &ids(&["a", "missing"]),
+                &ids(&["b"]),
+                true,
+            )
+            .await;
+
+        match result {
+            Err(AppError::ValidationError(_, Some(details))) => {
+                assert_eq!(details["not_found"], "missing");
+            }
+            r => panic!("unexpected result: {:?}", r),
+        }
+
+        let values = cache.values();
+        assert!(!values.contains_key("a"));
+        assert_eq!(values["b"].labels.len(), 2);
+    }
 }
@@ -2,8 +2,30 @@ pub mod pg;
 
 use anyhow::Result;
 
+/// Outcome of the bulk label update
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LabelBulkUpdate {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub not_found: Vec<String>,
+}
+
 pub trait Repo {
     fn add_label(&self, id: &str, label: &str) -> Result<bool>;
 
     fn delete_label(&self, id: &str, label: &str) -> Result<bool>;
+
+    /// Adds the label to the `add` assets and deletes it from the `remove` ones in a single transaction.
+    ///
+    /// Missing assets are skipped, or roll back the whole update if `strict` is set.
+    fn update_label_bulk(
+        &self,
+        label: &str,
+        add: &[&str],
+        remove: &[&str],
+        strict: bool,
+    ) -> Result<LabelBulkUpdate>;
+
+    /// Current labels of the assets, set by the data entries or by the admin, with the numbers of their assets
+    fn label_counts(&self) -> Result<Vec<(String, i64)>>;
 }
@@ -1,10 +1,27 @@
-use diesel::prelude::*;
+use diesel::sql_types::{Array, BigInt, Text};
+use diesel::{prelude::*, sql_query};
 
-use super::Repo;
+use super::{LabelBulkUpdate, Repo};
 use crate::db::PgPool;
 use crate::error::Error as AppError;
 use crate::schema::asset_wx_labels;
 
+const MAX_UID: i64 = i64::MAX - 1;
+
+#[derive(QueryableByName)]
+struct FoundAsset {
+    #[sql_type = "Text"]
+    id: String,
+}
+
+#[derive(QueryableByName)]
+struct LabelCount {
+    #[sql_type = "Text"]
+    label: String,
+    #[sql_type = "BigInt"]
+    count: i64,
+}
+
 pub struct PgRepo {
     pg_pool: PgPool,
 }
@@ -44,4 +61,188 @@ impl Repo for PgRepo {
             anyhow::Error::new(AppError::DbDieselError(err)).context(context)
         })
     }
+
+    fn update_label_bulk(
+        &self,
+        label: &str,
+        add: &[&str],
+        remove: &[&str],
+        strict: bool,
+    ) -> anyhow::Result<LabelBulkUpdate> {
+        let conn = self.pg_pool.get()?;
+
+        let mut update = LabelBulkUpdate::default();
+        let result = conn.transaction(|| {
+            let added = add_label_bulk(&conn, add, label)?;
+            let removed = delete_label_bulk(&conn, remove, label)?;
+
+            update.not_found = add
+                .iter()
+                .filter(|id| !added.iter().any(|a| a == *id))
+                .chain(remove.iter().filter(|id| !removed.iter().any(|r| r == *id)))
+                .map(|id| id.to_string())
+                .collect();
+            update.added = added;
+            update.removed = removed;
+
+            if strict && !update.not_found.is_empty() {
+                Err(diesel::result::Error::RollbackTransaction)
+            } else {
+                Ok(())
+            }
+        });
+
+        match result {
+            Ok(()) => Ok(update),
+            Err(diesel::result::Error::RollbackTransaction) => Ok(LabelBulkUpdate {
+                not_found: update.not_found,
+                ..Default::default()
+            }),
+            Err(err) => {
+                let context = format!("Cannot update asset labels: {}", err);
+                Err(anyhow::Error::new(AppError::DbDieselError(err)).context(context))
+            }
+        }
+    }
+
+    fn label_counts(&self) -> anyhow::Result<Vec<(String, i64)>> {
+        load_label_counts(&self.pg_pool.get()?).map_err(|err| {
+            let context = format!("Cannot count asset labels: {}", err);
+            anyhow::Error::new(AppError::DbDieselError(err)).context(context)
+        })
+    }
+}
+
+/// Counts an asset labeled both by the data entries and by the admin once,
+/// the most frequent labels first
+///
+/// Labels are normalized as `AssetLabel` does, so that `defi` and `DEFI` are counted together.
+fn load_label_counts(conn: &PgConnection) -> Result<Vec<(String, i64)>, diesel::result::Error> {
+    let counts: Vec<LabelCount> = sql_query(
+        "SELECT l.label, COUNT(*) AS count
+        FROM (
+            SELECT al.asset_id, UPPER(TRIM(label)) AS label
+            FROM asset_labels AS al, UNNEST(al.labels) AS label
+            WHERE al.superseded_by = $1
+            UNION
+            SELECT awl.asset_id, UPPER(TRIM(awl.label))
+            FROM asset_wx_labels AS awl
+        ) AS l
+        WHERE l.label <> ''
+        GROUP BY l.label
+        ORDER BY count DESC, l.label",
+    )
+    .bind::<BigInt, _>(MAX_UID)
+    .load(conn)?;
+
+    Ok(counts.into_iter().map(|c| (c.label, c.count)).collect())
+}
+
+/// Labels the existing assets among `ids`, returns their ids
+fn add_label_bulk(
+    conn: &PgConnection,
+    ids: &[&str],
+    label: &str,
+) -> Result<Vec<String>, diesel::result::Error> {
+    if ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let found: Vec<FoundAsset> = sql_query(
+        "WITH found AS (
+            SELECT DISTINCT a.id FROM UNNEST($1) AS u(id)
+            INNER JOIN assets a ON a.id = u.id AND a.superseded_by = $2
+        ), inserted AS (
+            INSERT INTO asset_wx_labels (asset_id, label)
+            SELECT id, $3 FROM found
+            ON CONFLICT DO NOTHING
+        )
+        SELECT id FROM found",
+    )
+    .bind::<Array<Text>, _>(ids)
+    .bind::<BigInt, _>(MAX_UID)
+    .bind::<Text, _>(label)
+    .load(conn)?;
+
+    Ok(found.into_iter().map(|f| f.id).collect())
+}
+
+/// Deletes the label from the existing assets among `ids`, returns their ids
+fn delete_label_bulk(
+    conn: &PgConnection,
+    ids: &[&str],
+    label: &str,
+) -> Result<Vec<String>, diesel::result::Error> {
+    if ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let found: Vec<FoundAsset> = sql_query(
+        "WITH found AS (
+            SELECT DISTINCT a.id FROM UNNEST($1) AS u(id)
+            INNER JOIN assets a ON a.id = u.id AND a.superseded_by = $2
+        ), deleted AS (
+            DELETE FROM asset_wx_labels
+            WHERE asset_id IN (SELECT id FROM found) AND label = $3
+        )
+        SELECT id FROM found",
+    )
+    .bind::<Array<Text>, _>(ids)
+    .bind::<BigInt, _>(MAX_UID)
+    .bind::<Text, _>(label)
+    .load(conn)?;
+
+    Ok(found.into_iter().map(|f| f.id).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::{sql_query, Connection, RunQueryDsl};
+
+    use super::load_label_counts;
+    use crate::{config, db};
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_count_assets_labeled_by_both_sources_once() {
+        let config = config::postgres::load().unwrap();
+        let conn = db::pool(&config).unwrap().get().unwrap();
+
+        conn.test_transaction::<_, diesel::result::Error, _>(|| {
+            sql_query("INSERT INTO blocks_microblocks (uid, id, height, time_stamp) VALUES (-1, 'labels_block', 1, 0)")
+                .execute(&conn)?;
+            // the superseded labels of test_asset_3 are not counted,
+            // the lowercase label of test_asset_1 is the same as its admin one
+            sql_query(
+                "INSERT INTO asset_labels (uid, superseded_by, block_uid, asset_id, labels) VALUES
+                    (-3, 9223372036854775806, -1, 'test_asset_1', ARRAY['test_shared', 'TEST_STATE']),
+                    (-2, 9223372036854775806, -1, 'test_asset_2', ARRAY['TEST_SHARED']),
+                    (-1, -2, -1, 'test_asset_3', ARRAY['TEST_STATE'])",
+            )
+            .execute(&conn)?;
+            sql_query(
+                "INSERT INTO asset_wx_labels (asset_id, label) VALUES
+                    ('test_asset_1', 'TEST_SHARED'),
+                    ('test_asset_3', 'TEST_SHARED'),
+                    ('test_asset_3', 'TEST_ADMIN')",
+            )
+            .execute(&conn)?;
+
+            let counts = load_label_counts(&conn)?
+                .into_iter()
+                .filter(|(label, _)| label.starts_with("TEST_"))
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                counts,
+                vec![
+                    ("TEST_SHARED".to_owned(), 3),
+                    ("TEST_ADMIN".to_owned(), 1),
+                    ("TEST_STATE".to_owned(), 1),
+                ]
+            );
+
+            Ok(())
+        });
+    }
 }
@@ -0,0 +1,44 @@
+use serde_json::Value;
+
+/// Encodes the sort keys of the search result row (JSON array ending with the asset id)
+/// into the opaque cursor
+pub fn encode(sort_keys: &str) -> String {
+    base64::encode_config(sort_keys, base64::URL_SAFE_NO_PAD)
+}
+
+/// Decodes the sort keys of the cursor
+///
+/// Returns `None` for legacy cursors holding the asset id itself
+pub fn decode(cursor: &str) -> Option<Vec<Value>> {
+    base64::decode_config(cursor, base64::URL_SAFE_NO_PAD)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Vec<Value>>(&bytes).ok())
+        .filter(|keys| !keys.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{decode, encode};
+
+    #[test]
+    fn should_decode_encoded_sort_keys() {
+        let cursor = encode(r#"[0.0607927, 12, "8LQW8f7P5d5PZM7GtZEBgaqRPGSzS3DfPuiXrURJ4AJS"]"#);
+
+        assert_eq!(
+            decode(&cursor),
+            Some(vec![
+                json!(0.0607927),
+                json!(12),
+                json!("8LQW8f7P5d5PZM7GtZEBgaqRPGSzS3DfPuiXrURJ4AJS")
+            ])
+        );
+    }
+
+    #[test]
+    fn should_not_decode_asset_id() {
+        assert_eq!(decode("8LQW8f7P5d5PZM7GtZEBgaqRPGSzS3DfPuiXrURJ4AJS"), None);
+        assert_eq!(decode("WAVES"), None);
+    }
+}
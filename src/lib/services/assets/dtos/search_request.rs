@@ -1,16 +1,36 @@
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 
+use crate::models::VerificationStatus;
+use crate::services::assets::repo::SearchSort;
+
 #[derive(Clone, Debug, Default, Deserialize)]
 pub struct SearchRequest {
     pub ids: Option<Vec<String>>,
     pub ticker: Option<String>,
+    pub ticker_in: Option<Vec<String>>,
     pub label: Option<String>,
     pub search: Option<String>,
     pub smart: Option<bool>,
+    pub reissuable: Option<bool>,
+    pub include_nfts: bool,
+    pub sponsored: Option<bool>,
+    pub precision_gte: Option<i32>,
+    pub precision_lte: Option<i32>,
+    pub created_height_gte: Option<i32>,
+    pub created_height_lte: Option<i32>,
+    pub created_after_height: Option<i32>,
+    pub created_before_height: Option<i32>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
     pub asset_label_in: Option<Vec<String>>,
+    pub asset_label_not_in: Option<Vec<String>>,
     pub issuer_in: Option<Vec<String>>,
+    pub verified_status_in: Option<Vec<VerificationStatus>>,
     pub limit: u32,
     pub after: Option<String>,
+    pub before: Option<String>,
+    pub sort: Option<SearchSort>,
 }
 
 impl SearchRequest {
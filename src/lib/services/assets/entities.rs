@@ -9,7 +9,7 @@ use crate::{
     cache::{AssetBlockchainData, AssetUserDefinedData},
     db::enums::DataEntryValueType,
     error::Error as AppError,
-    models::{AssetOracleDataEntry, AssetSponsorBalance, DataEntryType},
+    models::{AssetLabel, AssetOracleDataEntry, AssetSponsorBalance, DataEntryType},
 };
 
 #[derive(Clone, Debug, QueryableByName)]
@@ -46,6 +46,87 @@ pub struct Asset {
     pub ticker: Option<String>,
 }
 
+/// Change of the asset quantity by issue, reissue or burn
+#[derive(Clone, Debug, PartialEq, QueryableByName)]
+pub struct SupplyChange {
+    #[sql_type = "Integer"]
+    pub height: i32,
+    #[sql_type = "BigInt"]
+    pub prev_quantity: i64,
+    #[sql_type = "BigInt"]
+    pub new_quantity: i64,
+    #[sql_type = "BigInt"]
+    pub change: i64,
+}
+
+/// Asset fields as of the block `block_uid` where they were changed
+#[derive(Clone, Debug, PartialEq, QueryableByName)]
+pub struct AssetVersion {
+    #[sql_type = "BigInt"]
+    pub block_uid: i64,
+    #[sql_type = "Text"]
+    pub name: String,
+    #[sql_type = "Text"]
+    pub description: String,
+    #[sql_type = "BigInt"]
+    pub quantity: i64,
+    #[sql_type = "Bool"]
+    pub reissuable: bool,
+    #[sql_type = "Nullable<BigInt>"]
+    pub min_sponsored_fee: Option<i64>,
+    #[sql_type = "Bool"]
+    pub smart: bool,
+}
+
+/// Changed asset fields, `None` for unchanged ones
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AssetChanges {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub quantity: Option<i64>,
+    pub reissuable: Option<bool>,
+    /// `Some(None)` if the sponsorship was cancelled
+    pub min_sponsored_fee: Option<Option<i64>>,
+    pub smart: Option<bool>,
+}
+
+impl AssetChanges {
+    /// All the fields are changed if the asset was not known before
+    pub fn between(known: Option<&AssetVersion>, current: &AssetVersion) -> Self {
+        fn changed<T: Clone + PartialEq>(known: Option<&T>, current: &T) -> Option<T> {
+            if known == Some(current) {
+                None
+            } else {
+                Some(current.clone())
+            }
+        }
+
+        Self {
+            name: changed(known.map(|k| &k.name), &current.name),
+            description: changed(known.map(|k| &k.description), &current.description),
+            quantity: changed(known.map(|k| &k.quantity), &current.quantity),
+            reissuable: changed(known.map(|k| &k.reissuable), &current.reissuable),
+            min_sponsored_fee: changed(
+                known.map(|k| &k.min_sponsored_fee),
+                &current.min_sponsored_fee,
+            ),
+            smart: changed(known.map(|k| &k.smart), &current.smart),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Changes made after the client-known version of the asset
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssetDelta {
+    /// Current version of the asset
+    pub version: i64,
+    pub changes: AssetChanges,
+}
+
 #[derive(Clone, Debug, Queryable)]
 pub struct OracleDataEntry {
     pub asset_id: String,
@@ -56,6 +137,7 @@ pub struct OracleDataEntry {
     pub bool_val: Option<bool>,
     pub int_val: Option<i64>,
     pub str_val: Option<String>,
+    pub uid: i64,
 }
 
 impl From<&OracleDataEntry> for AssetOracleDataEntry {
@@ -69,6 +151,7 @@ impl From<&OracleDataEntry> for AssetOracleDataEntry {
             bool_val: de.bool_val,
             int_val: de.int_val,
             str_val: de.str_val.clone(),
+            uid: de.uid,
         }
     }
 }
@@ -135,7 +218,11 @@ pub struct UserDefinedData {
 
 impl From<&UserDefinedData> for AssetUserDefinedData {
     fn from(d: &UserDefinedData) -> Self {
-        let labels = d.labels.clone().into_iter().collect::<Vec<_>>();
+        let labels = d
+            .labels
+            .iter()
+            .map(|label| AssetLabel::from(label.as_str()))
+            .collect::<Vec<_>>();
         Self {
             asset_id: d.asset_id.clone(),
             labels,
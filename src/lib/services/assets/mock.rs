@@ -0,0 +1,391 @@
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::entities::{
+    Asset, AssetDelta, AssetVersion, OracleDataEntry, SupplyChange, UserDefinedData,
+};
+use super::repo::{AssetId, FindParams, Repo, SearchCandidate, SearchedAsset};
+use super::{
+    AbsenceReason, GetOptions, MgetOptions, MgetResult, SearchItem, SearchRequest, Service,
+};
+use crate::error::Error as AppError;
+use crate::models::AssetInfo;
+
+/// Assets service of the tests serving the given assets,
+/// the queries it has no data for find nothing
+#[derive(Default)]
+pub struct MockService {
+    pub assets: HashMap<String, AssetInfo>,
+    pub consumer_updated_at: Option<DateTime<Utc>>,
+    /// Fails the user defined data queries as with the database unavailable
+    pub user_defined_data_unavailable: bool,
+}
+
+impl MockService {
+    pub fn new(assets: Vec<AssetInfo>) -> Self {
+        Self {
+            assets: assets
+                .into_iter()
+                .map(|a| (a.asset.id.clone(), a))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_consumer_updated_at(mut self, updated_at: DateTime<Utc>) -> Self {
+        self.consumer_updated_at = Some(updated_at);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Service for MockService {
+    async fn get(&self, id: &str, _opts: &GetOptions) -> Result<Option<AssetInfo>, AppError> {
+        Ok(self.assets.get(id).cloned())
+    }
+
+    async fn mget(&self, ids: &[&str], opts: &MgetOptions) -> Result<Vec<MgetResult>, AppError> {
+        Ok(ids
+            .iter()
+            .map(|id| match self.assets.get(*id) {
+                Some(asset_info) if asset_info.asset.nft && !opts.include_nfts => MgetResult::Nft,
+                Some(asset_info) => MgetResult::Found(asset_info.clone()),
+                None => MgetResult::NotFound,
+            })
+            .collect())
+    }
+
+    async fn get_by_ticker(
+        &self,
+        ticker: &str,
+        _opts: &GetOptions,
+    ) -> Result<Option<AssetInfo>, AppError> {
+        Ok(self
+            .assets
+            .values()
+            .find(|a| a.asset.ticker.as_deref() == Some(ticker))
+            .cloned())
+    }
+
+    fn absence_reason(&self, id: &str) -> Result<Option<AbsenceReason>, AppError> {
+        Ok(match self.assets.get(id) {
+            Some(asset_info) if asset_info.asset.nft => Some(AbsenceReason::Hidden),
+            Some(_) => None,
+            None => Some(AbsenceReason::NotFound),
+        })
+    }
+
+    fn search(&self, _req: &SearchRequest) -> Result<Vec<SearchItem>, AppError> {
+        Ok(vec![])
+    }
+
+    fn count(&self, _req: &SearchRequest) -> Result<u64, AppError> {
+        Ok(0)
+    }
+
+    fn updated_between(
+        &self,
+        _from: DateTime<Utc>,
+        _to: DateTime<Utc>,
+        _after: Option<&str>,
+        _limit: u32,
+    ) -> Result<Vec<String>, AppError> {
+        Ok(vec![])
+    }
+
+    fn by_issuer(
+        &self,
+        _issuer: &str,
+        _after: Option<&str>,
+        _limit: u32,
+    ) -> Result<Vec<SearchItem>, AppError> {
+        Ok(vec![])
+    }
+
+    fn asset_ids_by_issuers(&self, issuers: &[&str]) -> Result<Vec<String>, AppError> {
+        Ok(self
+            .assets
+            .values()
+            .filter(|a| issuers.contains(&a.asset.issuer.as_str()))
+            .map(|a| a.asset.id.clone())
+            .collect())
+    }
+
+    fn user_defined_data(
+        &self,
+        after_asset_id: Option<&str>,
+        limit: u32,
+        has_ticker: Option<bool>,
+    ) -> Result<Vec<UserDefinedData>, AppError> {
+        if self.user_defined_data_unavailable {
+            return Err(AppError::DbError("database unavailable".to_owned()));
+        }
+
+        let ids = self
+            .assets
+            .values()
+            .filter(|a| after_asset_id.map_or(true, |after| a.asset.id.as_str() > after))
+            .filter(|a| {
+                has_ticker.map_or(true, |has| {
+                    a.asset.ticker.as_deref().map_or(false, |t| !t.is_empty()) == has
+                })
+            })
+            .map(|a| a.asset.id.as_str())
+            .sorted()
+            .take(limit as usize)
+            .collect::<Vec<_>>();
+        self.user_defined_data_by_ids(&ids)
+    }
+
+    fn user_defined_data_by_ids(&self, ids: &[&str]) -> Result<Vec<UserDefinedData>, AppError> {
+        Ok(ids
+            .iter()
+            .filter_map(|id| self.assets.get(*id))
+            .map(|a| UserDefinedData {
+                asset_id: a.asset.id.clone(),
+                ticker: a.asset.ticker.clone(),
+                labels: a.metadata.labels.iter().map(ToString::to_string).collect(),
+            })
+            .collect())
+    }
+
+    fn supply_changes(
+        &self,
+        _id: &str,
+        _height_gte: Option<i32>,
+        _height_lte: Option<i32>,
+    ) -> Result<Vec<SupplyChange>, AppError> {
+        Ok(vec![])
+    }
+
+    fn changes_since(&self, _id: &str, _version: i64) -> Result<Option<AssetDelta>, AppError> {
+        Ok(None)
+    }
+
+    fn consumer_updated_at(&self) -> Result<Option<DateTime<Utc>>, AppError> {
+        Ok(self.consumer_updated_at)
+    }
+}
+
+/// Assets repo of the tests serving the given assets and counting their lookups
+#[derive(Default)]
+pub struct MockRepo {
+    pub assets: HashMap<String, Asset>,
+    pub lookups: Mutex<usize>,
+    /// Versions of the single asset in the order of change
+    pub versions: Vec<AssetVersion>,
+    /// Oracle data entries with the heights of their changes in the order of change
+    pub oracle_data: Vec<(i32, OracleDataEntry)>,
+    /// Fails the test on any call, e.g. of a service expected to serve from the cache alone
+    pub unused: bool,
+}
+
+impl MockRepo {
+    pub fn unused() -> Self {
+        Self {
+            unused: true,
+            ..Default::default()
+        }
+    }
+
+    fn call(&self) {
+        assert!(!self.unused, "repo is used");
+    }
+}
+
+impl Repo for MockRepo {
+    fn find(&self, _params: FindParams) -> Result<Vec<SearchedAsset>, AppError> {
+        self.call();
+        Ok(vec![])
+    }
+
+    fn find_candidates(&self, _params: FindParams) -> Result<Vec<SearchCandidate>, AppError> {
+        self.call();
+        Ok(vec![])
+    }
+
+    fn count(&self, _params: FindParams) -> Result<u64, AppError> {
+        self.call();
+        Ok(0)
+    }
+
+    fn assets_updated_between(
+        &self,
+        _from: DateTime<Utc>,
+        _to: DateTime<Utc>,
+        _after: Option<&str>,
+        _limit: u32,
+    ) -> Result<Vec<AssetId>, AppError> {
+        self.call();
+        Ok(vec![])
+    }
+
+    fn issuer_assets(
+        &self,
+        _issuer: &str,
+        _after: Option<&str>,
+        _limit: u32,
+    ) -> Result<Vec<SearchedAsset>, AppError> {
+        self.call();
+        Ok(vec![])
+    }
+
+    fn get_last_asset_ids_by_issuers(&self, _issuers: &[&str]) -> Result<Vec<AssetId>, AppError> {
+        self.call();
+        Ok(vec![])
+    }
+
+    fn get(&self, id: &str, include_nfts: bool) -> Result<Option<Asset>, AppError> {
+        self.call();
+        *self.lookups.lock().unwrap() += 1;
+        Ok(self
+            .assets
+            .get(id)
+            .filter(|a| include_nfts || !a.nft)
+            .cloned())
+    }
+
+    fn mget(&self, ids: &[&str], include_nfts: bool) -> Result<Vec<Option<Asset>>, AppError> {
+        self.call();
+        *self.lookups.lock().unwrap() += 1;
+        Ok(ids
+            .iter()
+            .map(|id| {
+                self.assets
+                    .get(*id)
+                    .filter(|a| include_nfts || !a.nft)
+                    .cloned()
+            })
+            .collect())
+    }
+
+    fn get_by_ticker(&self, ticker: &str) -> Result<Option<Asset>, AppError> {
+        self.call();
+        *self.lookups.lock().unwrap() += 1;
+        Ok(self
+            .assets
+            .values()
+            .find(|a| !a.nft && a.ticker.as_deref() == Some(ticker))
+            .cloned())
+    }
+
+    fn mget_for_height(
+        &self,
+        ids: &[&str],
+        _height: i32,
+        _include_nfts: bool,
+    ) -> Result<Vec<Option<Asset>>, AppError> {
+        self.call();
+        *self.lookups.lock().unwrap() += 1;
+        Ok(ids.iter().map(|id| self.assets.get(*id).cloned()).collect())
+    }
+
+    fn data_entries(
+        &self,
+        _asset_ids: &[&str],
+        _oracle_addresses: &[String],
+    ) -> Result<Vec<OracleDataEntry>, AppError> {
+        self.call();
+        Ok(vec![])
+    }
+
+    fn data_entries_for_height(
+        &self,
+        _asset_ids: &[&str],
+        _oracle_addresses: &[String],
+        height: i32,
+    ) -> Result<Vec<OracleDataEntry>, AppError> {
+        self.call();
+        let entries = self
+            .oracle_data
+            .iter()
+            .filter(|(changed_at, _)| *changed_at <= height)
+            .map(|(_, entry)| {
+                (
+                    (entry.oracle_address.clone(), entry.key.clone()),
+                    entry.clone(),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        Ok(entries.into_values().collect())
+    }
+
+    fn get_asset_user_defined_data(&self, id: &str) -> Result<UserDefinedData, AppError> {
+        self.call();
+        Ok(UserDefinedData {
+            asset_id: id.to_owned(),
+            ticker: None,
+            labels: vec![],
+        })
+    }
+
+    fn mget_asset_user_defined_data(&self, ids: &[&str]) -> Result<Vec<UserDefinedData>, AppError> {
+        self.call();
+        Ok(ids
+            .iter()
+            .map(|id| UserDefinedData {
+                asset_id: (*id).to_owned(),
+                ticker: None,
+                labels: vec![],
+            })
+            .collect())
+    }
+
+    fn all_assets_user_defined_data(
+        &self,
+        after_asset_id: Option<&str>,
+        limit: u32,
+        has_ticker: Option<bool>,
+    ) -> Result<Vec<UserDefinedData>, AppError> {
+        self.call();
+        *self.lookups.lock().unwrap() += 1;
+        Ok(self
+            .assets
+            .values()
+            .filter(|a| after_asset_id.map_or(true, |after| a.id.as_str() > after))
+            .filter(|a| {
+                has_ticker.map_or(true, |has| {
+                    a.ticker.as_deref().map_or(false, |t| !t.is_empty()) == has
+                })
+            })
+            .sorted_by(|a, b| a.id.cmp(&b.id))
+            .take(limit as usize)
+            .map(|a| UserDefinedData {
+                asset_id: a.id.clone(),
+                ticker: a.ticker.clone(),
+                labels: vec![],
+            })
+            .collect())
+    }
+
+    fn supply_changes(
+        &self,
+        _asset_id: &str,
+        _height_gte: Option<i32>,
+        _height_lte: Option<i32>,
+    ) -> Result<Vec<SupplyChange>, AppError> {
+        self.call();
+        Ok(vec![])
+    }
+
+    fn consumer_updated_at(&self) -> Result<Option<DateTime<Utc>>, AppError> {
+        self.call();
+        Ok(None)
+    }
+
+    fn asset_version(
+        &self,
+        _id: &str,
+        block_uid_lte: Option<i64>,
+    ) -> Result<Option<AssetVersion>, AppError> {
+        self.call();
+        Ok(self
+            .versions
+            .iter()
+            .rev()
+            .find(|v| block_uid_lte.map_or(true, |uid| v.block_uid <= uid))
+            .cloned())
+    }
+}
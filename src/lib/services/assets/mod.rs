@@ -1,21 +1,29 @@
+pub mod cursor;
 pub mod dtos;
 pub mod entities;
+#[cfg(test)]
+pub mod mock;
+pub mod ranking;
 pub mod repo;
 
+use chrono::{DateTime, Utc};
 use itertools::Itertools;
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use wavesexchange_log::{timer, warn};
 
 pub use self::dtos::SearchRequest;
+pub use self::repo::SearchSort;
 use crate::cache;
-use crate::cache::{AssetBlockchainData, AssetUserDefinedData};
+use crate::cache::{AssetBlockchainData, AssetUserDefinedData, Cached};
 use crate::error::Error as AppError;
-use crate::models::AssetInfo;
+use crate::models::{Asset, AssetInfo, AssetLabel, AssetMetadata};
 use crate::waves::{WAVES_DESCR, WAVES_ID};
 
-use entities::UserDefinedData;
-use repo::{FindParams, LabelFilter, TickerFilter};
+use entities::{AssetChanges, AssetDelta, SupplyChange, UserDefinedData};
+use ranking::{ranked_page, SearchRanker};
+use repo::{FindParams, LabelCondition, LabelFilter, TickerFilter};
 
 #[derive(Clone, Debug, Default)]
 pub struct GetOptions {
@@ -26,6 +34,7 @@ pub struct GetOptions {
 pub struct MgetOptions {
     height: Option<i32>,
     bypass_cache: bool,
+    include_nfts: bool,
 }
 
 impl MgetOptions {
@@ -45,6 +54,12 @@ impl MgetOptions {
         opts
     }
 
+    pub fn set_include_nfts(&self, include_nfts: bool) -> Self {
+        let mut opts = self.clone();
+        opts.include_nfts = include_nfts;
+        opts
+    }
+
     pub fn with_height(height: i32) -> Self {
         Self::default().set_height(height)
     }
@@ -54,45 +69,245 @@ impl MgetOptions {
     }
 }
 
+/// Found asset id with the cursor pointing to it
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchItem {
+    pub id: String,
+    pub cursor: String,
+}
+
+/// Why the asset is missing in the `get` result
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AbsenceReason {
+    NotFound,
+    /// The asset exists but is excluded by policy (e.g. NFT)
+    Hidden,
+    /// The asset is an NFT excluded from the `mget` result
+    NftExcluded,
+}
+
+/// Result of `mget` for a single id
+#[derive(Clone, Debug)]
+pub enum MgetResult {
+    Found(AssetInfo),
+    /// Found asset served from the cache
+    Cached(AssetInfo),
+    /// NFT not requested by the options
+    Nft,
+    NotFound,
+}
+
+impl MgetResult {
+    pub fn found(self) -> Option<AssetInfo> {
+        match self {
+            Self::Found(asset_info) | Self::Cached(asset_info) => Some(asset_info),
+            _ => None,
+        }
+    }
+
+    /// Whether the data of the asset read by the call were cached, both the blockchain
+    /// and the user defined data for `mget`
+    pub fn from_cache(&self) -> bool {
+        matches!(self, Self::Cached(_))
+    }
+
+    /// Returns `None` if the asset is found
+    pub fn absence_reason(&self) -> Option<AbsenceReason> {
+        match self {
+            Self::Found(_) | Self::Cached(_) => None,
+            Self::Nft => Some(AbsenceReason::NftExcluded),
+            Self::NotFound => Some(AbsenceReason::NotFound),
+        }
+    }
+}
+
+/// Iterates over the pages of the user defined data of all assets, fetching a page at a time
+pub struct UserDefinedDataPages<'a, S: ?Sized> {
+    service: &'a S,
+    after_asset_id: Option<String>,
+    page_size: u32,
+    has_ticker: Option<bool>,
+    done: bool,
+}
+
+impl<'a, S: Service + ?Sized> UserDefinedDataPages<'a, S> {
+    pub fn new(service: &'a S, page_size: u32, has_ticker: Option<bool>) -> Self {
+        Self {
+            service,
+            after_asset_id: None,
+            page_size,
+            has_ticker,
+            done: false,
+        }
+    }
+}
+
+impl<'a, S: Service + ?Sized> Iterator for UserDefinedDataPages<'a, S> {
+    type Item = Result<Vec<UserDefinedData>, AppError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let page = self.service.user_defined_data(
+            self.after_asset_id.as_deref(),
+            self.page_size,
+            self.has_ticker,
+        );
+
+        match page {
+            Ok(page) if page.is_empty() => {
+                self.done = true;
+                None
+            }
+            Ok(page) => {
+                // a short page is the last one
+                self.done = (page.len() as u32) < self.page_size;
+                self.after_asset_id = page.last().map(|data| data.asset_id.clone());
+                Some(Ok(page))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
 #[async_trait::async_trait]
 pub trait Service {
     async fn get(&self, id: &str, opts: &GetOptions) -> Result<Option<AssetInfo>, AppError>;
 
-    async fn mget(
+    async fn mget(&self, ids: &[&str], opts: &MgetOptions) -> Result<Vec<MgetResult>, AppError>;
+
+    /// Like `mget`, for the responses without the metadata, which may be left empty
+    async fn mget_brief(
         &self,
         ids: &[&str],
         opts: &MgetOptions,
-    ) -> Result<Vec<Option<AssetInfo>>, AppError>;
+    ) -> Result<Vec<MgetResult>, AppError> {
+        self.mget(ids, opts).await
+    }
+
+    async fn get_by_ticker(
+        &self,
+        ticker: &str,
+        opts: &GetOptions,
+    ) -> Result<Option<AssetInfo>, AppError>;
+
+    /// Returns `None` if the asset is not missing
+    fn absence_reason(&self, id: &str) -> Result<Option<AbsenceReason>, AppError>;
+
+    fn search(&self, req: &SearchRequest) -> Result<Vec<SearchItem>, AppError>;
+
+    fn count(&self, req: &SearchRequest) -> Result<u64, AppError>;
+
+    fn updated_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        after: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<String>, AppError>;
+
+    /// Non-NFT assets issued by the address in the order of issue
+    fn by_issuer(
+        &self,
+        issuer: &str,
+        after: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<SearchItem>, AppError>;
+
+    /// Ids of all assets (including NFTs) issued by the addresses
+    fn asset_ids_by_issuers(&self, issuers: &[&str]) -> Result<Vec<String>, AppError>;
+
+    /// Page of the user defined data of all assets ordered by asset id,
+    /// `has_ticker` keeps only the assets with or without a ticker
+    fn user_defined_data(
+        &self,
+        after_asset_id: Option<&str>,
+        limit: u32,
+        has_ticker: Option<bool>,
+    ) -> Result<Vec<UserDefinedData>, AppError>;
 
-    fn search(&self, req: &SearchRequest) -> Result<Vec<String>, AppError>;
+    /// User defined data of the current assets with the given ids, missing assets are skipped
+    fn user_defined_data_by_ids(&self, ids: &[&str]) -> Result<Vec<UserDefinedData>, AppError>;
 
-    fn user_defined_data(&self) -> Result<Vec<UserDefinedData>, AppError>;
+    fn supply_changes(
+        &self,
+        id: &str,
+        height_gte: Option<i32>,
+        height_lte: Option<i32>,
+    ) -> Result<Vec<SupplyChange>, AppError>;
+
+    /// Changes of the asset made after the block `version`, `None` if the asset is not found
+    fn changes_since(&self, id: &str, version: i64) -> Result<Option<AssetDelta>, AppError>;
+
+    /// Time the consumer last saved its state, `None` if it has not handled any block yet
+    fn consumer_updated_at(&self) -> Result<Option<DateTime<Utc>>, AppError>;
 }
 
 pub struct AssetsService {
     repo: Arc<dyn repo::Repo + Send + Sync>,
-    asset_blockhaind_data_cache: Box<dyn cache::AsyncReadCache<AssetBlockchainData> + Send + Sync>,
+    asset_blockhaind_data_cache:
+        Box<dyn cache::AsyncNegativeCache<AssetBlockchainData> + Send + Sync>,
     asset_user_defined_data_cache:
         Box<dyn cache::AsyncReadCache<AssetUserDefinedData> + Send + Sync>,
-    waves_association_address: String,
+    waves_association_addresses: Vec<String>,
+    search_ranker: Option<Arc<dyn SearchRanker + Send + Sync>>,
+    cache_fallback_to_db: bool,
 }
 
 impl AssetsService {
     pub fn new(
         repo: Arc<dyn repo::Repo + Send + Sync>,
         asset_blockhaind_data_cache: Box<
-            dyn cache::AsyncReadCache<AssetBlockchainData> + Send + Sync,
+            dyn cache::AsyncNegativeCache<AssetBlockchainData> + Send + Sync,
         >,
         asset_user_defined_data_cache: Box<
             dyn cache::AsyncReadCache<AssetUserDefinedData> + Send + Sync,
         >,
-        waves_association_address: &str,
+        waves_association_addresses: &[String],
     ) -> Self {
         Self {
             repo,
             asset_blockhaind_data_cache,
             asset_user_defined_data_cache,
-            waves_association_address: waves_association_address.to_owned(),
+            waves_association_addresses: waves_association_addresses.to_owned(),
+            search_ranker: None,
+            cache_fallback_to_db: false,
+        }
+    }
+
+    /// Replaces the SQL search ranking with the custom one
+    pub fn with_search_ranker(
+        mut self,
+        search_ranker: Arc<dyn SearchRanker + Send + Sync>,
+    ) -> Self {
+        self.search_ranker = Some(search_ranker);
+        self
+    }
+
+    /// Treats unavailable cache as a cache miss instead of failing the request
+    pub fn with_cache_fallback_to_db(mut self, cache_fallback_to_db: bool) -> Self {
+        self.cache_fallback_to_db = cache_fallback_to_db;
+        self
+    }
+
+    /// Resolves the cache call failed for the unavailable cache to `miss` when falling back to the DB
+    fn or_cache_miss<T>(
+        &self,
+        result: Result<T, AppError>,
+        miss: impl FnOnce() -> T,
+    ) -> Result<T, AppError> {
+        match result {
+            Err(e) if self.cache_fallback_to_db && e.is_cache_unavailable() => {
+                warn!("cache is unavailable, falling back to the database: {}", e);
+                Ok(miss())
+            }
+            result => result,
         }
     }
 }
@@ -110,17 +325,28 @@ impl Service for AssetsService {
         let cached_asset = if opts.bypass_cache {
             None
         } else {
-            self.asset_blockhaind_data_cache.get(id).await?
+            self.or_cache_miss(
+                self.asset_blockhaind_data_cache.get_cached(id).await,
+                || None,
+            )?
         };
 
         let asset_blockchain_data = if let Some(cached) = cached_asset {
-            Some(cached)
+            match cached {
+                Cached::Value(cached) => Some(cached),
+                Cached::Absent => return Ok(None),
+            }
         } else {
-            let not_cached_asset = self.repo.get(&id)?;
+            // NFTs are fetched too to not confuse them with absent assets
+            let not_cached_asset = self.repo.get(&id, true)?;
+
+            if not_cached_asset.is_none() {
+                self.or_cache_miss(self.asset_blockhaind_data_cache.set_absent(id).await, || ())?;
+            }
 
             let asset_oracles_data = self
                 .repo
-                .data_entries(&[id], &self.waves_association_address)?;
+                .data_entries(&[id], &self.waves_association_addresses)?;
 
             let asset_oracles_data =
                 asset_oracles_data
@@ -150,7 +376,7 @@ impl Service for AssetsService {
             let cached_asset_user_defined_data = if opts.bypass_cache {
                 None
             } else {
-                self.asset_user_defined_data_cache.get(id).await?
+                self.or_cache_miss(self.asset_user_defined_data_cache.get(id).await, || None)?
             };
 
             let asset_user_defined_data = if let Some(cached) = cached_asset_user_defined_data {
@@ -162,30 +388,30 @@ impl Service for AssetsService {
 
             let asset_info = AssetInfo::from((&asset_blockchain_data, &asset_user_defined_data));
 
-            Ok(Some(asset_info))
+            Ok(Some(asset_info).filter(|ai| !ai.asset.nft))
         } else {
             Ok(None)
         }
     }
 
-    async fn mget(
-        &self,
-        ids: &[&str],
-        opts: &MgetOptions,
-    ) -> Result<Vec<Option<AssetInfo>>, AppError> {
+    async fn mget(&self, ids: &[&str], opts: &MgetOptions) -> Result<Vec<MgetResult>, AppError> {
         dbg!("AssetsService:mget");
 
-        let assets = match opts.height {
+        let (assets, cached_asset_ids) = match opts.height {
             Some(height) => {
                 let assets = {
                     timer!("assets_service::mget::mget_for_height");
-                    self.repo.mget_for_height(ids, height)?
+                    // NFTs are fetched too to not confuse them with absent assets
+                    self.repo.mget_for_height(ids, height, true)?
                 };
 
                 let asset_oracles_data = {
-                    timer!("assets_service::mget::data_entries");
-                    self.repo
-                        .data_entries(&ids, &self.waves_association_address)?
+                    timer!("assets_service::mget::data_entries_for_height");
+                    self.repo.data_entries_for_height(
+                        &ids,
+                        &self.waves_association_addresses,
+                        height,
+                    )?
                 };
 
                 let assets_oracles_data =
@@ -201,6 +427,7 @@ impl Service for AssetsService {
                             acc
                         });
 
+                // labels stay current, since the admin labels are not versioned
                 let assets_user_defined_data = {
                     timer!("assets_service::mget::mget_asset_user_defined_data");
                     self.repo.mget_asset_user_defined_data(&ids)?
@@ -242,15 +469,21 @@ impl Service for AssetsService {
                     },
                 )?;
 
-                ids.iter()
+                let assets = ids
+                    .iter()
                     .map(|id| assets.get(*id).cloned())
-                    .collect::<Vec<Option<_>>>()
+                    .collect::<Vec<Option<_>>>();
+
+                (assets, HashSet::new())
             }
             None => {
                 let cached_assets = if opts.bypass_cache {
                     vec![None; ids.len()]
                 } else {
-                    self.asset_blockhaind_data_cache.mget(ids).await?
+                    self.or_cache_miss(
+                        self.asset_blockhaind_data_cache.mget_cached(ids).await,
+                        || vec![None; ids.len()],
+                    )?
                 };
 
                 let not_cached_asset_ids = cached_assets
@@ -265,12 +498,47 @@ impl Service for AssetsService {
                     })
                     .collect_vec();
 
+                let cached_asset_ids = cached_assets
+                    .iter()
+                    .zip(ids)
+                    .filter_map(|(m, id)| match m {
+                        Some(Cached::Value(_)) => Some(*id),
+                        _ => None,
+                    })
+                    .collect::<HashSet<_>>();
+
+                // tombstones are resolved to not found assets
+                let cached_assets = cached_assets
+                    .into_iter()
+                    .map(|o| match o {
+                        Some(Cached::Value(abd)) => Some(abd),
+                        _ => None,
+                    })
+                    .collect_vec();
+
                 let assets_blockchain_data = if not_cached_asset_ids.len() > 0 {
-                    let assets = self.repo.mget(&not_cached_asset_ids)?;
+                    // NFTs are fetched too to not confuse them with absent assets,
+                    // they are filtered out below unless requested
+                    let assets = self.repo.mget(&not_cached_asset_ids, true)?;
+
+                    let found_asset_ids = assets
+                        .iter()
+                        .filter_map(|o| o.as_ref().map(|a| a.id.as_str()))
+                        .collect::<HashSet<_>>();
+
+                    for id in not_cached_asset_ids
+                        .iter()
+                        .filter(|id| !found_asset_ids.contains(*id))
+                    {
+                        self.or_cache_miss(
+                            self.asset_blockhaind_data_cache.set_absent(id).await,
+                            || (),
+                        )?;
+                    }
 
                     let asset_oracles_data = self
                         .repo
-                        .data_entries(&not_cached_asset_ids, &self.waves_association_address)?;
+                        .data_entries(&not_cached_asset_ids, &self.waves_association_addresses)?;
 
                     // AssetId -> OracleAddress -> Vec<DataEntry>
                     let assets_oracles_data =
@@ -321,7 +589,9 @@ impl Service for AssetsService {
                 let cached_assets_user_defined_data = if opts.bypass_cache {
                     vec![None; ids.len()]
                 } else {
-                    self.asset_user_defined_data_cache.mget(ids).await?
+                    self.or_cache_miss(self.asset_user_defined_data_cache.mget(ids).await, || {
+                        vec![None; ids.len()]
+                    })?
                 };
 
                 let not_cached_asset_user_defined_data_ids = cached_assets_user_defined_data
@@ -336,6 +606,13 @@ impl Service for AssetsService {
                     })
                     .collect_vec();
 
+                let cached_asset_ids = cached_assets_user_defined_data
+                    .iter()
+                    .zip(ids)
+                    .filter(|(m, id)| m.is_some() && cached_asset_ids.contains(*id))
+                    .map(|(_, id)| *id)
+                    .collect::<HashSet<_>>();
+
                 let assets_user_defined_data = if not_cached_asset_user_defined_data_ids.len() > 0 {
                     let assets_user_defined_data = self.repo.mget_asset_user_defined_data(&ids)?;
 
@@ -376,59 +653,759 @@ impl Service for AssetsService {
 
                 if let Some(asset) = assets.get_mut(WAVES_ID) {
                     if asset.asset.description != "" {
-                        warn!("Ignoring description of WAVES asset stored in database: {}", asset.asset.description);
+                        warn!(
+                            "Ignoring description of WAVES asset stored in database: {}",
+                            asset.asset.description
+                        );
                     }
                     asset.asset.description = WAVES_DESCR.to_owned();
                 }
 
-                ids.iter()
+                let assets = ids
+                    .iter()
                     .map(|id| assets.get(*id).cloned())
-                    .collect::<Vec<Option<_>>>()
+                    .collect::<Vec<Option<_>>>();
+
+                (assets, cached_asset_ids)
             }
         };
 
-        // not found assets should be returned as nulls
-        let nft_filtered_assets = assets
+        let results = assets
             .into_iter()
-            .map(|o| o.and_then(|ai| if ai.asset.nft { None } else { Some(ai) }))
+            .zip(ids)
+            .map(|(o, id)| match o {
+                Some(ai) if ai.asset.nft && !opts.include_nfts => MgetResult::Nft,
+                Some(ai) if cached_asset_ids.contains(id) => MgetResult::Cached(ai),
+                Some(ai) => MgetResult::Found(ai),
+                None => MgetResult::NotFound,
+            })
             .collect::<Vec<_>>();
 
-        Ok(nft_filtered_assets)
+        Ok(results)
+    }
+
+    /// Serves the assets found in the blockchain data cache without the user defined data and
+    /// the database, the missed ones through `mget`
+    async fn mget_brief(
+        &self,
+        ids: &[&str],
+        opts: &MgetOptions,
+    ) -> Result<Vec<MgetResult>, AppError> {
+        if opts.height.is_some() || opts.bypass_cache {
+            return self.mget(ids, opts).await;
+        }
+
+        let cached_assets = {
+            timer!("assets_service::mget_brief::mget_cached");
+            self.or_cache_miss(
+                self.asset_blockhaind_data_cache.mget_cached(ids).await,
+                || vec![None; ids.len()],
+            )?
+        };
+
+        let missed_ids = cached_assets
+            .iter()
+            .zip(ids)
+            .filter_map(|(cached, id)| cached.is_none().then(|| *id))
+            .collect_vec();
+
+        let mut missed_assets = if missed_ids.is_empty() {
+            vec![]
+        } else {
+            self.mget(&missed_ids, opts).await?
+        }
+        .into_iter();
+
+        let results = cached_assets
+            .into_iter()
+            .map(|cached| match cached {
+                Some(Cached::Value(abd)) if abd.nft && !opts.include_nfts => MgetResult::Nft,
+                Some(Cached::Value(abd)) => MgetResult::Cached(AssetInfo {
+                    asset: Asset::from(&abd),
+                    metadata: AssetMetadata::default(),
+                }),
+                Some(Cached::Absent) => MgetResult::NotFound,
+                None => missed_assets.next().unwrap_or(MgetResult::NotFound),
+            })
+            .collect();
+
+        Ok(results)
+    }
+
+    async fn get_by_ticker(
+        &self,
+        ticker: &str,
+        opts: &GetOptions,
+    ) -> Result<Option<AssetInfo>, AppError> {
+        match self.repo.get_by_ticker(ticker)? {
+            Some(asset) => self.get(&asset.id, opts).await,
+            None => Ok(None),
+        }
+    }
+
+    fn absence_reason(&self, id: &str) -> Result<Option<AbsenceReason>, AppError> {
+        let reason = match self.repo.get(id, true)? {
+            None => Some(AbsenceReason::NotFound),
+            Some(asset) if asset.nft => Some(AbsenceReason::Hidden),
+            Some(_) => None,
+        };
+
+        Ok(reason)
+    }
+
+    fn search(&self, req: &SearchRequest) -> Result<Vec<SearchItem>, AppError> {
+        let find_params = FindParams::from(req);
+
+        if let Some(ranker) = self.search_ranker.as_ref() {
+            let candidates = self.repo.find_candidates(FindParams {
+                limit: ranker.candidates_limit(),
+                ..find_params
+            })?;
+
+            let ranked = ranker.rank(candidates);
+
+            // ranked candidates are paginated by asset ids
+            return Ok(ranked_page(
+                &ranked,
+                req.after.as_deref(),
+                req.before.as_deref(),
+                req.limit,
+            )
+            .into_iter()
+            .map(|id| SearchItem {
+                cursor: id.clone(),
+                id,
+            })
+            .collect());
+        }
+
+        self.repo.find(find_params).map(|assets| {
+            assets
+                .into_iter()
+                .map(|asset| SearchItem {
+                    cursor: cursor::encode(&asset.sort_keys),
+                    id: asset.id,
+                })
+                .collect()
+        })
+    }
+
+    fn count(&self, req: &SearchRequest) -> Result<u64, AppError> {
+        self.repo.count(FindParams::from(req))
+    }
+
+    fn updated_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        after: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<String>, AppError> {
+        self.repo
+            .assets_updated_between(from, to, after, limit)
+            .map(|asset_ids| asset_ids.into_iter().map(|asset_id| asset_id.id).collect())
+    }
+
+    fn by_issuer(
+        &self,
+        issuer: &str,
+        after: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<SearchItem>, AppError> {
+        self.repo.issuer_assets(issuer, after, limit).map(|assets| {
+            assets
+                .into_iter()
+                .map(|asset| SearchItem {
+                    cursor: cursor::encode(&asset.sort_keys),
+                    id: asset.id,
+                })
+                .collect()
+        })
+    }
+
+    fn asset_ids_by_issuers(&self, issuers: &[&str]) -> Result<Vec<String>, AppError> {
+        self.repo
+            .get_last_asset_ids_by_issuers(issuers)
+            .map(|asset_ids| asset_ids.into_iter().map(|asset_id| asset_id.id).collect())
+    }
+
+    fn user_defined_data(
+        &self,
+        after_asset_id: Option<&str>,
+        limit: u32,
+        has_ticker: Option<bool>,
+    ) -> Result<Vec<UserDefinedData>, AppError> {
+        self.repo
+            .all_assets_user_defined_data(after_asset_id, limit, has_ticker)
+    }
+
+    fn user_defined_data_by_ids(&self, ids: &[&str]) -> Result<Vec<UserDefinedData>, AppError> {
+        self.repo.mget_asset_user_defined_data(ids)
+    }
+
+    fn supply_changes(
+        &self,
+        id: &str,
+        height_gte: Option<i32>,
+        height_lte: Option<i32>,
+    ) -> Result<Vec<SupplyChange>, AppError> {
+        self.repo.supply_changes(id, height_gte, height_lte)
+    }
+
+    fn consumer_updated_at(&self) -> Result<Option<DateTime<Utc>>, AppError> {
+        self.repo.consumer_updated_at()
     }
 
-    fn search(&self, req: &SearchRequest) -> Result<Vec<String>, AppError> {
-        let find_params = FindParams {
+    fn changes_since(&self, id: &str, version: i64) -> Result<Option<AssetDelta>, AppError> {
+        let current = match self.repo.asset_version(id, None)? {
+            Some(current) => current,
+            None => return Ok(None),
+        };
+
+        let changes = if current.block_uid <= version {
+            AssetChanges::default()
+        } else {
+            let known = self.repo.asset_version(id, Some(version))?;
+            AssetChanges::between(known.as_ref(), &current)
+        };
+
+        Ok(Some(AssetDelta {
+            version: current.block_uid,
+            changes,
+        }))
+    }
+}
+
+fn label_conditions(labels: &[String]) -> Vec<LabelCondition> {
+    labels
+        .iter()
+        .map(|label| LabelCondition::from(label.as_str()))
+        .unique()
+        .collect()
+}
+
+impl From<&SearchRequest> for FindParams {
+    fn from(req: &SearchRequest) -> Self {
+        Self {
             search: req.search.clone(),
-            ticker: req.ticker.as_ref().map(|ticker| {
-                if ticker.as_str() == "*" {
-                    TickerFilter::Any
-                } else {
-                    TickerFilter::One(ticker.to_owned())
-                }
-            }),
+            ticker: match (req.ticker.as_ref(), req.ticker_in.as_ref()) {
+                (Some(ticker), _) if ticker.as_str() == "*" => Some(TickerFilter::Any),
+                (Some(ticker), _) => Some(TickerFilter::One(ticker.to_owned())),
+                (None, Some(tickers)) => Some(TickerFilter::In(tickers.clone())),
+                (None, None) => None,
+            },
             label: req.label.as_ref().map(|label| {
                 if label.as_str() == "*" {
                     LabelFilter::Any
                 } else {
-                    LabelFilter::One(label.to_owned())
+                    LabelFilter::One(AssetLabel::from(label.as_str()))
                 }
             }),
             smart: req.smart,
-            asset_label_in: req.asset_label_in.clone(),
+            reissuable: req.reissuable,
+            include_nfts: req.include_nfts,
+            sponsored: req.sponsored,
+            precision_gte: req.precision_gte,
+            precision_lte: req.precision_lte,
+            created_height_gte: req.created_height_gte,
+            created_height_lte: req.created_height_lte,
+            created_after_height: req.created_after_height,
+            created_before_height: req.created_before_height,
+            created_after: req.created_after,
+            created_before: req.created_before,
+            asset_label_in: req
+                .asset_label_in
+                .as_ref()
+                .map(|labels| label_conditions(labels)),
+            asset_label_not_in: req
+                .asset_label_not_in
+                .as_ref()
+                .map(|labels| label_conditions(labels)),
             issuer_in: req.issuer_in.clone(),
+            verified_status_in: req.verified_status_in.clone(),
             after: req.after.clone(),
+            before: req.before.clone(),
+            sort: req.sort.clone(),
             limit: req.limit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::entities::{AssetChanges, AssetDelta};
+    use super::mock::MockRepo;
+    use super::repo::{Asset, AssetVersion, OracleDataEntry};
+    use super::{
+        AbsenceReason, AssetsService, GetOptions, MgetOptions, MgetResult, Service,
+        UserDefinedDataPages,
+    };
+    use crate::cache::{
+        memory_cache::MemoryCache, AssetBlockchainData, AssetUserDefinedData, AsyncNegativeCache,
+        AsyncReadCache, CacheKeyFn, Cached,
+    };
+    use crate::db::enums::DataEntryValueType;
+    use crate::error::Error as AppError;
+
+    /// Cache failing every call as if Redis was down
+    struct UnavailableCache;
+
+    impl UnavailableCache {
+        fn error() -> AppError {
+            AppError::Bb8RunError("connection refused".to_owned())
+        }
+    }
+
+    impl CacheKeyFn for UnavailableCache {
+        fn key_fn(&self, source_key: &str) -> String {
+            source_key.to_owned()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl<T: Send + Sync + 'static> AsyncReadCache<T> for UnavailableCache {
+        async fn get(&self, _key: &str) -> Result<Option<T>, AppError> {
+            Err(Self::error())
+        }
+
+        async fn mget(&self, _keys: &[&str]) -> Result<Vec<Option<T>>, AppError> {
+            Err(Self::error())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl<T: Send + Sync + 'static> AsyncNegativeCache<T> for UnavailableCache {
+        async fn get_cached(&self, _key: &str) -> Result<Option<Cached<T>>, AppError> {
+            Err(Self::error())
+        }
+
+        async fn mget_cached(&self, _keys: &[&str]) -> Result<Vec<Option<Cached<T>>>, AppError> {
+            Err(Self::error())
+        }
+
+        async fn set_absent(&self, _key: &str) -> Result<(), AppError> {
+            Err(Self::error())
+        }
+    }
+
+    fn asset(id: &str, ticker: Option<&str>, nft: bool) -> Asset {
+        Asset {
+            id: id.to_owned(),
+            name: id.to_uppercase(),
+            precision: 0,
+            description: "".to_owned(),
+            height: 1,
+            timestamp: Utc::now(),
+            issuer: "issuer".to_owned(),
+            quantity: 1,
+            reissuable: false,
+            min_sponsored_fee: None,
+            smart: false,
+            nft,
+            sponsor_regular_balance: None,
+            sponsor_out_leasing: None,
+            ticker: ticker.map(ToOwned::to_owned),
+        }
+    }
+
+    fn service(repo: Arc<MockRepo>) -> AssetsService {
+        AssetsService::new(
+            repo,
+            Box::new(MemoryCache::<AssetBlockchainData>::default()),
+            Box::new(MemoryCache::<AssetUserDefinedData>::default()),
+            &[],
+        )
+    }
+
+    #[tokio::test]
+    async fn should_not_lookup_absent_asset_twice() {
+        let repo = Arc::new(MockRepo::default());
+        let service = service(repo.clone());
+
+        assert!(service
+            .get("absent", &GetOptions::default())
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(*repo.lookups.lock().unwrap(), 1);
+
+        assert!(service
+            .get("absent", &GetOptions::default())
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(*repo.lookups.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_fall_back_to_db_when_cache_is_unavailable() {
+        let repo = Arc::new(MockRepo {
+            assets: vec![("btc".to_owned(), asset("btc", Some("BTC"), false))]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        });
+        let service = |cache_fallback_to_db| {
+            AssetsService::new(
+                repo.clone(),
+                Box::new(UnavailableCache),
+                Box::new(UnavailableCache),
+                &[],
+            )
+            .with_cache_fallback_to_db(cache_fallback_to_db)
+        };
+
+        assert!(service(false)
+            .get("btc", &GetOptions::default())
+            .await
+            .is_err());
+
+        let service = service(true);
+        let found = service.get("btc", &GetOptions::default()).await.unwrap();
+        assert_eq!(found.map(|ai| ai.asset.id), Some("btc".to_owned()));
+
+        let found = service
+            .mget(&["btc", "absent"], &MgetOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            found
+                .into_iter()
+                .map(|r| r.found().map(|ai| ai.asset.id))
+                .collect::<Vec<_>>(),
+            vec![Some("btc".to_owned()), None]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_lookup_absent_asset_when_bypassing_cache() {
+        let repo = Arc::new(MockRepo::default());
+        let service = service(repo.clone());
+        let opts = GetOptions { bypass_cache: true };
+
+        service.get("absent", &opts).await.unwrap();
+        service.get("absent", &opts).await.unwrap();
+        assert_eq!(*repo.lookups.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn should_explain_absence_of_not_existing_asset() {
+        let service = service(Arc::new(MockRepo::default()));
+
+        assert_eq!(
+            service.absence_reason("absent").unwrap(),
+            Some(AbsenceReason::NotFound)
+        );
+    }
+
+    #[test]
+    fn should_explain_absence_of_hidden_asset() {
+        let repo = MockRepo {
+            assets: vec![("nft".to_owned(), asset("nft", None, true))]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        let service = service(Arc::new(repo));
+
+        assert_eq!(
+            service.absence_reason("nft").unwrap(),
+            Some(AbsenceReason::Hidden)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_get_asset_by_exact_ticker() {
+        let repo = MockRepo {
+            assets: vec![
+                ("btc".to_owned(), asset("btc", Some("BTC"), false)),
+                ("nft".to_owned(), asset("nft", Some("NFT"), true)),
+            ]
+            .into_iter()
+            .collect(),
+            ..Default::default()
         };
+        let service = service(Arc::new(repo));
+
+        let found = service
+            .get_by_ticker("BTC", &GetOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(found.map(|ai| ai.asset.id), Some("btc".to_owned()));
+
+        for ticker in &["NFT", "BT"] {
+            let found = service
+                .get_by_ticker(ticker, &GetOptions::default())
+                .await
+                .unwrap();
+            assert!(found.is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn should_tell_assets_served_from_cache() {
+        let repo = Arc::new(MockRepo {
+            assets: vec![
+                ("cached".to_owned(), asset("cached", None, false)),
+                ("fresh".to_owned(), asset("fresh", None, false)),
+            ]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        });
 
-        self.repo.find(find_params).map(|asset_ids| {
-            asset_ids
+        let blockchain_data_cache = MemoryCache::<AssetBlockchainData>::default();
+        let cached = AssetBlockchainData::try_from_asset_and_oracles_data(
+            &asset("cached", None, false),
+            &HashMap::new(),
+        )
+        .unwrap();
+        blockchain_data_cache.insert("cached", Cached::Value(cached));
+        let user_defined_data_cache = MemoryCache::<AssetUserDefinedData>::default();
+        user_defined_data_cache
+            .insert("cached", Cached::Value(AssetUserDefinedData::new("cached")));
+
+        let service = AssetsService::new(
+            repo,
+            Box::new(blockchain_data_cache),
+            Box::new(user_defined_data_cache),
+            &[],
+        );
+
+        let results = service
+            .mget(&["fresh", "cached", "absent"], &MgetOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            results
                 .iter()
-                .map(|asset_id| asset_id.id.to_owned())
-                .collect()
-        })
+                .map(MgetResult::from_cache)
+                .collect::<Vec<_>>(),
+            vec![false, true, false]
+        );
+        assert_eq!(
+            results
+                .into_iter()
+                .map(|r| r.found().map(|ai| ai.asset.id))
+                .collect::<Vec<_>>(),
+            vec![Some("fresh".to_owned()), Some("cached".to_owned()), None]
+        );
+
+        let results = service
+            .mget(&["cached"], &MgetOptions::with_bypass_cache(true))
+            .await
+            .unwrap();
+        assert!(!results[0].from_cache());
+    }
+
+    fn blockchain_data_cache(
+        assets: &[Asset],
+        oracles_data: &HashMap<String, Vec<OracleDataEntry>>,
+    ) -> MemoryCache<AssetBlockchainData> {
+        let cache = MemoryCache::<AssetBlockchainData>::default();
+        for asset in assets {
+            let abd =
+                AssetBlockchainData::try_from_asset_and_oracles_data(asset, oracles_data).unwrap();
+            cache.insert(&asset.id, Cached::Value(abd));
+        }
+        cache
+    }
+
+    #[tokio::test]
+    async fn should_serve_brief_assets_from_cache_alone() {
+        let cache = blockchain_data_cache(
+            &[asset("btc", Some("BTC"), false), asset("nft", None, true)],
+            &HashMap::new(),
+        );
+        cache.insert("absent", Cached::Absent);
+
+        // neither the repo nor the user defined data are touched on a full cache hit
+        let service = AssetsService::new(
+            Arc::new(MockRepo::unused()),
+            Box::new(cache),
+            Box::new(UnavailableCache),
+            &[],
+        );
+
+        let results = service
+            .mget_brief(&["btc", "nft", "absent"], &MgetOptions::default())
+            .await
+            .unwrap();
+        assert!(results[0].from_cache());
+        assert!(matches!(results[1], MgetResult::Nft));
+        assert!(matches!(results[2], MgetResult::NotFound));
+
+        let btc = results.into_iter().next().unwrap().found().unwrap();
+        assert_eq!(
+            (btc.asset.id, btc.asset.name, btc.asset.ticker),
+            ("btc".to_owned(), "BTC".to_owned(), Some("BTC".to_owned()))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_fall_back_to_mget_for_brief_assets_missing_in_cache() {
+        let repo = Arc::new(MockRepo {
+            assets: vec![("fresh".to_owned(), asset("fresh", None, false))]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        });
+        let service = AssetsService::new(
+            repo.clone(),
+            Box::new(blockchain_data_cache(
+                &[asset("cached", None, false)],
+                &HashMap::new(),
+            )),
+            Box::new(MemoryCache::<AssetUserDefinedData>::default()),
+            &[],
+        );
+
+        let results = service
+            .mget_brief(&["fresh", "cached", "missing"], &MgetOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            results
+                .iter()
+                .map(MgetResult::from_cache)
+                .collect::<Vec<_>>(),
+            vec![false, true, false]
+        );
+        assert_eq!(
+            results
+                .into_iter()
+                .map(|r| r.found().map(|ai| ai.asset.id))
+                .collect::<Vec<_>>(),
+            vec![Some("fresh".to_owned()), Some("cached".to_owned()), None]
+        );
+        // only the missed assets are looked up, at once
+        assert_eq!(*repo.lookups.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn should_visit_all_user_defined_data_pages() {
+        let ids = ["a", "b", "c", "d", "e"];
+        let repo = Arc::new(MockRepo {
+            assets: ids
+                .iter()
+                .map(|id| {
+                    let ticker = if *id == "b" { Some("B") } else { None };
+                    (id.to_string(), asset(id, ticker, false))
+                })
+                .collect(),
+            ..Default::default()
+        });
+        let service = service(repo.clone());
+
+        let visited = UserDefinedDataPages::new(&service, 2, None)
+            .map(Result::unwrap)
+            .inspect(|page| assert!(page.len() <= 2))
+            .flatten()
+            .map(|data| data.asset_id)
+            .collect::<Vec<_>>();
+        assert_eq!(visited, ids);
+        // the last page is short
+        assert_eq!(*repo.lookups.lock().unwrap(), 3);
+
+        let visited = UserDefinedDataPages::new(&service, 2, Some(false))
+            .map(Result::unwrap)
+            .flatten()
+            .map(|data| data.asset_id)
+            .collect::<Vec<_>>();
+        assert_eq!(visited, ["a", "c", "d", "e"]);
+    }
+
+    fn asset_version(block_uid: i64, quantity: i64) -> AssetVersion {
+        AssetVersion {
+            block_uid,
+            name: "Asset".to_owned(),
+            description: "".to_owned(),
+            quantity,
+            reissuable: true,
+            min_sponsored_fee: None,
+            smart: false,
+        }
+    }
+
+    #[test]
+    fn should_return_changed_fields_only() {
+        let repo = Arc::new(MockRepo {
+            versions: vec![asset_version(10, 100), asset_version(20, 150)],
+            ..Default::default()
+        });
+        let service = service(repo);
+
+        assert_eq!(
+            service.changes_since("asset", 15).unwrap(),
+            Some(AssetDelta {
+                version: 20,
+                changes: AssetChanges {
+                    quantity: Some(150),
+                    ..Default::default()
+                },
+            })
+        );
+
+        let delta = service.changes_since("asset", 20).unwrap().unwrap();
+        assert!(delta.changes.is_empty());
+
+        // unknown asset version yields all the fields
+        let delta = service.changes_since("asset", 5).unwrap().unwrap();
+        assert_eq!(delta.changes.name, Some("Asset".to_owned()));
+        assert_eq!(delta.changes.min_sponsored_fee, Some(None));
+    }
+
+    fn oracle_data_entry(value: &str) -> OracleDataEntry {
+        OracleDataEntry {
+            asset_id: "asset".to_owned(),
+            oracle_address: "oracle".to_owned(),
+            key: "status_<asset>".to_owned(),
+            data_type: DataEntryValueType::Str,
+            bin_val: None,
+            bool_val: None,
+            int_val: None,
+            str_val: Some(value.to_owned()),
+            uid: 1,
+        }
     }
 
-    fn user_defined_data(&self) -> Result<Vec<UserDefinedData>, AppError> {
-        self.repo.all_assets_user_defined_data()
+    #[tokio::test]
+    async fn should_return_oracle_data_as_of_height() {
+        let repo = Arc::new(MockRepo {
+            assets: vec![("asset".to_owned(), asset("asset", None, false))]
+                .into_iter()
+                .collect(),
+            oracle_data: vec![
+                (10, oracle_data_entry("unverified")),
+                (20, oracle_data_entry("verified")),
+            ],
+            ..Default::default()
+        });
+        let service = service(repo);
+
+        let oracle_values = |results: Vec<MgetResult>| {
+            results
+                .into_iter()
+                .filter_map(MgetResult::found)
+                .flat_map(|ai| ai.metadata.oracles_data.into_values().flatten())
+                .filter_map(|entry| entry.str_val)
+                .collect::<Vec<_>>()
+        };
+
+        let before_change = service
+            .mget(&["asset"], &MgetOptions::with_height(15))
+            .await
+            .unwrap();
+        assert_eq!(oracle_values(before_change), vec!["unverified"]);
+
+        let after_change = service
+            .mget(&["asset"], &MgetOptions::with_height(20))
+            .await
+            .unwrap();
+        assert_eq!(oracle_values(after_change), vec!["verified"]);
     }
 }
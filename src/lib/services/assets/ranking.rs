@@ -0,0 +1,84 @@
+use super::repo::SearchCandidate;
+
+pub const DEFAULT_CANDIDATES_LIMIT: u32 = 1000;
+
+/// Custom search ranking applied on top of the SQL search
+///
+/// Post-ranking needs the whole candidates window to be fetched and ranked per request,
+/// so it is slower than the SQL ranking. Assets beyond the window are never returned.
+pub trait SearchRanker {
+    /// Max number of candidates (in SQL ranking order) fetched for ranking
+    fn candidates_limit(&self) -> u32 {
+        DEFAULT_CANDIDATES_LIMIT
+    }
+
+    fn rank(&self, candidates: Vec<SearchCandidate>) -> Vec<SearchCandidate>;
+}
+
+/// Takes page of ranked candidates ids following the `after` or preceding the `before` cursor
+pub fn ranked_page(
+    ranked: &[SearchCandidate],
+    after: Option<&str>,
+    before: Option<&str>,
+    limit: u32,
+) -> Vec<String> {
+    let position = |cursor: &str| ranked.iter().position(|c| c.id == cursor);
+
+    let page = match (after, before) {
+        (_, Some(before)) => {
+            let end = position(before).unwrap_or(0);
+            let start = end.saturating_sub(limit as usize);
+            &ranked[start..end]
+        }
+        (Some(after), _) => {
+            let start = position(after).map(|p| p + 1).unwrap_or(ranked.len());
+            let end = ranked.len().min(start + limit as usize);
+            &ranked[start..end]
+        }
+        (None, None) => &ranked[..ranked.len().min(limit as usize)],
+    };
+
+    page.iter().map(|c| c.id.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ranked_page, SearchRanker};
+    use crate::services::assets::repo::SearchCandidate;
+
+    struct ReverseRanker;
+
+    impl SearchRanker for ReverseRanker {
+        fn rank(&self, mut candidates: Vec<SearchCandidate>) -> Vec<SearchCandidate> {
+            candidates.sort_by(|a, b| b.rn.cmp(&a.rn));
+            candidates
+        }
+    }
+
+    #[test]
+    fn should_page_custom_ranked_candidates() {
+        let candidates = ["a", "b", "c", "d", "e"]
+            .iter()
+            .enumerate()
+            .map(|(i, id)| SearchCandidate {
+                id: id.to_string(),
+                rn: i as i64 + 1,
+            })
+            .collect();
+
+        let ranked = ReverseRanker.rank(candidates);
+
+        assert_eq!(ranked_page(&ranked, None, None, 2), vec!["e", "d"]);
+        assert_eq!(ranked_page(&ranked, Some("d"), None, 2), vec!["c", "b"]);
+        assert_eq!(ranked_page(&ranked, Some("b"), None, 2), vec!["a"]);
+        assert_eq!(ranked_page(&ranked, None, Some("b"), 2), vec!["d", "c"]);
+        assert_eq!(
+            ranked_page(&ranked, None, Some("e"), 2),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            ranked_page(&ranked, Some("x"), None, 2),
+            Vec::<String>::new()
+        );
+    }
+}
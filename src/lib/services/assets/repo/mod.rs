@@ -1,10 +1,14 @@
 pub mod pg;
 
-use diesel::sql_types::Text;
+use chrono::{DateTime, Utc};
+use diesel::sql_types::{BigInt, Text};
+use serde::Deserialize;
 
 use crate::error::Error as AppError;
+use crate::models::{AssetLabel, VerificationStatus};
 
-pub use super::entities::{Asset, OracleDataEntry, UserDefinedData};
+pub use super::entities::{Asset, AssetVersion, OracleDataEntry, SupplyChange, UserDefinedData};
+pub use crate::models::SearchWeights;
 
 #[derive(Clone, Debug, QueryableByName)]
 pub struct AssetId {
@@ -12,47 +16,183 @@ pub struct AssetId {
     pub id: String,
 }
 
+/// Search result row
+#[derive(Clone, Debug, QueryableByName)]
+pub struct SearchedAsset {
+    #[sql_type = "Text"]
+    pub id: String,
+    /// JSON array of the row sort keys ending with the asset id
+    #[sql_type = "Text"]
+    pub sort_keys: String,
+}
+
+/// Search candidate with features available for ranking
+#[derive(Clone, Debug, QueryableByName)]
+pub struct SearchCandidate {
+    #[sql_type = "Text"]
+    pub id: String,
+    /// Position in the SQL ranking, starting from 1
+    #[sql_type = "BigInt"]
+    pub rn: i64,
+}
+
 #[derive(Clone, Debug)]
 pub struct FindParams {
     pub search: Option<String>,
     pub ticker: Option<TickerFilter>,
     pub label: Option<LabelFilter>,
     pub smart: Option<bool>,
-    pub asset_label_in: Option<Vec<String>>,
+    pub reissuable: Option<bool>,
+    /// NFTs are excluded unless set, `smart` filter applies to included NFTs as well
+    pub include_nfts: bool,
+    pub sponsored: Option<bool>,
+    pub precision_gte: Option<i32>,
+    pub precision_lte: Option<i32>,
+    pub created_height_gte: Option<i32>,
+    pub created_height_lte: Option<i32>,
+    /// Exclusive bounds of the issue height
+    pub created_after_height: Option<i32>,
+    pub created_before_height: Option<i32>,
+    /// Exclusive bounds of the issue block time
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub asset_label_in: Option<Vec<LabelCondition>>,
+    pub asset_label_not_in: Option<Vec<LabelCondition>>,
     pub issuer_in: Option<Vec<String>>,
+    /// Assets without the oracle status entry are unknown
+    pub verified_status_in: Option<Vec<VerificationStatus>>,
     pub limit: u32,
     pub after: Option<String>,
+    pub before: Option<String>,
+    pub sort: Option<SearchSort>,
 }
 
 #[derive(Clone, Debug)]
 pub enum TickerFilter {
     Any,
     One(String),
+    In(Vec<String>),
 }
 #[derive(Clone, Debug)]
 pub enum LabelFilter {
     Any,
-    One(String),
+    One(AssetLabel),
+}
+
+/// Item of the `label__in` and `label__not_in` filters, the special `null` token stands for no labels
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum LabelCondition {
+    Unlabeled,
+    Label(AssetLabel),
+}
+
+impl From<&str> for LabelCondition {
+    fn from(label: &str) -> Self {
+        match label {
+            "null" => Self::Unlabeled,
+            label => Self::Label(AssetLabel::from(label)),
+        }
+    }
+}
+
+/// Search results ordering, relevance (or creation order if not searching by text) by default
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchSort {
+    CreatedAsc,
+    CreatedDesc,
+    NameAsc,
+    NameDesc,
+    QuantityAsc,
+    QuantityDesc,
+    /// Assets having the labels of the highest priority first, then by creation
+    LabelPriority,
 }
 
 pub trait Repo {
-    fn find(&self, params: FindParams) -> Result<Vec<AssetId>, AppError>;
+    fn find(&self, params: FindParams) -> Result<Vec<SearchedAsset>, AppError>;
 
-    fn get(&self, id: &str) -> Result<Option<Asset>, AppError>;
+    /// Finds up to `params.limit` candidates in SQL ranking order ignoring cursors
+    fn find_candidates(&self, params: FindParams) -> Result<Vec<SearchCandidate>, AppError>;
 
-    fn mget(&self, ids: &[&str]) -> Result<Vec<Option<Asset>>, AppError>;
+    fn count(&self, params: FindParams) -> Result<u64, AppError>;
 
-    fn mget_for_height(&self, ids: &[&str], height: i32) -> Result<Vec<Option<Asset>>, AppError>;
+    fn assets_updated_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        after: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<AssetId>, AppError>;
+
+    /// Finds current non-NFT assets of the issuer ordered by the block of their first issue
+    fn issuer_assets(
+        &self,
+        issuer: &str,
+        after: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<SearchedAsset>, AppError>;
+
+    /// Ids of the current assets (including NFTs) issued by any of the addresses
+    fn get_last_asset_ids_by_issuers(&self, issuers: &[&str]) -> Result<Vec<AssetId>, AppError>;
+
+    fn get(&self, id: &str, include_nfts: bool) -> Result<Option<Asset>, AppError>;
+
+    fn mget(&self, ids: &[&str], include_nfts: bool) -> Result<Vec<Option<Asset>>, AppError>;
+
+    /// Finds the current non-NFT asset having exactly the given ticker,
+    /// the one that took it last if several assets hold it
+    fn get_by_ticker(&self, ticker: &str) -> Result<Option<Asset>, AppError>;
+
+    fn mget_for_height(
+        &self,
+        ids: &[&str],
+        height: i32,
+        include_nfts: bool,
+    ) -> Result<Vec<Option<Asset>>, AppError>;
+
+    /// Version of the asset current at the block `block_uid_lte`, or the current one if unset
+    fn asset_version(
+        &self,
+        id: &str,
+        block_uid_lte: Option<i64>,
+    ) -> Result<Option<AssetVersion>, AppError>;
 
     fn data_entries(
         &self,
         asset_ids: &[&str],
-        oracle_address: &str,
+        oracle_addresses: &[String],
+    ) -> Result<Vec<OracleDataEntry>, AppError>;
+
+    /// Oracle data entries as they were at the height
+    fn data_entries_for_height(
+        &self,
+        asset_ids: &[&str],
+        oracle_addresses: &[String],
+        height: i32,
     ) -> Result<Vec<OracleDataEntry>, AppError>;
 
     fn get_asset_user_defined_data(&self, id: &str) -> Result<UserDefinedData, AppError>;
 
     fn mget_asset_user_defined_data(&self, ids: &[&str]) -> Result<Vec<UserDefinedData>, AppError>;
 
-    fn all_assets_user_defined_data(&self) -> Result<Vec<UserDefinedData>, AppError>;
+    /// Page of the user defined data of the current assets ordered by asset id,
+    /// `has_ticker` keeps only the assets with or without a ticker, an empty ticker is none
+    fn all_assets_user_defined_data(
+        &self,
+        after_asset_id: Option<&str>,
+        limit: u32,
+        has_ticker: Option<bool>,
+    ) -> Result<Vec<UserDefinedData>, AppError>;
+
+    /// Supply changes of the asset within the height range in the order of occurrence
+    fn supply_changes(
+        &self,
+        asset_id: &str,
+        height_gte: Option<i32>,
+        height_lte: Option<i32>,
+    ) -> Result<Vec<SupplyChange>, AppError>;
+
+    /// Time the consumer last saved its state, `None` if it has not handled any block yet
+    fn consumer_updated_at(&self) -> Result<Option<DateTime<Utc>>, AppError>;
 }
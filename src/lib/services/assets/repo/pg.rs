@@ -1,16 +1,26 @@
+use chrono::{DateTime, Utc};
 use diesel::dsl::sql;
-use diesel::sql_types::{Array, BigInt, Integer, Text};
+use diesel::sql_types::{Array, BigInt, Bool, Integer, Nullable, Text};
 use diesel::{prelude::*, sql_query};
 use itertools::Itertools;
 use lazy_static::lazy_static;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::iter::once;
 use wavesexchange_log::error;
 
-use super::{Asset, AssetId, FindParams, OracleDataEntry, Repo, TickerFilter, UserDefinedData};
+use super::{
+    Asset, AssetId, AssetVersion, FindParams, OracleDataEntry, Repo, SearchCandidate, SearchSort,
+    SearchWeights, SearchedAsset, SupplyChange, TickerFilter, UserDefinedData,
+};
 use crate::db::enums::DataEntryValueTypeMapping;
 use crate::db::PgPool;
 use crate::error::Error as AppError;
-use crate::schema::data_entries;
-use crate::services::assets::repo::LabelFilter;
+use crate::models::{AssetLabel, VerificationStatus};
+use crate::schema::{consumer_state, data_entries};
+use crate::services::assets::cursor;
+use crate::services::assets::repo::{LabelCondition, LabelFilter};
+use crate::waves;
 
 const MAX_UID: i64 = i64::MAX - 1;
 
@@ -39,219 +49,224 @@ lazy_static! {
     ", MAX_UID, MAX_UID, MAX_UID);
 }
 
+#[derive(QueryableByName)]
+struct AssetsCount {
+    #[sql_type = "BigInt"]
+    count: i64,
+}
+
 pub struct PgRepo {
     pg_pool: PgPool,
+    search_weights: SearchWeights,
+    search_source: SearchSource,
 }
 
-impl PgRepo {
-    pub fn new(pg_pool: PgPool) -> Self {
-        Self { pg_pool }
-    }
+/// Source of the searchable asset projection (id, name, ticker, labels, smart, issuer, issue block)
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SearchSource {
+    /// Current versions of the asset tables, always consistent with the last handled block
+    Live,
+    /// `searchable_assets` materialized view refreshed by the consumer
+    ///
+    /// Cheaper to query, but results lag behind the live tables until the next refresh
+    MaterializedView,
 }
 
-impl Repo for PgRepo {
-    fn find(&self, params: FindParams) -> Result<Vec<AssetId>, AppError> {
-        // conditions have to be collected before assets_cte_query construction
-        // because of difference in searching by text and searching by ticker
-        let mut conditions = vec![];
-
-        // AssetLabel Filtering
-        if let Some(asset_labels) = params.asset_label_in {
-            let mut label_filters = vec![];
+impl SearchSource {
+    /// Current assets as `a` joined with their tickers as `ast`, followed by the `WHERE` clause
+    fn current_assets(&self) -> String {
+        match self {
+            Self::Live => format!("assets AS a LEFT JOIN asset_tickers AS ast ON ast.asset_id = a.id AND ast.superseded_by = {} WHERE a.superseded_by = {}", MAX_UID, MAX_UID),
+            Self::MaterializedView => "searchable_assets AS a CROSS JOIN LATERAL (SELECT a.ticker AS ticker) AS ast WHERE 1=1".to_owned(),
+        }
+    }
 
-            if asset_labels.contains(&"null".to_string()) {
-                label_filters.push(format!("awl.labels IS NULL"));
-            }
+    /// Uid of the block where the current asset `a` was issued
+    fn issue_block_uid(&self) -> &'static str {
+        match self {
+            Self::Live => "(SELECT min(block_uid) AS block_uid FROM assets WHERE id = a.id)",
+            Self::MaterializedView => "a.block_uid",
+        }
+    }
 
-            if asset_labels.len() > 0 {
-                let labels_filter = format!(
-                    "awl.labels && ARRAY[{}]",
-                    asset_labels
-                        .iter()
-                        .map(|label| format!("'{}'", utils::pg_escape(&label)))
-                        .join(",")
-                );
-                label_filters.push(labels_filter);
+    /// Joins the current asset as `a` to the search result
+    fn search_asset_join(&self) -> String {
+        match self {
+            Self::Live => format!(
+                "LEFT JOIN assets AS a ON a.id = search.id AND a.superseded_by = {}",
+                MAX_UID
+            ),
+            Self::MaterializedView => {
+                "LEFT JOIN searchable_assets AS a ON a.id = search.id".to_owned()
             }
+        }
+    }
 
-            conditions.push(format!("({})", label_filters.join(" OR ")));
+    /// Current assets as `a` having the issue `block_uid`, joined with their tickers as `ast`
+    fn filtered_assets(&self, nft_condition: &str) -> String {
+        match self {
+            Self::Live => format!(
+                "(SELECT a.id, a.smart, (SELECT min(a1.block_uid) FROM assets a1 WHERE a1.id = a.id) AS block_uid, a.issuer, a.name, a.quantity, a.min_sponsored_fee, a.precision, a.reissuable FROM assets AS a WHERE a.superseded_by = {} AND {}) AS a
+            LEFT JOIN asset_tickers AS ast ON ast.asset_id = a.id and ast.superseded_by = {}",
+                MAX_UID, nft_condition, MAX_UID
+            ),
+            Self::MaterializedView => format!(
+                "(SELECT * FROM searchable_assets AS a WHERE {}) AS a
+            CROSS JOIN LATERAL (SELECT a.ticker AS ticker) AS ast",
+                nft_condition
+            ),
         }
+    }
 
-        if let Some(smart) = params.smart {
-            conditions.push(format!("a.smart = {}", smart));
+    /// Joins the labels of the asset `asset_id` as `awl.labels`
+    fn labels_join(&self, asset_id: &str) -> String {
+        match self {
+            Self::Live => format!(
+                "LEFT JOIN (
+                SELECT asset_id, ARRAY_AGG(DISTINCT labels_list) AS labels
+                FROM (
+                    SELECT al.asset_id as asset_id, al.labels
+                    FROM asset_labels AS al
+                    WHERE al.superseded_by = {}
+                    UNION
+                    SELECT awl.asset_id as asset_id, ARRAY_AGG(awl.label) as labels
+                    FROM asset_wx_labels AS awl
+                    GROUP BY awl.asset_id
+                ) AS data, UNNEST(labels) AS labels_list
+                GROUP BY asset_id
+            ) AS awl ON awl.asset_id = {}",
+                MAX_UID, asset_id
+            ),
+            // the view row is already joined as `a`
+            Self::MaterializedView => {
+                "CROSS JOIN LATERAL (SELECT a.labels AS labels) AS awl".to_owned()
+            }
         }
+    }
+}
 
-        if let Some(issuer_in) = params.issuer_in {
-            conditions.push(format!(
-                "a.issuer = ANY(ARRAY[{}])",
-                issuer_in
-                    .iter()
-                    .map(|addr| format!("'{}'", utils::pg_escape(&addr)))
-                    .join(",")
-            ));
+impl PgRepo {
+    pub fn new(pg_pool: PgPool) -> Self {
+        Self {
+            pg_pool,
+            search_weights: SearchWeights::default(),
+            search_source: SearchSource::Live,
         }
+    }
 
-        let assets_cte_query = if let Some(search) = params.search.as_ref() {
-            let search = utils::pg_escape(search);
-            let min_block_uid_subquery =
-                "SELECT min(block_uid) AS block_uid FROM assets WHERE id = a.id";
-
-            let search_escaped_for_like = utils::escape_for_like(&search);
-
-            let search_by_id_query = format!("SELECT a.id, a.smart, ({}) as block_uid, CASE WHEN (ast.ticker IS NULL or ast.ticker = '') THEN 128 ELSE 256 END AS rank FROM assets AS a LEFT JOIN asset_tickers AS ast ON ast.asset_id = a.id and ast.superseded_by = {} WHERE a.superseded_by = {} AND a.nft = {} AND a.id ILIKE '{}'", min_block_uid_subquery, MAX_UID, MAX_UID, false, search_escaped_for_like);
-            // UNION
-            let search_by_meta_query = format!("SELECT id, false AS smart, block_uid, ts_rank(to_tsvector('simple', name), plainto_tsquery('simple', '{}'), 3) * CASE WHEN ticker IS NULL THEN 64 ELSE 128 END AS rank FROM asset_metadatas WHERE name ILIKE '{}%'", search, search_escaped_for_like);
-            // UNION
-            let search_by_ticker_query = format!("SELECT a.id, a.smart, ({}) as block_uid, 32 AS rank FROM assets AS a LEFT JOIN asset_tickers AS ast ON a.id = ast.asset_id and ast.superseded_by = {} WHERE a.superseded_by = {} AND a.nft = {} AND ast.ticker ILIKE '{}%'", min_block_uid_subquery, MAX_UID, MAX_UID, false, search_escaped_for_like);
-            // UNION
-            let tsquery_condition = {
-                let search_escaped_for_tsquery = utils::escape_for_tsquery(&search);
-                if search_escaped_for_tsquery.len() > 0 {
-                    format!(
-                        "to_tsvector('simple', a.name) @@ to_tsquery('simple', '{}:*')",
-                        search_escaped_for_tsquery
-                    )
-                } else {
-                    "1=1".to_owned()
-                }
-            };
-            let search_by_tsquery_query = format!("SELECT a.id, a.smart, ({}) as block_uid, ts_rank(to_tsvector('simple', a.name), plainto_tsquery('simple', '{}'), 3) * CASE WHEN (ast.ticker IS NULL or ast.ticker = '') THEN 16 ELSE 32 END AS rank FROM assets a LEFT JOIN asset_tickers AS ast ON ast.asset_id = a.id and ast.superseded_by = {} WHERE a.superseded_by = {} AND a.nft = {} AND {}", min_block_uid_subquery, search, MAX_UID, MAX_UID, false, tsquery_condition);
-            // UNION
-            let search_by_name_query = format!("SELECT a.id, a.smart, ({}) as block_uid, ts_rank(to_tsvector('simple', a.name), plainto_tsquery('simple', '{}'), 3) * CASE WHEN (ast.ticker IS NULL or ast.ticker = '') THEN 16 ELSE 32 END AS rank FROM assets a LEFT JOIN asset_tickers AS ast ON ast.asset_id = a.id and ast.superseded_by = {} WHERE a.superseded_by = {} AND a.nft = {} AND a.name ILIKE '{}%'", min_block_uid_subquery, search, MAX_UID, MAX_UID, false, search_escaped_for_like);
+    /// Searches the `searchable_assets` materialized view instead of joining the live tables
+    pub fn with_materialized_view(mut self, enabled: bool) -> Self {
+        self.search_source = if enabled {
+            SearchSource::MaterializedView
+        } else {
+            SearchSource::Live
+        };
+        self
+    }
 
-            let search_query_vec = vec![
-                search_by_id_query,
-                search_by_meta_query,
-                search_by_ticker_query,
-                search_by_tsquery_query,
-                search_by_name_query,
-            ];
+    pub fn with_search_weights(mut self, search_weights: SearchWeights) -> Self {
+        self.search_weights = search_weights;
+        self
+    }
+}
 
-            match params.label.as_ref() {
-                Some(LabelFilter::One(label)) => {
-                    let label = utils::pg_escape(label);
-                    conditions.push(format!("'{}' = ANY(labels)", label));
-                }
-                Some(LabelFilter::Any) => {
-                    conditions.push(format!("array_length(labels,1) > 0"));
-                }
-                None => {}
-            }
+impl Repo for PgRepo {
+    fn find(&self, params: FindParams) -> Result<Vec<SearchedAsset>, AppError> {
+        let assets_cte_query = assets_cte_query(&params, &self.search_weights, self.search_source);
 
-            let search_query = search_query_vec.join("\n UNION \n");
+        let keys_desc = sort_keys(params.sort.as_ref(), params.search.is_some())
+            .into_iter()
+            .map(|(_, desc)| desc)
+            .collect_vec();
 
-            let conditions = if conditions.len() > 0 {
-                format!("WHERE {}", conditions.iter().join(" AND "))
-            } else {
-                "".to_owned()
-            };
+        let sql = paginate(
+            &assets_cte_query,
+            &keys_desc,
+            params.after.as_deref(),
+            params.before.as_deref(),
+        )?;
 
-            format!(
-                "SELECT DISTINCT ON (search.id)
-                    search.id,
-                    ROW_NUMBER() OVER (ORDER BY search.rank DESC, search.block_uid ASC, search.id ASC) AS rn
-                FROM
-                    ({}) AS search
-                LEFT JOIN assets AS a ON a.id = search.id AND a.superseded_by = {}
-                LEFT JOIN (
-                    SELECT asset_id, ARRAY_AGG(DISTINCT labels_list) AS labels
-                    FROM (
-                        SELECT al.asset_id as asset_id, al.labels
-                        FROM asset_labels AS al
-                        WHERE al.superseded_by = {}
-                        UNION
-                        SELECT awl.asset_id as asset_id, ARRAY_AGG(awl.label) as labels
-                        FROM asset_wx_labels AS awl
-                        GROUP BY awl.asset_id
-                    ) AS data, UNNEST(labels) AS labels_list
-                    GROUP BY asset_id
-                ) AS awl ON awl.asset_id = search.id
-                {}
-                ORDER BY search.id ASC, search.rank DESC",
-                search_query,
-                MAX_UID,
-                MAX_UID,
-                conditions
-            )
-        } else {
-            // search by ticker only if there is not searching by text
-            if let Some(ticker) = params.ticker.as_ref() {
-                match ticker {
-                    TickerFilter::One(ticker) => {
-                        conditions.push(format!("ast.ticker = '{}'", utils::pg_escape(ticker)));
-                    }
-                    TickerFilter::Any => {
-                        conditions.push(format!("ast.ticker IS NOT NULL AND ast.ticker != ''"));
-                    }
-                }
-            }
+        //println!("sql: {sql}");
 
-            // search by label only if there is not searching by text
-            if let Some(filter_label) = params.label.as_ref() {
-                match filter_label {
-                    LabelFilter::One(label) => {
-                        conditions.push(format!("'{}' = ANY(labels)", utils::pg_escape(&label)));
-                    }
-                    LabelFilter::Any => {
-                        conditions.push(format!("array_length(labels,1) > 0"));
-                    }
-                }
-            }
+        let q = sql_query(sql).bind::<Integer, _>(params.limit as i32);
 
-            let conditions = if conditions.len() > 0 {
-                format!("WHERE {}", conditions.iter().join(" AND "))
-            } else {
-                "".to_owned()
-            };
+        q.load(&self.pg_pool.get()?).map_err(|e| {
+            error!("{:?}", e);
+            AppError::from(e)
+        })
+    }
 
-            format!(
-                "SELECT DISTINCT ON (a.id, a.block_uid)
-                    a.id,
-                    ROW_NUMBER() OVER (ORDER BY a.block_uid ASC, a.id ASC) AS rn
-                FROM
-                    (SELECT a.id, a.smart, (SELECT min(a1.block_uid) FROM assets a1 WHERE a1.id = a.id) AS block_uid, a.issuer FROM assets AS a WHERE a.superseded_by = {} AND a.nft = {}) AS a
-                LEFT JOIN asset_tickers AS ast ON ast.asset_id = a.id and ast.superseded_by = {}
-                LEFT JOIN (
-                    SELECT asset_id, ARRAY_AGG(DISTINCT labels_list) AS labels
-                    FROM (
-                        SELECT al.asset_id as asset_id, al.labels
-                        FROM asset_labels AS al
-                        WHERE al.superseded_by = {}
-                        UNION
-                        SELECT awl.asset_id as asset_id, ARRAY_AGG(awl.label) as labels
-                        FROM asset_wx_labels AS awl
-                        GROUP BY awl.asset_id
-                    ) AS data, UNNEST(labels) AS labels_list
-                    GROUP BY asset_id
-                ) AS awl ON awl.asset_id = a.id
-                {}
-                ORDER BY a.block_uid ASC",
-                MAX_UID,
-                false,
-                MAX_UID,
-                MAX_UID,
-                conditions
-            )
-        };
+    fn find_candidates(&self, params: FindParams) -> Result<Vec<SearchCandidate>, AppError> {
+        let sql = format!(
+            "WITH assets_cte AS ({}) SELECT a.id, a.rn FROM assets_cte AS a ORDER BY a.rn LIMIT $1",
+            assets_cte_query(&params, &self.search_weights, self.search_source)
+        );
+
+        let q = sql_query(sql).bind::<Integer, _>(params.limit as i32);
 
-        let mut query = format!(
-            "WITH assets_cte AS ({}) SELECT a.id FROM assets_cte AS a",
-            assets_cte_query
+        q.load(&self.pg_pool.get()?).map_err(|e| {
+            error!("{:?}", e);
+            AppError::from(e)
+        })
+    }
+
+    fn count(&self, params: FindParams) -> Result<u64, AppError> {
+        let sql = format!(
+            "WITH assets_cte AS ({}) SELECT count(*) AS count FROM assets_cte",
+            assets_cte_query(&params, &self.search_weights, self.search_source)
         );
 
-        if let Some(after) = params.after {
-            query = format!(
-                "{} WHERE a.rn > (SELECT rn FROM assets_cte WHERE id = '{}')",
-                query,
-                utils::pg_escape(&after)
-            );
-        }
+        let q = sql_query(sql);
 
-        let sql = format!("{} ORDER BY a.rn LIMIT $1", query);
+        q.get_result::<AssetsCount>(&self.pg_pool.get()?)
+            .map(|c| c.count as u64)
+            .map_err(|e| {
+                error!("{:?}", e);
+                AppError::from(e)
+            })
+    }
 
-        //println!("sql: {sql}");
+    fn assets_updated_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        after: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<AssetId>, AppError> {
+        let q = sql_query(assets_updated_between_query(after))
+            .bind::<BigInt, _>(from.timestamp_millis())
+            .bind::<BigInt, _>(to.timestamp_millis())
+            .bind::<Integer, _>(limit as i32);
 
-        let q = sql_query(sql).bind::<Integer, _>(params.limit as i32);
+        q.load(&self.pg_pool.get()?).map_err(|e| {
+            error!("{:?}", e);
+            AppError::from(e)
+        })
+    }
+
+    fn issuer_assets(
+        &self,
+        issuer: &str,
+        after: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<SearchedAsset>, AppError> {
+        let q = sql_query(issuer_assets_query(after)?)
+            .bind::<Text, _>(issuer)
+            .bind::<BigInt, _>(MAX_UID)
+            .bind::<Integer, _>(limit as i32);
+
+        q.load(&self.pg_pool.get()?).map_err(|e| {
+            error!("{:?}", e);
+            AppError::from(e)
+        })
+    }
+
+    fn get_last_asset_ids_by_issuers(&self, issuers: &[&str]) -> Result<Vec<AssetId>, AppError> {
+        let q = sql_query(
+            "SELECT DISTINCT id FROM assets WHERE superseded_by = $1 AND issuer = ANY($2) ORDER BY id",
+        )
+        .bind::<BigInt, _>(MAX_UID)
+        .bind::<Array<Text>, _>(issuers);
 
         q.load(&self.pg_pool.get()?).map_err(|e| {
             error!("{:?}", e);
@@ -259,10 +274,11 @@ impl Repo for PgRepo {
         })
     }
 
-    fn get(&self, id: &str) -> Result<Option<Asset>, AppError> {
+    fn get(&self, id: &str, include_nfts: bool) -> Result<Option<Asset>, AppError> {
         let q = sql_query(&format!(
-            "{} WHERE a.uid = (SELECT DISTINCT ON (a.id) a.uid FROM assets a WHERE a.nft = false AND a.superseded_by = $1 AND a.id = $2 ORDER BY a.id, a.uid DESC LIMIT 1)",
-            ASSETS_BLOCKCHAIN_DATA_BASE_SQL_QUERY.as_str()
+            "{} WHERE a.uid = (SELECT DISTINCT ON (a.id) a.uid FROM assets a WHERE {} AND a.superseded_by = $1 AND a.id = $2 ORDER BY a.id, a.uid DESC LIMIT 1)",
+            ASSETS_BLOCKCHAIN_DATA_BASE_SQL_QUERY.as_str(),
+            nft_condition(include_nfts)
         ))
         .bind::<BigInt, _>(MAX_UID)
         .bind::<Text, _>(id);
@@ -273,10 +289,11 @@ impl Repo for PgRepo {
         })
     }
 
-    fn mget(&self, ids: &[&str]) -> Result<Vec<Option<Asset>>, AppError> {
+    fn mget(&self, ids: &[&str], include_nfts: bool) -> Result<Vec<Option<Asset>>, AppError> {
         let q = sql_query(&format!(
-            "{} WHERE a.uid IN (SELECT DISTINCT ON (a.id) a.uid FROM assets a WHERE a.nft = false AND a.superseded_by = $1 AND a.id = ANY($2) ORDER BY a.id, a.uid DESC)",
-            ASSETS_BLOCKCHAIN_DATA_BASE_SQL_QUERY.as_str()
+            "{} WHERE a.uid IN (SELECT DISTINCT ON (a.id) a.uid FROM assets a WHERE {} AND a.superseded_by = $1 AND a.id = ANY($2) ORDER BY a.id, a.uid DESC)",
+            ASSETS_BLOCKCHAIN_DATA_BASE_SQL_QUERY.as_str(),
+            nft_condition(include_nfts)
         ))
         .bind::<BigInt, _>(MAX_UID)
         .bind::<Array<Text>, _>(ids);
@@ -287,9 +304,28 @@ impl Repo for PgRepo {
         })
     }
 
-    fn mget_for_height(&self, ids: &[&str], height: i32) -> Result<Vec<Option<Asset>>, AppError> {
+    fn get_by_ticker(&self, ticker: &str) -> Result<Option<Asset>, AppError> {
+        let q = sql_query(&format!(
+            "{} WHERE a.superseded_by = $1 AND a.id = (SELECT ast1.asset_id FROM asset_tickers AS ast1 INNER JOIN assets AS a1 ON a1.id = ast1.asset_id AND a1.superseded_by = $1 AND a1.nft = false WHERE ast1.superseded_by = $1 AND ast1.ticker = $2 ORDER BY ast1.uid DESC LIMIT 1)",
+            ASSETS_BLOCKCHAIN_DATA_BASE_SQL_QUERY.as_str()
+        ))
+        .bind::<BigInt, _>(MAX_UID)
+        .bind::<Text, _>(ticker);
+
+        q.get_result(&self.pg_pool.get()?).optional().map_err(|e| {
+            error!("{:?}", e);
+            AppError::from(e)
+        })
+    }
+
+    fn mget_for_height(
+        &self,
+        ids: &[&str],
+        height: i32,
+        include_nfts: bool,
+    ) -> Result<Vec<Option<Asset>>, AppError> {
         let q = sql_query(&format!("
-            {} WHERE a.uid IN (SELECT DISTINCT ON (a.id) a.uid FROM assets a WHERE a.nft = false AND a.id = ANY($1) AND a.block_uid <= (SELECT uid FROM blocks_microblocks WHERE height = $2 LIMIT 1) ORDER BY a.id, a.uid DESC)", ASSETS_BLOCKCHAIN_DATA_BASE_SQL_QUERY.as_str()))
+            {} WHERE a.uid IN (SELECT DISTINCT ON (a.id) a.uid FROM assets a WHERE {} AND a.id = ANY($1) AND a.block_uid <= (SELECT uid FROM blocks_microblocks WHERE height = $2 LIMIT 1) ORDER BY a.id, a.uid DESC)", ASSETS_BLOCKCHAIN_DATA_BASE_SQL_QUERY.as_str(), nft_condition(include_nfts)))
             .bind::<Array<Text>, _>(ids)
             .bind::<Integer, _>(height);
 
@@ -299,10 +335,25 @@ impl Repo for PgRepo {
         })
     }
 
+    fn asset_version(
+        &self,
+        id: &str,
+        block_uid_lte: Option<i64>,
+    ) -> Result<Option<AssetVersion>, AppError> {
+        let q = sql_query("SELECT block_uid, name, description, quantity, reissuable, min_sponsored_fee, smart FROM assets WHERE id = $1 AND ($2::int8 IS NULL OR block_uid <= $2) ORDER BY uid DESC LIMIT 1")
+            .bind::<Text, _>(id)
+            .bind::<Nullable<BigInt>, _>(block_uid_lte);
+
+        q.get_result(&self.pg_pool.get()?).optional().map_err(|e| {
+            error!("{:?}", e);
+            AppError::from(e)
+        })
+    }
+
     fn data_entries(
         &self,
         asset_ids: &[&str],
-        oracle_address: &str,
+        oracle_addresses: &[String],
     ) -> Result<Vec<OracleDataEntry>, AppError> {
         let q = data_entries::table
             .select((
@@ -314,10 +365,46 @@ impl Repo for PgRepo {
                 data_entries::bool_val,
                 data_entries::int_val,
                 data_entries::str_val,
+                data_entries::uid,
             ))
             .filter(data_entries::superseded_by.eq(MAX_UID))
-            .filter(data_entries::address.eq_all(oracle_address))
+            .filter(data_entries::address.eq_any(oracle_addresses))
+            .filter(data_entries::related_asset_id.eq_any(asset_ids))
+            .filter(data_entries::data_type.is_not_null());
+
+        q.load(&self.pg_pool.get()?).map_err(|e| {
+            error!("{:?}", e);
+            AppError::from(e)
+        })
+    }
+
+    fn data_entries_for_height(
+        &self,
+        asset_ids: &[&str],
+        oracle_addresses: &[String],
+        height: i32,
+    ) -> Result<Vec<OracleDataEntry>, AppError> {
+        // the last version of the entry at the height, deleted entries have no data type
+        let version_at_height = format!(
+            "data_entries.uid = (SELECT max(de.uid) FROM data_entries AS de WHERE de.address = data_entries.address AND de.key = data_entries.key AND de.block_uid <= (SELECT uid FROM blocks_microblocks WHERE height = {} LIMIT 1))",
+            height
+        );
+
+        let q = data_entries::table
+            .select((
+                sql::<Text>("related_asset_id"),
+                data_entries::address,
+                data_entries::key,
+                sql::<DataEntryValueTypeMapping>("data_type"),
+                data_entries::bin_val,
+                data_entries::bool_val,
+                data_entries::int_val,
+                data_entries::str_val,
+                data_entries::uid,
+            ))
+            .filter(data_entries::address.eq_any(oracle_addresses))
             .filter(data_entries::related_asset_id.eq_any(asset_ids))
+            .filter(sql::<Bool>(&version_at_height))
             .filter(data_entries::data_type.is_not_null());
 
         q.load(&self.pg_pool.get()?).map_err(|e| {
@@ -357,115 +444,1653 @@ impl Repo for PgRepo {
         })
     }
 
-    fn all_assets_user_defined_data(&self) -> Result<Vec<UserDefinedData>, AppError> {
+    fn all_assets_user_defined_data(
+        &self,
+        after_asset_id: Option<&str>,
+        limit: u32,
+        has_ticker: Option<bool>,
+    ) -> Result<Vec<UserDefinedData>, AppError> {
+        let ticker_condition = match has_ticker {
+            Some(true) => "AND ast.ticker IS NOT NULL AND ast.ticker <> ''",
+            Some(false) => "AND (ast.ticker IS NULL OR ast.ticker = '')",
+            None => "",
+        };
+
+        // any asset id is greater than the empty string
         let q = sql_query(&format!(
-            "{} WHERE a.superseded_by = $1",
-            generate_assets_user_defined_data_base_sql_query()
+            "{} WHERE a.superseded_by = $1 AND a.id > $2 {} ORDER BY a.id LIMIT $3",
+            generate_assets_user_defined_data_base_sql_query(),
+            ticker_condition
         ))
-        .bind::<BigInt, _>(MAX_UID);
+        .bind::<BigInt, _>(MAX_UID)
+        .bind::<Text, _>(after_asset_id.unwrap_or(""))
+        .bind::<Integer, _>(limit as i32);
 
         q.load(&self.pg_pool.get()?).map_err(|e| {
             error!("{:?}", e);
             AppError::from(e)
         })
     }
+
+    fn supply_changes(
+        &self,
+        asset_id: &str,
+        height_gte: Option<i32>,
+        height_lte: Option<i32>,
+    ) -> Result<Vec<SupplyChange>, AppError> {
+        let q = sql_query("SELECT height, prev_quantity, new_quantity, change FROM supply_changes WHERE asset_id = $1 AND ($2::int4 IS NULL OR height >= $2) AND ($3::int4 IS NULL OR height <= $3) ORDER BY uid")
+            .bind::<Text, _>(asset_id)
+            .bind::<Nullable<Integer>, _>(height_gte)
+            .bind::<Nullable<Integer>, _>(height_lte);
+
+        q.load(&self.pg_pool.get()?).map_err(|e| {
+            error!("{:?}", e);
+            AppError::from(e)
+        })
+    }
+
+    fn consumer_updated_at(&self) -> Result<Option<DateTime<Utc>>, AppError> {
+        consumer_state::table
+            .select(consumer_state::updated_at)
+            .first(&self.pg_pool.get()?)
+            .optional()
+            .map_err(|e| {
+                error!("{:?}", e);
+                AppError::from(e)
+            })
+    }
 }
 
-fn generate_assets_user_defined_data_base_sql_query() -> String {
-    format!(
-        "SELECT 
-        a.id as asset_id,
-        ast.ticker,
-        COALESCE(awl.labels, ARRAY[]::text[])  AS labels
-        FROM assets a
-        LEFT JOIN asset_tickers ast ON a.id = ast.asset_id and ast.superseded_by = {}
-        LEFT JOIN (
-            SELECT asset_id, ARRAY_AGG(DISTINCT labels_list) AS labels
-            FROM (
-                SELECT al.asset_id as asset_id, al.labels
-                FROM asset_labels AS al
-                WHERE al.superseded_by = {}
-                UNION
-                SELECT awl.asset_id as asset_id, ARRAY_AGG(awl.label) as labels
-                FROM asset_wx_labels AS awl
-                GROUP BY awl.asset_id
-            ) AS data, UNNEST(labels) AS labels_list
-            GROUP BY asset_id
-        ) AS awl ON awl.asset_id = a.id
-    ",
-        MAX_UID, MAX_UID
-    )
+/// Builds the query ranking assets matching the find params
+///
+/// Returns `(id, rn)` rows, where `rn` is the row number in the resulting order
+fn assets_cte_query(params: &FindParams, weights: &SearchWeights, source: SearchSource) -> String {
+    build_assets_cte_query(params, weights, source, true)
 }
 
-mod utils {
-    use regex::Regex;
-    use std::borrow::Cow;
+/// Searching by a full asset id looks the asset up by id only when `exact_id_fast_path` is set,
+/// since asset names and tickers are shorter than ids
+fn build_assets_cte_query(
+    params: &FindParams,
+    weights: &SearchWeights,
+    source: SearchSource,
+    exact_id_fast_path: bool,
+) -> String {
+    // conditions have to be collected before assets_cte_query construction
+    // because of difference in searching by text and searching by ticker
+    let mut conditions = vec![];
 
-    pub(super) fn escape_for_tsquery(query: &str) -> String {
-        let p1 = Regex::new(r"[^\w\s]|_").unwrap();
-        let p2 = Regex::new(r"\s+").unwrap();
-        let query = p1.replace_all(&query, "").to_string();
-        p2.replace_all(query.trim(), " & ").to_string()
+    // AssetLabel Filtering
+    if let Some(asset_labels) = params.asset_label_in.as_ref() {
+        let mut label_filters = vec![];
+
+        if asset_labels.contains(&LabelCondition::Unlabeled) {
+            label_filters.push(format!("awl.labels IS NULL"));
+        }
+
+        let labels = labels_of(asset_labels);
+        if labels.len() > 0 {
+            let labels_filter = format!("awl.labels && ARRAY[{}]", labels_array(&labels));
+            label_filters.push(labels_filter);
+        }
+
+        conditions.push(format!("({})", label_filters.join(" OR ")));
     }
 
-    pub(super) fn escape_for_like(query: &str) -> String {
-        let p = Regex::new(r"%").unwrap();
-        p.replace_all(&query, "\\%").to_string()
+    // AssetLabel Exclusion
+    if let Some(asset_labels) = params.asset_label_not_in.as_ref() {
+        if let Some(condition) = asset_label_not_in_condition(&asset_labels) {
+            conditions.push(condition);
+        }
     }
 
-    pub(super) fn pg_escape<'a>(text: &'a str) -> Cow<'a, str> {
-        let bytes = text.as_bytes();
+    if let Some(smart) = params.smart {
+        conditions.push(format!("a.smart = {}", smart));
+    }
 
-        let mut owned = None;
+    if let Some(reissuable) = params.reissuable {
+        conditions.push(format!("a.reissuable = {}", reissuable));
+    }
 
-        for pos in 0..bytes.len() {
-            let special = match bytes[pos] {
-                0x07 => Some(b'a'),
-                0x08 => Some(b'b'),
-                b'\t' => Some(b't'),
-                b'\n' => Some(b'n'),
-                0x0b => Some(b'v'),
-                0x0c => Some(b'f'),
-                b'\r' => Some(b'r'),
-                b' ' => Some(b' '),
-                b'\\' => Some(b'\\'),
-                b'\'' => Some(b'\''),
-                _ => None,
-            };
+    if let Some(precision_gte) = params.precision_gte {
+        conditions.push(format!("a.precision >= {}", precision_gte));
+    }
 
-            if let Some(s) = special {
-                if owned.is_none() {
-                    owned = Some(bytes[0..pos].to_owned());
-                }
-                if s.eq(&b'\'') {
-                    owned.as_mut().unwrap().push(b'\'');
-                } else {
-                    owned.as_mut().unwrap().push(b'\\');
-                }
-                owned.as_mut().unwrap().push(s);
-            } else if let Some(owned) = owned.as_mut() {
-                owned.push(bytes[pos]);
-            }
-        }
+    if let Some(precision_lte) = params.precision_lte {
+        conditions.push(format!("a.precision <= {}", precision_lte));
+    }
 
-        if let Some(owned) = owned {
-            unsafe { Cow::Owned(String::from_utf8_unchecked(owned)) }
+    // cbm is the block where the asset was issued
+    let created_height_conditions =
+        created_height_conditions(params.created_height_gte, params.created_height_lte)
+            .into_iter()
+            .chain(created_range_conditions(params))
+            .collect_vec();
+    let filter_by_created_height = !created_height_conditions.is_empty();
+    conditions.extend(created_height_conditions);
+
+    if let Some(sponsored) = params.sponsored {
+        if sponsored {
+            conditions.push("a.min_sponsored_fee IS NOT NULL".to_owned());
         } else {
-            unsafe { Cow::Borrowed(std::str::from_utf8_unchecked(bytes)) }
+            conditions.push("a.min_sponsored_fee IS NULL".to_owned());
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::utils::escape_for_tsquery;
+    if let Some(issuer_in) = params.issuer_in.as_ref() {
+        conditions.push(format!(
+            "a.issuer = ANY(ARRAY[{}])",
+            issuer_in
+                .iter()
+                .map(|addr| format!("'{}'", utils::pg_escape(&addr)))
+                .join(",")
+        ));
+    }
 
-    #[test]
-    fn should_escape_for_tsquery() {
-        let test_cases = vec![("asd", "asd"), ("asd+", "asd"), ("asd dsa", "asd & dsa")];
+    if let Some(statuses) = params.verified_status_in.as_ref() {
+        conditions.push(verification_status_condition(statuses));
+    }
 
-        test_cases.into_iter().for_each(|(src, expected)| {
-            assert_eq!(escape_for_tsquery(src), expected);
-        });
+    let nft_condition = nft_condition(params.include_nfts);
+
+    if let Some(search) = params.search.as_ref() {
+        let exact_id = exact_id_fast_path && waves::is_asset_id(search);
+        let search = utils::pg_escape(search);
+        let current_assets = source.current_assets();
+        let issue_block_uid = source.issue_block_uid();
+
+        let search_escaped_for_like = utils::escape_for_like(&search);
+
+        let id_condition = if exact_id {
+            format!("a.id = '{}'", search)
+        } else {
+            format!("a.id ILIKE '{}'", search_escaped_for_like)
+        };
+        let search_by_id_query = format!("SELECT a.id, a.smart, {} as block_uid, CASE WHEN (ast.ticker IS NULL or ast.ticker = '') THEN {} ELSE {} END AS rank FROM {} AND {} AND {}", issue_block_uid, weights.id, weights.id_with_ticker, current_assets, nft_condition, id_condition);
+        // UNION
+        let search_by_meta_query = format!("SELECT id, false AS smart, block_uid, ts_rank(to_tsvector('simple', name), plainto_tsquery('simple', '{}'), 3) * CASE WHEN ticker IS NULL THEN {} ELSE {} END AS rank FROM asset_metadatas WHERE name ILIKE '{}%'", search, weights.metadata, weights.metadata_with_ticker, search_escaped_for_like);
+        // UNION
+        let search_by_ticker_query = format!("SELECT a.id, a.smart, {} as block_uid, {} AS rank FROM {} AND {} AND ast.ticker ILIKE '{}%'", issue_block_uid, weights.ticker, current_assets, nft_condition, search_escaped_for_like);
+        // UNION
+        let tsquery_condition = {
+            let search_escaped_for_tsquery = utils::escape_for_tsquery(&search);
+            if search_escaped_for_tsquery.len() > 0 {
+                format!(
+                    "to_tsvector('simple', a.name) @@ to_tsquery('simple', '{}:*')",
+                    search_escaped_for_tsquery
+                )
+            } else {
+                "1=1".to_owned()
+            }
+        };
+        let search_by_tsquery_query = format!("SELECT a.id, a.smart, {} as block_uid, ts_rank(to_tsvector('simple', a.name), plainto_tsquery('simple', '{}'), 3) * CASE WHEN (ast.ticker IS NULL or ast.ticker = '') THEN {} ELSE {} END AS rank FROM {} AND {} AND {}", issue_block_uid, search, weights.name, weights.name_with_ticker, current_assets, nft_condition, tsquery_condition);
+        // UNION
+        let search_by_name_query = format!("SELECT a.id, a.smart, {} as block_uid, ts_rank(to_tsvector('simple', a.name), plainto_tsquery('simple', '{}'), 3) * CASE WHEN (ast.ticker IS NULL or ast.ticker = '') THEN {} ELSE {} END AS rank FROM {} AND {} AND a.name ILIKE '{}%'", issue_block_uid, search, weights.name, weights.name_with_ticker, current_assets, nft_condition, search_escaped_for_like);
+
+        let [search_by_exact_ticker_query, search_by_exact_name_query] =
+            exact_match_queries(&search_escaped_for_like, nft_condition, weights, source);
+
+        match params.label.as_ref() {
+            Some(LabelFilter::One(label)) => {
+                let label = utils::pg_escape(label.as_str());
+                conditions.push(format!("'{}' = ANY(awl.labels)", label));
+            }
+            Some(LabelFilter::Any) => {
+                conditions.push(format!("array_length(awl.labels,1) > 0"));
+            }
+            None => {}
+        }
+
+        let search_query = if exact_id {
+            search_by_id_query
+        } else {
+            vec![
+                search_by_exact_ticker_query,
+                search_by_exact_name_query,
+                search_by_id_query,
+                search_by_meta_query,
+                search_by_ticker_query,
+                search_by_tsquery_query,
+                search_by_name_query,
+            ]
+            .join("\n UNION \n")
+        };
+
+        let conditions = if conditions.len() > 0 {
+            format!("WHERE {}", conditions.iter().join(" AND "))
+        } else {
+            "".to_owned()
+        };
+
+        format!(
+            "SELECT DISTINCT ON (search.id)
+                search.id,
+                ROW_NUMBER() OVER (ORDER BY {}) AS rn
+                {}
+            FROM
+                ({}) AS search
+            {}
+            {}
+            {}
+            {}
+            {}
+            ORDER BY search.id ASC, search.rank DESC",
+            rn_order_by(params.sort.as_ref(), true),
+            sort_key_columns(params.sort.as_ref(), true),
+            search_query,
+            source.search_asset_join(),
+            created_block_join(filter_by_created_height, "search"),
+            source.labels_join("search.id"),
+            label_priority_join(params.sort.as_ref(), weights),
+            conditions
+        )
+    } else {
+        // search by ticker only if there is not searching by text
+        if let Some(ticker) = params.ticker.as_ref() {
+            conditions.push(ticker_condition(ticker));
+        }
+
+        // search by label only if there is not searching by text
+        if let Some(filter_label) = params.label.as_ref() {
+            match filter_label {
+                LabelFilter::One(label) => {
+                    conditions.push(format!(
+                        "'{}' = ANY(awl.labels)",
+                        utils::pg_escape(label.as_str())
+                    ));
+                }
+                LabelFilter::Any => {
+                    conditions.push(format!("array_length(awl.labels,1) > 0"));
+                }
+            }
+        }
+
+        let conditions = if conditions.len() > 0 {
+            format!("WHERE {}", conditions.iter().join(" AND "))
+        } else {
+            "".to_owned()
+        };
+
+        format!(
+            "SELECT DISTINCT ON (a.id, a.block_uid)
+                a.id,
+                ROW_NUMBER() OVER (ORDER BY {}) AS rn
+                {}
+            FROM
+                {}
+            {}
+            {}
+            {}
+            {}
+            ORDER BY a.block_uid ASC",
+            rn_order_by(params.sort.as_ref(), false),
+            sort_key_columns(params.sort.as_ref(), false),
+            source.filtered_assets(nft_condition),
+            created_block_join(filter_by_created_height, "a"),
+            source.labels_join("a.id"),
+            label_priority_join(params.sort.as_ref(), weights),
+            conditions
+        )
+    }
+}
+
+/// Builds search queries matching ticker and name exactly (case-insensitive)
+///
+/// Exact matches are ranked strictly above any prefix or full-text match
+fn exact_match_queries(
+    search_escaped_for_like: &str,
+    nft_condition: &str,
+    weights: &SearchWeights,
+    source: SearchSource,
+) -> [String; 2] {
+    let current_assets = source.current_assets();
+    let issue_block_uid = source.issue_block_uid();
+
+    [
+        format!("SELECT a.id, a.smart, {} as block_uid, {} AS rank FROM {} AND {} AND ast.ticker ILIKE '{}'", issue_block_uid, weights.exact_ticker, current_assets, nft_condition, search_escaped_for_like),
+        format!("SELECT a.id, a.smart, {} as block_uid, {} AS rank FROM {} AND {} AND a.name ILIKE '{}'", issue_block_uid, weights.exact_name, current_assets, nft_condition, search_escaped_for_like),
+    ]
+}
+
+/// Deleted tickers are stored as NULL and match neither filter,
+/// empty tickers are kept but are no tickers for `ticker=*` as for the search ranking
+fn ticker_condition(ticker: &TickerFilter) -> String {
+    match ticker {
+        TickerFilter::One(ticker) => format!("ast.ticker = '{}'", utils::pg_escape(ticker)),
+        TickerFilter::Any => "ast.ticker IS NOT NULL AND ast.ticker <> ''".to_owned(),
+        TickerFilter::In(tickers) => format!(
+            "ast.ticker = ANY(ARRAY[{}])",
+            tickers
+                .iter()
+                .map(|ticker| format!("'{}'", utils::pg_escape(ticker)))
+                .join(",")
+        ),
+    }
+}
+
+/// Builds condition on the status of the asset `a` published by the oracles,
+/// the last written status entry wins as in `AssetMetadata::verification_status`
+///
+/// Deleted entries are skipped, as they are missing in the oracles data of the asset.
+fn verification_status_condition(statuses: &[VerificationStatus]) -> String {
+    let status = [VerificationStatus::Verified, VerificationStatus::Declined]
+        .iter()
+        .map(|s| format!("WHEN {} THEN '{}'", s.code(), s.as_str()))
+        .join(" ");
+
+    format!(
+        "(CASE (SELECT de.int_val FROM data_entries AS de WHERE de.related_asset_id = a.id AND de.key LIKE '{}%' AND de.data_type IS NOT NULL AND de.superseded_by = {} ORDER BY de.uid DESC LIMIT 1) {} ELSE '{}' END) = ANY(ARRAY[{}])",
+        VerificationStatus::KEY_PREFIX.replace('_', "\\_"),
+        MAX_UID,
+        status,
+        VerificationStatus::Unknown.as_str(),
+        statuses
+            .iter()
+            .map(|s| format!("'{}'", s.as_str()))
+            .join(",")
+    )
+}
+
+/// Builds conditions on the issue height of the asset
+fn created_height_conditions(gte: Option<i32>, lte: Option<i32>) -> Vec<String> {
+    gte.map(|h| format!("cbm.height >= {}", h))
+        .into_iter()
+        .chain(lte.map(|h| format!("cbm.height <= {}", h)))
+        .collect()
+}
+
+/// Builds exclusive conditions on the issue height and time of the asset
+///
+/// Assets whose issue block is unknown, or has no timestamp for the time bounds, never match
+fn created_range_conditions(params: &FindParams) -> Vec<String> {
+    let heights = params
+        .created_after_height
+        .map(|h| format!("cbm.height > {}", h))
+        .into_iter()
+        .chain(
+            params
+                .created_before_height
+                .map(|h| format!("cbm.height < {}", h)),
+        );
+    let times = params
+        .created_after
+        .map(|t| format!("cbm.time_stamp > {}", t.timestamp_millis()))
+        .into_iter()
+        .chain(
+            params
+                .created_before
+                .map(|t| format!("cbm.time_stamp < {}", t.timestamp_millis())),
+        );
+
+    heights.chain(times).collect()
+}
+
+/// Joins the block where the asset was issued, `alias` is the source having the min `block_uid`
+fn created_block_join(needed: bool, alias: &str) -> String {
+    if needed {
+        format!(
+            "INNER JOIN blocks_microblocks AS cbm ON cbm.uid = {}.block_uid",
+            alias
+        )
+    } else {
+        "".to_owned()
+    }
+}
+
+/// Builds condition excluding NFTs unless they are included explicitly
+fn nft_condition(include_nfts: bool) -> &'static str {
+    if include_nfts {
+        "1=1"
+    } else {
+        "a.nft = false"
+    }
+}
+
+/// Builds the ordering of `rn` for the given sort
+///
+/// Asset id is always the last ordering column, so the order is stable for cursors
+fn rn_order_by(sort: Option<&SearchSort>, searching: bool) -> String {
+    let id = if searching { "search.id" } else { "a.id" };
+
+    sort_keys(sort, searching)
+        .into_iter()
+        .map(|(expr, desc)| format!("{} {}", expr, if desc { "DESC" } else { "ASC" }))
+        .chain(once(format!("{} ASC", id)))
+        .join(", ")
+}
+
+/// Sort keys preceding the asset id (which is always ascending), `true` means descending
+fn sort_keys(sort: Option<&SearchSort>, searching: bool) -> Vec<(&'static str, bool)> {
+    let block_uid = if searching {
+        "search.block_uid"
+    } else {
+        "a.block_uid"
+    };
+
+    match sort {
+        // equally ranked assets are ordered by issue (the first block containing the asset)
+        None if searching => vec![("search.rank", true), (block_uid, false)],
+        None | Some(SearchSort::CreatedAsc) => vec![(block_uid, false)],
+        Some(SearchSort::CreatedDesc) => vec![(block_uid, true)],
+        Some(SearchSort::NameAsc) => vec![("a.name", false)],
+        Some(SearchSort::NameDesc) => vec![("a.name", true)],
+        Some(SearchSort::QuantityAsc) => vec![("a.quantity", false)],
+        Some(SearchSort::QuantityDesc) => vec![("a.quantity", true)],
+        Some(SearchSort::LabelPriority) => vec![("lp.priority", true), (block_uid, false)],
+    }
+}
+
+/// Joins the priority of the labels `awl.labels` as `lp.priority` for the `label_priority` sort,
+/// zero for assets without prioritized labels
+fn label_priority_join(sort: Option<&SearchSort>, weights: &SearchWeights) -> String {
+    if sort != Some(&SearchSort::LabelPriority) {
+        return "".to_owned();
+    }
+
+    if weights.label_priorities.is_empty() {
+        return "CROSS JOIN LATERAL (SELECT 0 AS priority) AS lp".to_owned();
+    }
+
+    let cases = weights
+        .label_priorities
+        .iter()
+        .map(|(label, priority)| {
+            format!(
+                "WHEN '{}' THEN {}",
+                utils::pg_escape(label.as_str()),
+                priority
+            )
+        })
+        .join(" ");
+
+    format!(
+        "CROSS JOIN LATERAL (SELECT COALESCE(MAX(CASE l {} END), 0) AS priority FROM UNNEST(awl.labels) AS l) AS lp",
+        cases
+    )
+}
+
+/// Sort keys exposed as `k0`, `k1`, ... columns of the assets CTE for keyset pagination
+fn sort_key_columns(sort: Option<&SearchSort>, searching: bool) -> String {
+    sort_keys(sort, searching)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (expr, _))| format!(", {} AS k{}", expr, i))
+        .collect()
+}
+
+/// Builds condition excluding assets having any of the given labels
+///
+/// The special `null` token excludes assets without labels
+fn asset_label_not_in_condition(asset_labels: &[LabelCondition]) -> Option<String> {
+    let mut label_filters = vec![];
+
+    if asset_labels.contains(&LabelCondition::Unlabeled) {
+        label_filters.push(format!("awl.labels IS NOT NULL"));
+    }
+
+    let labels = labels_of(asset_labels);
+    if labels.len() > 0 {
+        // unlabeled assets have NULL labels, which have to pass the exclusion
+        let labels_filter = format!(
+            "NOT (COALESCE(awl.labels, ARRAY[]::text[]) && ARRAY[{}])",
+            labels_array(&labels)
+        );
+        label_filters.push(labels_filter);
+    }
+
+    if label_filters.len() > 0 {
+        Some(format!("({})", label_filters.join(" AND ")))
+    } else {
+        None
+    }
+}
+
+fn labels_of(conditions: &[LabelCondition]) -> Vec<&AssetLabel> {
+    conditions
+        .iter()
+        .filter_map(|condition| match condition {
+            LabelCondition::Label(label) => Some(label),
+            LabelCondition::Unlabeled => None,
+        })
+        .collect()
+}
+
+fn labels_array(labels: &[&AssetLabel]) -> String {
+    labels
+        .iter()
+        .map(|label| format!("'{}'", utils::pg_escape(label.as_str())))
+        .join(",")
+}
+
+/// Builds query selecting distinct ids of assets updated in blocks within `[$1, $2)` time range
+/// (block timestamps in milliseconds), paginated by asset id
+fn assets_updated_between_query(after: Option<&str>) -> String {
+    let after_condition = match after {
+        Some(after) => format!("AND a.id > '{}'", utils::pg_escape(after)),
+        None => "".to_owned(),
+    };
+
+    format!(
+        "SELECT DISTINCT a.id FROM assets AS a INNER JOIN blocks_microblocks AS bm ON bm.uid = a.block_uid WHERE a.nft = false AND bm.time_stamp >= $1 AND bm.time_stamp < $2 {} ORDER BY a.id LIMIT $3",
+        after_condition
+    )
+}
+
+/// Pages issuer assets by the keyset cursor of the first issue block uid and asset id
+fn issuer_assets_query(after: Option<&str>) -> Result<String, AppError> {
+    let after_condition = match after {
+        Some(after) => match cursor::decode(after).as_deref() {
+            Some([Value::Number(block_uid), Value::String(id)]) if block_uid.is_i64() => format!(
+                "WHERE (i.block_uid, i.id) > ({}, '{}')",
+                block_uid,
+                utils::pg_escape(id)
+            ),
+            _ => return Err(invalid_cursor("after")),
+        },
+        None => "".to_owned(),
+    };
+
+    Ok(format!(
+        "SELECT i.id, json_build_array(i.block_uid, i.id)::text AS sort_keys FROM (SELECT a.id, (SELECT MIN(block_uid) FROM assets WHERE id = a.id) AS block_uid FROM assets AS a WHERE a.issuer = $1 AND a.nft = false AND a.superseded_by = $2) AS i {} ORDER BY i.block_uid, i.id LIMIT $3",
+        after_condition
+    ))
+}
+
+/// Wraps assets cte query into the paginated query selecting asset ids with their sort keys
+///
+/// `after` and `before` hold the cursors of the sort keys encoded with `cursor::encode`,
+/// which are decoded by `cursor_condition`. `after` selects the page following the cursor row,
+/// `before` selects the page immediately preceding it (rows are fetched in descending order
+/// and re-reversed, so the result is always ordered by `rn` ascending)
+fn paginate(
+    assets_cte_query: &str,
+    keys_desc: &[bool],
+    after: Option<&str>,
+    before: Option<&str>,
+) -> Result<String, AppError> {
+    let select = format!(
+        "a.id, json_build_array({})::text AS sort_keys",
+        sort_key_names(keys_desc.len()).join(", ")
+    );
+
+    let sql = match (after, before) {
+        (_, Some(before)) => format!(
+            "WITH assets_cte AS ({}) SELECT page.id, page.sort_keys FROM (SELECT {}, a.rn FROM assets_cte AS a WHERE {} ORDER BY a.rn DESC LIMIT $1) AS page ORDER BY page.rn",
+            assets_cte_query,
+            select,
+            cursor_condition("before", before, keys_desc, false)?
+        ),
+        (Some(after), None) => format!(
+            "WITH assets_cte AS ({}) SELECT {} FROM assets_cte AS a WHERE {} ORDER BY a.rn LIMIT $1",
+            assets_cte_query,
+            select,
+            cursor_condition("after", after, keys_desc, true)?
+        ),
+        (None, None) => format!(
+            "WITH assets_cte AS ({}) SELECT {} FROM assets_cte AS a ORDER BY a.rn LIMIT $1",
+            assets_cte_query, select
+        ),
+    };
+
+    Ok(sql)
+}
+
+fn sort_key_names(keys_count: usize) -> Vec<String> {
+    (0..keys_count)
+        .map(|i| format!("a.k{}", i))
+        .chain(once("a.id".to_owned()))
+        .collect()
+}
+
+/// Builds condition on rows following (or preceding unless `forward`) the cursor row
+///
+/// Cursors holding the sort keys keep their position regardless of the rows inserted since.
+/// Legacy cursors holding the asset id are resolved to the current row number of the asset.
+fn cursor_condition(
+    param: &str,
+    cursor: &str,
+    keys_desc: &[bool],
+    forward: bool,
+) -> Result<String, AppError> {
+    let values = match cursor::decode(cursor) {
+        Some(values) => values,
+        None => {
+            return Ok(format!(
+                "a.rn {} (SELECT rn FROM assets_cte WHERE id = '{}')",
+                if forward { ">" } else { "<" },
+                utils::pg_escape(cursor)
+            ))
+        }
+    };
+
+    // cursor of the search with another sort
+    if values.len() != keys_desc.len() + 1 {
+        return Err(invalid_cursor(param));
+    }
+
+    let literals = values
+        .iter()
+        .map(|v| match v {
+            Value::String(s) => Ok(format!("'{}'", utils::pg_escape(s))),
+            Value::Number(n) => Ok(format!("'{}'", n)),
+            _ => Err(invalid_cursor(param)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let names = sort_key_names(keys_desc.len());
+    let directions = keys_desc.iter().copied().chain(once(false));
+
+    let conditions = names
+        .iter()
+        .zip(directions)
+        .enumerate()
+        .map(|(i, (name, desc))| {
+            let op = if desc == forward { "<" } else { ">" };
+            let condition = names[..i]
+                .iter()
+                .zip(&literals)
+                .map(|(name, literal)| format!("{} = {}", name, literal))
+                .chain(once(format!("{} {} {}", name, op, literals[i])))
+                .join(" AND ");
+            format!("({})", condition)
+        })
+        .join(" OR ");
+
+    Ok(format!("({})", conditions))
+}
+
+fn invalid_cursor(param: &str) -> AppError {
+    let details = vec![("reason".to_owned(), "invalid cursor".to_owned())]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+    AppError::ValidationError(param.to_owned(), Some(details))
+}
+
+/// The labels are aggregated for the selected assets only, e.g. for a single page of them
+fn generate_assets_user_defined_data_base_sql_query() -> String {
+    format!(
+        "SELECT 
+        a.id as asset_id,
+        ast.ticker,
+        COALESCE(awl.labels, ARRAY[]::text[])  AS labels
+        FROM assets a
+        LEFT JOIN asset_tickers ast ON a.id = ast.asset_id and ast.superseded_by = {}
+        LEFT JOIN LATERAL (
+            SELECT ARRAY_AGG(DISTINCT labels_list) AS labels
+            FROM (
+                SELECT al.labels
+                FROM asset_labels AS al
+                WHERE al.asset_id = a.id AND al.superseded_by = {}
+                UNION
+                SELECT ARRAY_AGG(awl.label) as labels
+                FROM asset_wx_labels AS awl
+                WHERE awl.asset_id = a.id
+            ) AS data, UNNEST(labels) AS labels_list
+        ) AS awl ON true
+    ",
+        MAX_UID, MAX_UID
+    )
+}
+
+mod utils {
+    use regex::Regex;
+    use std::borrow::Cow;
+
+    pub(super) fn escape_for_tsquery(query: &str) -> String {
+        let p1 = Regex::new(r"[^\w\s]|_").unwrap();
+        let p2 = Regex::new(r"\s+").unwrap();
+        let query = p1.replace_all(&query, "").to_string();
+        p2.replace_all(query.trim(), " & ").to_string()
+    }
+
+    /// Escapes the wildcards of the `pg_escape`d query, whose backslashes are escaped already
+    pub(super) fn escape_for_like(query: &str) -> String {
+        let p = Regex::new(r"[%_]").unwrap();
+        p.replace_all(&query, "\\$0").to_string()
+    }
+
+    pub(super) fn pg_escape<'a>(text: &'a str) -> Cow<'a, str> {
+        let bytes = text.as_bytes();
+
+        let mut owned = None;
+
+        for pos in 0..bytes.len() {
+            let special = match bytes[pos] {
+                0x07 => Some(b'a'),
+                0x08 => Some(b'b'),
+                b'\t' => Some(b't'),
+                b'\n' => Some(b'n'),
+                0x0b => Some(b'v'),
+                0x0c => Some(b'f'),
+                b'\r' => Some(b'r'),
+                b' ' => Some(b' '),
+                b'\\' => Some(b'\\'),
+                b'\'' => Some(b'\''),
+                _ => None,
+            };
+
+            if let Some(s) = special {
+                if owned.is_none() {
+                    owned = Some(bytes[0..pos].to_owned());
+                }
+                if s.eq(&b'\'') {
+                    owned.as_mut().unwrap().push(b'\'');
+                } else {
+                    owned.as_mut().unwrap().push(b'\\');
+                }
+                owned.as_mut().unwrap().push(s);
+            } else if let Some(owned) = owned.as_mut() {
+                owned.push(bytes[pos]);
+            }
+        }
+
+        if let Some(owned) = owned {
+            unsafe { Cow::Owned(String::from_utf8_unchecked(owned)) }
+        } else {
+            unsafe { Cow::Borrowed(std::str::from_utf8_unchecked(bytes)) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use diesel::connection::SimpleConnection;
+    use diesel::{sql_query, RunQueryDsl};
+    use itertools::Itertools;
+
+    use super::utils::{escape_for_like, escape_for_tsquery};
+    use super::{
+        assets_cte_query, build_assets_cte_query, created_range_conditions, issuer_assets_query,
+        paginate, PgRepo, SearchSource, MAX_UID,
+    };
+    use crate::config;
+    use crate::db::{self, PgPool};
+    use crate::models::{AssetLabel, AssetMetadata, AssetOracleDataEntry, VerificationStatus};
+    use crate::services::assets::cursor;
+    use crate::services::assets::repo::{
+        AssetId, FindParams, LabelCondition, LabelFilter, Repo, SearchCandidate, SearchSort,
+        SearchWeights, SearchedAsset, TickerFilter,
+    };
+
+    #[test]
+    fn should_escape_for_tsquery() {
+        let test_cases = vec![("asd", "asd"), ("asd+", "asd"), ("asd dsa", "asd & dsa")];
+
+        test_cases.into_iter().for_each(|(src, expected)| {
+            assert_eq!(escape_for_tsquery(src), expected);
+        });
+    }
+
+    #[test]
+    fn should_escape_for_like() {
+        let test_cases = vec![("asd", "asd"), ("a%d", "a\\%d"), ("a_d", "a\\_d")];
+
+        test_cases.into_iter().for_each(|(src, expected)| {
+            assert_eq!(escape_for_like(src), expected);
+        });
+    }
+
+    /// Assets of `test_issuer` issued at the heights 10, 20 and 30, 10 seconds apart
+    /// in the blocks of a far future, so that no real block precedes them in time
+    const TEST_ASSETS: &str = "
+        INSERT INTO blocks_microblocks (uid, id, height, time_stamp) VALUES
+            (-3, 'test_block_10', 10, 4100000010000),
+            (-2, 'test_block_20', 20, 4100000020000),
+            (-1, 'test_block_30', 30, 4100000030000);
+        INSERT INTO assets (uid, block_uid, id, name, description, time_stamp, issuer, precision, smart, nft, quantity, reissuable, min_sponsored_fee) VALUES
+            (-5, -3, 'test_btc', 'Test Bitcoin', '', to_timestamp(4100000010), 'test_issuer', 8, false, false, 2100, true, NULL),
+            (-4, -2, 'test_eth', 'Test Ether', '', to_timestamp(4100000020), 'test_issuer', 8, false, false, 1200, false, NULL),
+            (-3, -2, 'test_usd', 'Test Dollar', '', to_timestamp(4100000020), 'test_issuer', 2, true, false, 5000, true, 100000),
+            (-2, -1, 'test_waves', 'Test Waves', '', to_timestamp(4100000030), 'test_issuer', 6, false, false, 100, false, NULL),
+            (-1, -1, 'test_nft', 'Test NFT', '', to_timestamp(4100000030), 'test_issuer', 0, false, true, 1, false, NULL);
+        INSERT INTO asset_tickers (uid, block_uid, asset_id, ticker) VALUES
+            (-3, -3, 'test_btc', 'TBTC'),
+            (-2, -2, 'test_eth', 'TETH'),
+            (-1, -2, 'test_usd', 'TUSD');
+        INSERT INTO asset_labels (uid, block_uid, asset_id, labels) VALUES
+            (-2, -3, 'test_btc', ARRAY['GATEWAY']),
+            (-1, -2, 'test_usd', ARRAY['DEFI', 'WA_VERIFIED']);
+    ";
+
+    /// Pool seeded with the test assets, which are discarded with the pool
+    fn test_pool() -> PgPool {
+        let pool = db::test_pool();
+        pool.get().unwrap().batch_execute(TEST_ASSETS).unwrap();
+        pool
+    }
+
+    fn test_repo() -> PgRepo {
+        PgRepo::new(test_pool())
+    }
+
+    /// Params finding the test assets only, in the order of their issue
+    fn test_params() -> FindParams {
+        FindParams {
+            search: None,
+            issuer_in: Some(vec!["test_issuer".to_owned()]),
+            ..search_params("")
+        }
+    }
+
+    fn ids(assets: &[SearchedAsset]) -> Vec<String> {
+        assets.iter().map(|a| a.id.clone()).collect()
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_page_back_and_forth_by_cursors() {
+        let repo = test_repo();
+        let params = |after: Option<&SearchedAsset>, before: Option<&SearchedAsset>| FindParams {
+            limit: 2,
+            after: after.map(|a| cursor::encode(&a.sort_keys)),
+            before: before.map(|a| cursor::encode(&a.sort_keys)),
+            ..test_params()
+        };
+
+        let first_page = repo.find(params(None, None)).unwrap();
+        assert_eq!(ids(&first_page), vec!["test_btc", "test_eth"]);
+
+        let second_page = repo.find(params(first_page.last(), None)).unwrap();
+        assert_eq!(ids(&second_page), vec!["test_usd", "test_waves"]);
+
+        // the page right before the cursor is ordered as the forward one
+        let previous_page = repo.find(params(None, second_page.first())).unwrap();
+        assert_eq!(ids(&previous_page), vec!["test_btc", "test_eth"]);
+
+        let previous_page = repo.find(params(None, second_page.last())).unwrap();
+        assert_eq!(ids(&previous_page), vec!["test_eth", "test_usd"]);
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_page_after_legacy_asset_id_cursor() {
+        let repo = test_repo();
+        let params = FindParams {
+            after: Some("test_eth".to_owned()),
+            ..test_params()
+        };
+
+        assert_eq!(
+            ids(&repo.find(params).unwrap()),
+            vec!["test_usd", "test_waves"]
+        );
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_keep_cursor_position_after_concurrent_inserts() {
+        let pool = test_pool();
+        let repo = PgRepo::new(pool.clone());
+        let params = |search: Option<&str>, after: Option<&SearchedAsset>| FindParams {
+            search: search.map(|s| s.to_owned()),
+            limit: 2,
+            after: after.map(|a| cursor::encode(&a.sort_keys)),
+            ..test_params()
+        };
+
+        let by_issue = repo.find(params(None, None)).unwrap();
+        let by_relevance = repo.find(params(Some("Test"), None)).unwrap();
+        assert_eq!(ids(&by_issue), vec!["test_btc", "test_eth"]);
+        assert_eq!(ids(&by_relevance), vec!["test_btc", "test_eth"]);
+
+        // the asset issued meanwhile precedes all the others in both orders
+        pool.get()
+            .unwrap()
+            .batch_execute(
+                "INSERT INTO assets (uid, block_uid, id, name, description, time_stamp, issuer, precision, smart, nft, quantity, reissuable, min_sponsored_fee) VALUES
+                    (-6, -3, 'test_aaa', 'Test AAA', '', to_timestamp(4100000010), 'test_issuer', 8, false, false, 1000, false, NULL);
+                INSERT INTO asset_tickers (uid, block_uid, asset_id, ticker) VALUES
+                    (-4, -3, 'test_aaa', 'TAAA');",
+            )
+            .unwrap();
+
+        assert_eq!(
+            ids(&repo.find(params(None, None)).unwrap()),
+            vec!["test_aaa", "test_btc"]
+        );
+        assert_eq!(
+            ids(&repo.find(params(None, by_issue.last())).unwrap()),
+            vec!["test_usd", "test_waves"]
+        );
+        assert_eq!(
+            ids(&repo
+                .find(params(Some("Test"), by_relevance.last()))
+                .unwrap()),
+            vec!["test_usd", "test_waves"]
+        );
+    }
+
+    #[test]
+    fn should_reject_cursor_of_another_sort() {
+        // cursor of the search by text used for the search with a single sort key
+        let after = cursor::encode(r#"[0.5, 12, "asd"]"#);
+        assert!(paginate("SELECT 1", &[false], Some(&after), None).is_err());
+
+        let after = cursor::encode(r#"[null, "asd"]"#);
+        assert!(paginate("SELECT 1", &[false], Some(&after), None).is_err());
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_exclude_assets_by_labels() {
+        let repo = test_repo();
+        let find = |labels: &[&str]| {
+            let params = FindParams {
+                asset_label_not_in: Some(labels.iter().map(|l| LabelCondition::from(*l)).collect()),
+                ..test_params()
+            };
+            ids(&repo.find(params).unwrap())
+        };
+
+        assert_eq!(
+            find(&[]),
+            vec!["test_btc", "test_eth", "test_usd", "test_waves"]
+        );
+        assert_eq!(find(&["DEFI"]), vec!["test_btc", "test_eth", "test_waves"]);
+        assert_eq!(find(&["null"]), vec!["test_btc", "test_usd"]);
+        assert_eq!(find(&["DEFI", "null"]), vec!["test_btc"]);
+        // labels are normalized and escaped
+        assert_eq!(
+            find(&[" defi ", "it's"]),
+            vec!["test_btc", "test_eth", "test_waves"]
+        );
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_find_assets_updated_between() {
+        let repo = test_repo();
+        let updated_between = |after: Option<&str>, limit: u32| {
+            repo.assets_updated_between(
+                Utc.timestamp(4_100_000_015, 0),
+                Utc.timestamp(4_100_000_030, 0),
+                after,
+                limit,
+            )
+            .unwrap()
+            .into_iter()
+            .map(|a| a.id)
+            .collect::<Vec<_>>()
+        };
+
+        // the upper bound is exclusive, NFTs are skipped
+        assert_eq!(updated_between(None, 10), vec!["test_eth", "test_usd"]);
+        assert_eq!(updated_between(None, 1), vec!["test_eth"]);
+        assert_eq!(updated_between(Some("test_eth"), 10), vec!["test_usd"]);
+    }
+
+    #[test]
+    fn should_page_issuer_assets_by_cursor() {
+        assert!(issuer_assets_query(None)
+            .unwrap()
+            .ends_with("AS i  ORDER BY i.block_uid, i.id LIMIT $3"));
+
+        let after = cursor::encode(r#"[12, "asd'"]"#);
+        assert!(issuer_assets_query(Some(&after)).unwrap().ends_with(
+            "AS i WHERE (i.block_uid, i.id) > (12, 'asd''') ORDER BY i.block_uid, i.id LIMIT $3"
+        ));
+
+        assert!(issuer_assets_query(Some("asd")).is_err());
+        let after = cursor::encode(r#"[0.5, "asd"]"#);
+        assert!(issuer_assets_query(Some(&after)).is_err());
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_order_by_sort() {
+        let repo = test_repo();
+        let test_cases = vec![
+            (None, vec!["test_btc", "test_eth", "test_usd", "test_waves"]),
+            (
+                Some(SearchSort::CreatedAsc),
+                vec!["test_btc", "test_eth", "test_usd", "test_waves"],
+            ),
+            // assets of the same block are ordered by id
+            (
+                Some(SearchSort::CreatedDesc),
+                vec!["test_waves", "test_eth", "test_usd", "test_btc"],
+            ),
+            (
+                Some(SearchSort::NameAsc),
+                vec!["test_btc", "test_usd", "test_eth", "test_waves"],
+            ),
+            (
+                Some(SearchSort::NameDesc),
+                vec!["test_waves", "test_eth", "test_usd", "test_btc"],
+            ),
+            (
+                Some(SearchSort::QuantityAsc),
+                vec!["test_waves", "test_eth", "test_btc", "test_usd"],
+            ),
+            (
+                Some(SearchSort::QuantityDesc),
+                vec!["test_usd", "test_btc", "test_eth", "test_waves"],
+            ),
+        ];
+
+        for (sort, expected) in test_cases {
+            let params = FindParams {
+                sort: sort.clone(),
+                ..test_params()
+            };
+            assert_eq!(ids(&repo.find(params).unwrap()), expected, "{:?}", sort);
+
+            // the sort overrides the relevance of the search by text
+            if sort.is_some() {
+                let params = FindParams {
+                    search: Some("Test".to_owned()),
+                    sort: sort.clone(),
+                    ..test_params()
+                };
+                assert_eq!(ids(&repo.find(params).unwrap()), expected, "{:?}", sort);
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_rank_exact_matches_above_prefix_matches() {
+        let pool = test_pool();
+        // issued before test_eth, so it would come first among equally ranked matches
+        pool.get()
+            .unwrap()
+            .batch_execute(
+                "INSERT INTO assets (uid, block_uid, id, name, description, time_stamp, issuer, precision, smart, nft, quantity, reissuable, min_sponsored_fee) VALUES
+                    (-6, -3, 'test_etc', 'Test Ether Classic', '', to_timestamp(4100000010), 'test_issuer', 8, false, false, 1000, false, NULL);
+                INSERT INTO asset_tickers (uid, block_uid, asset_id, ticker) VALUES
+                    (-4, -3, 'test_etc', 'TETHC');",
+            )
+            .unwrap();
+        let repo = PgRepo::new(pool);
+        let search = |search: &str| {
+            let params = FindParams {
+                search: Some(search.to_owned()),
+                ..test_params()
+            };
+            ids(&repo.find(params).unwrap())
+        };
+
+        assert_eq!(search("TETH"), vec!["test_eth", "test_etc"]);
+        assert_eq!(search("teth"), vec!["test_eth", "test_etc"]);
+        assert_eq!(search("Test Ether"), vec!["test_eth", "test_etc"]);
+        assert_eq!(search("TETHC"), vec!["test_etc"]);
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_match_underscore_of_search_literally() {
+        let pool = test_pool();
+        pool.get()
+            .unwrap()
+            .batch_execute(
+                "INSERT INTO assets (uid, block_uid, id, name, description, time_stamp, issuer, precision, smart, nft, quantity, reissuable, min_sponsored_fee) VALUES
+                    (-7, -3, 'test_lookalike', 'Test Lookalike', '', to_timestamp(4100000010), 'test_issuer', 8, false, false, 1000, false, NULL),
+                    (-6, -3, 'test_underscored', 'Test Underscored', '', to_timestamp(4100000010), 'test_issuer', 8, false, false, 1000, false, NULL);
+                INSERT INTO asset_tickers (uid, block_uid, asset_id, ticker) VALUES
+                    (-5, -3, 'test_lookalike', 'TXETH'),
+                    (-4, -3, 'test_underscored', 'T_ETH');",
+            )
+            .unwrap();
+        let repo = PgRepo::new(pool);
+        let search = |search: &str| {
+            let params = FindParams {
+                search: Some(search.to_owned()),
+                ..test_params()
+            };
+            ids(&repo.find(params).unwrap())
+        };
+
+        // `_` would match any character of `TXETH` both exactly and by the prefix
+        assert_eq!(search("T_ETH"), vec!["test_underscored"]);
+        assert_eq!(search("t_eth"), vec!["test_underscored"]);
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_include_nfts_on_request() {
+        let repo = test_repo();
+        let params = |include_nfts: bool| FindParams {
+            include_nfts,
+            ..test_params()
+        };
+
+        assert_eq!(
+            ids(&repo.find(params(false)).unwrap()),
+            vec!["test_btc", "test_eth", "test_usd", "test_waves"]
+        );
+        assert_eq!(
+            ids(&repo.find(params(true)).unwrap()),
+            vec!["test_btc", "test_eth", "test_usd", "test_nft", "test_waves"]
+        );
+        assert_eq!(repo.count(params(false)).unwrap(), 4);
+        assert_eq!(repo.count(params(true)).unwrap(), 5);
+
+        assert!(repo.get("test_nft", false).unwrap().is_none());
+        assert!(repo.get("test_nft", true).unwrap().is_some());
+
+        let mget = |include_nfts: bool| {
+            repo.mget(&["test_btc", "test_nft"], include_nfts)
+                .unwrap()
+                .into_iter()
+                .flatten()
+                .map(|a| a.id)
+                .sorted()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(mget(false), vec!["test_btc"]);
+        assert_eq!(mget(true), vec!["test_btc", "test_nft"]);
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_filter_by_created_height() {
+        let repo = test_repo();
+        let find = |search: Option<&str>, gte: Option<i32>, lte: Option<i32>| {
+            let params = FindParams {
+                search: search.map(|s| s.to_owned()),
+                created_height_gte: gte,
+                created_height_lte: lte,
+                sort: Some(SearchSort::CreatedAsc),
+                ..test_params()
+            };
+            ids(&repo.find(params).unwrap())
+        };
+
+        assert_eq!(
+            find(None, Some(20), None),
+            vec!["test_eth", "test_usd", "test_waves"]
+        );
+        assert_eq!(
+            find(None, None, Some(20)),
+            vec!["test_btc", "test_eth", "test_usd"]
+        );
+        assert_eq!(find(None, Some(20), Some(20)), vec!["test_eth", "test_usd"]);
+        assert_eq!(find(Some("Test"), Some(30), None), vec!["test_waves"]);
+    }
+
+    #[test]
+    fn should_build_created_range_conditions() {
+        let mut params = search_params("BTC");
+        assert!(created_range_conditions(&params).is_empty());
+
+        params.created_after_height = Some(100);
+        params.created_before_height = Some(200);
+        params.created_after = Some(Utc.timestamp(1_600_000_000, 0));
+        params.created_before = Some(Utc.timestamp_millis(1_600_000_000_500));
+        assert_eq!(
+            created_range_conditions(&params),
+            vec![
+                "cbm.height > 100",
+                "cbm.height < 200",
+                "cbm.time_stamp > 1600000000000",
+                "cbm.time_stamp < 1600000000500",
+            ]
+        );
+
+        let sql = assets_cte_query(&params, &SearchWeights::default(), SearchSource::Live);
+        assert!(sql.contains("INNER JOIN blocks_microblocks AS cbm ON cbm.uid = search.block_uid"));
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_filter_by_creation_range() {
+        let config = config::postgres::load().unwrap();
+        let conn = db::pool(&config).unwrap().get().unwrap();
+
+        conn.test_transaction::<_, diesel::result::Error, _>(|| {
+            // issue blocks at heights 10, 20 and 30, 1000 seconds apart, the microblock has no timestamp
+            sql_query(
+                "INSERT INTO blocks_microblocks (uid, id, height, time_stamp) VALUES
+                    (-4, 'created_10', 10, 1600000010000),
+                    (-3, 'created_20', 20, 1600000020000),
+                    (-2, 'created_30', 30, 1600000030000),
+                    (-1, 'created_30_micro', 30, NULL)",
+            )
+            .execute(&conn)?;
+
+            let find = |params: &FindParams| {
+                let conditions = created_range_conditions(params);
+                sql_query(format!(
+                    "SELECT cbm.id FROM blocks_microblocks AS cbm WHERE cbm.uid < 0 AND {} ORDER BY cbm.uid",
+                    conditions.join(" AND ")
+                ))
+                .load::<AssetId>(&conn)
+                .map(|ids| ids.into_iter().map(|a| a.id).collect::<Vec<_>>())
+            };
+
+            let mut params = search_params("");
+            params.created_after_height = Some(10);
+            params.created_before_height = Some(30);
+            assert_eq!(find(&params)?, vec!["created_20"]);
+
+            let mut params = search_params("");
+            params.created_after = Some(Utc.timestamp(1_600_000_015, 0));
+            assert_eq!(find(&params)?, vec!["created_20", "created_30"]);
+
+            let mut params = search_params("");
+            params.created_after_height = Some(20);
+            assert_eq!(find(&params)?, vec!["created_30", "created_30_micro"]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_rank_by_search_weights() {
+        let pool = test_pool();
+        let search = |weights: SearchWeights| {
+            let repo = PgRepo::new(pool.clone()).with_search_weights(weights);
+            let params = FindParams {
+                search: Some("Test".to_owned()),
+                ..test_params()
+            };
+            ids(&repo.find(params).unwrap())
+        };
+
+        // the names of the assets having a ticker weigh more
+        assert_eq!(
+            search(SearchWeights::default()),
+            vec!["test_btc", "test_eth", "test_usd", "test_waves"]
+        );
+
+        let weights = SearchWeights {
+            name: 64,
+            name_with_ticker: 1,
+            ..SearchWeights::default()
+        };
+        assert_eq!(
+            search(weights),
+            vec!["test_waves", "test_btc", "test_eth", "test_usd"]
+        );
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_search_materialized_view_like_live_tables() {
+        let pool = test_pool();
+        pool.get()
+            .unwrap()
+            .batch_execute("REFRESH MATERIALIZED VIEW searchable_assets")
+            .unwrap();
+        let live = PgRepo::new(pool.clone());
+        let view = PgRepo::new(pool).with_materialized_view(true);
+
+        let searches = [
+            FindParams {
+                search: Some("Test".to_owned()),
+                ..test_params()
+            },
+            FindParams {
+                search: Some("TUSD".to_owned()),
+                ..test_params()
+            },
+            FindParams {
+                label: Some(LabelFilter::Any),
+                reissuable: Some(true),
+                ..test_params()
+            },
+            FindParams {
+                smart: Some(false),
+                precision_gte: Some(6),
+                created_height_gte: Some(20),
+                ..test_params()
+            },
+            FindParams {
+                asset_label_in: Some(vec![LabelCondition::Label(AssetLabel::Gateway)]),
+                ..test_params()
+            },
+            FindParams {
+                sort: Some(SearchSort::NameDesc),
+                ..test_params()
+            },
+        ];
+        for params in searches {
+            let found = ids(&live.find(params.clone()).unwrap());
+            assert!(!found.is_empty());
+            assert_eq!(found, ids(&view.find(params).unwrap()));
+        }
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_filter_by_reissuable() {
+        let repo = test_repo();
+        let find = |search: Option<&str>, reissuable: Option<bool>| {
+            let params = FindParams {
+                search: search.map(ToOwned::to_owned),
+                reissuable,
+                ..test_params()
+            };
+            ids(&repo.find(params).unwrap())
+                .into_iter()
+                .sorted()
+                .collect_vec()
+        };
+
+        for search in [None, Some("Test")] {
+            assert_eq!(find(search, Some(true)), vec!["test_btc", "test_usd"]);
+            assert_eq!(find(search, Some(false)), vec!["test_eth", "test_waves"]);
+            assert_eq!(find(search, None).len(), 4);
+        }
+    }
+
+    fn search_params(search: &str) -> FindParams {
+        FindParams {
+            search: Some(search.to_owned()),
+            ticker: None,
+            label: None,
+            smart: None,
+            reissuable: None,
+            include_nfts: false,
+            sponsored: None,
+            precision_gte: None,
+            precision_lte: None,
+            created_height_gte: None,
+            created_height_lte: None,
+            created_after_height: None,
+            created_before_height: None,
+            created_after: None,
+            created_before: None,
+            asset_label_in: None,
+            asset_label_not_in: None,
+            issuer_in: None,
+            verified_status_in: None,
+            limit: 10,
+            after: None,
+            before: None,
+            sort: None,
+        }
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_look_up_exact_asset_id_only() {
+        let asset_id = "4yhnnC8x9Vobpz8WfkUEYBDgeLGPRgUk4pbJGqZ7dXV3";
+        let pool = test_pool();
+        pool.get()
+            .unwrap()
+            .batch_execute(&format!(
+                "INSERT INTO assets (uid, block_uid, id, name, description, time_stamp, issuer, precision, smart, nft, quantity, reissuable, min_sponsored_fee) VALUES
+                    (-6, -1, '{}', 'Test Token', '', to_timestamp(4100000030), 'test_issuer', 8, false, false, 1000, false, NULL);",
+                asset_id
+            ))
+            .unwrap();
+        let repo = PgRepo::new(pool.clone());
+        let params = |search: &str| FindParams {
+            search: Some(search.to_owned()),
+            ..test_params()
+        };
+
+        assert_eq!(ids(&repo.find(params(asset_id)).unwrap()), vec![asset_id]);
+        // partial ids are searched as usual
+        assert!(repo.find(params(&asset_id[..20])).unwrap().is_empty());
+
+        // the full search finds the same asset
+        let sql = format!(
+            "WITH assets_cte AS ({}) SELECT a.id, a.rn FROM assets_cte AS a ORDER BY a.rn",
+            build_assets_cte_query(
+                &params(asset_id),
+                &SearchWeights::default(),
+                SearchSource::Live,
+                false
+            )
+        );
+        let found = sql_query(sql)
+            .load::<SearchCandidate>(&pool.get().unwrap())
+            .unwrap();
+        assert_eq!(
+            found.into_iter().map(|c| c.id).collect::<Vec<_>>(),
+            vec![asset_id]
+        );
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_filter_by_verification_status() {
+        let pool = test_pool();
+        // the declined btc was verified by another oracle before, the eth one was verified
+        // before its status was changed, the status of usd is neither, the last status entry
+        // of waves is deleted
+        pool.get()
+            .unwrap()
+            .batch_execute(&format!(
+                "INSERT INTO data_entries (uid, superseded_by, block_uid, address, key, data_type, int_val, related_asset_id) VALUES
+                    (-8, {max_uid}, -1, 'oracle_a', 'status_<test_btc>', 'int', 2, 'test_btc'),
+                    (-7, {max_uid}, -1, 'oracle_b', 'status_<test_btc>', 'int', -2, 'test_btc'),
+                    (-6, -5, -1, 'oracle_a', 'status_<test_eth>', 'int', 2, 'test_eth'),
+                    (-5, {max_uid}, -1, 'oracle_a', 'status_<test_eth>', 'int', -2, 'test_eth'),
+                    (-4, {max_uid}, -1, 'oracle_a', 'status_<test_usd>', 'int', 1, 'test_usd'),
+                    (-3, {max_uid}, -1, 'oracle_a', 'description_<test_waves>', 'int', 2, 'test_waves'),
+                    (-2, {max_uid}, -1, 'oracle_a', 'status_<test_waves>', 'int', 2, 'test_waves'),
+                    (-1, {max_uid}, -1, 'oracle_b', 'status_<test_waves>', NULL, NULL, 'test_waves');",
+                max_uid = MAX_UID
+            ))
+            .unwrap();
+        let repo = PgRepo::new(pool);
+
+        let find = |statuses: &[VerificationStatus]| {
+            let params = FindParams {
+                verified_status_in: Some(statuses.to_vec()),
+                ..test_params()
+            };
+            ids(&repo.find(params).unwrap())
+        };
+
+        assert_eq!(find(&[VerificationStatus::Verified]), vec!["test_waves"]);
+        assert_eq!(
+            find(&[VerificationStatus::Declined]),
+            vec!["test_btc", "test_eth"]
+        );
+        assert_eq!(find(&[VerificationStatus::Unknown]), vec!["test_usd"]);
+        assert_eq!(
+            find(&[VerificationStatus::Verified, VerificationStatus::Unknown]),
+            vec!["test_usd", "test_waves"]
+        );
+
+        // the status of the asset metadata follows the same rule
+        let oracles = vec!["oracle_a".to_owned(), "oracle_b".to_owned()];
+        for status in [
+            VerificationStatus::Verified,
+            VerificationStatus::Declined,
+            VerificationStatus::Unknown,
+        ] {
+            for id in find(&[status]) {
+                let mut metadata = AssetMetadata::default();
+                for de in repo.data_entries(&[&id], &oracles).unwrap() {
+                    metadata
+                        .oracles_data
+                        .entry(de.oracle_address.clone())
+                        .or_insert_with(Vec::new)
+                        .push(AssetOracleDataEntry::from(&de));
+                }
+                assert_eq!(metadata.verification_status(), status, "{}", id);
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_sort_verified_assets_above_unlabeled() {
+        let pool = test_pool();
+        // test_btc is a gateway, test_usd is verified, and test_waves carries a label of no priority
+        pool.get()
+            .unwrap()
+            .batch_execute(
+                "INSERT INTO asset_wx_labels (asset_id, label) VALUES ('test_eth', 'COMMUNITY_VERIFIED'), ('test_waves', 'DEFI');",
+            )
+            .unwrap();
+        let repo = PgRepo::new(pool);
+        let find = |sort: Option<SearchSort>, after: Option<&SearchedAsset>| {
+            let params = FindParams {
+                sort,
+                limit: 2,
+                after: after.map(|a| cursor::encode(&a.sort_keys)),
+                ..test_params()
+            };
+            repo.find(params).unwrap()
+        };
+
+        let first_page = find(Some(SearchSort::LabelPriority), None);
+        assert_eq!(ids(&first_page), vec!["test_usd", "test_btc"]);
+        let second_page = find(Some(SearchSort::LabelPriority), first_page.last());
+        assert_eq!(ids(&second_page), vec!["test_eth", "test_waves"]);
+
+        // the labels don't affect the other sorts
+        assert_eq!(ids(&find(None, None)), vec!["test_btc", "test_eth"]);
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_get_latest_non_nft_asset_by_ticker() {
+        let pool = test_pool();
+        // the ticker of test_usd is taken by the NFT, and the one of test_btc by test_eth later
+        pool.get()
+            .unwrap()
+            .batch_execute(
+                "INSERT INTO asset_tickers (uid, block_uid, asset_id, ticker) VALUES (0, -1, 'test_nft', 'TUSD');
+                UPDATE asset_tickers SET ticker = 'TBTC' WHERE asset_id = 'test_eth';",
+            )
+            .unwrap();
+        let repo = PgRepo::new(pool);
+        let get_by_ticker = |ticker: &str| repo.get_by_ticker(ticker).unwrap().map(|a| a.id);
+
+        assert_eq!(get_by_ticker("TUSD"), Some("test_usd".to_owned()));
+        assert_eq!(get_by_ticker("TBTC"), Some("test_eth".to_owned()));
+        assert_eq!(get_by_ticker("TETH"), None);
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_page_user_defined_data_with_labels() {
+        let pool = test_pool();
+        pool.get()
+            .unwrap()
+            .batch_execute(
+                "INSERT INTO asset_wx_labels (asset_id, label) VALUES ('test_eth', 'DEFI'), ('test_usd', 'GATEWAY');",
+            )
+            .unwrap();
+        let repo = PgRepo::new(pool);
+        let page = |after: &str| {
+            repo.all_assets_user_defined_data(Some(after), 2, None)
+                .unwrap()
+                .into_iter()
+                .map(|data| {
+                    let labels = data.labels.into_iter().sorted().collect::<Vec<_>>();
+                    (data.asset_id, data.ticker, labels)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            page("test_"),
+            vec![
+                (
+                    "test_btc".to_owned(),
+                    Some("TBTC".to_owned()),
+                    vec!["GATEWAY".to_owned()]
+                ),
+                (
+                    "test_eth".to_owned(),
+                    Some("TETH".to_owned()),
+                    vec!["DEFI".to_owned()]
+                ),
+            ]
+        );
+        assert_eq!(
+            page("test_eth"),
+            vec![
+                ("test_nft".to_owned(), None, vec![]),
+                (
+                    "test_usd".to_owned(),
+                    Some("TUSD".to_owned()),
+                    vec![
+                        "DEFI".to_owned(),
+                        "GATEWAY".to_owned(),
+                        "WA_VERIFIED".to_owned()
+                    ]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_filter_by_ticker() {
+        let pool = test_pool();
+        // the ticker of test_eth is deleted, the one of test_waves is set empty
+        pool.get()
+            .unwrap()
+            .batch_execute(
+                "UPDATE asset_tickers SET ticker = NULL WHERE asset_id = 'test_eth';
+                INSERT INTO asset_tickers (uid, block_uid, asset_id, ticker) VALUES (-4, -1, 'test_waves', '');",
+            )
+            .unwrap();
+        let repo = PgRepo::new(pool);
+        let find = |ticker: TickerFilter| {
+            let params = FindParams {
+                ticker: Some(ticker),
+                ..test_params()
+            };
+            ids(&repo.find(params).unwrap())
+        };
+
+        assert_eq!(find(TickerFilter::Any), vec!["test_btc", "test_usd"]);
+        assert_eq!(find(TickerFilter::One("TUSD".to_owned())), vec!["test_usd"]);
+        assert!(find(TickerFilter::One("TETH".to_owned())).is_empty());
+        assert_eq!(
+            find(TickerFilter::In(vec![
+                "TBTC".to_owned(),
+                "TUSD".to_owned(),
+                "US'DT".to_owned()
+            ])),
+            vec!["test_btc", "test_usd"]
+        );
+    }
+
+    #[test]
+    #[ignore = "requires running Postgres configured with POSTGRES__* env variables"]
+    fn should_get_data_entries_as_of_height() {
+        let pool = test_pool();
+        // heights of no real block
+        pool.get()
+            .unwrap()
+            .batch_execute(&format!(
+                "INSERT INTO blocks_microblocks (uid, id, height, time_stamp) VALUES
+                    (-5, 'test_block_-2', -2, 4100000040000),
+                    (-4, 'test_block_-1', -1, 4100000050000);
+                INSERT INTO data_entries (uid, superseded_by, block_uid, address, key, data_type, str_val, related_asset_id) VALUES
+                    (-3, -2, -5, 'test_oracle', 'status_<test_btc>', 'str', 'verified', 'test_btc'),
+                    (-2, {max_uid}, -4, 'test_oracle', 'status_<test_btc>', 'str', 'scam', 'test_btc'),
+                    (-1, {max_uid}, -4, 'test_oracle', 'status_<test_eth>', 'str', 'verified', 'test_eth');",
+                max_uid = MAX_UID
+            ))
+            .unwrap();
+        let repo = PgRepo::new(pool);
+        let statuses = |height: i32| {
+            repo.data_entries_for_height(
+                &["test_btc", "test_eth"],
+                &["test_oracle".to_owned()],
+                height,
+            )
+            .unwrap()
+            .into_iter()
+            .map(|de| (de.asset_id, de.str_val.unwrap()))
+            .sorted()
+            .collect_vec()
+        };
+
+        assert_eq!(
+            statuses(-2),
+            vec![("test_btc".to_owned(), "verified".to_owned())]
+        );
+        assert_eq!(
+            statuses(-1),
+            vec![
+                ("test_btc".to_owned(), "scam".to_owned()),
+                ("test_eth".to_owned(), "verified".to_owned())
+            ]
+        );
     }
 }
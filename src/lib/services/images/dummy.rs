@@ -27,4 +27,14 @@ impl Service for DummyService {
             .collect::<Result<_, AppError>>()?;
         Ok(has_images)
     }
+
+    async fn get_image_url(&self, id: &str) -> Result<Option<String>, AppError> {
+        trace!("get image url"; "id" => format!("{:?}", id));
+        Ok(None)
+    }
+
+    async fn get_image_urls(&self, ids: &[&str]) -> Result<Vec<Option<String>>, AppError> {
+        trace!("get image urls"; "ids" => format!("{:?}", ids));
+        Ok(vec![None; ids.len()])
+    }
 }
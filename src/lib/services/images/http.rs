@@ -1,38 +1,80 @@
+use std::sync::Arc;
+use std::time::Duration;
 use wavesexchange_log::debug;
 
-use super::Service;
+use super::{image_url, Service};
 use crate::api_clients::images;
+use crate::cache::{lru_mem, AsyncReadCache, CacheKeyFn};
 use crate::error::Error as AppError;
 
 pub struct HttpService {
-    images_api_client: Box<dyn images::Client + Send + Sync>,
+    images_api_client: Arc<dyn images::Client + Send + Sync>,
+    images_existence: Box<dyn AsyncReadCache<bool> + Send + Sync>,
 }
 
 impl HttpService {
     pub fn new(client: impl images::Client + Send + Sync + 'static) -> Self {
+        let images_api_client: Arc<dyn images::Client + Send + Sync> = Arc::new(client);
         Self {
-            images_api_client: Box::new(client),
+            images_existence: Box::new(ImagesExistence(images_api_client.clone())),
+            images_api_client,
         }
     }
+
+    /// Remembers whether the images exist for `ttl` in the memory of this service
+    pub fn with_cache(mut self, capacity: usize, ttl: Duration) -> Self {
+        self.images_existence = Box::new(lru_mem::new::<bool, _>(
+            ImagesExistence(self.images_api_client.clone()),
+            capacity,
+            ttl,
+        ));
+        self
+    }
+}
+
+/// Existence of the images read from the images service, all of them in a single request
+struct ImagesExistence(Arc<dyn images::Client + Send + Sync>);
+
+impl CacheKeyFn for ImagesExistence {
+    fn key_fn(&self, source_key: &str) -> String {
+        source_key.to_owned()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncReadCache<bool> for ImagesExistence {
+    async fn get(&self, key: &str) -> Result<Option<bool>, AppError> {
+        Ok(self.mget(&[key]).await?.into_iter().next().flatten())
+    }
+
+    async fn mget(&self, keys: &[&str]) -> Result<Vec<Option<bool>>, AppError> {
+        let has_images = self
+            .0
+            .has_svgs(keys)
+            .await
+            .map_err(|e| AppError::UpstreamAPIBadResponse(e.to_string()))?;
+        Ok(has_images.into_iter().map(Some).collect())
+    }
 }
 
 #[async_trait::async_trait]
 impl Service for HttpService {
+    /// Goes through the batch check, so that single and batch checks agree
     async fn has_image(&self, id: &str) -> Result<bool, AppError> {
-        match self.images_api_client.has_svg(id).await {
-            Ok(res) => Ok(res),
-            Err(err) => Err(AppError::UpstreamAPIBadResponse(err.to_string())),
-        }
+        let has_images = self.has_images(&[id]).await?;
+        Ok(has_images.first().copied().unwrap_or(false))
     }
 
     async fn has_images(&self, ids: &[&str]) -> Result<Vec<bool>, AppError> {
         let start_time = tokio::time::Instant::now();
 
         let has_images = self
-            .images_api_client
-            .has_svgs(ids)
-            .await
-            .map_err(|e| AppError::UpstreamAPIBadResponse(e.to_string()))?;
+            .images_existence
+            .mget(ids)
+            .await?
+            .into_iter()
+            .map(|has_image| has_image.unwrap_or(false))
+            .collect();
         debug!(
             "has images: completed in {}ms ({} images)",
             start_time.elapsed().as_millis(),
@@ -40,4 +82,116 @@ impl Service for HttpService {
         );
         Ok(has_images)
     }
+
+    async fn get_image_url(&self, id: &str) -> Result<Option<String>, AppError> {
+        let image_urls = self.get_image_urls(&[id]).await?;
+        Ok(image_urls.into_iter().next().flatten())
+    }
+
+    /// The images are checked by a single request, the URLs are built from the base URL of the client
+    async fn get_image_urls(&self, ids: &[&str]) -> Result<Vec<Option<String>>, AppError> {
+        let base_url = self.images_api_client.base_url();
+        let has_images = self.has_images(ids).await?;
+
+        Ok(ids
+            .iter()
+            .zip(has_images)
+            .map(|(id, has_image)| {
+                if has_image {
+                    Some(image_url(&base_url, id))
+                } else {
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use warp::Filter;
+
+    use super::HttpService;
+    use crate::api_clients::HttpClient;
+    use crate::services::images::Service;
+
+    #[derive(Deserialize)]
+    struct ImagesExistenceRequest {
+        images: Vec<String>,
+    }
+
+    /// Images backend having the images of the ids starting with `logo`, counting the requests
+    async fn images_backend(requests: Arc<AtomicUsize>) -> String {
+        let route = warp::path!("images_existence")
+            .and(warp::post())
+            .and(warp::body::json())
+            .map(move |req: ImagesExistenceRequest| {
+                requests.fetch_add(1, Ordering::Relaxed);
+                let has_images = req
+                    .images
+                    .iter()
+                    .map(|image| image.starts_with("logo"))
+                    .collect::<Vec<_>>();
+                warp::reply::json(&has_images)
+            });
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        format!("http://{}/", addr)
+    }
+
+    #[tokio::test]
+    async fn should_check_images_in_single_request() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let url = images_backend(requests.clone()).await;
+        let service = HttpService::new(HttpClient::new(url.clone()).unwrap());
+
+        let ids = ["logo1", "asset2", "logo3", "asset4"];
+        let has_images = service.has_images(&ids).await.unwrap();
+
+        assert_eq!(has_images, vec![true, false, true, false]);
+        assert_eq!(requests.load(Ordering::Relaxed), 1);
+
+        assert!(service.has_image("logo5").await.unwrap());
+        assert_eq!(requests.load(Ordering::Relaxed), 2);
+
+        // nothing to check
+        assert!(service.has_images(&[]).await.unwrap().is_empty());
+        assert_eq!(requests.load(Ordering::Relaxed), 2);
+
+        let image_urls = service.get_image_urls(&["logo6", "asset7"]).await.unwrap();
+        assert_eq!(image_urls, vec![Some(format!("{}logo6.svg", url)), None]);
+        assert_eq!(requests.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn should_not_request_cached_images_within_ttl() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let url = images_backend(requests.clone()).await;
+        let service = HttpService::new(HttpClient::new(url).unwrap())
+            .with_cache(10, Duration::from_millis(200));
+
+        let has_images = service.has_images(&["logo1", "asset2"]).await.unwrap();
+        assert_eq!(has_images, vec![true, false]);
+        assert_eq!(requests.load(Ordering::Relaxed), 1);
+
+        // both the existing and the missing images are cached
+        let has_images = service.has_images(&["asset2", "logo1"]).await.unwrap();
+        assert_eq!(has_images, vec![false, true]);
+        assert!(!service.has_image("asset2").await.unwrap());
+        assert_eq!(requests.load(Ordering::Relaxed), 1);
+
+        // only the ids missing in the cache are requested
+        let has_images = service.has_images(&["logo1", "logo3"]).await.unwrap();
+        assert_eq!(has_images, vec![true, true]);
+        assert_eq!(requests.load(Ordering::Relaxed), 2);
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+
+        assert!(service.has_image("logo1").await.unwrap());
+        assert_eq!(requests.load(Ordering::Relaxed), 3);
+    }
 }
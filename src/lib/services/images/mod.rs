@@ -8,4 +8,45 @@ pub trait Service {
     async fn has_image(&self, id: &str) -> Result<bool, AppError>;
 
     async fn has_images(&self, ids: &[&str]) -> Result<Vec<bool>, AppError>;
+
+    /// URL of the logo of the asset, none if there is no logo
+    async fn get_image_url(&self, id: &str) -> Result<Option<String>, AppError>;
+
+    /// URLs of the logos in the order of the ids
+    async fn get_image_urls(&self, ids: &[&str]) -> Result<Vec<Option<String>>, AppError>;
+}
+
+/// URL of the SVG logo of the asset at the images service
+pub fn image_url(base_url: &str, id: &str) -> String {
+    format!("{}/{}.svg", base_url.trim_end_matches('/'), id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::dummy::DummyService;
+    use super::{image_url, Service};
+
+    #[test]
+    fn should_join_base_url_and_asset_id() {
+        let id = "8LQW8f7P5d5PZM7GtZEBgaqRPGSzS3DfPuiXrURJ4AJS";
+        let expected =
+            "https://images.example.com/assets/8LQW8f7P5d5PZM7GtZEBgaqRPGSzS3DfPuiXrURJ4AJS.svg";
+
+        assert_eq!(
+            image_url("https://images.example.com/assets/", id),
+            expected
+        );
+        assert_eq!(image_url("https://images.example.com/assets", id), expected);
+    }
+
+    #[tokio::test]
+    async fn should_have_no_image_urls_without_images_service() {
+        let service = DummyService::new();
+
+        assert_eq!(service.get_image_url("asset").await.unwrap(), None);
+        assert_eq!(
+            service.get_image_urls(&["asset1", "asset2"]).await.unwrap(),
+            vec![None, None]
+        );
+    }
 }
@@ -1,3 +1,3 @@
-pub mod assets;
 pub mod admin_assets;
+pub mod assets;
 pub mod images;
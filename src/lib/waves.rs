@@ -1,7 +1,11 @@
 use bytes::{BufMut, BytesMut};
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
+
+use crate::error::Error as AppError;
+
+pub const PUBLIC_KEY_LENGTH: usize = 32;
 
 lazy_static! {
     pub static ref ASSET_ORACLE_DATA_ENTRY_KEY_REGEX: Regex =
@@ -58,23 +62,22 @@ impl From<(RawPublicKey, u8)> for Address {
     }
 }
 
-impl From<(&[u8], u8)> for Address {
-    fn from(data: (&[u8], u8)) -> Self {
-        let (pk, chain_id) = data;
-
-        let pkh = keccak256(&blake2b256(pk));
-
-        let mut addr = BytesMut::with_capacity(26); // VERSION + CHAIN_ID + PKH + checksum
+impl TryFrom<(&[u8], u8)> for Address {
+    type Error = AppError;
 
-        addr.put_u8(1); // address version is always 1
-        addr.put_u8(chain_id);
-        addr.put_slice(&pkh[..20]);
+    /// Fails if the bytes are not a public key
+    fn try_from(data: (&[u8], u8)) -> Result<Self, Self::Error> {
+        let (pk, chain_id) = data;
 
-        let chks = &keccak256(&blake2b256(&addr[..22]))[..4];
+        if pk.len() != PUBLIC_KEY_LENGTH {
+            return Err(AppError::InvalidMessage(format!(
+                "public key of {} bytes, expected {}",
+                pk.len(),
+                PUBLIC_KEY_LENGTH
+            )));
+        }
 
-        addr.put_slice(chks);
-
-        Address(bs58::encode(addr).into_string())
+        Ok(Address::from((RawPublicKey(pk.to_vec()), chain_id)))
     }
 }
 
@@ -106,6 +109,13 @@ pub fn is_valid_base58(src: &str) -> bool {
     bs58::decode(src).into_vec().is_ok()
 }
 
+/// Whether the string is a base58 encoded asset id (32 bytes), e.g. pasted into the search
+pub fn is_asset_id(src: &str) -> bool {
+    bs58::decode(src).into_vec().map_or(false, |bytes| {
+        bytes.len() == 32 && get_asset_id(&bytes) == src
+    })
+}
+
 pub const WAVES_ID: &str = "WAVES";
 pub const WAVES_NAME: &str = "Waves";
 pub const WAVES_PRECISION: i32 = 8;
@@ -183,11 +193,29 @@ pub fn parse_waves_association_key(
 
 #[cfg(test)]
 mod tests {
+    use std::convert::TryFrom;
+
     use super::{
-        is_valid_base58, parse_waves_association_key, WavesAssociationKey,
+        is_asset_id, is_valid_base58, parse_waves_association_key, Address, WavesAssociationKey,
         KNOWN_WAVES_ASSOCIATION_ASSET_ATTRIBUTES,
     };
 
+    #[test]
+    fn should_reject_public_key_of_invalid_length() {
+        assert!(Address::try_from((&[3u8; 32][..], b'W')).is_ok());
+        assert!(Address::try_from((&[3u8; 20][..], b'W')).is_err());
+        assert!(Address::try_from((&[][..], b'W')).is_err());
+    }
+
+    #[test]
+    fn should_recognize_asset_id() {
+        assert!(is_asset_id("8LQW8f7P5d5PZM7GtZEBgaqRPGSzS3DfPuiXrURJ4AJS"));
+        assert!(!is_asset_id("8LQW8f7P5d5PZM7GtZEBgaqRPGSzS3DfPuiXrURJ4AJ"));
+        assert!(!is_asset_id("3PC9BfRwJWWiw9AREE2B3eWzCks3CYtg4yo"));
+        assert!(!is_asset_id("WAVES"));
+        assert!(!is_asset_id("8LQW8f7P5d5PZM7GtZEBgaqRPGSzS3DfPuiXrURJ4AJ0"));
+    }
+
     #[test]
     fn should_validate_base58_string() {
         let test_cases = vec![